@@ -0,0 +1,240 @@
+use colored::Colorize;
+use dkn_executor::{benchmark::BenchmarkSample, DriaExecutorsManager, Model, TaskBody};
+use eyre::{Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate};
+
+use crate::offline::read_offline_tasks;
+use crate::DRIA_COMPUTE_NODE_VERSION;
+
+/// Default number of measured executions per model, excluding the warm-up.
+const DEFAULT_BENCHMARK_ITERATIONS: usize = 3;
+
+/// Prompt used for benchmarking when no task file is given.
+const BENCHMARK_PROMPT: &str = "Please write a poem about Kapadokya.";
+
+/// A machine-readable benchmark report for all configured models.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkReport {
+    /// Node version, e.g. `0.1.0`.
+    pub version: String,
+    /// Number of measured executions per model.
+    pub iterations: usize,
+    /// Results for each model.
+    pub models: Vec<ModelBenchmark>,
+}
+
+/// Benchmark results of a model, averaged over the successful executions.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelBenchmark {
+    pub model: String,
+    pub provider: String,
+    /// Number of successful executions.
+    pub succeeded: usize,
+    /// Number of failed executions.
+    pub failed: usize,
+    /// Mean latency of a whole execution, in milliseconds.
+    pub latency_ms: Option<f64>,
+    /// Mean time to first token, in milliseconds.
+    pub ttft_ms: Option<f64>,
+    /// Mean output tokens per second.
+    pub tokens_per_sec: Option<f64>,
+    /// Memory used by the model as reported by its provider, in bytes.
+    pub model_memory_bytes: Option<u64>,
+    /// Resident memory of the node process after the executions, in bytes.
+    pub process_memory_bytes: Option<u64>,
+    /// The last error, if any.
+    pub error: Option<String>,
+}
+
+impl ModelBenchmark {
+    /// Aggregates the samples of a model.
+    fn new(
+        model: Model,
+        samples: &[BenchmarkSample],
+        failed: usize,
+        error: Option<String>,
+    ) -> Self {
+        /// Returns the mean of the values that are available, if any.
+        fn mean(values: impl Iterator<Item = Option<f64>>) -> Option<f64> {
+            let values = values.flatten().collect::<Vec<_>>();
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        }
+
+        Self {
+            model: model.to_string(),
+            provider: model.provider().to_string(),
+            succeeded: samples.len(),
+            failed,
+            latency_ms: mean(
+                samples
+                    .iter()
+                    .map(|s| Some(s.latency.as_secs_f64() * 1000.0)),
+            ),
+            ttft_ms: mean(samples.iter().map(|s| {
+                s.time_to_first_token
+                    .map(|ttft| ttft.as_secs_f64() * 1000.0)
+            })),
+            tokens_per_sec: mean(samples.iter().map(BenchmarkSample::tokens_per_second)),
+            model_memory_bytes: None,
+            process_memory_bytes: None,
+            error,
+        }
+    }
+}
+
+/// Runs the `benchmark [--iterations <n>] [--task <file>] [--output <file>]` command, which measures
+/// latency, time to first token, tokens per second and memory for each model in `DKN_MODELS`.
+///
+/// The tasks are executed by the same executors as in production after the usual service checks, and
+/// each model is warmed up with an execution that is not measured. The report is printed as JSON,
+/// or written to the output file if given.
+pub async fn run_benchmark_command(args: &[String]) -> Result<()> {
+    let mut iterations = DEFAULT_BENCHMARK_ITERATIONS;
+    let mut task_path = None;
+    let mut output_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| eyre::eyre!("missing value after {arg}"))
+        };
+        match arg.as_str() {
+            "--iterations" | "-n" => {
+                iterations = value()?.parse().wrap_err("invalid number of iterations")?;
+            }
+            "--task" | "-t" => task_path = Some(PathBuf::from(value()?)),
+            "--output" | "-o" => output_path = Some(PathBuf::from(value()?)),
+            arg => eyre::bail!("unexpected argument {arg}"),
+        }
+    }
+
+    // the first task in the file is used for all models, if given
+    let task = match task_path {
+        Some(path) => Some(
+            read_offline_tasks(&path)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| eyre::eyre!("no tasks in {}", path.display()))?
+                .input,
+        ),
+        None => None,
+    };
+
+    let models = Model::from_csv(std::env::var("DKN_MODELS").unwrap_or_default());
+    let mut executors = DriaExecutorsManager::new_from_env_for_models(models.into_iter())?;
+    executors.check_services().await;
+    if executors.models.is_empty() {
+        eyre::bail!("No valid models left after service checks, exiting.");
+    }
+
+    let mut models = executors.models.iter().copied().collect::<Vec<_>>();
+    models.sort_by_key(|model| model.to_string());
+    let mut system = sysinfo::System::new();
+    let mut report = BenchmarkReport {
+        version: DRIA_COMPUTE_NODE_VERSION.to_string(),
+        iterations,
+        models: Vec::new(),
+    };
+    for model in models {
+        log::info!("Benchmarking {}", model.to_string().yellow());
+        let executor = executors.get_executor(&model).await?;
+        let task = match &task {
+            Some(task) => TaskBody {
+                model,
+                ..task.clone()
+            },
+            None => TaskBody::new_prompt(BENCHMARK_PROMPT, model),
+        };
+
+        // warm-up is not measured, but its error is reported if it fails
+        if let Err(err) = executor.benchmark(task.clone()).await {
+            log::warn!("Could not warm up {model}: {err}");
+            report.models.push(ModelBenchmark::new(
+                model,
+                &[],
+                iterations,
+                Some(err.to_string()),
+            ));
+            continue;
+        }
+
+        let mut samples = Vec::new();
+        let mut error = None;
+        for _ in 0..iterations {
+            match executor.benchmark(task.clone()).await {
+                Ok(sample) => samples.push(sample),
+                Err(err) => {
+                    log::warn!("Benchmark of {model} failed: {err}");
+                    error = Some(err.to_string());
+                }
+            }
+        }
+
+        let mut model_benchmark =
+            ModelBenchmark::new(model, &samples, iterations - samples.len(), error);
+        model_benchmark.model_memory_bytes = executor.model_memory(&model).await;
+        model_benchmark.process_memory_bytes = process_memory(&mut system);
+        report.models.push(model_benchmark);
+    }
+
+    let report = serde_json::to_string_pretty(&report).wrap_err("could not serialize report")?;
+    match output_path {
+        Some(path) => {
+            std::fs::write(&path, report)
+                .wrap_err_with(|| format!("could not write {}", path.display()))?;
+            log::info!("Benchmark report written to {}", path.display());
+        }
+        None => println!("{report}"),
+    }
+
+    Ok(())
+}
+
+/// Returns the resident memory of the current process, in bytes.
+fn process_memory(system: &mut sysinfo::System) -> Option<u64> {
+    let pid = sysinfo::get_current_pid().ok()?;
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_memory(),
+    );
+    system.process(pid).map(|process| process.memory())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_model_benchmark() {
+        let samples = [
+            BenchmarkSample {
+                latency: Duration::from_millis(1000),
+                time_to_first_token: Some(Duration::from_millis(100)),
+                output_tokens: Some(50),
+                generation_time: Some(Duration::from_millis(500)),
+            },
+            BenchmarkSample {
+                latency: Duration::from_millis(3000),
+                time_to_first_token: None,
+                output_tokens: None,
+                generation_time: None,
+            },
+        ];
+
+        let benchmark = ModelBenchmark::new(Model::Gemma3_4b, &samples, 1, None);
+        assert_eq!(benchmark.succeeded, 2);
+        assert_eq!(benchmark.failed, 1);
+        assert_eq!(benchmark.latency_ms, Some(2000.0));
+        assert_eq!(benchmark.ttft_ms, Some(100.0));
+        assert_eq!(benchmark.tokens_per_sec, Some(100.0));
+
+        let benchmark = ModelBenchmark::new(Model::Gemma3_4b, &[], 3, Some("oops".into()));
+        assert_eq!(benchmark.latency_ms, None);
+    }
+}
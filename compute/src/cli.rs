@@ -0,0 +1,183 @@
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Command-line interface of the compute node.
+///
+/// The flags override the variables of the environment & the environment file, and running without
+/// a command starts the node.
+#[derive(Debug, Parser)]
+#[command(version, about = "Dria Compute Node", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// Profile within the environment file, same as `DKN_PROFILE`.
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+    /// Shows a dashboard instead of the logs.
+    #[arg(long, global = true)]
+    pub tui: bool,
+    #[command(flatten)]
+    pub start: StartArgs,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Starts the node, this is the default.
+    Start(StartArgs),
+    /// Prints the specs of this machine as sent to the RPC.
+    Specs,
+    /// Prints the known & discovered RPCs of the network.
+    Peers,
+    /// Prints the version of the node.
+    Version,
+    /// Generates a new wallet, optionally written to an encrypted keystore.
+    Keygen {
+        /// Keystore file to write the wallet to, for `DKN_KEYSTORE_PATH`.
+        #[arg(long, value_name = "FILE")]
+        keystore: Option<PathBuf>,
+    },
+    /// Generates a new wallet to replace the current one, along with the rotation signed by the current one.
+    RotateKey {
+        /// Keystore file to write the new wallet to, for `DKN_KEYSTORE_PATH`.
+        #[arg(long, value_name = "FILE")]
+        keystore: Option<PathBuf>,
+    },
+    /// Runs a task file with the real executor, and prints its response payload.
+    Task {
+        #[command(subcommand)]
+        command: TaskCommand,
+    },
+    /// Measures the latency & throughput of the models.
+    Benchmark(PassthroughArgs),
+    /// Exports a diagnostics bundle for support.
+    Diagnose(PassthroughArgs),
+    /// Checks the configuration end-to-end, and tells how to fix the failed checks.
+    Doctor,
+    /// Prints the points & percentile of a node.
+    Points(PassthroughArgs),
+    /// Prints the recent tasks from the task history.
+    History(PassthroughArgs),
+    /// Upgrades an environment file of an older layout to the current one, printing the changes.
+    MigrateConfig {
+        /// Environment file to migrate, defaults to `DKN_COMPUTE_ENV` or `.env`.
+        #[arg(value_name = "FILE")]
+        path: Option<PathBuf>,
+        /// Writes the migrated file in place, keeping the original with a `.bak` extension.
+        #[arg(long)]
+        write: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TaskCommand {
+    /// Runs the given task file.
+    Run(PassthroughArgs),
+}
+
+/// Arguments that are parsed by the command itself, see its documentation in the README.
+#[derive(Debug, Clone, Args)]
+pub struct PassthroughArgs {
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    pub args: Vec<String>,
+}
+
+/// Flags of the node, overriding their environment variables.
+#[derive(Debug, Clone, Default, Args)]
+pub struct StartArgs {
+    /// P2P listen addresses, same as `DKN_P2P_LISTEN_ADDR`.
+    #[arg(long = "listen-addr", value_name = "MULTIADDR")]
+    pub listen_addrs: Vec<String>,
+    /// Models to serve, comma-separated, same as `DKN_MODELS`.
+    #[arg(long)]
+    pub models: Option<String>,
+    /// Network to join, same as `DKN_NETWORK`.
+    #[arg(long)]
+    pub network: Option<String>,
+    /// Runs the given task files without joining the network, same as `DKN_OFFLINE`.
+    #[arg(long)]
+    pub offline: bool,
+    /// Task files to run in offline mode.
+    pub paths: Vec<PathBuf>,
+}
+
+impl Cli {
+    /// Returns the flags of the node, given either with or without the `start` command.
+    pub fn start_args(&self) -> &StartArgs {
+        match &self.command {
+            Some(Command::Start(args)) => args,
+            _ => &self.start,
+        }
+    }
+}
+
+impl StartArgs {
+    /// Returns the environment variables that are overridden by the flags.
+    pub fn env_overrides(&self) -> Vec<(String, String)> {
+        let mut overrides = Vec::new();
+        if !self.listen_addrs.is_empty() {
+            overrides.push((
+                "DKN_P2P_LISTEN_ADDR".to_string(),
+                self.listen_addrs.join(","),
+            ));
+        }
+        if let Some(models) = &self.models {
+            overrides.push(("DKN_MODELS".to_string(), models.clone()));
+        }
+        if let Some(network) = &self.network {
+            overrides.push(("DKN_NETWORK".to_string(), network.clone()));
+        }
+
+        overrides
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli() {
+        let cli = Cli::try_parse_from([
+            "dkn-compute-node",
+            "--models",
+            "gemma3:4b,gpt-4o",
+            "--listen-addr",
+            "/ip4/0.0.0.0/tcp/4001",
+            "--listen-addr",
+            "/ip6/::/tcp/4001",
+            "--profile",
+            "test",
+        ])
+        .unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.profile.as_deref(), Some("test"));
+        assert_eq!(
+            cli.start_args().env_overrides(),
+            vec![
+                (
+                    "DKN_P2P_LISTEN_ADDR".to_string(),
+                    "/ip4/0.0.0.0/tcp/4001,/ip6/::/tcp/4001".to_string()
+                ),
+                ("DKN_MODELS".to_string(), "gemma3:4b,gpt-4o".to_string()),
+            ]
+        );
+
+        let cli =
+            Cli::try_parse_from(["dkn-compute-node", "start", "--network", "testnet"]).unwrap();
+        assert_eq!(cli.start_args().network.as_deref(), Some("testnet"));
+
+        // the arguments of the older commands are given to them as-is
+        let cli =
+            Cli::try_parse_from(["dkn-compute-node", "benchmark", "--iterations", "3"]).unwrap();
+        let Some(Command::Benchmark(args)) = cli.command else {
+            panic!("expected benchmark command");
+        };
+        assert_eq!(args.args, vec!["--iterations", "3"]);
+
+        let cli =
+            Cli::try_parse_from(["dkn-compute-node", "--offline", "a.json", "b.json"]).unwrap();
+        assert!(cli.start_args().offline);
+        assert_eq!(cli.start_args().paths.len(), 2);
+    }
+}
@@ -1,70 +1,359 @@
-use dkn_executor::DriaExecutorsManager;
+use dkn_executor::{DriaExecutorsManager, Model, ModelProvider};
+use dkn_p2p::libp2p::identity::{KeyType, Keypair};
 use dkn_p2p::libp2p::{Multiaddr, PeerId};
-use eyre::{eyre, Result};
+use dkn_p2p::{DriaGossipPeerScore, DriaP2PConfig, DriaP2PProtocol};
+use eyre::{eyre, Context, Result};
 use libsecp256k1::{PublicKey, SecretKey};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use std::{env, str::FromStr};
 
 use dkn_utils::{
-    crypto::{public_key_to_address, secret_to_keypair},
+    crypto::{
+        public_key_to_address, secret_to_ed25519_keypair, secret_to_keypair, verify_key_rotation,
+    },
+    payloads::{KeyRotation, TaskError},
     DriaNetwork, SemanticVersion,
 };
 
+use crate::utils::{secret_key_from_keystore_env, LocalSigner, QuietHours, RemoteSigner, Signer};
+use crate::workers::task::TaskWorker;
+
 const DEFAULT_TASK_BATCH_SIZE: usize = 5;
+const DEFAULT_PENDING_HIGH_WATER_MARK: usize = 64;
+const DEFAULT_RPC_STANDBY_COUNT: usize = 2;
+const DEFAULT_WEBHOOK_TASK_ERRORS: usize = 10;
 const DEFAULT_P2P_LISTEN_ADDR: &str = "/ip4/0.0.0.0/tcp/4001";
+const DEFAULT_NTP_SERVER: &str = "pool.ntp.org";
+const DEFAULT_HISTORY_RETENTION_DAYS: u64 = 30;
+
+/// Duration after a wallet rotation that it is announced for, see [`DriaComputeNodeConfig::key_rotation`].
+pub const KEY_ROTATION_WINDOW: chrono::Duration = chrono::Duration::days(14);
+
+/// Intervals of the periodic jobs within the main loop of the node.
+///
+/// The defaults are meant for the public networks, shorter ones are useful for test networks & debugging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeIntervals {
+    /// Duration between refreshing for diagnostic prints, the first heartbeat & specs are sent relative to it.
+    ///
+    /// Given by `DKN_DIAGNOSTIC_REFRESH_SECS`, defaults to 45 seconds.
+    pub diagnostic_refresh: Duration,
+    /// Duration between each specs update sent to the RPC.
+    ///
+    /// Given by `DKN_SPECS_INTERVAL_SECS`, defaults to 5 minutes.
+    pub specs: Duration,
+    /// Duration between checking the RPC connection, re-attempts are spaced out further by a backoff.
+    ///
+    /// Given by `DKN_RPC_LIVENESS_REFRESH_SECS`, defaults to 5 seconds.
+    pub rpc_liveness_refresh: Duration,
+    /// Duration between refreshing for points update.
+    ///
+    /// Given by `DKN_POINTS_REFRESH_SECS`, defaults to 3 minutes.
+    pub points_refresh: Duration,
+    /// Deadline of each heartbeat, past which the RPC considers it dead; heartbeats are sent at this interval as well.
+    ///
+    /// Given by `DKN_HEARTBEAT_DEADLINE_SECS`, defaults to 60 seconds.
+    pub heartbeat_deadline: Duration,
+    /// Duration such that if the last acknowledged heartbeat is older than this, the node is considered unreachable.
+    ///
+    /// Given by `DKN_HEARTBEAT_LIVENESS_SECS`, defaults to 4 minutes.
+    pub heartbeat_liveness: Duration,
+}
+
+impl NodeIntervals {
+    /// Bounds of the heartbeat deadline, too short ones can not be acknowledged in time
+    /// and too long ones keep a dead node as available.
+    pub const HEARTBEAT_DEADLINE_BOUNDS: (Duration, Duration) =
+        (Duration::from_secs(10), Duration::from_secs(10 * 60));
+    /// Upper bound of the heartbeat liveness, the lower bound is twice the heartbeat deadline
+    /// so that a single late heartbeat does not mark the node as unreachable.
+    pub const MAX_HEARTBEAT_LIVENESS: Duration = Duration::from_secs(60 * 60);
+
+    /// Checks that the heartbeat deadline & liveness are within their bounds.
+    pub fn check_heartbeat_bounds(&self) -> Result<()> {
+        let (min_deadline, max_deadline) = Self::HEARTBEAT_DEADLINE_BOUNDS;
+        if self.heartbeat_deadline < min_deadline || self.heartbeat_deadline > max_deadline {
+            eyre::bail!(
+                "heartbeat deadline must be between {} and {} seconds",
+                min_deadline.as_secs(),
+                max_deadline.as_secs()
+            );
+        }
+
+        let min_liveness = self.heartbeat_deadline * 2;
+        if self.heartbeat_liveness < min_liveness
+            || self.heartbeat_liveness > Self::MAX_HEARTBEAT_LIVENESS
+        {
+            eyre::bail!(
+                "heartbeat liveness must be between {} and {} seconds, w.r.t the heartbeat deadline",
+                min_liveness.as_secs(),
+                Self::MAX_HEARTBEAT_LIVENESS.as_secs()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for NodeIntervals {
+    fn default() -> Self {
+        Self {
+            diagnostic_refresh: Duration::from_secs(45),
+            specs: Duration::from_secs(5 * 60),
+            rpc_liveness_refresh: Duration::from_secs(5),
+            points_refresh: Duration::from_secs(3 * 60),
+            heartbeat_deadline: Duration::from_secs(60),
+            heartbeat_liveness: Duration::from_secs(4 * 60),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct DriaComputeNodeConfig {
-    /// Wallet secret/private key.
+    /// Secret key of the P2P identity, which is the wallet secret key unless a remote signer is used.
     pub secret_key: SecretKey,
-    /// Wallet public key, derived from the secret key.
+    /// Keypair of the P2P identity, which is of the secret key above unless `DKN_P2P_KEY_TYPE` is `ed25519`,
+    /// where it is an Ed25519 key derived from the secret key or given by `DKN_P2P_ED25519_SECRET_KEY`.
+    ///
+    /// The wallet signs with secp256k1 regardless of this key.
+    pub p2p_keypair: Keypair,
+    /// Signer of the messages with the wallet, either with the secret key above or a remote signer.
+    ///
+    /// The remote signer is given by `DKN_REMOTE_SIGNER_URL`, see [`RemoteSigner::from_env`].
+    pub signer: Arc<dyn Signer>,
+    /// Wallet public key, derived from the signer.
     pub public_key: PublicKey,
     /// Wallet address in hex without `0x` prefix, derived from the public key.
     pub address: String,
-    /// Peer ID of the node.
+    /// Rotation from a previous wallet to this one, signed by the previous wallet, which is announced
+    /// within the heartbeats & specs for [`KEY_ROTATION_WINDOW`] after the rotation.
+    ///
+    /// Given by `DKN_KEY_ROTATION` as printed by the `rotate-key` command.
+    pub key_rotation: Option<KeyRotation>,
+    /// Peer ID of the node, derived from the P2P keypair.
     pub peer_id: PeerId,
     /// Compute node version.
     pub version: SemanticVersion,
-    /// P2P listen address, e.g. `/ip4/0.0.0.0/tcp/4001`.
-    pub p2p_listen_addr: Multiaddr,
+    /// P2P listen addresses, e.g. `/ip4/0.0.0.0/tcp/4001`.
+    ///
+    /// Given by `DKN_P2P_LISTEN_ADDR` as a comma-separated list.
+    pub p2p_listen_addrs: Vec<Multiaddr>,
+    /// P2P connection limits, idle connection timeout, peer allow/block lists and request-response limits.
+    ///
+    /// Given by `DKN_P2P_MAX_CONNECTIONS`, `DKN_P2P_MAX_CONNECTIONS_PER_PEER`,
+    /// `DKN_P2P_MAX_PENDING_CONNECTIONS` and `DKN_P2P_IDLE_TIMEOUT_SECS`, along with
+    /// `DKN_P2P_BLOCKED_PEERS`, `DKN_P2P_RPC_ONLY` and `DKN_P2P_ALLOWED_PEERS`, and
+    /// `DKN_P2P_MAX_REQUEST_SIZE`, `DKN_P2P_MAX_RESPONSE_SIZE` and `DKN_P2P_REQUEST_TIMEOUT_SECS`.
+    /// External addresses to advertise are given by `DKN_P2P_EXTERNAL_ADDR`, and compression
+    /// can be disabled with `DKN_P2P_COMPRESSION`. The DNS resolver is given by `DKN_P2P_DNS`,
+    /// and a SOCKS5 proxy to dial through by `DKN_P2P_PROXY`. The relay server is enabled
+    /// by `DKN_P2P_RELAY_SERVER`.
+    pub p2p_config: DriaP2PConfig,
     /// Executor manager, handles models and providers.
     pub executors: DriaExecutorsManager,
     /// Network type of the node.
     pub network: DriaNetwork,
+    /// P2P protocol of a private network, instead of the one of the network type.
+    ///
+    /// Given by `DKN_P2P_PROTOCOL` as `name/version` (e.g. `acme-dria/0.5`) or only `name`,
+    /// where the version defaults to the `major.minor` version of the node.
+    pub protocol: Option<DriaP2PProtocol>,
     /// Batch size for batchable tasks (e.g. API-based ones).
     ///
     /// A higher value will help execute more tasks concurrently,
     /// at the risk of hitting rate-limits.
     pub batch_size: usize,
+    /// Batch sizes of the providers that override `batch_size`, e.g. to respect the rate-limits of each.
+    ///
+    /// Given by `DKN_PROVIDER_BATCH_SIZES` as comma-separated `provider=size` pairs.
+    pub provider_batch_sizes: HashMap<ModelProvider, usize>,
+    /// Number of pending tasks (single & batch) at which new tasks are rejected
+    /// with an overloaded error, instead of being queued.
+    pub pending_high_water_mark: usize,
+    /// Maximum number of pending tasks (single & batch) at which new tasks are rejected with a capacity error
+    /// instead, so that the memory of the node is bounded even if the RPC over-assigns; it takes the place of
+    /// the high-water mark, see [`Self::pending_capacity_error`].
+    ///
+    /// Given by `DKN_MAX_PENDING_TASKS`, unlimited if not given.
+    pub max_pending_tasks: Option<usize>,
     /// An optional first-attempt RPC address, will be dialled at startup.
     ///
     /// TODO: this is `None` after startup due to `Option::take`, can we do any better?
     pub initial_rpc_addr: Option<Multiaddr>,
+    /// A pinned RPC address, which is always used instead of the discovery API, including
+    /// when the connection is lost.
+    ///
+    /// Given by `DKN_RPC_ADDR`, with the peer id given within it or by `DKN_RPC_PEER_ID`.
+    pub pinned_rpc_addr: Option<Multiaddr>,
+    /// Base URL of the discovery API that returns the available RPCs, instead of the one of the network,
+    /// e.g. for private deployments.
+    ///
+    /// Given by `DKN_DISCOVERY_URL`, where the `major.minor` version is appended as a path segment.
+    pub discovery_url: Option<String>,
+    /// Path of the file where known RPCs & their health are persisted, so that a restarted node
+    /// can reconnect even when the discovery API is down.
+    ///
+    /// Given by `DKN_PEER_STORE_PATH`, not persisted if not given.
+    pub peer_store_path: Option<PathBuf>,
+    /// Path of the file where the node state is snapshotted, e.g. completed tasks & the points baseline,
+    /// so that a restart continues the session.
+    ///
+    /// Given by `DKN_STATE_PATH`, not persisted if not given.
+    pub state_path: Option<PathBuf>,
+    /// Path of the file where the lifetime statistics are kept, e.g. uptime & total tasks across restarts.
+    ///
+    /// Given by `DKN_STATS_PATH`, kept only for the current session if not given.
+    pub stats_path: Option<PathBuf>,
+    /// Path of the file where the accepted & completed tasks are journaled, so that the tasks lost
+    /// by a crash are reported to the RPC after a restart.
+    ///
+    /// Given by `DKN_JOURNAL_PATH`, not journaled if not given.
+    pub journal_path: Option<PathBuf>,
+    /// Path of the file where a compact JSON status is written continuously, e.g. the heartbeat age & the last error,
+    /// so that external monitors can check the node health without an HTTP server.
+    ///
+    /// Given by `DKN_STATUS_PATH`, not written if not given.
+    pub status_path: Option<PathBuf>,
+    /// Path of the file where the points are recorded at each refresh, so that the points earned per day & week
+    /// are known across restarts.
+    ///
+    /// Given by `DKN_POINTS_LEDGER_PATH`, kept only for the current session if not given.
+    pub points_ledger_path: Option<PathBuf>,
+    /// Path of the SQLite database where the completed & failed tasks are recorded, to be queried
+    /// via the admin API & the `history` command.
+    ///
+    /// Given by `DKN_HISTORY_PATH`, not recorded if not given.
+    pub history_path: Option<PathBuf>,
+    /// How long the tasks are kept in the history.
+    ///
+    /// Given by `DKN_HISTORY_RETENTION_DAYS`, defaults to 30 days.
+    pub history_retention: Duration,
+    /// Path of the file where the P2P metrics are written in the OpenMetrics text format
+    /// at every diagnostics refresh, e.g. for the textfile collector of a Prometheus exporter.
+    ///
+    /// Given by `DKN_METRICS_PATH`, not written if not given.
+    pub metrics_path: Option<PathBuf>,
+    /// Address to serve the node & P2P metrics at `/metrics`, to be scraped by Prometheus.
+    ///
+    /// Given by `DKN_METRICS_ADDR`, not served if not given.
+    pub metrics_addr: Option<SocketAddr>,
+    /// URL that is POSTed a JSON event when the node gets partitioned from the network
+    /// and when it recovers, e.g. for alerting.
+    ///
+    /// Given by `DKN_PARTITION_WEBHOOK`, not called if not given.
+    pub partition_webhook: Option<String>,
+    /// URL that is POSTed a JSON event for operational events, such as RPC disconnects, heartbeat failures,
+    /// task errors above the threshold and new releases.
+    ///
+    /// Given by `DKN_WEBHOOK_URL`, not called if not given.
+    pub webhook_url: Option<String>,
+    /// Number of task errors within 10 minutes that emits an event, `0` to disable.
+    ///
+    /// Given by `DKN_WEBHOOK_TASK_ERRORS`, defaults to 10.
+    pub webhook_task_errors: usize,
+    /// Prices of the models in USD per million tokens, to estimate the cost of the tokens in the daily summary.
+    ///
+    /// Given by `DKN_TOKEN_PRICES` as comma-separated `model=price` pairs, no cost is estimated if not given.
+    pub token_prices: HashMap<Model, f64>,
+    /// Address to serve the `/healthz` & `/readyz` endpoints at, e.g. for Docker or Kubernetes.
+    ///
+    /// Given by `DKN_HEALTH_ADDR`, not served if not given.
+    pub health_addr: Option<SocketAddr>,
+    /// Address to serve the local admin API at, along with the bearer token required by each request.
+    ///
+    /// Given by `DKN_ADMIN_ADDR` & `DKN_ADMIN_TOKEN`, not served if no address is given.
+    pub admin_api: Option<(SocketAddr, String)>,
+    /// Number of standby RPCs to keep connections with, so that the node can fail over
+    /// to one of them when the primary RPC drops.
+    ///
+    /// Given by `DKN_RPC_STANDBY_COUNT`, and not used with an initial RPC address.
+    pub rpc_standby_count: usize,
+    /// Whether the connections with the RPCs are kept alive regardless of the idle connection timeout,
+    /// so that they are not dropped during long idle periods.
+    ///
+    /// Given by `DKN_RPC_KEEP_ALIVE`, enabled by default.
+    pub rpc_keep_alive: bool,
+    /// NTP server to check the drift of the local clock against, so that the deadlines shared with the RPC
+    /// are compensated if the local clock is skewed.
+    ///
+    /// Given by `DKN_NTP_SERVER`, defaults to `pool.ntp.org` and can be disabled with `false`.
+    pub ntp_server: Option<String>,
+    /// Public key that the releases must be signed with, if the node updates itself to new releases.
+    /// The node restarts with the new binary once its pending tasks are completed.
+    ///
+    /// Given by `DKN_UPDATE_PUBLIC_KEY` when `DKN_AUTO_UPDATE` is `true`, disabled by default.
+    pub auto_update: Option<PublicKey>,
+    /// RPC peers whose requests are always accepted, in addition to the primary & standby RPCs,
+    /// e.g. for deployments where several RPCs serve the same node.
+    ///
+    /// Given by `DKN_AUTHORIZED_RPCS` as comma-separated peer ids.
+    pub authorized_rpcs: Vec<PeerId>,
     /// Execution platform, mainly for diagnostics.
     ///
     /// Given by `DKN_EXEC_PLATFORM`.
     pub exec_platform: String,
+    /// Quiet hours during which the node does not advertise capacity nor accept tasks.
+    ///
+    /// Given by `DKN_QUIET_HOURS` as a cron expression, see [`QuietHours`].
+    pub quiet_hours: Option<QuietHours>,
+    /// Whether to report the progress of tasks to the RPC.
+    ///
+    /// Given by `DKN_TASK_PROGRESS`, disabled by default.
+    pub task_progress: bool,
+    /// BLS key to sign the receipts of the task results with, so that the RPC can aggregate them.
+    ///
+    /// It is derived from the wallet key once, or given explicitly as the wallet key is not on this host
+    /// with a remote signer.
+    ///
+    /// Given by `DKN_BLS_RECEIPTS` along with `DKN_BLS_SECRET_KEY`, disabled by default.
+    #[cfg(feature = "bls")]
+    pub bls_secret_key: Option<dkn_utils::crypto::bls::SecretKey>,
+    /// Whether to offer end-to-end encrypted sessions to the RPC within the heartbeats, so that the payloads
+    /// of the tasks & their results are encrypted above the transport as well.
+    ///
+    /// Given by `DKN_SESSION_ENCRYPTION`, disabled by default.
+    pub session_encryption: bool,
+    /// Time-to-live of the messages of the node, which is signed along with their timestamps so that
+    /// the RPC can reject the delayed ones; the messages are signed over their payloads alone if not given.
+    ///
+    /// Given by `DKN_MESSAGE_TTL_SECS`, not given by default.
+    pub message_ttl: Option<Duration>,
+    /// Whether to reject the requests without a signed TTL, whose timestamps are not signed either.
+    ///
+    /// Given by `DKN_REQUIRE_MESSAGE_TTL`, disabled by default.
+    pub require_message_ttl: bool,
+    /// Intervals of the periodic jobs of the node, see [`NodeIntervals`].
+    pub intervals: NodeIntervals,
 }
 
 #[allow(clippy::new_without_default)]
 impl DriaComputeNodeConfig {
     /// Creates new config from environment variables.
     pub fn new(executors: DriaExecutorsManager) -> Self {
-        let secret_key = match env::var("DKN_WALLET_SECRET_KEY") {
-            Ok(secret_env) => {
-                let secret_dec = hex::decode(secret_env.trim_start_matches("0x"))
-                    .expect("Secret key should be 32-bytes hex encoded.");
-
-                // if secret key is all-zeros, create one randomly
-                // this is useful for testing & creating nodes on the fly
-                if secret_dec.iter().all(|b| b == &0) {
-                    SecretKey::random(&mut rand::thread_rng())
-                } else {
-                    SecretKey::parse_slice(&secret_dec).expect("Secret key should be parseable.")
-                }
+        // with a remote signer the wallet key is kept off this host, and only the P2P identity is local
+        let remote_signer = RemoteSigner::from_env().expect("could not parse the remote signer");
+        #[cfg(feature = "bls")]
+        let is_remote_signer = remote_signer.is_some();
+        let secret_key = if remote_signer.is_some() {
+            match env::var("DKN_P2P_SECRET_KEY") {
+                Ok(secret_env) => parse_secret_key(&secret_env),
+                Err(_) => SecretKey::random(&mut rand::thread_rng()),
             }
-            Err(err) => {
-                log::error!("No secret key provided: {err}");
-                panic!("Please provide a secret key.");
+        } else {
+            // the encrypted keystore takes precedence over the plaintext secret key
+            let keystore_secret_key =
+                secret_key_from_keystore_env().expect("could not decrypt the wallet keystore");
+            match (keystore_secret_key, env::var("DKN_WALLET_SECRET_KEY")) {
+                (Some(secret_key), _) => secret_key,
+                (None, Ok(secret_env)) => parse_secret_key(&secret_env),
+                (None, Err(err)) => {
+                    log::error!("No secret key provided: {err}");
+                    panic!("Please provide a secret key, or a keystore with DKN_KEYSTORE_PATH.");
+                }
             }
         };
         log::info!(
@@ -73,27 +362,133 @@ impl DriaComputeNodeConfig {
             ".".repeat(64)
         );
 
-        let public_key = PublicKey::from_secret_key(&secret_key);
+        let signer: Arc<dyn Signer> = match remote_signer {
+            Some(remote_signer) => {
+                log::info!("Signing with the remote signer, the peer id is not of the wallet.");
+                Arc::new(remote_signer)
+            }
+            None => Arc::new(LocalSigner::new(secret_key)),
+        };
+        let (public_key, address) = derive_address(&signer.public_key());
+        let p2p_keypair = parse_p2p_keypair(
+            &env::var("DKN_P2P_KEY_TYPE").unwrap_or_default(),
+            env::var("DKN_P2P_ED25519_SECRET_KEY").ok().as_deref(),
+            &secret_key,
+        )
+        .expect("could not parse the P2P identity");
+        let peer_id = p2p_keypair.public().to_peer_id();
+        if p2p_keypair.key_type() == KeyType::Ed25519 {
+            log::info!("Using an Ed25519 P2P identity, the wallet still signs with secp256k1.");
+        }
         log::info!(
             "Node Public Key:  0x{}",
             hex::encode(public_key.serialize_compressed())
         );
-
-        // print address
-        let address = hex::encode(public_key_to_address(&public_key));
         log::info!("Node Address:     0x{address}");
-
-        // to this here to log the peer id at start
-        let peer_id = secret_to_keypair(&secret_key).public().to_peer_id();
+        let key_rotation = env::var("DKN_KEY_ROTATION")
+            .ok()
+            .filter(|rotation| !rotation.trim().is_empty())
+            .map(|rotation| parse_key_rotation(&rotation, &address))
+            .transpose()
+            .expect("could not use the given key rotation");
+        if let Some(rotation) = &key_rotation {
+            match rotation.rotated_at + KEY_ROTATION_WINDOW > chrono::Utc::now() {
+                true => log::info!(
+                    "Announcing the rotation from 0x{} until {}",
+                    rotation.previous_address,
+                    rotation.rotated_at + KEY_ROTATION_WINDOW
+                ),
+                false => log::warn!(
+                    "The rotation from 0x{} is no longer announced, DKN_KEY_ROTATION can be removed.",
+                    rotation.previous_address
+                ),
+            }
+        }
         log::info!("Node PeerID:      {peer_id}");
 
-        // parse listen address
-        let p2p_listen_addr_str = env::var("DKN_P2P_LISTEN_ADDR")
+        // parse listen addresses
+        let p2p_listen_addrs_str = env::var("DKN_P2P_LISTEN_ADDR")
             .map(|addr| addr.trim_matches('"').to_string())
             .unwrap_or(DEFAULT_P2P_LISTEN_ADDR.to_string());
-        let p2p_listen_addr = Multiaddr::from_str(&p2p_listen_addr_str)
+        let p2p_listen_addrs = parse_addrs(&p2p_listen_addrs_str)
             .expect("could not parse the given P2P listen address.");
 
+        // parse external addresses, if any
+        let p2p_external_addrs = env::var("DKN_P2P_EXTERNAL_ADDR")
+            .ok()
+            .map(|addrs| addrs.trim_matches('"').to_string())
+            .filter(|addrs| !addrs.trim().is_empty())
+            .map(|addrs| {
+                parse_addrs(&addrs).expect("could not parse the given P2P external address.")
+            })
+            .unwrap_or_default();
+
+        // parse connection limits, where `0` means unlimited
+        let default_p2p_config = DriaP2PConfig::default();
+        let max_pending = parse_connection_limit(
+            "DKN_P2P_MAX_PENDING_CONNECTIONS",
+            default_p2p_config.max_pending_incoming,
+        );
+        let p2p_config = DriaP2PConfig {
+            max_established: parse_connection_limit(
+                "DKN_P2P_MAX_CONNECTIONS",
+                default_p2p_config.max_established,
+            ),
+            max_established_per_peer: parse_connection_limit(
+                "DKN_P2P_MAX_CONNECTIONS_PER_PEER",
+                default_p2p_config.max_established_per_peer,
+            ),
+            max_pending_incoming: max_pending,
+            max_pending_outgoing: max_pending,
+            idle_connection_timeout: env::var("DKN_P2P_IDLE_TIMEOUT_SECS")
+                .ok()
+                .and_then(|secs| secs.trim().parse::<u64>().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default_p2p_config.idle_connection_timeout),
+            blocked_peers: parse_peer_ids(&env::var("DKN_P2P_BLOCKED_PEERS").unwrap_or_default())
+                .expect("could not parse the given blocked peers."),
+            // the RPC peers are added to the allowed peers by the node itself
+            allowed_peers: env::var("DKN_P2P_RPC_ONLY")
+                .is_ok_and(|s| s == "true")
+                .then(|| {
+                    parse_peer_ids(&env::var("DKN_P2P_ALLOWED_PEERS").unwrap_or_default())
+                        .expect("could not parse the given allowed peers.")
+                }),
+            // the RPC peers are kept alive by the node itself, see `rpc_keep_alive`
+            keep_alive_peers: Vec::new(),
+            max_request_size: env::var("DKN_P2P_MAX_REQUEST_SIZE")
+                .ok()
+                .and_then(|size| size.trim().parse::<u64>().ok())
+                .unwrap_or(default_p2p_config.max_request_size),
+            max_response_size: env::var("DKN_P2P_MAX_RESPONSE_SIZE")
+                .ok()
+                .and_then(|size| size.trim().parse::<u64>().ok())
+                .unwrap_or(default_p2p_config.max_response_size),
+            request_timeout: env::var("DKN_P2P_REQUEST_TIMEOUT_SECS")
+                .ok()
+                .and_then(|secs| secs.trim().parse::<u64>().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default_p2p_config.request_timeout),
+            // the streams of the RPC peers are authorized by the node itself
+            stream_peers: Vec::new(),
+            max_inbound_streams: default_p2p_config.max_inbound_streams,
+            external_addrs: p2p_external_addrs,
+            dns_resolver: env::var("DKN_P2P_DNS")
+                .map(|s| s.parse().expect("could not parse the given DNS resolver."))
+                .unwrap_or_default(),
+            proxy: env::var("DKN_P2P_PROXY")
+                .ok()
+                .filter(|s| !s.trim().is_empty())
+                .map(|s| s.parse().expect("could not parse the given SOCKS5 proxy.")),
+            compression: env::var("DKN_P2P_COMPRESSION")
+                .map(|s| s.trim() != "false")
+                .unwrap_or(default_p2p_config.compression),
+            relay_server: env::var("DKN_P2P_RELAY_SERVER").is_ok_and(|s| s.trim() == "true"),
+            gossip_peer_score: env::var("DKN_P2P_GOSSIP_PEER_SCORE")
+                .is_ok_and(|s| s.trim() == "true")
+                .then(DriaGossipPeerScore::default),
+        };
+
         // parse network type
         let network_type = env::var("DKN_NETWORK")
             // if there is an explicit value, default to testnet on error
@@ -104,10 +499,44 @@ impl DriaComputeNodeConfig {
             log::warn!("Using testnet network!");
         }
 
+        // parse protocol of a private network, if any
+        let protocol = env::var("DKN_P2P_PROTOCOL")
+            .ok()
+            .filter(|protocol| !protocol.trim().is_empty())
+            .map(|protocol| {
+                protocol
+                    .parse::<DriaP2PProtocol>()
+                    .expect("could not parse the given P2P protocol.")
+            });
+
         // parse batch size
-        let batch_size = env::var("DKN_BATCH_SIZE")
-            .map(|s| s.parse::<usize>().unwrap_or(DEFAULT_TASK_BATCH_SIZE))
-            .unwrap_or(DEFAULT_TASK_BATCH_SIZE);
+        let batch_size = parse_batch_size();
+        let provider_batch_sizes = provider_batch_sizes_from_env()
+            .expect("could not parse the given provider batch sizes.");
+
+        // parse pending tasks high-water mark
+        let pending_high_water_mark = env::var("DKN_PENDING_HIGH_WATER_MARK")
+            .map(|s| {
+                s.parse::<usize>()
+                    .unwrap_or(DEFAULT_PENDING_HIGH_WATER_MARK)
+            })
+            .unwrap_or(DEFAULT_PENDING_HIGH_WATER_MARK);
+
+        // parse maximum pending tasks, if any
+        let max_pending_tasks = env::var("DKN_MAX_PENDING_TASKS")
+            .ok()
+            .filter(|max| !max.trim().is_empty())
+            .map(|max| {
+                let max = max
+                    .trim()
+                    .parse::<usize>()
+                    .expect("could not parse the given maximum pending tasks.");
+                assert!(max > 0, "DKN_MAX_PENDING_TASKS must be positive.");
+                max
+            });
+        if max_pending_tasks.is_some() && env::var("DKN_PENDING_HIGH_WATER_MARK").is_ok() {
+            log::warn!("DKN_MAX_PENDING_TASKS is given, DKN_PENDING_HIGH_WATER_MARK is ignored.");
+        }
 
         // parse version
         let version = env!("CARGO_PKG_VERSION")
@@ -122,26 +551,459 @@ impl DriaComputeNodeConfig {
                 Multiaddr::from_str(&addr).expect("could not parse the given initial RPC address.")
             });
 
+        // parse pinned rpc address, if any
+        let pinned_rpc_addr = env::var("DKN_RPC_ADDR")
+            .ok()
+            .filter(|addr| !addr.trim().is_empty())
+            .map(|addr| {
+                let peer_id = env::var("DKN_RPC_PEER_ID").ok();
+                parse_rpc_addr(
+                    addr.trim(),
+                    peer_id
+                        .as_deref()
+                        .map(str::trim)
+                        .filter(|id| !id.is_empty()),
+                )
+                .expect("could not parse the given RPC address.")
+            });
+
+        // parse discovery url, if any
+        let discovery_url = parse_discovery_url();
+
+        // parse peer store path, if any
+        let peer_store_path = env::var("DKN_PEER_STORE_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| PathBuf::from(path.trim()));
+
+        // parse state path, if any
+        let state_path = env::var("DKN_STATE_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| PathBuf::from(path.trim()));
+
+        // parse lifetime stats path, if any
+        let stats_path = env::var("DKN_STATS_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| PathBuf::from(path.trim()));
+
+        // parse task journal path, if any
+        let journal_path = env::var("DKN_JOURNAL_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| PathBuf::from(path.trim()));
+
+        // parse status path, if any
+        let status_path = env::var("DKN_STATUS_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| PathBuf::from(path.trim()));
+
+        // parse points ledger path, if any
+        let points_ledger_path = env::var("DKN_POINTS_LEDGER_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| PathBuf::from(path.trim()));
+
+        // parse task history path, if any
+        let history_path = env::var("DKN_HISTORY_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| PathBuf::from(path.trim()));
+
+        // parse task history retention
+        let history_retention = Duration::from_secs(
+            env::var("DKN_HISTORY_RETENTION_DAYS")
+                .ok()
+                .and_then(|days| days.trim().parse::<u64>().ok())
+                .unwrap_or(DEFAULT_HISTORY_RETENTION_DAYS)
+                * 24
+                * 60
+                * 60,
+        );
+
+        // parse metrics path, if any
+        let metrics_path = env::var("DKN_METRICS_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| PathBuf::from(path.trim()));
+
+        // parse metrics server address, if any
+        let metrics_addr = env::var("DKN_METRICS_ADDR")
+            .ok()
+            .filter(|addr| !addr.trim().is_empty())
+            .map(|addr| {
+                addr.trim()
+                    .parse()
+                    .expect("could not parse the given metrics address.")
+            });
+
+        // parse partition webhook, if any
+        let partition_webhook = env::var("DKN_PARTITION_WEBHOOK")
+            .ok()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty());
+
+        // parse operational events webhook, if any
+        let webhook_url = env::var("DKN_WEBHOOK_URL")
+            .ok()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty());
+        let webhook_task_errors = env::var("DKN_WEBHOOK_TASK_ERRORS")
+            .ok()
+            .filter(|count| !count.trim().is_empty())
+            .map(|count| {
+                count
+                    .trim()
+                    .parse()
+                    .expect("could not parse the given task error threshold.")
+            })
+            .unwrap_or(DEFAULT_WEBHOOK_TASK_ERRORS);
+
+        // parse token prices, if any
+        let token_prices = env::var("DKN_TOKEN_PRICES")
+            .map(|prices| {
+                parse_token_prices(&prices).expect("could not parse the given token prices.")
+            })
+            .unwrap_or_default();
+
+        // parse health server address, if any
+        let health_addr = env::var("DKN_HEALTH_ADDR")
+            .ok()
+            .filter(|addr| !addr.trim().is_empty())
+            .map(|addr| {
+                addr.trim()
+                    .parse()
+                    .expect("could not parse the given health address.")
+            });
+
+        // parse admin API address & token, the token is required so that the API is never open
+        let admin_api = env::var("DKN_ADMIN_ADDR")
+            .ok()
+            .filter(|addr| !addr.trim().is_empty())
+            .map(|addr| {
+                let addr = addr
+                    .trim()
+                    .parse()
+                    .expect("could not parse the given admin address.");
+                let token = env::var("DKN_ADMIN_TOKEN")
+                    .ok()
+                    .map(|token| token.trim().to_string())
+                    .filter(|token| !token.is_empty())
+                    .expect("DKN_ADMIN_TOKEN must be set to serve the admin API.");
+                (addr, token)
+            });
+
+        // parse standby RPC count
+        let rpc_standby_count = env::var("DKN_RPC_STANDBY_COUNT")
+            .map(|s| s.parse::<usize>().unwrap_or(DEFAULT_RPC_STANDBY_COUNT))
+            .unwrap_or(DEFAULT_RPC_STANDBY_COUNT);
+
+        // RPC connections are kept alive unless disabled explicitly
+        let rpc_keep_alive = env::var("DKN_RPC_KEEP_ALIVE").map_or(true, |s| s.trim() != "false");
+
+        // parse NTP server, the clock drift is checked unless disabled explicitly
+        let ntp_server = match env::var("DKN_NTP_SERVER") {
+            Ok(server) if server.trim() == "false" => None,
+            Ok(server) if !server.trim().is_empty() => Some(server.trim().to_string()),
+            _ => Some(DEFAULT_NTP_SERVER.to_string()),
+        };
+
+        // parse the release signing key, if auto-update is enabled
+        let auto_update = env::var("DKN_AUTO_UPDATE")
+            .is_ok_and(|s| s.trim() == "true")
+            .then(|| {
+                let public_key = env::var("DKN_UPDATE_PUBLIC_KEY")
+                    .expect("DKN_UPDATE_PUBLIC_KEY must be given for auto-update.");
+                let public_key = hex::decode(public_key.trim().trim_start_matches("0x"))
+                    .expect("could not decode the given update public key.");
+                PublicKey::parse_slice(&public_key, None)
+                    .expect("could not parse the given update public key.")
+            });
+
+        // parse the explicitly authorized RPCs, if any
+        let authorized_rpcs = parse_peer_ids(&env::var("DKN_AUTHORIZED_RPCS").unwrap_or_default())
+            .expect("could not parse the given authorized RPCs.");
+
         // parse execution platform
         let exec_platform = env::var("DKN_EXEC_PLATFORM").unwrap_or_else(|_| "unknown".to_string());
 
+        // parse quiet hours, if any
+        let quiet_hours = env::var("DKN_QUIET_HOURS")
+            .ok()
+            .filter(|hours| !hours.trim().is_empty())
+            .map(|hours| {
+                QuietHours::from_str(hours.trim_matches('"'))
+                    .expect("could not parse the given quiet hours.")
+            });
+
+        // parse task progress reporting
+        let task_progress = env::var("DKN_TASK_PROGRESS")
+            .map(|s| s == "true")
+            .unwrap_or(false);
+
+        // parse BLS signing of the task receipts
+        let bls_receipts = env::var("DKN_BLS_RECEIPTS")
+            .map(|s| s == "true")
+            .unwrap_or(false);
+        assert!(
+            !bls_receipts || cfg!(feature = "bls"),
+            "DKN_BLS_RECEIPTS requires the node to be built with the `bls` feature."
+        );
+        // the secret key is only of the P2P identity with a remote signer, so the BLS key must be given
+        #[cfg(feature = "bls")]
+        let bls_secret_key = bls_receipts.then(|| match env::var("DKN_BLS_SECRET_KEY") {
+            Ok(bls_env) => dkn_utils::crypto::bls::parse_bls_secret_key(&bls_env)
+                .expect("could not parse DKN_BLS_SECRET_KEY"),
+            Err(_) => {
+                assert!(
+                    !is_remote_signer,
+                    "DKN_BLS_RECEIPTS requires DKN_BLS_SECRET_KEY with a remote signer."
+                );
+                dkn_utils::crypto::bls::bls_secret_key(&secret_key)
+            }
+        });
+
+        // parse end-to-end session encryption
+        let session_encryption = env::var("DKN_SESSION_ENCRYPTION")
+            .map(|s| s == "true")
+            .unwrap_or(false);
+
+        // parse the TTL of the messages, if any
+        let message_ttl = env::var("DKN_MESSAGE_TTL_SECS")
+            .ok()
+            .filter(|secs| !secs.trim().is_empty())
+            .map(|secs| {
+                secs.trim()
+                    .parse::<u64>()
+                    .expect("DKN_MESSAGE_TTL_SECS must be a number of seconds")
+            })
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs);
+
+        // parse whether the requests must have a signed TTL
+        let require_message_ttl = env::var("DKN_REQUIRE_MESSAGE_TTL")
+            .map(|s| s == "true")
+            .unwrap_or(false);
+
+        // parse the intervals of the main loop
+        let default_intervals = NodeIntervals::default();
+        let intervals = NodeIntervals {
+            diagnostic_refresh: parse_interval(
+                "DKN_DIAGNOSTIC_REFRESH_SECS",
+                default_intervals.diagnostic_refresh,
+            ),
+            specs: parse_interval("DKN_SPECS_INTERVAL_SECS", default_intervals.specs),
+            rpc_liveness_refresh: parse_interval(
+                "DKN_RPC_LIVENESS_REFRESH_SECS",
+                default_intervals.rpc_liveness_refresh,
+            ),
+            points_refresh: parse_interval(
+                "DKN_POINTS_REFRESH_SECS",
+                default_intervals.points_refresh,
+            ),
+            heartbeat_deadline: parse_interval(
+                "DKN_HEARTBEAT_DEADLINE_SECS",
+                default_intervals.heartbeat_deadline,
+            ),
+            heartbeat_liveness: parse_interval(
+                "DKN_HEARTBEAT_LIVENESS_SECS",
+                default_intervals.heartbeat_liveness,
+            ),
+        };
+        intervals
+            .check_heartbeat_bounds()
+            .expect("could not use the given heartbeat intervals.");
+
         Self {
             secret_key,
+            p2p_keypair,
+            signer,
             public_key,
             address,
+            key_rotation,
             peer_id,
             version,
             executors,
-            p2p_listen_addr,
+            p2p_listen_addrs,
+            p2p_config,
             network: network_type,
+            protocol,
             batch_size,
+            provider_batch_sizes,
+            pending_high_water_mark,
+            max_pending_tasks,
             initial_rpc_addr,
+            pinned_rpc_addr,
+            discovery_url,
+            peer_store_path,
+            state_path,
+            stats_path,
+            journal_path,
+            status_path,
+            points_ledger_path,
+            history_path,
+            history_retention,
+            metrics_path,
+            metrics_addr,
+            partition_webhook,
+            webhook_url,
+            webhook_task_errors,
+            token_prices,
+            health_addr,
+            admin_api,
+            rpc_standby_count,
+            rpc_keep_alive,
+            ntp_server,
+            auto_update,
+            authorized_rpcs,
             exec_platform,
+            quiet_hours,
+            task_progress,
+            #[cfg(feature = "bls")]
+            bls_secret_key,
+            session_encryption,
+            message_ttl,
+            require_message_ttl,
+            intervals,
+        }
+    }
+
+    /// Creates a new config with the given secret key & executors, without looking at the environment variables.
+    ///
+    /// Everything else is set to its default, i.e. the node listens on `DKN_P2P_LISTEN_ADDR`'s default
+    /// on mainnet, with the optional servers, webhooks & persistence disabled.
+    pub fn with_defaults(secret_key: SecretKey, executors: DriaExecutorsManager) -> Self {
+        let (public_key, address, peer_id) = derive_identity(&secret_key);
+
+        Self {
+            secret_key,
+            p2p_keypair: secret_to_keypair(&secret_key),
+            signer: Arc::new(LocalSigner::new(secret_key)),
+            public_key,
+            address,
+            key_rotation: None,
+            peer_id,
+            version: env!("CARGO_PKG_VERSION")
+                .parse()
+                .expect("could not parse version"),
+            executors,
+            p2p_listen_addrs: vec![DEFAULT_P2P_LISTEN_ADDR
+                .parse()
+                .expect("default listen address is valid")],
+            p2p_config: DriaP2PConfig::default(),
+            network: DriaNetwork::Mainnet,
+            protocol: None,
+            batch_size: DEFAULT_TASK_BATCH_SIZE,
+            provider_batch_sizes: HashMap::new(),
+            pending_high_water_mark: DEFAULT_PENDING_HIGH_WATER_MARK,
+            max_pending_tasks: None,
+            initial_rpc_addr: None,
+            pinned_rpc_addr: None,
+            discovery_url: None,
+            peer_store_path: None,
+            state_path: None,
+            stats_path: None,
+            journal_path: None,
+            status_path: None,
+            points_ledger_path: None,
+            history_path: None,
+            history_retention: Duration::from_secs(DEFAULT_HISTORY_RETENTION_DAYS * 24 * 60 * 60),
+            metrics_path: None,
+            metrics_addr: None,
+            partition_webhook: None,
+            webhook_url: None,
+            webhook_task_errors: DEFAULT_WEBHOOK_TASK_ERRORS,
+            token_prices: HashMap::new(),
+            health_addr: None,
+            admin_api: None,
+            rpc_standby_count: DEFAULT_RPC_STANDBY_COUNT,
+            rpc_keep_alive: true,
+            ntp_server: Some(DEFAULT_NTP_SERVER.to_string()),
+            auto_update: None,
+            authorized_rpcs: Vec::new(),
+            exec_platform: "unknown".to_string(),
+            quiet_hours: None,
+            task_progress: false,
+            #[cfg(feature = "bls")]
+            bls_secret_key: None,
+            session_encryption: false,
+            message_ttl: None,
+            require_message_ttl: false,
+            intervals: NodeIntervals::default(),
+        }
+    }
+
+    /// Sets the wallet secret key, along with the signer, public key, address & P2P identity derived from it.
+    ///
+    /// The key type of the P2P identity is kept, where an Ed25519 identity is derived from the new secret key
+    /// even if it was given separately, so that it is not shared with the previous one; the same goes for the
+    /// BLS key if the receipts are signed.
+    pub fn set_secret_key(&mut self, secret_key: SecretKey) {
+        let (public_key, address) = derive_address(&PublicKey::from_secret_key(&secret_key));
+        self.secret_key = secret_key;
+        self.p2p_keypair = match self.p2p_keypair.key_type() {
+            KeyType::Ed25519 => secret_to_ed25519_keypair(&secret_key),
+            _ => secret_to_keypair(&secret_key),
+        };
+        self.signer = Arc::new(LocalSigner::new(secret_key));
+        self.public_key = public_key;
+        self.address = address;
+        self.peer_id = self.p2p_keypair.public().to_peer_id();
+        #[cfg(feature = "bls")]
+        if self.bls_secret_key.is_some() {
+            self.bls_secret_key = Some(dkn_utils::crypto::bls::bls_secret_key(&secret_key));
         }
+        self.retain_key_rotation();
     }
 
-    /// Asserts that the configured listen address is free.
-    /// Throws an error if the address is already in use.
+    /// Sets the signer of the wallet, along with the public key & address derived from it.
+    ///
+    /// The P2P identity is kept as is.
+    pub fn set_signer(&mut self, signer: Arc<dyn Signer>) {
+        let (public_key, address) = derive_address(&signer.public_key());
+        self.signer = signer;
+        self.public_key = public_key;
+        self.address = address;
+        self.retain_key_rotation();
+    }
+
+    /// Drops the key rotation if it is not to the current wallet, e.g. once the wallet is changed.
+    fn retain_key_rotation(&mut self) {
+        self.key_rotation = self
+            .key_rotation
+            .take()
+            .filter(|rotation| rotation.new_address == self.address);
+    }
+
+    /// Returns the error to reject a new task with at the given number of pending tasks, if the node is at
+    /// its capacity.
+    ///
+    /// The capacity is the maximum pending tasks if given, which can not be exceeded at all, and the high-water
+    /// mark otherwise, where the task can be reassigned.
+    pub fn pending_capacity_error(&self, pending: usize) -> Option<TaskError> {
+        match self.max_pending_tasks {
+            Some(max_pending) => (pending >= max_pending).then_some(TaskError::CapacityExceeded {
+                pending,
+                max_pending,
+            }),
+            None => (pending >= self.pending_high_water_mark).then_some(TaskError::Overloaded {
+                pending,
+                high_water_mark: self.pending_high_water_mark,
+            }),
+        }
+    }
+
+    /// Returns the key rotation to announce at the given time, if it is within [`KEY_ROTATION_WINDOW`].
+    pub fn active_key_rotation(&self, now: chrono::DateTime<chrono::Utc>) -> Option<&KeyRotation> {
+        self.key_rotation
+            .as_ref()
+            .filter(|rotation| now < rotation.rotated_at + KEY_ROTATION_WINDOW)
+    }
+
+    /// Asserts that the configured listen addresses are free.
+    /// Throws an error if any address is already in use.
     ///
     /// Uses `is_port_reachable` function internally, which makes a simple
     /// TCP connection to the given address.
@@ -149,35 +1011,498 @@ impl DriaComputeNodeConfig {
     /// Can be inlined because the function is small and called only once.
     #[inline]
     pub fn assert_address_not_in_use(&self) -> Result<()> {
-        use dkn_p2p::libp2p::multiaddr::Protocol;
         use port_check::is_port_reachable;
-        use std::net::{Ipv4Addr, SocketAddrV4};
-
-        let address_in_use = self
-            .p2p_listen_addr
-            .iter()
-            // find the port within our multiaddr
-            .find_map(|protocol| match protocol {
-                Protocol::Tcp(port) => Some(port),
-                _ => None,
-            })
-            // check if its reachable or not
-            .map(|port| is_port_reachable(SocketAddrV4::new(Ipv4Addr::LOCALHOST, port)))
-            .unwrap_or_else(|| {
-                log::error!(
-                    "could not find any TCP port in the given address: {:?}",
-                    self.p2p_listen_addr
-                );
-                false
-            });
 
-        if address_in_use {
+        for listen_addr in &self.p2p_listen_addrs {
+            let address_in_use = listen_socket_addr(listen_addr)
+                // check if its reachable or not
+                .map(is_port_reachable)
+                .unwrap_or_else(|| {
+                    log::error!(
+                        "could not find any TCP port in the given address: {listen_addr:?}"
+                    );
+                    false
+                });
+
+            if address_in_use {
+                return Err(eyre!("Listen address {listen_addr} is already in use."));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the socket address to check whether the given listen address is in use.
+///
+/// Unspecified addresses (`0.0.0.0` or `::`) are checked on the loopback address of the same family,
+/// and addresses without an IP (e.g. `/dns`) are checked on IPv4 loopback.
+fn listen_socket_addr(listen_addr: &Multiaddr) -> Option<SocketAddr> {
+    use dkn_p2p::libp2p::multiaddr::Protocol;
+
+    let mut ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let mut port = None;
+    for protocol in listen_addr.iter() {
+        match protocol {
+            Protocol::Ip4(addr) if addr.is_unspecified() => ip = Ipv4Addr::LOCALHOST.into(),
+            Protocol::Ip4(addr) => ip = addr.into(),
+            Protocol::Ip6(addr) if addr.is_unspecified() => ip = Ipv6Addr::LOCALHOST.into(),
+            Protocol::Ip6(addr) => ip = addr.into(),
+            Protocol::Tcp(tcp_port) => port = Some(tcp_port),
+            _ => {}
+        }
+    }
+
+    port.map(|port| SocketAddr::new(ip, port))
+}
+
+/// Returns the public key, the address (hex without `0x`) & the peer id of the given secret key.
+pub(crate) fn derive_identity(secret_key: &SecretKey) -> (PublicKey, String, PeerId) {
+    let (public_key, address) = derive_address(&PublicKey::from_secret_key(secret_key));
+    (public_key, address, derive_peer_id(secret_key))
+}
+
+/// Returns the public key along with its address in hex without `0x` prefix.
+fn derive_address(public_key: &PublicKey) -> (PublicKey, String) {
+    (*public_key, hex::encode(public_key_to_address(public_key)))
+}
+
+/// Returns the peer id of the P2P identity of the given secret key.
+fn derive_peer_id(secret_key: &SecretKey) -> PeerId {
+    secret_to_keypair(secret_key).public().to_peer_id()
+}
+
+/// Returns the P2P identity of the given key type (`secp256k1` by default, or `ed25519`) for the secret key,
+/// where an Ed25519 identity is derived from the secret key unless its own hex encoded secret key is given.
+fn parse_p2p_keypair(
+    key_type: &str,
+    ed25519_secret_key: Option<&str>,
+    secret_key: &SecretKey,
+) -> Result<Keypair> {
+    let ed25519_secret_key = ed25519_secret_key
+        .map(str::trim)
+        .filter(|secret_key| !secret_key.is_empty());
+
+    match key_type.trim().to_lowercase().as_str() {
+        "" | "secp256k1" => match ed25519_secret_key {
+            Some(_) => Err(eyre!(
+                "DKN_P2P_ED25519_SECRET_KEY is given but DKN_P2P_KEY_TYPE is not ed25519"
+            )),
+            None => Ok(secret_to_keypair(secret_key)),
+        },
+        "ed25519" => match ed25519_secret_key {
+            Some(ed25519_secret_key) => {
+                let mut bytes = hex::decode(ed25519_secret_key.trim_start_matches("0x"))
+                    .wrap_err("DKN_P2P_ED25519_SECRET_KEY is not hex encoded")?;
+                Keypair::ed25519_from_bytes(&mut bytes)
+                    .wrap_err("DKN_P2P_ED25519_SECRET_KEY is not a 32-byte secret key")
+            }
+            None => Ok(secret_to_ed25519_keypair(secret_key)),
+        },
+        other => Err(eyre!(
+            "unknown DKN_P2P_KEY_TYPE {other}, expected secp256k1 or ed25519"
+        )),
+    }
+}
+
+/// Parses the key rotation as printed by the `rotate-key` command, which must be signed by its previous wallet
+/// and be to the given address (hex without `0x` prefix).
+fn parse_key_rotation(rotation: &str, address: &str) -> Result<KeyRotation> {
+    let rotation: KeyRotation =
+        serde_json::from_str(rotation.trim()).wrap_err("DKN_KEY_ROTATION is not valid JSON")?;
+    if !verify_key_rotation(&rotation) {
+        eyre::bail!(
+            "DKN_KEY_ROTATION is not signed by its previous wallet 0x{}",
+            rotation.previous_address
+        );
+    }
+    if rotation.new_address != address {
+        eyre::bail!(
+            "DKN_KEY_ROTATION is to 0x{} but the wallet is 0x{address}",
+            rotation.new_address
+        );
+    }
+
+    Ok(rotation)
+}
+
+/// Parses a hex encoded secret key, where an all-zeros key creates one randomly.
+fn parse_secret_key(secret_env: &str) -> SecretKey {
+    let secret_dec = hex::decode(secret_env.trim().trim_start_matches("0x"))
+        .expect("Secret key should be 32-bytes hex encoded.");
+
+    // if secret key is all-zeros, create one randomly
+    // this is useful for testing & creating nodes on the fly
+    if secret_dec.iter().all(|b| b == &0) {
+        SecretKey::random(&mut rand::thread_rng())
+    } else {
+        SecretKey::parse_slice(&secret_dec).expect("Secret key should be parseable.")
+    }
+}
+
+/// Parses the discovery API URL from `DKN_DISCOVERY_URL`, if given.
+pub(crate) fn parse_discovery_url() -> Option<String> {
+    env::var("DKN_DISCOVERY_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+        .map(|url| {
+            let url = url.trim().trim_end_matches('/');
+            url::Url::parse(url).expect("could not parse the given discovery URL.");
+            url.to_string()
+        })
+}
+
+/// Parses the batch size from `DKN_BATCH_SIZE`, with a default value if it is not given or invalid.
+pub(crate) fn parse_batch_size() -> usize {
+    env::var("DKN_BATCH_SIZE")
+        .map(|s| s.parse::<usize>().unwrap_or(DEFAULT_TASK_BATCH_SIZE))
+        .unwrap_or(DEFAULT_TASK_BATCH_SIZE)
+}
+
+/// Parses the batch sizes of the providers from `DKN_PROVIDER_BATCH_SIZES`, empty if it is not given.
+pub(crate) fn provider_batch_sizes_from_env() -> Result<HashMap<ModelProvider, usize>> {
+    env::var("DKN_PROVIDER_BATCH_SIZES")
+        .map(|sizes| parse_provider_batch_sizes(&sizes))
+        .unwrap_or_else(|_| Ok(HashMap::new()))
+}
+
+/// Parses an interval in seconds from the given environment variable, which must be positive.
+fn parse_interval(var: &str, default: Duration) -> Duration {
+    env::var(var)
+        .ok()
+        .filter(|secs| !secs.trim().is_empty())
+        .map(|secs| {
+            let secs = secs
+                .trim()
+                .parse::<u64>()
+                .unwrap_or_else(|_| panic!("could not parse the given {var}."));
+            assert!(secs > 0, "{var} must be positive.");
+            Duration::from_secs(secs)
+        })
+        .unwrap_or(default)
+}
+
+/// Parses a connection limit from the given environment variable, where `0` means unlimited.
+///
+/// Returns the default limit if the variable is not set or invalid.
+fn parse_connection_limit(var: &str, default: Option<u32>) -> Option<u32> {
+    match env::var(var).ok().map(|limit| limit.trim().parse::<u32>()) {
+        Some(Ok(0)) => None,
+        Some(Ok(limit)) => Some(limit),
+        Some(Err(_)) | None => default,
+    }
+}
+
+/// Parses an RPC address, appending the given peer id to it if it does not have one.
+///
+/// Returns an error if the address has a peer id that is different than the given one.
+fn parse_rpc_addr(addr: &str, peer_id: Option<&str>) -> Result<Multiaddr> {
+    use dkn_p2p::libp2p::multiaddr::Protocol;
+
+    let mut addr = Multiaddr::from_str(addr).wrap_err_with(|| format!("invalid address {addr}"))?;
+    let Some(peer_id) = peer_id else {
+        return Ok(addr);
+    };
+
+    let peer_id =
+        PeerId::from_str(peer_id).wrap_err_with(|| format!("invalid peer id {peer_id}"))?;
+    match addr.iter().find_map(|p| match p {
+        Protocol::P2p(addr_peer_id) => Some(addr_peer_id),
+        _ => None,
+    }) {
+        Some(addr_peer_id) if addr_peer_id != peer_id => {
             return Err(eyre!(
-                "Listen address {} is already in use.",
-                self.p2p_listen_addr
+                "address has peer id {addr_peer_id} but {peer_id} was given"
             ));
         }
+        Some(_) => {}
+        None => addr.push(Protocol::P2p(peer_id)),
+    }
 
-        Ok(())
+    Ok(addr)
+}
+
+/// Parses a comma-separated list of peer ids, ignoring empty entries.
+fn parse_peer_ids(peer_ids: &str) -> Result<Vec<PeerId>> {
+    peer_ids
+        .split(',')
+        .map(str::trim)
+        .filter(|peer_id| !peer_id.is_empty())
+        .map(|peer_id| {
+            PeerId::from_str(peer_id).wrap_err_with(|| format!("invalid peer id {peer_id}"))
+        })
+        .collect()
+}
+
+/// Parses a comma-separated list of `model=price` pairs, ignoring empty entries.
+fn parse_token_prices(prices: &str) -> Result<HashMap<Model, f64>> {
+    prices
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (model, price) = entry
+                .rsplit_once('=')
+                .ok_or_else(|| eyre!("expected model=price, got {entry}"))?;
+            let model =
+                Model::try_from(model.trim()).map_err(|model| eyre!("unknown model {model}"))?;
+            let price = price
+                .trim()
+                .parse::<f64>()
+                .wrap_err_with(|| format!("invalid price for {model}"))?;
+            Ok((model, price))
+        })
+        .collect()
+}
+
+/// Parses a comma-separated list of `provider=size` pairs, ignoring empty entries.
+fn parse_provider_batch_sizes(sizes: &str) -> Result<HashMap<ModelProvider, usize>> {
+    sizes
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (provider, size) = entry
+                .split_once('=')
+                .ok_or_else(|| eyre!("expected provider=size, got {entry}"))?;
+            let provider = ModelProvider::from_str(&provider.trim().to_lowercase())
+                .map_err(|err| eyre!(err))?;
+            let size = size
+                .trim()
+                .parse::<usize>()
+                .wrap_err_with(|| format!("invalid batch size for {provider}"))?;
+            if size == 0 || size > TaskWorker::MAX_BATCH_SIZE {
+                eyre::bail!(
+                    "batch size of {provider} must be between 1 and {}",
+                    TaskWorker::MAX_BATCH_SIZE
+                );
+            }
+            Ok((provider, size))
+        })
+        .collect()
+}
+
+/// Parses a comma-separated list of addresses, ignoring empty entries.
+pub(crate) fn parse_addrs(addrs: &str) -> Result<Vec<Multiaddr>> {
+    let addrs = addrs
+        .split(',')
+        .map(str::trim)
+        .filter(|addr| !addr.is_empty())
+        .map(|addr| Multiaddr::from_str(addr).wrap_err_with(|| format!("invalid address {addr}")))
+        .collect::<Result<Vec<_>>>()?;
+
+    if addrs.is_empty() {
+        return Err(eyre!("no address given"));
+    }
+
+    Ok(addrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_addrs() {
+        let addrs = parse_addrs("/ip4/0.0.0.0/tcp/4001, /ip6/::/tcp/4001,").unwrap();
+        assert_eq!(
+            addrs,
+            vec![
+                Multiaddr::from_str("/ip4/0.0.0.0/tcp/4001").unwrap(),
+                Multiaddr::from_str("/ip6/::/tcp/4001").unwrap()
+            ]
+        );
+
+        assert!(parse_addrs("").is_err());
+        assert!(parse_addrs("/ip4/0.0.0.0/tcp/4001,not-an-address").is_err());
+    }
+
+    #[test]
+    fn test_parse_rpc_addr() {
+        let peer_id = "16Uiu2HAmB5HGdwLNHX81u7ey1fvDx5Mr4ofa2PdSSVxFKrrcErAN";
+        let expected =
+            Multiaddr::from_str(&format!("/ip4/12.34.56.78/tcp/4001/p2p/{peer_id}")).unwrap();
+
+        assert_eq!(
+            parse_rpc_addr("/ip4/12.34.56.78/tcp/4001", Some(peer_id)).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_rpc_addr(&expected.to_string(), Some(peer_id)).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_rpc_addr(&expected.to_string(), None).unwrap(),
+            expected
+        );
+        assert!(parse_rpc_addr(
+            &expected.to_string(),
+            Some("16Uiu2HAmG7qrpSh8kenjuYqyrwxgEVdzqRV4wM1hHAZRq4j25VBC")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_peer_ids() {
+        let peer_id = "16Uiu2HAmB5HGdwLNHX81u7ey1fvDx5Mr4ofa2PdSSVxFKrrcErAN";
+        assert_eq!(
+            parse_peer_ids(&format!("{peer_id}, ")).unwrap(),
+            vec![PeerId::from_str(peer_id).unwrap()]
+        );
+        assert!(parse_peer_ids("").unwrap().is_empty());
+        assert!(parse_peer_ids("not-a-peer-id").is_err());
+    }
+
+    #[test]
+    fn test_heartbeat_bounds() {
+        let intervals = NodeIntervals::default();
+        assert!(intervals.check_heartbeat_bounds().is_ok());
+
+        // a test network with shorter heartbeats
+        let intervals = NodeIntervals {
+            heartbeat_deadline: Duration::from_secs(15),
+            heartbeat_liveness: Duration::from_secs(30),
+            ..Default::default()
+        };
+        assert!(intervals.check_heartbeat_bounds().is_ok());
+
+        let intervals = NodeIntervals {
+            heartbeat_deadline: Duration::from_secs(5),
+            ..Default::default()
+        };
+        assert!(intervals.check_heartbeat_bounds().is_err());
+
+        // liveness must cover at least two heartbeats
+        let intervals = NodeIntervals {
+            heartbeat_deadline: Duration::from_secs(180),
+            ..Default::default()
+        };
+        assert!(intervals.check_heartbeat_bounds().is_err());
+    }
+
+    #[test]
+    fn test_parse_key_rotation() {
+        let previous = SecretKey::parse_slice(b"driadriadriadriadriadriadriadria").unwrap();
+        let secret_key = SecretKey::parse(&[1u8; 32]).unwrap();
+        let (_, address, _) = derive_identity(&secret_key);
+        let rotated_at = chrono::Utc::now() - chrono::Duration::days(1);
+        let rotation = dkn_utils::crypto::sign_key_rotation(&previous, &address, rotated_at);
+        let json = serde_json::to_string(&rotation).unwrap();
+
+        let mut config = DriaComputeNodeConfig::with_defaults(secret_key, Default::default());
+        config.key_rotation = Some(parse_key_rotation(&json, &address).unwrap());
+        assert!(config.active_key_rotation(chrono::Utc::now()).is_some());
+        assert!(config
+            .active_key_rotation(rotated_at + KEY_ROTATION_WINDOW)
+            .is_none());
+
+        // only to the wallet of the node
+        assert!(parse_key_rotation(&json, &"11".repeat(20)).is_err());
+        config.set_secret_key(previous);
+        assert!(config.key_rotation.is_none());
+
+        let forged = json.replace(&rotation.previous_address, &"22".repeat(20));
+        assert!(parse_key_rotation(&forged, &address).is_err());
+    }
+
+    #[test]
+    fn test_parse_p2p_keypair() {
+        let secret_key = SecretKey::parse_slice(b"driadriadriadriadriadriadriadria").unwrap();
+        let secp256k1 = parse_p2p_keypair("", None, &secret_key).unwrap();
+        assert_eq!(secp256k1.key_type(), KeyType::Secp256k1);
+        assert_eq!(secp256k1.public().to_peer_id(), derive_peer_id(&secret_key));
+
+        // derived from the wallet, deterministically
+        let derived = parse_p2p_keypair("Ed25519", None, &secret_key).unwrap();
+        assert_eq!(derived.key_type(), KeyType::Ed25519);
+        assert_eq!(
+            derived.public(),
+            parse_p2p_keypair("ed25519", Some(" "), &secret_key)
+                .unwrap()
+                .public()
+        );
+        assert_ne!(derived.public(), secp256k1.public());
+
+        // given separately
+        let separate = parse_p2p_keypair("ed25519", Some(&"11".repeat(32)), &secret_key).unwrap();
+        assert_eq!(separate.key_type(), KeyType::Ed25519);
+        assert_ne!(separate.public(), derived.public());
+
+        assert!(parse_p2p_keypair("ed25519", Some("1111"), &secret_key).is_err());
+        assert!(parse_p2p_keypair("secp256k1", Some(&"11".repeat(32)), &secret_key).is_err());
+        assert!(parse_p2p_keypair("rsa", None, &secret_key).is_err());
+    }
+
+    #[test]
+    fn test_parse_provider_batch_sizes() {
+        assert_eq!(
+            parse_provider_batch_sizes("Wasm=3, external=1,").unwrap(),
+            HashMap::from([(ModelProvider::Wasm, 3), (ModelProvider::External, 1)])
+        );
+        assert!(parse_provider_batch_sizes("").unwrap().is_empty());
+        assert!(parse_provider_batch_sizes("wasm").is_err());
+        assert!(parse_provider_batch_sizes("wasm=0").is_err());
+        assert!(parse_provider_batch_sizes("wasm=100").is_err());
+        assert!(parse_provider_batch_sizes("not-a-provider=1").is_err());
+    }
+
+    #[test]
+    fn test_pending_capacity_error() {
+        let secret_key = SecretKey::parse(&[1u8; 32]).unwrap();
+        let mut config = DriaComputeNodeConfig::with_defaults(secret_key, Default::default());
+        config.pending_high_water_mark = 4;
+        assert!(config.pending_capacity_error(3).is_none());
+        assert!(matches!(
+            config.pending_capacity_error(4),
+            Some(TaskError::Overloaded {
+                pending: 4,
+                high_water_mark: 4
+            })
+        ));
+
+        // the maximum takes the place of the high-water mark
+        config.max_pending_tasks = Some(8);
+        assert!(config.pending_capacity_error(4).is_none());
+        assert!(matches!(
+            config.pending_capacity_error(8),
+            Some(TaskError::CapacityExceeded {
+                pending: 8,
+                max_pending: 8
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_token_prices() {
+        assert_eq!(
+            parse_token_prices("gemma3:4b=0.05, ").unwrap(),
+            HashMap::from([(Model::Gemma3_4b, 0.05)])
+        );
+        assert!(parse_token_prices("").unwrap().is_empty());
+        assert!(parse_token_prices("gemma3:4b").is_err());
+        assert!(parse_token_prices("not-a-model=1").is_err());
+    }
+
+    #[test]
+    fn test_listen_socket_addr() {
+        let socket_addr = |addr: &str| listen_socket_addr(&Multiaddr::from_str(addr).unwrap());
+
+        assert_eq!(
+            socket_addr("/ip4/0.0.0.0/tcp/4001"),
+            Some("127.0.0.1:4001".parse().unwrap())
+        );
+        assert_eq!(
+            socket_addr("/ip4/192.168.1.2/tcp/4001"),
+            Some("192.168.1.2:4001".parse().unwrap())
+        );
+        assert_eq!(
+            socket_addr("/ip6/::/tcp/4001"),
+            Some("[::1]:4001".parse().unwrap())
+        );
+        assert_eq!(
+            socket_addr("/ip6/2001:db8::1/tcp/4001"),
+            Some("[2001:db8::1]:4001".parse().unwrap())
+        );
+        assert_eq!(socket_addr("/ip6/::/udp/4001/quic-v1"), None);
     }
 }
@@ -0,0 +1,364 @@
+use dkn_executor::{DriaExecutorsManager, Model};
+use dkn_p2p::libp2p::{multiaddr::Protocol, Multiaddr};
+use dkn_utils::payloads::LifetimeStats;
+use eyre::{Context, Result};
+use flate2::{write::GzEncoder, Compression};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{
+    node::{read_stats, DriaRPC},
+    utils::{LogFileConfig, SpecCollector},
+    DriaComputeNodeConfig, DRIA_COMPUTE_NODE_VERSION,
+};
+
+/// Maximum number of bytes from the end of the log file to include.
+const MAX_LOG_BYTES: u64 = 2 * 1024 * 1024;
+/// Number of RPCs from the discovery API that are dialed.
+const MAX_RPC_CHECKS: usize = 3;
+/// Timeout for each connectivity check.
+const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Prefixes of the environment variables that are included in the bundle.
+const ENV_PREFIXES: [&str; 6] = ["DKN_", "OLLAMA_", "WASM_", "EXTERNAL_", "RUST_LOG", "OTEL_"];
+/// Parts of the variable names whose values are redacted, as they may hold secrets.
+const SENSITIVE_PARTS: [&str; 7] = [
+    "SECRET", "KEY", "TOKEN", "PASSWORD", "WEBHOOK", "PROXY", "COMMAND",
+];
+
+/// Result of a single check within the bundle.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CheckResult {
+    pub(crate) name: String,
+    pub(crate) ok: bool,
+    pub(crate) detail: String,
+}
+
+impl CheckResult {
+    pub(crate) fn new(name: impl Into<String>, result: Result<String>) -> Self {
+        let name = name.into();
+        match result {
+            Ok(detail) => Self {
+                name,
+                ok: true,
+                detail,
+            },
+            Err(err) => Self {
+                name,
+                ok: false,
+                detail: format!("{err:#}"),
+            },
+        }
+    }
+}
+
+/// Runs the `diagnose [--output <file>]` command, which collects the sanitized configuration, recent logs,
+/// specs, connectivity checks & provider checks into a `.tar.gz` archive to be attached to support tickets.
+///
+/// Secrets such as keys & tokens are redacted, and the wallet is only identified by its address.
+pub async fn run_diagnose_command(args: &[String]) -> Result<()> {
+    let mut output_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" | "-o" => {
+                output_path = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| eyre::eyre!("missing value after {arg}"))?,
+                ))
+            }
+            arg => eyre::bail!("unexpected argument {arg}"),
+        }
+    }
+    let output_path = output_path.unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "dkn-diagnostics-{}.tar.gz",
+            chrono::Utc::now().format("%Y%m%d-%H%M%S")
+        ))
+    });
+
+    let mut files: Vec<(&str, Vec<u8>)> = Vec::new();
+    let mut checks = Vec::new();
+
+    // sanitized environment, along with the platform
+    let env = sanitize_env(std::env::vars());
+    files.push((
+        "environment.json",
+        serde_json::to_vec_pretty(&serde_json::json!({
+            "version": DRIA_COMPUTE_NODE_VERSION,
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "env": env,
+        }))?,
+    ));
+
+    // providers & models, where the service checks tell whether they are reachable
+    log::info!("Checking the providers of the models.");
+    let models = Model::from_csv(std::env::var("DKN_MODELS").unwrap_or_default());
+    let mut executors =
+        DriaExecutorsManager::new_from_env_for_models(models.iter().copied()).unwrap_or_default();
+    let model_perf = executors.check_services().await;
+    for model in &models {
+        let result = match model_perf.get(model) {
+            Some(perf) if executors.models.contains(model) => Ok(perf.to_string()),
+            Some(perf) => Err(eyre::eyre!("{perf}")),
+            None => Err(eyre::eyre!("provider is not available")),
+        };
+        checks.push(CheckResult::new(format!("model {model}"), result));
+    }
+
+    // the config is parsed just like the node does, reporting the errors instead of exiting
+    let config = match parse_config(executors.clone()) {
+        Ok(config) => {
+            checks.push(CheckResult::new("config", Ok("valid".to_string())));
+            Some(config)
+        }
+        Err(err) => {
+            checks.push(CheckResult::new("config", Err(err)));
+            None
+        }
+    };
+
+    if let Some(config) = &config {
+        log::info!("Checking the connectivity of the node.");
+        checks.push(CheckResult::new(
+            "listen addresses",
+            config
+                .assert_address_not_in_use()
+                .map(|_| "not in use".to_string()),
+        ));
+        checks.extend(check_rpcs(config).await);
+
+        let lifetime = config
+            .stats_path
+            .as_deref()
+            .and_then(read_stats)
+            .unwrap_or_default();
+        files.push((
+            "specs.json",
+            collect_specs(config, model_perf, lifetime).await?,
+        ));
+    }
+    files.push(("checks.json", serde_json::to_vec_pretty(&checks)?));
+
+    // recent logs, if they are written to a file
+    match LogFileConfig::from_env() {
+        Ok(Some(log_config)) => match read_tail(&log_config.path, MAX_LOG_BYTES) {
+            Ok(logs) => files.push(("logs.txt", logs)),
+            Err(err) => log::warn!("Could not read the logs: {err:#}"),
+        },
+        Ok(None) => log::warn!("Logs are not included, as DKN_LOG_FILE is not set."),
+        Err(err) => log::warn!("Could not read the log file config: {err:#}"),
+    }
+
+    write_archive(&output_path, &files)?;
+    for check in &checks {
+        match check.ok {
+            true => log::info!("{}: {}", check.name, check.detail),
+            false => log::warn!("{}: {}", check.name, check.detail),
+        }
+    }
+    log::info!("Diagnostics are written to {}", output_path.display());
+
+    Ok(())
+}
+
+/// Returns the relevant environment variables, with the values of the sensitive ones redacted.
+fn sanitize_env(vars: impl Iterator<Item = (String, String)>) -> BTreeMap<String, String> {
+    vars.filter(|(name, _)| ENV_PREFIXES.iter().any(|prefix| name.starts_with(prefix)))
+        .map(|(name, value)| {
+            let is_sensitive = SENSITIVE_PARTS.iter().any(|part| name.contains(part));
+            let value = if is_sensitive && !value.trim().is_empty() {
+                "<redacted>".to_string()
+            } else {
+                value
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+/// Parses the node config from the environment, returning the error instead of panicking.
+pub(crate) fn parse_config(executors: DriaExecutorsManager) -> Result<DriaComputeNodeConfig> {
+    // the config panics on invalid values, which is caught quietly to be reported within the checks
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        DriaComputeNodeConfig::new(executors)
+    }));
+    std::panic::set_hook(hook);
+
+    result.map_err(|panic| {
+        let message = panic
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_else(|| "unknown error".to_string());
+        eyre::eyre!("invalid config: {message}")
+    })
+}
+
+/// Checks the discovery API, and dials the first few RPCs it returns (or the pinned RPC) over TCP.
+pub(crate) async fn check_rpcs(config: &DriaComputeNodeConfig) -> Vec<CheckResult> {
+    let mut checks = Vec::new();
+    let addrs = match &config.pinned_rpc_addr {
+        Some(addr) => vec![addr.clone()],
+        None => match DriaRPC::candidates_for_network(
+            config.network,
+            &config.version,
+            config.discovery_url.as_deref(),
+        )
+        .await
+        {
+            Ok(rpcs) => {
+                checks.push(CheckResult::new(
+                    "discovery API",
+                    Ok(format!("{} RPCs for {}", rpcs.len(), config.network)),
+                ));
+                rpcs.into_iter()
+                    .take(MAX_RPC_CHECKS)
+                    .map(|rpc| rpc.addr)
+                    .collect()
+            }
+            Err(err) => {
+                checks.push(CheckResult::new("discovery API", Err(err)));
+                Vec::new()
+            }
+        },
+    };
+
+    for addr in addrs {
+        let result = match tcp_target(&addr) {
+            Some(target) => {
+                match tokio::time::timeout(
+                    CONNECTIVITY_TIMEOUT,
+                    tokio::net::TcpStream::connect(&target),
+                )
+                .await
+                {
+                    Ok(Ok(_)) => Ok(format!("reachable at {target}")),
+                    Ok(Err(err)) => Err(eyre::eyre!("could not connect to {target}: {err}")),
+                    Err(_) => Err(eyre::eyre!("timed out connecting to {target}")),
+                }
+            }
+            None => Err(eyre::eyre!("not a TCP address")),
+        };
+        checks.push(CheckResult::new(format!("RPC {addr}"), result));
+    }
+
+    checks
+}
+
+/// Returns the `host:port` of the given address to connect over TCP, if it has one.
+fn tcp_target(addr: &Multiaddr) -> Option<String> {
+    let mut host = None;
+    let mut port = None;
+    for protocol in addr.iter() {
+        match protocol {
+            Protocol::Ip4(ip) => host = Some(ip.to_string()),
+            Protocol::Ip6(ip) => host = Some(format!("[{ip}]")),
+            Protocol::Dns(name) | Protocol::Dns4(name) | Protocol::Dns6(name) => {
+                host = Some(name.to_string())
+            }
+            Protocol::Tcp(tcp_port) => port = Some(tcp_port),
+            _ => {}
+        }
+    }
+
+    Some(format!("{}:{}", host?, port?))
+}
+
+/// Collects the specs of the node, as they would be sent to the RPC.
+async fn collect_specs(
+    config: &DriaComputeNodeConfig,
+    model_perf: std::collections::HashMap<Model, dkn_utils::payloads::SpecModelPerformance>,
+    lifetime: LifetimeStats,
+) -> Result<Vec<u8>> {
+    let mut spec_collector = SpecCollector::new(
+        config.executors.get_model_names(),
+        model_perf,
+        config.version,
+        config.exec_platform.clone(),
+        config.peer_id,
+    );
+    let specs = spec_collector
+        .collect(config.executors.get_model_states().await, lifetime)
+        .await;
+
+    serde_json::to_vec_pretty(&specs).wrap_err("could not serialize specs")
+}
+
+/// Reads at most the given number of bytes from the end of the file.
+fn read_tail(path: &Path, max_bytes: u64) -> Result<Vec<u8>> {
+    let mut file =
+        std::fs::File::open(path).wrap_err_with(|| format!("could not open {}", path.display()))?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(max_bytes)))?;
+
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// Writes the given files into a gzipped tarball at the given path.
+fn write_archive(path: &Path, files: &[(&str, Vec<u8>)]) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .wrap_err_with(|| format!("could not create {}", path.display()))?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let mtime = chrono::Utc::now().timestamp().max(0) as u64;
+    for (name, content) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        archive
+            .append_data(&mut header, name, content.as_slice())
+            .wrap_err_with(|| format!("could not add {name} to the archive"))?;
+    }
+    archive.into_inner()?.finish()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_env() {
+        let env = sanitize_env(
+            [
+                ("DKN_WALLET_SECRET_KEY", "0xabc"),
+                ("DKN_ADMIN_TOKEN", "token"),
+                ("DKN_MODELS", "gemma3:4b"),
+                ("DKN_WEBHOOK_URL", "https://hooks.example.com/secret"),
+                ("DKN_P2P_PROXY", ""),
+                ("OLLAMA_HOST", "http://127.0.0.1"),
+                ("HOME", "/root"),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+
+        assert_eq!(env["DKN_WALLET_SECRET_KEY"], "<redacted>");
+        assert_eq!(env["DKN_ADMIN_TOKEN"], "<redacted>");
+        assert_eq!(env["DKN_WEBHOOK_URL"], "<redacted>");
+        assert_eq!(env["DKN_MODELS"], "gemma3:4b");
+        assert_eq!(env["DKN_P2P_PROXY"], "");
+        assert_eq!(env["OLLAMA_HOST"], "http://127.0.0.1");
+        assert!(!env.contains_key("HOME"));
+
+        assert_eq!(
+            tcp_target(&"/dns4/rpc.dria.co/tcp/4001/p2p/16Uiu2HAmB8KjTgLs9pZtvRBEYhzP9WTiobf6sLBW3iL4WgpdxM79".parse().unwrap()),
+            Some("rpc.dria.co:4001".to_string())
+        );
+        assert_eq!(
+            tcp_target(&"/ip4/1.2.3.4/udp/4001/quic-v1".parse().unwrap()),
+            None
+        );
+    }
+}
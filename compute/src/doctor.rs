@@ -0,0 +1,198 @@
+use colored::Colorize;
+use dkn_executor::{DriaExecutorsManager, Model, ModelProvider};
+use dkn_utils::payloads::SpecModelPerformance;
+use eyre::{Context, Result};
+use libsecp256k1::SecretKey;
+use std::env;
+
+use crate::{
+    diagnose::{check_rpcs, parse_config, CheckResult},
+    utils::{Keystore, RemoteSigner},
+};
+
+/// A check along with what to do if it fails.
+struct DoctorCheck {
+    result: CheckResult,
+    hint: Option<String>,
+}
+
+impl DoctorCheck {
+    fn new(result: CheckResult, hint: impl Into<String>) -> Self {
+        let hint = (!result.ok).then(|| hint.into());
+        Self { result, hint }
+    }
+}
+
+/// Runs the `doctor` command, which checks the configuration end-to-end: the wallet key, the listen ports,
+/// the providers & models, the discovery API and the connectivity to the RPCs.
+///
+/// Each failed check is printed along with how to fix it, and the command fails if any check fails.
+pub async fn run_doctor_command() -> Result<()> {
+    let mut checks = vec![check_wallet()];
+
+    // models that could not be parsed are dropped by the node, so they are reported here
+    let models_var = env::var("DKN_MODELS").unwrap_or_default();
+    for name in models_var
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        if Model::try_from(name).is_err() {
+            checks.push(DoctorCheck::new(
+                CheckResult::new(format!("model {name}"), Err(eyre::eyre!("unknown model"))),
+                "remove it from DKN_MODELS, the supported models are listed in the README",
+            ));
+        }
+    }
+
+    // providers & models, where the service checks tell whether they are reachable
+    let models = Model::from_csv(&models_var);
+    if models.is_empty() {
+        checks.push(DoctorCheck::new(
+            CheckResult::new("models", Err(eyre::eyre!("no models are given"))),
+            "set DKN_MODELS to the comma-separated models to serve, e.g. gemma3:4b",
+        ));
+    }
+    let mut executors = match DriaExecutorsManager::new_from_env_for_models(models.iter().copied())
+    {
+        Ok(executors) => executors,
+        Err(err) => {
+            checks.push(DoctorCheck::new(
+                CheckResult::new("providers", Err(err.into())),
+                "set the variables of the providers, see .env.example",
+            ));
+            DriaExecutorsManager::default()
+        }
+    };
+    let model_perf = executors.check_services().await;
+    for model in &models {
+        let result = match model_perf.get(model) {
+            Some(perf) if executors.models.contains(model) => Ok(perf.to_string()),
+            Some(perf) => Err(eyre::eyre!("{perf}")),
+            None => Err(eyre::eyre!("provider is not available")),
+        };
+        checks.push(DoctorCheck::new(
+            CheckResult::new(format!("model {model}"), result),
+            model_hint(model, model_perf.get(model)),
+        ));
+    }
+
+    // the rest of the config is parsed just like the node does
+    match parse_config(executors) {
+        Ok(config) => {
+            checks.push(DoctorCheck::new(
+                CheckResult::new(
+                    "listen addresses",
+                    config
+                        .assert_address_not_in_use()
+                        .map(|_| "not in use".to_string()),
+                ),
+                "stop the other process on the port, or change DKN_P2P_LISTEN_ADDR (or --listen-addr)",
+            ));
+            for check in check_rpcs(&config).await {
+                let hint = match check.name.as_str() {
+                    "discovery API" => "check the internet connection, or set DKN_HTTP_PROXY if behind a proxy",
+                    _ => "allow outbound TCP connections to the RPC in the firewall, or pin another RPC with DKN_RPC_ADDR",
+                };
+                checks.push(DoctorCheck::new(check, hint));
+            }
+        }
+        Err(err) => checks.push(DoctorCheck::new(
+            CheckResult::new("config", Err(err)),
+            "fix the variable named in the error, see .env.example",
+        )),
+    }
+
+    let mut num_failed = 0;
+    for check in &checks {
+        let CheckResult { name, ok, detail } = &check.result;
+        if *ok {
+            println!("{} {name}: {detail}", "✓".green());
+        } else {
+            num_failed += 1;
+            println!("{} {name}: {detail}", "✗".red());
+            if let Some(hint) = &check.hint {
+                println!("    {} {hint}", "→".yellow());
+            }
+        }
+    }
+
+    match num_failed {
+        0 => {
+            println!("All {} checks passed.", checks.len());
+            Ok(())
+        }
+        _ => Err(eyre::eyre!(
+            "{num_failed} of {} checks failed",
+            checks.len()
+        )),
+    }
+}
+
+/// Checks that the wallet is given in a valid format, through a remote signer, a keystore or a secret key.
+fn check_wallet() -> DoctorCheck {
+    let keystore_path = env::var("DKN_KEYSTORE_PATH")
+        .ok()
+        .filter(|path| !path.trim().is_empty());
+
+    if env::var("DKN_REMOTE_SIGNER_URL").is_ok_and(|url| !url.trim().is_empty()) {
+        DoctorCheck::new(
+            CheckResult::new(
+                "wallet",
+                RemoteSigner::from_env().map(|_| "remote signer".to_string()),
+            ),
+            "set DKN_REMOTE_SIGNER_PUBLIC_KEY to the hex encoded public key of the remote wallet",
+        )
+    } else if let Some(path) = keystore_path {
+        let result = Keystore::read(path.trim().as_ref()).and_then(|keystore| {
+            // the password is only checked if it is given, as it would be prompted otherwise
+            match env::var("DKN_KEYSTORE_PASSWORD") {
+                Ok(password) => keystore
+                    .decrypt(&password)
+                    .map(|_| "keystore with a valid password".to_string()),
+                Err(_) => Ok("keystore, its password is prompted at startup".to_string()),
+            }
+        });
+        DoctorCheck::new(
+            CheckResult::new("wallet", result),
+            "check DKN_KEYSTORE_PATH & DKN_KEYSTORE_PASSWORD, or create a keystore with `keygen --keystore <file>`",
+        )
+    } else {
+        let result = env::var("DKN_WALLET_SECRET_KEY")
+            .wrap_err("no wallet is given")
+            .and_then(|secret_key| {
+                let secret_key = hex::decode(secret_key.trim().trim_start_matches("0x"))
+                    .wrap_err("secret key is not hex encoded")?;
+                if secret_key.iter().all(|b| *b == 0) {
+                    return Ok("random secret key for each run".to_string());
+                }
+                SecretKey::parse_slice(&secret_key)
+                    .map_err(|_| eyre::eyre!("secret key is not a valid 32-byte key"))?;
+                Ok("secret key".to_string())
+            });
+        DoctorCheck::new(
+            CheckResult::new("wallet", result),
+            "set DKN_WALLET_SECRET_KEY to a 32-byte hex secret key, e.g. one printed by `keygen`",
+        )
+    }
+}
+
+/// Returns what to do when the given model is not available, w.r.t its provider & performance.
+fn model_hint(model: &Model, perf: Option<&SpecModelPerformance>) -> String {
+    match (model.provider(), perf) {
+        (ModelProvider::Ollama, Some(SpecModelPerformance::NotFound)) => {
+            format!("pull it with `ollama pull {model}`, or set OLLAMA_AUTO_PULL=true")
+        }
+        (
+            ModelProvider::Ollama,
+            Some(SpecModelPerformance::Timeout | SpecModelPerformance::FailedWithTPS(_)),
+        ) => "this machine is too slow for the model, serve a smaller one instead".to_string(),
+        (ModelProvider::Ollama, _) => {
+            "make sure Ollama is running (`ollama serve`) at OLLAMA_HOST & OLLAMA_PORT".to_string()
+        }
+        (ModelProvider::Wasm, _) => "check the WASM_* variables, see .env.example".to_string(),
+        (ModelProvider::External, _) => {
+            "check that EXTERNAL_EXECUTOR_COMMAND runs & responds, see the README".to_string()
+        }
+    }
+}
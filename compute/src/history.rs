@@ -0,0 +1,89 @@
+use colored::Colorize;
+use eyre::{Context, Result};
+use std::env;
+use std::path::PathBuf;
+
+use crate::node::{HistoryQuery, TaskHistory, TaskRecord};
+
+/// Runs the `history [--db <file>] [--model <model>] [--failed | --completed] [--since <time>] [--limit <n>] [--json]`
+/// command, which prints the most recent tasks from the history of a node, even while it is running.
+///
+/// The database defaults to the one that the node records to at `DKN_HISTORY_PATH`,
+/// and `--since` is an RFC 3339 time such as `2025-01-01T00:00:00Z`.
+pub async fn run_history_command(args: &[String]) -> Result<()> {
+    let mut path = env::var("DKN_HISTORY_PATH")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+        .map(|path| PathBuf::from(path.trim()));
+    let mut query = HistoryQuery::default();
+    let mut as_json = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| eyre::eyre!("missing value after {arg}"))
+        };
+        match arg.as_str() {
+            "--db" => path = Some(PathBuf::from(value()?)),
+            "--model" => query.model = Some(value()?.to_string()),
+            "--failed" => query.success = Some(false),
+            "--completed" => query.success = Some(true),
+            "--since" => {
+                let since = value()?;
+                query.since = Some(
+                    chrono::DateTime::parse_from_rfc3339(since)
+                        .wrap_err_with(|| format!("could not parse time {since}"))?
+                        .to_utc(),
+                );
+            }
+            "--limit" => {
+                let limit = value()?;
+                query.limit = limit
+                    .parse()
+                    .wrap_err_with(|| format!("could not parse limit {limit}"))?;
+            }
+            "--json" => as_json = true,
+            arg => eyre::bail!("unexpected argument {arg}"),
+        }
+    }
+    let path = path
+        .ok_or_else(|| eyre::eyre!("a database must be given, or DKN_HISTORY_PATH must be set"))?;
+
+    let records = TaskHistory::open_read_only(&path)?.query(&query)?;
+    if as_json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&records).wrap_err("could not serialize history")?
+        );
+    } else {
+        print_records(&records);
+    }
+
+    Ok(())
+}
+
+/// Prints the records in a human-readable way, one task per line.
+fn print_records(records: &[TaskRecord]) {
+    if records.is_empty() {
+        println!("No tasks in the history.");
+        return;
+    }
+
+    for record in records {
+        let status = match &record.error_class {
+            None => "ok".green(),
+            Some(class) => class.red(),
+        };
+        println!(
+            "{}  {}  {:<24} {:>7}ms queued {:>7}ms executed {:>6} tokens  {}",
+            record.completed_at.format("%Y-%m-%d %H:%M:%S"),
+            record.row_id,
+            record.model,
+            record.queue_ms,
+            record.execution_ms,
+            record.tokens,
+            status
+        );
+    }
+}
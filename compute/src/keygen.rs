@@ -0,0 +1,128 @@
+use colored::Colorize;
+use dkn_utils::crypto::sign_key_rotation;
+use eyre::{Context, Result};
+use libsecp256k1::SecretKey;
+use std::env;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+
+use crate::{
+    config::{derive_identity, KEY_ROTATION_WINDOW},
+    utils::{secret_key_from_keystore_env, Keystore},
+};
+
+/// Runs the `keygen [--keystore <file>]` command, which generates a new wallet and prints its address,
+/// public key & peer id.
+///
+/// With a keystore, the secret key is encrypted with the password in `DKN_KEYSTORE_PASSWORD` or the one
+/// prompted from the terminal, and written to the file for `DKN_KEYSTORE_PATH`. Otherwise, the secret key
+/// is printed for `DKN_WALLET_SECRET_KEY`.
+pub fn run_keygen_command(keystore_path: Option<&Path>) -> Result<()> {
+    let secret_key = SecretKey::random(&mut rand::thread_rng());
+    print_new_wallet(&secret_key, keystore_path)
+}
+
+/// Runs the `rotate-key [--keystore <file>]` command, which generates a new wallet to replace the current one,
+/// and prints the rotation signed by the current wallet for `DKN_KEY_ROTATION`.
+///
+/// The current wallet is read from the keystore at `DKN_KEYSTORE_PATH` or `DKN_WALLET_SECRET_KEY`, and the new
+/// one is written or printed just like `keygen`. Once the node runs with the new wallet & the rotation, the rotation
+/// is announced to the RPC for a while so that the previous address is linked to the new one.
+pub fn run_rotate_key_command(keystore_path: Option<&Path>) -> Result<()> {
+    if env::var("DKN_REMOTE_SIGNER_URL").is_ok_and(|url| !url.trim().is_empty()) {
+        eyre::bail!("the wallet of a remote signer can not be rotated by the node");
+    }
+    let previous_secret_key = match secret_key_from_keystore_env()? {
+        Some(secret_key) => secret_key,
+        None => {
+            let secret_key = env::var("DKN_WALLET_SECRET_KEY")
+                .ok()
+                .filter(|secret_key| !secret_key.trim().is_empty())
+                .ok_or_else(|| eyre::eyre!("no wallet is given to rotate from"))?;
+            hex::decode(secret_key.trim().trim_start_matches("0x"))
+                .ok()
+                .and_then(|secret_key| SecretKey::parse_slice(&secret_key).ok())
+                .ok_or_else(|| eyre::eyre!("DKN_WALLET_SECRET_KEY is not a valid secret key"))?
+        }
+    };
+
+    let secret_key = SecretKey::random(&mut rand::thread_rng());
+    let (_, address, _) = derive_identity(&secret_key);
+    let rotation = sign_key_rotation(&previous_secret_key, &address, chrono::Utc::now());
+
+    println!("Rotating from 0x{}", rotation.previous_address);
+    print_new_wallet(&secret_key, keystore_path)?;
+    println!(
+        "Rotation:    DKN_KEY_ROTATION='{}'",
+        serde_json::to_string(&rotation)?.yellow()
+    );
+    println!(
+        "Replace the wallet with the new one & set DKN_KEY_ROTATION, which is announced for {} days.",
+        KEY_ROTATION_WINDOW.num_days()
+    );
+
+    Ok(())
+}
+
+/// Writes the new wallet to the keystore if given, or prints its secret key otherwise, along with its
+/// address, public key & peer id.
+fn print_new_wallet(secret_key: &SecretKey, keystore_path: Option<&Path>) -> Result<()> {
+    let (public_key, address, peer_id) = derive_identity(secret_key);
+
+    if let Some(path) = keystore_path {
+        let keystore = Keystore::encrypt(secret_key, &new_password()?)?;
+        write_new_file(path, &serde_json::to_vec_pretty(&keystore)?)?;
+        println!("Keystore:    {}", path.display());
+    } else {
+        println!(
+            "Secret Key:  0x{}",
+            hex::encode(secret_key.serialize()).yellow()
+        );
+    }
+    println!(
+        "Public Key:  0x{}",
+        hex::encode(public_key.serialize_compressed())
+    );
+    println!("Address:     0x{address}");
+    println!("Peer ID:     {peer_id}");
+
+    Ok(())
+}
+
+/// Returns the password in `DKN_KEYSTORE_PASSWORD`, or prompts it twice from the terminal.
+fn new_password() -> Result<String> {
+    if let Ok(password) = env::var("DKN_KEYSTORE_PASSWORD") {
+        return Ok(password);
+    }
+    if !std::io::stdin().is_terminal() {
+        eyre::bail!("DKN_KEYSTORE_PASSWORD must be set to create a keystore without a terminal");
+    }
+
+    let password = rpassword::prompt_password("Keystore password: ")?;
+    if password.is_empty() {
+        eyre::bail!("keystore password can not be empty");
+    }
+    if rpassword::prompt_password("Repeat password: ")? != password {
+        eyre::bail!("passwords do not match");
+    }
+
+    Ok(password)
+}
+
+/// Writes the content to a new file that is only readable by the user, an existing file is not overwritten.
+fn write_new_file(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .wrap_err_with(|| format!("could not create {}", parent.display()))?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content))
+        .wrap_err_with(|| format!("could not write {}", path.display()))
+}
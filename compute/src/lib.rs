@@ -1,6 +1,19 @@
+pub mod benchmark;
+pub mod cli;
 pub mod config;
+pub mod diagnose;
+pub mod doctor;
+pub mod history;
+pub mod keygen;
+pub mod migrate;
 pub mod node;
+pub mod offline;
+pub mod peers;
+pub mod points;
 pub mod reqres;
+pub mod specs;
+pub mod supervisor;
+pub mod tui;
 pub mod utils;
 pub mod workers;
 
@@ -8,5 +21,5 @@ pub mod workers;
 /// This value is attached within the published messages.
 pub const DRIA_COMPUTE_NODE_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-pub use config::DriaComputeNodeConfig;
+pub use config::{DriaComputeNodeConfig, NodeIntervals};
 pub use node::DriaComputeNode;
@@ -1,26 +1,84 @@
+use clap::Parser;
 use dkn_compute::*;
 use dkn_executor::{DriaExecutorsManager, Model};
+use dkn_utils::payloads::SpecModelPerformance;
 use eyre::Result;
+use std::collections::HashMap;
 use std::env;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use workers::task::TaskWorker;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // load a particular environment file specified by DKN_COMPUTE_ENV, or `.env` by default
-    let env_path = env::var("DKN_COMPUTE_ENV").unwrap_or_else(|_| ".env".to_string());
-    let dotenv_result = dotenvy::from_path(&env_path);
-
-    env_logger::builder()
-        .format_timestamp(Some(env_logger::TimestampPrecision::Millis))
-        .filter(None, log::LevelFilter::Off)
-        .filter_module("dkn_compute", log::LevelFilter::Info)
-        .filter_module("dkn_p2p", log::LevelFilter::Info)
-        .filter_module("dkn_utils", log::LevelFilter::Info)
-        .filter_module("dkn_executor", log::LevelFilter::Info)
-        .filter_module("libp2p", log::LevelFilter::Error)
-        .parse_default_env() // reads RUST_LOG variable
-        .init();
+    let cli = cli::Cli::parse();
+    match &cli.command {
+        Some(cli::Command::Version) => {
+            println!("{DRIA_COMPUTE_NODE_VERSION}");
+            return Ok(());
+        }
+        Some(cli::Command::Keygen { keystore }) => {
+            return keygen::run_keygen_command(keystore.as_deref());
+        }
+        // the file is migrated before it is loaded, as an older layout may not load at all
+        Some(cli::Command::MigrateConfig { path, write }) => {
+            return migrate::run_migrate_config_command(path.as_deref(), *write);
+        }
+        _ => {}
+    }
+
+    // the profile within the environment file is given by `--profile <name>` or DKN_PROFILE
+    let profile = cli.profile.clone().or_else(|| {
+        env::var("DKN_PROFILE")
+            .ok()
+            .filter(|profile| !profile.trim().is_empty())
+            .map(|profile| profile.trim().to_string())
+    });
+
+    // load a particular environment file specified by DKN_COMPUTE_ENV, or `.env` by default,
+    // where the flags override the variables within
+    let env_file = utils::EnvFile::new(
+        env::var("DKN_COMPUTE_ENV").unwrap_or_else(|_| ".env".to_string()),
+        profile,
+    )
+    .with_overrides(cli.start_args().env_overrides());
+    let dotenv_result = env_file.load();
+
+    // with the dashboard, logs are shown within it instead of the terminal
+    let is_tui = cli.tui;
+    let logs = tui::LogBuffer::default();
+
+    // logs are written to a rotating file as well, if configured
+    let log_file = utils::LogFileConfig::from_env()?
+        .map(utils::RotatingFile::open)
+        .transpose()?;
+
+    let mut logger = env_logger::builder();
+    if is_tui {
+        colored::control::set_override(false);
+    }
+    if is_tui || log_file.is_some() {
+        let mut writers: Vec<Box<dyn Write + Send>> = match is_tui {
+            true => vec![Box::new(logs.clone())],
+            false => vec![Box::new(std::io::stderr())],
+        };
+        writers.extend(log_file.map(|file| Box::new(file) as Box<dyn Write + Send>));
+
+        // colors are kept for the terminal, the log file strips them
+        let write_style = match !is_tui && std::io::stderr().is_terminal() {
+            true => env_logger::WriteStyle::Always,
+            false => env_logger::WriteStyle::Never,
+        };
+        logger
+            .target(env_logger::Target::Pipe(Box::new(utils::LogTee(writers))))
+            .write_style(write_style);
+    }
+    // log levels are read from the RUST_LOG variable, and can be reloaded
+    let log_filter =
+        utils::init_logger(logger.format_timestamp(Some(env_logger::TimestampPrecision::Millis)))?;
 
     log::info!(
         r#"
@@ -35,11 +93,33 @@ async fn main() -> Result<()> {
     );
 
     // log about env usage
-    match dotenv_result {
-        Ok(_) => log::info!("Loaded environment file from {env_path}"),
-        Err(err) => log::warn!("Could not load environment file from {env_path}: {err}"),
+    match (dotenv_result, &env_file.profile) {
+        (Ok(_), None) => log::info!("Loaded environment file from {}", env_file.path),
+        (Ok(_), Some(profile)) => log::info!(
+            "Loaded environment file from {} with profile {profile}",
+            env_file.path
+        ),
+        (Err(err), _) => log::warn!(
+            "Could not load environment file from {}: {err:#}",
+            env_file.path
+        ),
     }
 
+    // variables of the older layouts are ignored, so they are pointed out
+    migrate::warn_legacy_config();
+
+    // route the HTTP requests through a proxy, if configured
+    utils::apply_http_proxy()?;
+
+    // read the secrets that are not given from the OS keychain, if configured
+    utils::apply_keychain_secrets()?;
+
+    // export the task spans, if configured
+    let otlp_endpoint = env::var("DKN_OTLP_ENDPOINT")
+        .ok()
+        .filter(|endpoint| !endpoint.trim().is_empty());
+    let _telemetry = utils::init_telemetry(otlp_endpoint.as_deref().map(str::trim))?;
+
     // task tracker for multiple threads
     let task_tracker = TaskTracker::new();
     let cancellation = CancellationToken::new();
@@ -68,6 +148,34 @@ async fn main() -> Result<()> {
         task_tracker_to_close.close();
     });
 
+    // subcommands are handled separately from the node
+    match &cli.command {
+        Some(cli::Command::Task {
+            command: cli::TaskCommand::Run(rest),
+        }) => return offline::run_task_command(&rest.args).await,
+        Some(cli::Command::Benchmark(rest)) => {
+            return benchmark::run_benchmark_command(&rest.args).await
+        }
+        Some(cli::Command::Diagnose(rest)) => {
+            return diagnose::run_diagnose_command(&rest.args).await
+        }
+        Some(cli::Command::Points(rest)) => return points::run_points_command(&rest.args).await,
+        Some(cli::Command::History(rest)) => return history::run_history_command(&rest.args).await,
+        Some(cli::Command::Specs) => return specs::run_specs_command().await,
+        Some(cli::Command::Doctor) => return doctor::run_doctor_command().await,
+        Some(cli::Command::Peers) => return peers::run_peers_command().await,
+        Some(cli::Command::RotateKey { keystore }) => {
+            return keygen::run_rotate_key_command(keystore.as_deref())
+        }
+        Some(
+            cli::Command::Start(_)
+            | cli::Command::Version
+            | cli::Command::Keygen { .. }
+            | cli::Command::MigrateConfig { .. },
+        )
+        | None => {}
+    }
+
     // create configurations
     let models = Model::from_csv(env::var("DKN_MODELS").unwrap_or_default());
     let executors_config = DriaExecutorsManager::new_from_env_for_models(models.into_iter())?;
@@ -79,10 +187,34 @@ async fn main() -> Result<()> {
         "Initial provided models are: {}",
         executors_config.get_model_names().join(", ")
     );
+
+    // in offline mode, the given task files are executed without joining the network
+    let start_args = cli.start_args();
+    if start_args.offline || offline::is_offline_from_env() {
+        let paths = start_args.paths.clone();
+        return run_offline_mode(executors_config, paths, cancellation).await;
+    }
+
     let mut config = DriaComputeNodeConfig::new(executors_config);
 
+    // in supervisor mode, a node is run for each identity in the given file
+    let identities = env::var("DKN_IDENTITIES_FILE")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+        .map(|path| supervisor::load_identities(path.trim().as_ref()))
+        .transpose()?;
+
     // check address in use
-    config.assert_address_not_in_use()?;
+    match &identities {
+        Some(identities) => {
+            for identity in identities {
+                identity
+                    .apply(config.clone())?
+                    .assert_address_not_in_use()?;
+            }
+        }
+        None => config.assert_address_not_in_use()?,
+    }
 
     // check services & models, will exit if there is an error
     // since service check can take time, we allow early-exit here as well
@@ -109,10 +241,157 @@ async fn main() -> Result<()> {
                 .join("\n")
         );
     }
+
+    // the checked models are shared by the nodes of all identities
+    let configs = match &identities {
+        Some(identities) => identities
+            .iter()
+            .map(|identity| {
+                log::info!("Running a node for identity {}.", identity.name);
+                identity.apply(config.clone())
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => vec![config],
+    };
+
+    // create & spawn the nodes
+    let reload = node::ReloadHandle::default();
+    let mut commanders = Vec::new();
+    let mut node_handles = Vec::new();
+    for config in configs {
+        let (commander, node_handle) = spawn_node(
+            config,
+            model_perf.clone(),
+            reload.clone(),
+            &task_tracker,
+            &cancellation,
+        )
+        .await?;
+        commanders.push(commander);
+        node_handles.push(node_handle);
+    }
+
+    // reload the configuration on SIGHUP, or when requested by the admin API
+    let reload_commanders = commanders.clone();
+    let reload_requests = reload.clone();
+    let reload_cancellation = cancellation.clone();
+    task_tracker.spawn(async move {
+        loop {
+            tokio::select! {
+                _ = reload_requests.requested() => tokio::select! {
+                    result = node::reload_config(&env_file, &log_filter, &reload_requests, &reload_commanders) => {
+                        if let Err(err) = result {
+                            log::error!("Could not reload configuration, keeping the current one: {err:?}");
+                        }
+                    }
+                    _ = reload_cancellation.cancelled() => return,
+                },
+                _ = reload_cancellation.cancelled() => return,
+            }
+        }
+    });
+    #[cfg(unix)]
+    {
+        let hangup_cancellation = cancellation.clone();
+        task_tracker.spawn(async move {
+            if let Err(err) = wait_for_hangups(reload, hangup_cancellation).await {
+                log::error!("Could not listen for SIGHUP: {err:?}");
+            }
+        });
+    }
+
+    // show the dashboard, if requested
+    if is_tui {
+        if commanders.len() > 1 {
+            log::info!("The dashboard shows the node of the first identity.");
+        }
+        let commander = commanders[0].clone();
+        let tui_cancellation = cancellation.clone();
+        task_tracker.spawn(async move {
+            if let Err(err) = tui::run_tui(commander, logs, tui_cancellation).await {
+                log::error!("Could not run the dashboard: {err:?}");
+            }
+        });
+    }
+
+    // wait for all tasks to finish
+    task_tracker.wait().await;
+    log::info!("All tasks have exited succesfully.");
+
+    // restart with the new binary, if the node has shut down for an update
+    for node_handle in node_handles {
+        if let Ok(Some(staged_path)) = node_handle.await {
+            drop(_telemetry);
+            return utils::install_update_and_restart(&staged_path);
+        }
+    }
+
+    log::info!("Bye!");
+    Ok(())
+}
+
+/// Creates a node with the given configuration, and spawns it along with its p2p client, workers & servers.
+///
+/// Returns the admin commander of the node, and the handle of the node that returns its staged update, if any.
+async fn spawn_node(
+    config: DriaComputeNodeConfig,
+    model_perf: HashMap<Model, SpecModelPerformance>,
+    reload: node::ReloadHandle,
+    task_tracker: &TaskTracker,
+    cancellation: &CancellationToken,
+) -> Result<(
+    mpsc::Sender<node::AdminCommand>,
+    JoinHandle<Option<PathBuf>>,
+)> {
     // create the node
     let batch_size = config.batch_size;
+    let provider_batch_sizes = config.provider_batch_sizes.clone();
     let (mut node, p2p, worker_batch, worker_single) =
         DriaComputeNode::new(config, model_perf).await?;
+    let commander = node.admin_commander();
+
+    // serve the health endpoints, if configured
+    if let Some(health_addr) = node.config.health_addr {
+        let health = node.health();
+        let health_token = cancellation.clone();
+        task_tracker.spawn(async move {
+            if let Err(err) = utils::serve_health(health_addr, health, health_token).await {
+                log::error!("Could not serve health endpoints: {err:?}");
+            }
+        });
+    }
+
+    // serve the admin API, if configured
+    if let Some((admin_addr, admin_token)) = node.config.admin_api.clone() {
+        let commander = node.admin_commander();
+        let admin_cancellation = cancellation.clone();
+        task_tracker.spawn(async move {
+            if let Err(err) = node::serve_admin(
+                admin_addr,
+                admin_token,
+                commander,
+                reload,
+                admin_cancellation,
+            )
+            .await
+            {
+                log::error!("Could not serve admin API: {err:?}");
+            }
+        });
+    }
+
+    // serve the metrics, if configured
+    if let Some(metrics_addr) = node.config.metrics_addr {
+        let commander = node.admin_commander();
+        let metrics_cancellation = cancellation.clone();
+        task_tracker.spawn(async move {
+            if let Err(err) =
+                node::serve_metrics(metrics_addr, commander, metrics_cancellation).await
+            {
+                log::error!("Could not serve metrics: {err:?}");
+            }
+        });
+    }
 
     // spawn p2p client first
     log::info!("Spawning peer-to-peer client thread.");
@@ -125,7 +404,11 @@ async fn main() -> Result<()> {
             "batch size too large"
         );
         log::info!("Spawning batch executor worker thread. (batch size {batch_size})");
-        task_tracker.spawn(async move { worker_batch.run_batch(batch_size).await });
+        task_tracker.spawn(async move {
+            worker_batch
+                .run_batch(batch_size, provider_batch_sizes)
+                .await
+        });
     }
 
     // spawn single worker thread if we are using such models (e.g. Ollama)
@@ -137,19 +420,72 @@ async fn main() -> Result<()> {
     // spawn compute node thread
     log::info!("Spawning compute node thread.");
     let node_token = cancellation.clone();
-    task_tracker.spawn(async move {
+    let node_handle = task_tracker.spawn(async move {
         node.run(node_token).await;
-        log::info!("Closing node.")
+        log::info!("Closing node.");
+        node.staged_update()
     });
 
-    // wait for all tasks to finish
-    task_tracker.wait().await;
-    log::info!("All tasks have exited succesfully.");
+    Ok((commander, node_handle))
+}
 
-    log::info!("Bye!");
+/// Checks the services & runs the tasks in the given files without joining the network,
+/// returning an error if any of the tasks fail.
+async fn run_offline_mode(
+    mut executors: DriaExecutorsManager,
+    paths: Vec<PathBuf>,
+    cancellation: CancellationToken,
+) -> Result<()> {
+    if paths.is_empty() {
+        return Err(eyre::eyre!(
+            "No task files were provided, make sure to pass at least one task file in offline mode."
+        ));
+    }
+
+    let model_perf = tokio::select! {
+        result = executors.check_services() => result,
+        _ = cancellation.cancelled() => {
+            log::info!("Service check cancelled, exiting.");
+            return Ok(());
+        }
+    };
+    if executors.models.is_empty() {
+        return Err(eyre::eyre!(
+            "No valid models left after service checks, exiting."
+        ));
+    }
+    for (model, perf) in model_perf {
+        log::info!("{model}: {perf}");
+    }
+
+    let num_failed = offline::run_offline(&executors, &paths, cancellation).await?;
+    if num_failed > 0 {
+        return Err(eyre::eyre!("{num_failed} tasks failed in offline mode."));
+    }
+
+    log::info!("All offline tasks succeeded, bye!");
     Ok(())
 }
 
+/// Requests a configuration reload for each `SIGHUP`, until cancelled.
+#[cfg(unix)]
+async fn wait_for_hangups(
+    reload: node::ReloadHandle,
+    cancellation: CancellationToken,
+) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut sighup = signal(SignalKind::hangup())?;
+    loop {
+        tokio::select! {
+            _ = sighup.recv() => {
+                log::warn!("Received SIGHUP");
+                reload.request();
+            }
+            _ = cancellation.cancelled() => return Ok(()),
+        }
+    }
+}
+
 /// Waits for various termination signals, and cancels the given token when the signal is received.
 ///
 /// Handles Unix and Windows [target families](https://doc.rust-lang.org/reference/conditional-compilation.html#target_family).
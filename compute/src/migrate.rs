@@ -0,0 +1,263 @@
+use colored::Colorize;
+use eyre::{Context, Result};
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Current version of the config layout, given by `DKN_CONFIG_VERSION` in the environment file.
+///
+/// Files without it are of the first layout, and are upgraded with `migrate-config`.
+pub const CONFIG_VERSION: u32 = 2;
+
+/// Variables of the previous layouts that are no longer read, along with what to do instead.
+const LEGACY_VARS: [(&str, &str); 7] = [
+    ("DKN_ADMIN_PUBLIC_KEY", "the admin key is no longer used"),
+    (
+        "DKN_BOOTSTRAP_NODES",
+        "RPCs are found with the discovery API, pin one with DKN_RPC_ADDR instead",
+    ),
+    ("DKN_RELAY_NODES", "relays are found through the RPC"),
+    ("DKN_TASKS", "the tasks follow the models in DKN_MODELS"),
+    ("SERPER_API_KEY", "search tools are no longer used"),
+    ("JINA_API_KEY", "search tools are no longer used"),
+    ("BROWSERLESS_TOKEN", "search tools are no longer used"),
+];
+
+/// A line of the file that is changed by the migration, where `old` is `None` for an added line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LineChange {
+    line: usize,
+    old: Option<String>,
+    new: String,
+}
+
+/// Runs the `migrate-config` command, which upgrades the environment file at the given path
+/// (or `DKN_COMPUTE_ENV`, `.env` by default) to the current layout and prints the changes.
+///
+/// The file is only written with `write`, where the original one is kept with a `.bak` extension.
+pub fn run_migrate_config_command(path: Option<&Path>, write: bool) -> Result<()> {
+    let path = path.map(Path::to_path_buf).unwrap_or_else(|| {
+        PathBuf::from(env::var("DKN_COMPUTE_ENV").unwrap_or_else(|_| ".env".to_string()))
+    });
+    let content = std::fs::read_to_string(&path)
+        .wrap_err_with(|| format!("could not read {}", path.display()))?;
+
+    let (migrated, changes) = migrate_content(&content)?;
+    if changes.is_empty() {
+        println!(
+            "{} is already at config version {CONFIG_VERSION}.",
+            path.display()
+        );
+        return Ok(());
+    }
+
+    println!("--- {}", path.display());
+    println!("+++ {} (config version {CONFIG_VERSION})", path.display());
+    for change in &changes {
+        println!("@@ line {} @@", change.line);
+        if let Some(old) = &change.old {
+            println!("{}", format!("-{old}").red());
+        }
+        println!("{}", format!("+{}", change.new).green());
+    }
+
+    if write {
+        let mut backup = path.clone().into_os_string();
+        backup.push(".bak");
+        let backup = PathBuf::from(backup);
+        std::fs::copy(&path, &backup)
+            .wrap_err_with(|| format!("could not back up to {}", backup.display()))?;
+        std::fs::write(&path, migrated)
+            .wrap_err_with(|| format!("could not write {}", path.display()))?;
+        println!(
+            "Migrated {}, the original is kept at {}.",
+            path.display(),
+            backup.display()
+        );
+    } else {
+        println!("Run again with --write to apply these changes.");
+    }
+
+    Ok(())
+}
+
+/// Warns about the variables of the previous layouts within the environment, which are silently ignored otherwise.
+pub fn warn_legacy_config() {
+    for (var, note) in LEGACY_VARS {
+        if env::var(var).is_ok_and(|value| !value.trim().is_empty()) {
+            log::warn!("{var} is no longer used ({note}), run `migrate-config` to upgrade your environment file.");
+        }
+    }
+
+    if let Some(version) = env::var("DKN_CONFIG_VERSION")
+        .ok()
+        .and_then(|version| version.trim().parse::<u32>().ok())
+        .filter(|version| *version > CONFIG_VERSION)
+    {
+        log::warn!("Environment file is of config version {version}, which is newer than {CONFIG_VERSION} of this node.");
+    }
+}
+
+/// Upgrades the content to the current layout, returning it along with the changed lines.
+fn migrate_content(content: &str) -> Result<(String, Vec<LineChange>)> {
+    let mut lines = Vec::new();
+    let mut changes = Vec::new();
+    let mut has_version = false;
+    for (index, line) in content.lines().enumerate() {
+        let migrated = match parse_assignment(line) {
+            Some((key, _)) if key.eq_ignore_ascii_case("DKN_CONFIG_VERSION") => {
+                has_version = true;
+                let version = line
+                    .split_once('=')
+                    .and_then(|(_, version)| version.trim().trim_matches('"').parse::<u32>().ok())
+                    .ok_or_else(|| {
+                        eyre::eyre!("invalid DKN_CONFIG_VERSION at line {}", index + 1)
+                    })?;
+                if version > CONFIG_VERSION {
+                    eyre::bail!("config version {version} is newer than {CONFIG_VERSION} of this node, update the node instead");
+                }
+                format!("DKN_CONFIG_VERSION={CONFIG_VERSION}")
+            }
+            _ => migrate_line(line),
+        };
+
+        if migrated != line {
+            changes.push(LineChange {
+                line: index + 1,
+                old: Some(line.to_string()),
+                new: migrated.clone(),
+            });
+        }
+        lines.push(migrated);
+    }
+
+    // the version is written at the top, so that it is within the base section
+    if !has_version {
+        let version = format!("DKN_CONFIG_VERSION={CONFIG_VERSION}");
+        changes.insert(
+            0,
+            LineChange {
+                line: 1,
+                old: None,
+                new: version.clone(),
+            },
+        );
+        lines.insert(0, version);
+    }
+
+    let mut migrated = lines.join("\n");
+    migrated.push('\n');
+    Ok((migrated, changes))
+}
+
+/// Upgrades a single line, returning it as is if nothing is changed.
+fn migrate_line(line: &str) -> String {
+    let trimmed = line.trim();
+
+    // TOML tables are profiles, e.g. `[test]` is `[profile.test]`
+    if trimmed.starts_with('[') && trimmed.ends_with(']') {
+        let name = trimmed[1..trimmed.len() - 1].trim();
+        return match name.starts_with("profile.") {
+            true => line.to_string(),
+            false => format!("[profile.{name}]"),
+        };
+    }
+
+    let Some((key, value)) = parse_assignment(line) else {
+        return line.to_string();
+    };
+
+    if let Some((_, note)) = LEGACY_VARS
+        .iter()
+        .find(|(var, _)| var.eq_ignore_ascii_case(key))
+    {
+        return format!("# {trimmed} (no longer used: {note})");
+    }
+
+    // lowercase TOML keys are not read, as the variables are uppercase
+    let key = match key.to_ascii_lowercase().starts_with("dkn_") {
+        true => key.to_ascii_uppercase(),
+        false => key.to_string(),
+    };
+
+    // TOML arrays are comma-separated lists, e.g. for `DKN_MODELS`
+    let value = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        Some(items) => items
+            .split(',')
+            .map(|item| item.trim().trim_matches(['"', '\'']))
+            .filter(|item| !item.is_empty())
+            .collect::<Vec<_>>()
+            .join(","),
+        None => value.to_string(),
+    };
+
+    let migrated = format!("{key}={value}");
+    // only rewritten if the key or the value changed, so that the formatting is kept otherwise
+    match parse_assignment(line) == Some((key.as_str(), value.as_str())) {
+        true => line.to_string(),
+        false => migrated,
+    }
+}
+
+/// Parses a `KEY=value` (or TOML-like `key = value`) line into its key & trimmed value,
+/// `None` for comments, empty lines & sections.
+fn parse_assignment(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('[') {
+        return None;
+    }
+
+    let (key, value) = trimmed.split_once('=')?;
+    let key = key.trim();
+    let is_identifier = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    is_identifier.then_some((key, value.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_content() {
+        let content = r#"DKN_WALLET_SECRET_KEY=0xabc
+DKN_ADMIN_PUBLIC_KEY=0x123
+dkn_models = ["gemma3:4b", "gemma3:12b"]
+# DKN_BATCH_SIZE=
+OLLAMA_HOST = "http://localhost"
+
+[test]
+DKN_NETWORK=testnet
+"#;
+
+        let (migrated, changes) = migrate_content(content).unwrap();
+        assert_eq!(
+            migrated,
+            r#"DKN_CONFIG_VERSION=2
+DKN_WALLET_SECRET_KEY=0xabc
+# DKN_ADMIN_PUBLIC_KEY=0x123 (no longer used: the admin key is no longer used)
+DKN_MODELS=gemma3:4b,gemma3:12b
+# DKN_BATCH_SIZE=
+OLLAMA_HOST = "http://localhost"
+
+[profile.test]
+DKN_NETWORK=testnet
+"#
+        );
+        assert_eq!(
+            changes.iter().map(|c| c.line).collect::<Vec<_>>(),
+            vec![1, 2, 3, 7]
+        );
+
+        // migrating again changes nothing
+        let (again, changes) = migrate_content(&migrated).unwrap();
+        assert_eq!(again, migrated);
+        assert!(changes.is_empty());
+
+        // a newer layout is not downgraded
+        assert!(migrate_content("DKN_CONFIG_VERSION=99\n").is_err());
+    }
+}
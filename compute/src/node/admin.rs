@@ -0,0 +1,646 @@
+use chrono::{DateTime, Utc};
+use eyre::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+
+use dkn_executor::Model;
+
+use crate::{
+    node::{
+        history::HISTORY_MAX_LIMIT, DriaNodeEvent, HistoryQuery, NodeReconfig, ReloadHandle,
+        TaskRecord,
+    },
+    utils::{read_request, write_response, HttpRequest, HTTP_REQUEST_TIMEOUT},
+    DriaComputeNode, DRIA_COMPUTE_NODE_VERSION,
+};
+
+/// Buffer size for the commands sent by the admin API.
+pub(crate) const ADMIN_COMMANDS_BUFSIZE: usize = 16;
+
+/// Commands that the admin API sends to the node, each with a sender for its result.
+#[derive(Debug)]
+pub enum AdminCommand {
+    /// Get the status of the node.
+    Status {
+        sender: oneshot::Sender<AdminStatus>,
+    },
+    /// Pause or resume accepting new tasks, pending tasks are still completed.
+    /// Returns whether the intake was paused before.
+    SetPaused {
+        paused: bool,
+        sender: oneshot::Sender<bool>,
+    },
+    /// Get the names of the models served by the node, i.e. without the ones that have
+    /// completed tasks before being removed.
+    Models {
+        sender: oneshot::Sender<Vec<String>>,
+    },
+    /// Re-check the RPC connection right away, and refresh the standby RPCs.
+    RefreshRpc { sender: oneshot::Sender<()> },
+    /// Get the node & P2P metrics in the OpenMetrics text format.
+    Metrics { sender: oneshot::Sender<String> },
+    /// Apply a reloaded configuration, see [`reload_config`](crate::node::reload_config).
+    Reconfigure {
+        reconfig: Box<NodeReconfig>,
+        sender: oneshot::Sender<()>,
+    },
+    /// Subscribe to the node events.
+    SubscribeEvents {
+        sender: oneshot::Sender<broadcast::Receiver<DriaNodeEvent>>,
+    },
+    /// Query the task history, returns an error if it is not enabled or could not be queried.
+    History {
+        query: HistoryQuery,
+        sender: oneshot::Sender<Result<Vec<TaskRecord>, String>>,
+    },
+}
+
+/// Status of the node, as returned by the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminStatus {
+    pub version: String,
+    pub address: String,
+    pub peer_id: String,
+    /// Address of the primary RPC.
+    pub rpc: String,
+    /// Whether the node is connected to the primary RPC.
+    pub rpc_connected: bool,
+    /// Addresses of the standby RPCs.
+    pub standby_rpcs: Vec<String>,
+    /// Whether the task intake is paused.
+    pub paused: bool,
+    /// Pending tasks, as `[single, batch]`.
+    pub pending_tasks: [usize; 2],
+    /// Completed tasks, as `[single, batch]`.
+    pub completed_tasks: [usize; 2],
+    /// Pending & completed tasks of each model served by the node.
+    pub models: BTreeMap<String, ModelTaskCount>,
+    /// Peers with an active connection.
+    pub connected_peers: Vec<String>,
+    pub last_heartbeat_at: DateTime<Utc>,
+    /// Total points, if they could be fetched.
+    pub points: Option<f64>,
+    /// Points earned in this run, if they could be fetched.
+    pub points_earned: Option<f64>,
+}
+
+/// Pending & completed tasks of a model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ModelTaskCount {
+    pub pending: usize,
+    pub completed: usize,
+}
+
+impl DriaComputeNode {
+    /// Returns a sender of commands to the node, to be used by the admin API.
+    pub fn admin_commander(&self) -> mpsc::Sender<AdminCommand> {
+        self.admin_tx.clone()
+    }
+
+    /// Handles a command from the admin API.
+    ///
+    /// Returns `true` if the heartbeat & specs should be sent right away, i.e. connecting to the RPC
+    /// was re-attempted or the models have changed.
+    pub(crate) async fn handle_admin_command(&mut self, command: AdminCommand) -> bool {
+        match command {
+            AdminCommand::Status { sender } => {
+                let connected_peers = self
+                    .p2p
+                    .connected_peers()
+                    .await
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|peer_id| peer_id.to_string())
+                    .collect();
+                let rpc_connected = self
+                    .p2p
+                    .is_connected(self.dria_rpc.peer_id)
+                    .await
+                    .unwrap_or(false);
+
+                // every model is listed, even if it had no tasks yet
+                let mut models = self
+                    .config
+                    .executors
+                    .get_model_names()
+                    .into_iter()
+                    .map(|model| (model, ModelTaskCount::default()))
+                    .collect::<BTreeMap<_, _>>();
+                for metadata in self
+                    .pending_tasks_single
+                    .values()
+                    .chain(self.pending_tasks_batch.values())
+                {
+                    models
+                        .entry(metadata.model.to_string())
+                        .or_default()
+                        .pending += 1;
+                }
+                for (model, completed) in &self.completed_tasks_per_model {
+                    models.entry(model.to_string()).or_default().completed = *completed;
+                }
+
+                let status = AdminStatus {
+                    version: DRIA_COMPUTE_NODE_VERSION.to_string(),
+                    address: format!("0x{}", self.config.address),
+                    peer_id: self.config.peer_id.to_string(),
+                    rpc: self.dria_rpc.addr.to_string(),
+                    rpc_connected,
+                    standby_rpcs: self
+                        .standby_rpcs
+                        .iter()
+                        .map(|rpc| rpc.addr.to_string())
+                        .collect(),
+                    paused: self.is_paused,
+                    pending_tasks: self.get_pending_task_count(),
+                    completed_tasks: [self.completed_tasks_single, self.completed_tasks_batch],
+                    models,
+                    connected_peers,
+                    last_heartbeat_at: self.last_heartbeat_at,
+                    points: self.points.as_ref().map(|points| points.score),
+                    points_earned: self
+                        .points
+                        .as_ref()
+                        .map(|points| points.score - self.points_client.initial),
+                };
+                let _ = sender.send(status);
+                false
+            }
+            AdminCommand::SetPaused { paused, sender } => {
+                if paused != self.is_paused {
+                    match paused {
+                        true => log::warn!("Task intake is paused by the admin API."),
+                        false => log::info!("Task intake is resumed by the admin API."),
+                    }
+                }
+                let _ = sender.send(std::mem::replace(&mut self.is_paused, paused));
+                false
+            }
+            AdminCommand::Models { sender } => {
+                let _ = sender.send(self.config.executors.get_model_names());
+                false
+            }
+            AdminCommand::RefreshRpc { sender } => {
+                log::info!("Refreshing the RPCs by the admin API.");
+                self.rpc_backoff.reset();
+                let is_reattempted = self.handle_rpc_liveness_check().await;
+                self.handle_standby_rpcs_refresh().await;
+                self.handle_health_refresh().await;
+                let _ = sender.send(());
+                is_reattempted
+            }
+            AdminCommand::Metrics { sender } => {
+                let _ = sender.send(self.encode_metrics().await);
+                false
+            }
+            AdminCommand::Reconfigure { reconfig, sender } => {
+                self.handle_reconfigure(*reconfig);
+                let _ = sender.send(());
+                true
+            }
+            AdminCommand::SubscribeEvents { sender } => {
+                let _ = sender.send(self.subscribe_events());
+                false
+            }
+            AdminCommand::History { query, sender } => {
+                let records = match &self.history {
+                    Some(history) => history.query(&query).map_err(|err| format!("{err:#}")),
+                    None => Err("task history is not enabled".to_string()),
+                };
+                let _ = sender.send(records);
+                false
+            }
+        }
+    }
+}
+
+/// Serves the admin API at the given address until cancelled, where each request must have
+/// the `Authorization: Bearer <token>` header:
+///
+/// - `GET /status` returns the [`AdminStatus`] of the node.
+/// - `POST /tasks/pause` & `POST /tasks/resume` pause & resume accepting new tasks.
+/// - `POST /rpc/refresh` re-checks the RPC connection & refreshes the standby RPCs.
+/// - `POST /config/reload` reloads the configuration in the background, same as `SIGHUP`.
+/// - `GET /models` returns the served models, and `POST /models` changes them with the comma-separated
+///   `add` & `remove` query parameters, reloading the configuration with them in the background.
+/// - `GET /events` streams the [`DriaNodeEvent`]s as server-sent events, until the client disconnects.
+/// - `GET /history` returns the most recent [`TaskRecord`]s, filtered by the `model`, `status`
+///   (`completed` or `failed`), `since` (RFC 3339) & `limit` query parameters.
+///
+/// This API is meant to be local, so it should not be served at a public address.
+pub async fn serve_admin(
+    addr: SocketAddr,
+    token: String,
+    commander: mpsc::Sender<AdminCommand>,
+    reload: ReloadHandle,
+    cancellation: CancellationToken,
+) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .wrap_err_with(|| format!("could not bind admin API to {addr}"))?;
+    log::info!("Serving admin API at http://{addr}");
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let token = token.clone();
+                    let commander = commander.clone();
+                    let reload = reload.clone();
+                    let cancellation = cancellation.clone();
+                    tokio::spawn(async move {
+                        handle_connection(stream, &token, &commander, &reload, &cancellation).await;
+                    });
+                }
+                Err(err) => log::warn!("Could not accept admin connection: {err}"),
+            },
+            _ = cancellation.cancelled() => {
+                log::info!("Closing admin API.");
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Reads a single request & writes its response, the connection is closed afterwards.
+///
+/// The event stream is kept open until the client disconnects, other requests are timed out.
+async fn handle_connection(
+    mut stream: TcpStream,
+    token: &str,
+    commander: &mpsc::Sender<AdminCommand>,
+    reload: &ReloadHandle,
+    cancellation: &CancellationToken,
+) {
+    let Ok(Some(request)) =
+        tokio::time::timeout(HTTP_REQUEST_TIMEOUT, read_request(&mut stream)).await
+    else {
+        log::debug!("Could not read admin request in time.");
+        return;
+    };
+
+    let is_authorized = is_authorized(&request, token);
+    if is_authorized && request.method == "GET" && request.path == "/events" {
+        if let Err(err) = stream_events(stream, commander, cancellation).await {
+            log::debug!("Admin event stream is closed: {err}");
+        }
+        return;
+    }
+
+    let respond = async {
+        let (status, body) = if !is_authorized {
+            ("401 Unauthorized", error_body("unauthorized"))
+        } else {
+            match route(&request, commander, reload).await {
+                Ok(response) => response,
+                Err(err) => {
+                    log::warn!("Could not handle admin request: {err:?}");
+                    ("503 Service Unavailable", error_body("node is not running"))
+                }
+            }
+        };
+
+        write_response(&mut stream, &request, status, &body, "application/json").await;
+    };
+    if let Err(err) = tokio::time::timeout(HTTP_REQUEST_TIMEOUT, respond).await {
+        log::debug!("Admin request timed out: {err}");
+    }
+}
+
+/// Writes the node events to the stream as server-sent events, until the client disconnects,
+/// the node stops or the API is cancelled.
+async fn stream_events(
+    mut stream: TcpStream,
+    commander: &mpsc::Sender<AdminCommand>,
+    cancellation: &CancellationToken,
+) -> Result<()> {
+    let (sender, receiver) = oneshot::channel();
+    commander
+        .send(AdminCommand::SubscribeEvents { sender })
+        .await?;
+    let mut events = receiver.await?;
+
+    stream
+        .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n")
+        .await?;
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("Admin event stream has missed {skipped} events.");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = cancellation.cancelled() => break,
+        };
+        stream.write_all(sse_frame(&event)?.as_bytes()).await?;
+    }
+
+    let _ = stream.shutdown().await;
+    Ok(())
+}
+
+/// Formats the event as a server-sent event, named after its type.
+fn sse_frame(event: &DriaNodeEvent) -> Result<String> {
+    let data = serde_json::to_value(event)?;
+    let name = data["event"].as_str().unwrap_or("message");
+    Ok(format!("event: {name}\ndata: {data}\n\n"))
+}
+
+/// Sends the command of the request to the node, and returns the status line & JSON body of the response.
+async fn route(
+    request: &HttpRequest,
+    commander: &mpsc::Sender<AdminCommand>,
+    reload: &ReloadHandle,
+) -> Result<(&'static str, String)> {
+    let body = match (request.method.as_str(), request.path.as_str()) {
+        ("GET" | "HEAD", "/status") => {
+            let (sender, receiver) = oneshot::channel();
+            commander.send(AdminCommand::Status { sender }).await?;
+            serde_json::to_string(&receiver.await?)?
+        }
+        ("POST", path @ ("/tasks/pause" | "/tasks/resume")) => {
+            let paused = path == "/tasks/pause";
+            let (sender, receiver) = oneshot::channel();
+            commander
+                .send(AdminCommand::SetPaused { paused, sender })
+                .await?;
+            let was_paused = receiver.await?;
+            serde_json::json!({ "paused": paused, "was_paused": was_paused }).to_string()
+        }
+        ("POST", "/rpc/refresh") => {
+            let (sender, receiver) = oneshot::channel();
+            commander.send(AdminCommand::RefreshRpc { sender }).await?;
+            receiver.await?;
+            serde_json::json!({ "refreshed": true }).to_string()
+        }
+        ("GET", "/history") => {
+            let query = match parse_history_query(request) {
+                Ok(query) => query,
+                Err(err) => return Ok(("400 Bad Request", error_body(&err))),
+            };
+            let (sender, receiver) = oneshot::channel();
+            commander
+                .send(AdminCommand::History { query, sender })
+                .await?;
+            match receiver.await? {
+                Ok(records) => serde_json::to_string(&records)?,
+                Err(err) => return Ok(("503 Service Unavailable", error_body(&err))),
+            }
+        }
+        ("POST", "/config/reload") => {
+            // the models are checked while reloading, which may take a while
+            reload.request();
+            let body = serde_json::json!({ "reloading": true }).to_string();
+            return Ok(("202 Accepted", body));
+        }
+        ("GET", "/models") => {
+            let models = served_models(commander).await?;
+            serde_json::json!({ "models": models }).to_string()
+        }
+        ("POST", "/models") => {
+            let served = served_models(commander).await?;
+            let models = match parse_models_change(request, served) {
+                Ok(models) => models,
+                Err(err) => return Ok(("400 Bad Request", error_body(&err))),
+            };
+            let names = models.iter().map(ToString::to_string).collect::<Vec<_>>();
+            reload.request_models(models);
+            let body = serde_json::json!({ "reloading": true, "models": names }).to_string();
+            return Ok(("202 Accepted", body));
+        }
+        (
+            _,
+            "/status" | "/tasks/pause" | "/tasks/resume" | "/rpc/refresh" | "/config/reload"
+            | "/models" | "/events" | "/history",
+        ) => return Ok(("405 Method Not Allowed", error_body("method not allowed"))),
+        _ => return Ok(("404 Not Found", error_body("not found"))),
+    };
+
+    Ok(("200 OK", body))
+}
+
+/// Returns the names of the models served by the node.
+async fn served_models(commander: &mpsc::Sender<AdminCommand>) -> Result<Vec<String>> {
+    let (sender, receiver) = oneshot::channel();
+    commander.send(AdminCommand::Models { sender }).await?;
+    Ok(receiver.await?)
+}
+
+/// Returns the models to serve after applying the `add` & `remove` query parameters of the request
+/// to the served ones.
+fn parse_models_change(request: &HttpRequest, served: Vec<String>) -> Result<Vec<Model>, String> {
+    let parse = |param: &str| {
+        request
+            .query_param(param)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| Model::try_from(name).map_err(|_| format!("unknown model: {name}")))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let add = parse("add")?;
+    let remove = parse("remove")?;
+    if add.is_empty() && remove.is_empty() {
+        return Err("no models to add or remove".to_string());
+    }
+
+    let mut models = served
+        .iter()
+        .filter_map(|name| Model::try_from(name.as_str()).ok())
+        .collect::<Vec<_>>();
+    for model in add {
+        if !models.contains(&model) {
+            models.push(model);
+        }
+    }
+    models.retain(|model| !remove.contains(model));
+    if models.is_empty() {
+        return Err("at least one model must be served".to_string());
+    }
+
+    Ok(models)
+}
+
+/// Parses the filters of a history query from the query parameters of the request.
+fn parse_history_query(request: &HttpRequest) -> Result<HistoryQuery, String> {
+    let mut query = HistoryQuery {
+        model: request.query_param("model").map(ToString::to_string),
+        ..Default::default()
+    };
+    query.success = match request.query_param("status") {
+        None => None,
+        Some("completed") => Some(true),
+        Some("failed") => Some(false),
+        Some(status) => return Err(format!("unknown status: {status}")),
+    };
+    if let Some(since) = request.query_param("since") {
+        let since =
+            DateTime::parse_from_rfc3339(since).map_err(|err| format!("invalid since: {err}"))?;
+        query.since = Some(since.to_utc());
+    }
+    if let Some(limit) = request.query_param("limit") {
+        query.limit = limit
+            .parse::<usize>()
+            .map_err(|err| format!("invalid limit: {err}"))?
+            .min(HISTORY_MAX_LIMIT);
+    }
+
+    Ok(query)
+}
+
+/// Checks the bearer token of the request, in constant time w.r.t. the token contents.
+fn is_authorized(request: &HttpRequest, token: &str) -> bool {
+    let Some(given) = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn error_body(error: &str) -> String {
+    serde_json::json!({ "error": error }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_admin_routes() {
+        let (commander, mut commands) = mpsc::channel(ADMIN_COMMANDS_BUFSIZE);
+        tokio::spawn(async move {
+            let mut paused = false;
+            while let Some(command) = commands.recv().await {
+                match command {
+                    AdminCommand::SetPaused { paused: p, sender } => {
+                        let _ = sender.send(std::mem::replace(&mut paused, p));
+                    }
+                    AdminCommand::Models { sender } => {
+                        let _ = sender.send(vec!["gemma3:4b".to_string()]);
+                    }
+                    AdminCommand::RefreshRpc { sender } => {
+                        let _ = sender.send(());
+                    }
+                    AdminCommand::History { query, sender } => {
+                        assert_eq!(query.model.as_deref(), Some("gemma3:4b"));
+                        assert_eq!(query.success, Some(false));
+                        assert_eq!(query.limit, HISTORY_MAX_LIMIT);
+                        let _ = sender.send(Ok(Vec::new()));
+                    }
+                    AdminCommand::Status { .. }
+                    | AdminCommand::Metrics { .. }
+                    | AdminCommand::Reconfigure { .. }
+                    | AdminCommand::SubscribeEvents { .. } => unimplemented!(),
+                }
+            }
+        });
+
+        let reload = ReloadHandle::default();
+        let request = |method: &str, path: &str| HttpRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: Vec::new(),
+            headers: vec![("authorization".to_string(), "Bearer secret".to_string())],
+        };
+
+        let (status, body) = route(&request("POST", "/tasks/pause"), &commander, &reload)
+            .await
+            .unwrap();
+        assert_eq!(status, "200 OK");
+        assert_eq!(body, r#"{"paused":true,"was_paused":false}"#);
+        let (_, body) = route(&request("POST", "/tasks/resume"), &commander, &reload)
+            .await
+            .unwrap();
+        assert_eq!(body, r#"{"paused":false,"was_paused":true}"#);
+
+        let (status, _) = route(&request("POST", "/rpc/refresh"), &commander, &reload)
+            .await
+            .unwrap();
+        assert_eq!(status, "200 OK");
+        let (status, _) = route(&request("GET", "/tasks/pause"), &commander, &reload)
+            .await
+            .unwrap();
+        assert_eq!(status, "405 Method Not Allowed");
+        let mut history = request("GET", "/history");
+        history.query = vec![
+            ("model".to_string(), "gemma3:4b".to_string()),
+            ("status".to_string(), "failed".to_string()),
+            ("limit".to_string(), "5000".to_string()),
+        ];
+        let (status, body) = route(&history, &commander, &reload).await.unwrap();
+        assert_eq!(status, "200 OK");
+        assert_eq!(body, "[]");
+        history.query = vec![("since".to_string(), "yesterday".to_string())];
+        let (status, _) = route(&history, &commander, &reload).await.unwrap();
+        assert_eq!(status, "400 Bad Request");
+
+        let (status, _) = route(&request("GET", "/nope"), &commander, &reload)
+            .await
+            .unwrap();
+        assert_eq!(status, "404 Not Found");
+
+        let (status, _) = route(&request("POST", "/config/reload"), &commander, &reload)
+            .await
+            .unwrap();
+        assert_eq!(status, "202 Accepted");
+        tokio::time::timeout(std::time::Duration::from_secs(1), reload.requested())
+            .await
+            .unwrap();
+
+        let (status, body) = route(&request("GET", "/models"), &commander, &reload)
+            .await
+            .unwrap();
+        assert_eq!(status, "200 OK");
+        assert_eq!(body, r#"{"models":["gemma3:4b"]}"#);
+
+        let mut change = request("POST", "/models");
+        change.query = vec![
+            ("add".to_string(), "gemma3:12b,gemma3:4b".to_string()),
+            ("remove".to_string(), "mistral-nemo:12b".to_string()),
+        ];
+        let served = vec!["gemma3:4b".to_string(), "mistral-nemo:12b".to_string()];
+        assert_eq!(
+            parse_models_change(&change, served.clone()).unwrap(),
+            vec![Model::Gemma3_4b, Model::Gemma3_12b]
+        );
+        change.query = vec![("add".to_string(), "gpt-5".to_string())];
+        assert!(parse_models_change(&change, served.clone()).is_err());
+        change.query = vec![(
+            "remove".to_string(),
+            "gemma3:4b,mistral-nemo:12b".to_string(),
+        )];
+        assert!(parse_models_change(&change, served).is_err());
+
+        let frame = sse_frame(&DriaNodeEvent::RpcChanged {
+            previous: "a".to_string(),
+            rpc: "b".to_string(),
+        })
+        .unwrap();
+        assert_eq!(
+            frame,
+            "event: rpc_changed\ndata: {\"event\":\"rpc_changed\",\"previous\":\"a\",\"rpc\":\"b\"}\n\n"
+        );
+
+        assert!(is_authorized(&request("GET", "/status"), "secret"));
+        assert!(!is_authorized(&request("GET", "/status"), "secreT"));
+        assert!(!is_authorized(&request("GET", "/status"), "secrets"));
+        assert!(!is_authorized(&HttpRequest::default(), "secret"));
+    }
+}
@@ -0,0 +1,329 @@
+use dkn_executor::{
+    DriaExecutor, DriaExecutorsManager, Model, ModelProvider, OllamaClient, WasmClient,
+};
+use dkn_p2p::{libp2p::Multiaddr, DriaP2PClient};
+use dkn_utils::DriaNetwork;
+use eyre::{eyre, Result};
+use libsecp256k1::SecretKey;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{
+    config::{DriaComputeNodeConfig, NodeIntervals},
+    utils::Signer,
+    workers::task::TaskWorker,
+    DriaComputeNode,
+};
+
+/// Builds a compute node without looking at the environment variables, for applications that
+/// embed the node as a library.
+///
+/// ```no_run
+/// # async fn example() -> eyre::Result<()> {
+/// use dkn_compute::DriaComputeNode;
+/// use dkn_executor::Model;
+///
+/// let (node, p2p, worker_batch, worker_single) = DriaComputeNode::builder()
+///     .secret_key(libsecp256k1::SecretKey::random(&mut rand::thread_rng()))
+///     .models([Model::Llama3_1_8bInstructQ4Km])
+///     .listen_addr("/ip4/0.0.0.0/tcp/4001".parse()?)
+///     .build()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The built node, P2P client & workers are to be run just like the binary does.
+#[derive(Default)]
+pub struct DriaComputeNodeBuilder {
+    secret_key: Option<SecretKey>,
+    signer: Option<Arc<dyn Signer>>,
+    models: Vec<Model>,
+    executors: DriaExecutorsManager,
+    listen_addrs: Vec<Multiaddr>,
+    network: Option<DriaNetwork>,
+    batch_size: Option<usize>,
+    rpc_addr: Option<Multiaddr>,
+    state_path: Option<PathBuf>,
+    peer_store_path: Option<PathBuf>,
+    stats_path: Option<PathBuf>,
+    journal_path: Option<PathBuf>,
+    status_path: Option<PathBuf>,
+    points_ledger_path: Option<PathBuf>,
+    history_path: Option<PathBuf>,
+    intervals: Option<NodeIntervals>,
+}
+
+impl DriaComputeNode {
+    /// Returns a builder for a node that is configured in code, see [`DriaComputeNodeBuilder`].
+    pub fn builder() -> DriaComputeNodeBuilder {
+        DriaComputeNodeBuilder::default()
+    }
+}
+
+impl DriaComputeNodeBuilder {
+    /// Sets the wallet secret key, which is required.
+    ///
+    /// With a [`Self::signer`], this is only the secret key of the P2P identity.
+    pub fn secret_key(mut self, secret_key: SecretKey) -> Self {
+        self.secret_key = Some(secret_key);
+        self
+    }
+
+    /// Signs the messages with the given signer instead of the secret key, e.g. a [`crate::utils::RemoteSigner`].
+    pub fn signer(mut self, signer: Arc<dyn Signer>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Adds models that are served with the default executor of their provider,
+    /// e.g. Ollama at its default host & port.
+    ///
+    /// Providers without a default, such as external executors, must be given with [`Self::executor`].
+    pub fn models(mut self, models: impl IntoIterator<Item = Model>) -> Self {
+        self.models.extend(models);
+        self
+    }
+
+    /// Adds models that are served with the given executor, e.g. Ollama at a custom host.
+    pub fn executor(
+        mut self,
+        executor: DriaExecutor,
+        models: impl IntoIterator<Item = Model>,
+    ) -> Self {
+        self.executors = self.executors.with_executor(executor, models);
+        self
+    }
+
+    /// Adds a P2P listen address, defaults to `/ip4/0.0.0.0/tcp/4001` if none is given.
+    pub fn listen_addr(mut self, addr: Multiaddr) -> Self {
+        self.listen_addrs.push(addr);
+        self
+    }
+
+    /// Sets the network, defaults to mainnet.
+    pub fn network(mut self, network: DriaNetwork) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// Sets the batch size for batchable tasks.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Pins the RPC to connect to, instead of using the discovery API.
+    pub fn rpc_addr(mut self, addr: Multiaddr) -> Self {
+        self.rpc_addr = Some(addr);
+        self
+    }
+
+    /// Sets the path where the node state is snapshotted, not persisted by default.
+    pub fn state_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_path = Some(path.into());
+        self
+    }
+
+    /// Sets the path where known RPCs are persisted, not persisted by default.
+    pub fn peer_store_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.peer_store_path = Some(path.into());
+        self
+    }
+
+    /// Sets the path where the lifetime statistics are kept, only kept for the session by default.
+    pub fn stats_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.stats_path = Some(path.into());
+        self
+    }
+
+    /// Sets the path where the pending tasks are journaled, not journaled by default.
+    pub fn journal_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.journal_path = Some(path.into());
+        self
+    }
+
+    /// Sets the path where the status is written for external monitors, not written by default.
+    pub fn status_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.status_path = Some(path.into());
+        self
+    }
+
+    /// Sets the path where the points history is kept, only kept for the session by default.
+    pub fn points_ledger_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.points_ledger_path = Some(path.into());
+        self
+    }
+
+    /// Sets the path of the database where the tasks are recorded, not recorded by default.
+    pub fn history_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.history_path = Some(path.into());
+        self
+    }
+
+    /// Sets the intervals of the periodic jobs, e.g. shorter ones for a test network.
+    pub fn intervals(mut self, intervals: NodeIntervals) -> Self {
+        self.intervals = Some(intervals);
+        self
+    }
+
+    /// Returns the config of the node, without checking the services of the models.
+    ///
+    /// Further options can be set on the returned config before passing it to [`DriaComputeNode::new`].
+    pub fn build_config(self) -> Result<DriaComputeNodeConfig> {
+        let secret_key = self
+            .secret_key
+            .ok_or_else(|| eyre!("a secret key is required to build the node"))?;
+
+        let mut executors = self.executors;
+        for model in self.models {
+            let provider = model.provider();
+            match executors.providers.get_mut(&provider) {
+                Some((_, models)) => {
+                    models.insert(model);
+                    executors.models.insert(model);
+                }
+                None => {
+                    let executor = match provider {
+                        ModelProvider::Ollama => {
+                            DriaExecutor::Ollama(OllamaClient::with_defaults())
+                        }
+                        ModelProvider::Wasm => DriaExecutor::Wasm(WasmClient::with_defaults()?),
+                        ModelProvider::External => {
+                            return Err(eyre!(
+                            "{model} requires an executor, see `DriaComputeNodeBuilder::executor`"
+                        ))
+                        }
+                    };
+                    executors = executors.with_executor(executor, [model]);
+                }
+            }
+        }
+        if executors.models.is_empty() {
+            return Err(eyre!("at least one model is required to build the node"));
+        }
+
+        let mut config = DriaComputeNodeConfig::with_defaults(secret_key, executors);
+        if let Some(signer) = self.signer {
+            config.set_signer(signer);
+        }
+        if !self.listen_addrs.is_empty() {
+            config.p2p_listen_addrs = self.listen_addrs;
+        }
+        if let Some(network) = self.network {
+            config.network = network;
+        }
+        if let Some(batch_size) = self.batch_size {
+            if batch_size > TaskWorker::MAX_BATCH_SIZE {
+                return Err(eyre!(
+                    "batch size must be at most {}",
+                    TaskWorker::MAX_BATCH_SIZE
+                ));
+            }
+            config.batch_size = batch_size;
+        }
+        config.pinned_rpc_addr = self.rpc_addr;
+        config.state_path = self.state_path;
+        config.peer_store_path = self.peer_store_path;
+        config.stats_path = self.stats_path;
+        config.journal_path = self.journal_path;
+        config.status_path = self.status_path;
+        config.points_ledger_path = self.points_ledger_path;
+        config.history_path = self.history_path;
+        if let Some(intervals) = self.intervals {
+            let NodeIntervals {
+                diagnostic_refresh,
+                specs,
+                rpc_liveness_refresh,
+                points_refresh,
+                ..
+            } = intervals;
+            if [
+                diagnostic_refresh,
+                specs,
+                rpc_liveness_refresh,
+                points_refresh,
+            ]
+            .iter()
+            .any(Duration::is_zero)
+            {
+                return Err(eyre!("intervals must be positive"));
+            }
+            intervals.check_heartbeat_bounds()?;
+            config.intervals = intervals;
+        }
+
+        Ok(config)
+    }
+
+    /// Checks the services of the models, and creates the node along with its P2P client & workers.
+    ///
+    /// Returns an error if no model is left after the service checks.
+    pub async fn build(
+        self,
+    ) -> Result<(
+        DriaComputeNode,
+        DriaP2PClient,
+        Option<TaskWorker>,
+        Option<TaskWorker>,
+    )> {
+        let mut config = self.build_config()?;
+        let model_perf = config.executors.check_services().await;
+        if config.executors.models.is_empty() {
+            return Err(eyre!("no valid models left after service checks"));
+        }
+
+        DriaComputeNode::new(config, model_perf).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dkn_executor::ExternalClient;
+
+    #[test]
+    fn test_builder_config() {
+        let secret_key = SecretKey::random(&mut rand::thread_rng());
+        let listen_addr: Multiaddr = "/ip4/127.0.0.1/tcp/4321".parse().unwrap();
+        let config = DriaComputeNode::builder()
+            .secret_key(secret_key)
+            .models([Model::Wasm])
+            .listen_addr(listen_addr.clone())
+            .network(DriaNetwork::Testnet)
+            .batch_size(3)
+            .build_config()
+            .unwrap();
+        assert_eq!(config.secret_key, secret_key);
+        assert_eq!(config.p2p_listen_addrs, vec![listen_addr]);
+        assert_eq!(config.network, DriaNetwork::Testnet);
+        assert_eq!(config.batch_size, 3);
+        assert!(config.executors.models.contains(&Model::Wasm));
+
+        // a secret key & a model are required
+        assert!(DriaComputeNode::builder()
+            .models([Model::Wasm])
+            .build_config()
+            .is_err());
+        assert!(DriaComputeNode::builder()
+            .secret_key(secret_key)
+            .build_config()
+            .is_err());
+
+        // external models require an explicit executor
+        assert!(DriaComputeNode::builder()
+            .secret_key(secret_key)
+            .models([Model::External])
+            .build_config()
+            .is_err());
+        let executor =
+            DriaExecutor::External(ExternalClient::new("cat", vec![], Duration::from_secs(1)));
+        let config = DriaComputeNode::builder()
+            .secret_key(secret_key)
+            .executor(executor, [Model::External])
+            .build_config()
+            .unwrap();
+        assert!(config.executors.models.contains(&Model::External));
+    }
+}
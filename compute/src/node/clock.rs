@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+
+use crate::{utils::measure_clock_offset, DriaComputeNode};
+
+/// Clock drift beyond which the deadlines are compensated, as the RPC would consider them skewed.
+const CLOCK_DRIFT_THRESHOLD: chrono::Duration = chrono::Duration::seconds(2);
+
+impl DriaComputeNode {
+    /// Returns the current time, corrected by the clock drift if it exceeds the threshold.
+    ///
+    /// This is to be used for the deadlines that are shared with the RPC.
+    pub(crate) fn network_now(&self) -> DateTime<Utc> {
+        Utc::now() + self.clock_offset
+    }
+
+    /// Measures the drift of the local clock against the NTP server, if any, and warns if it
+    /// exceeds the threshold, in which case the deadlines are compensated for it.
+    ///
+    /// The previous offset is kept if the NTP server can not be reached.
+    pub(crate) async fn handle_clock_check(&mut self) {
+        let Some(server) = &self.config.ntp_server else {
+            return;
+        };
+
+        let offset = match measure_clock_offset(server).await {
+            Ok(offset) => offset,
+            Err(err) => {
+                log::debug!("Could not check the clock drift: {err:?}");
+                return;
+            }
+        };
+
+        let compensated = compensated_offset(offset, CLOCK_DRIFT_THRESHOLD);
+        if !compensated.is_zero() {
+            log::warn!(
+                "Local clock is off by {}ms from {server}, the deadlines are compensated but you should sync your system clock.",
+                offset.num_milliseconds()
+            );
+        } else if !self.clock_offset.is_zero() {
+            log::info!("Local clock is in sync with {server} again.");
+        }
+        self.clock_offset = compensated;
+    }
+}
+
+/// Returns the offset to compensate for, i.e. the given one if it exceeds the threshold in either direction,
+/// and zero otherwise so that small drifts & the jitter of the measurements are ignored.
+fn compensated_offset(offset: chrono::Duration, threshold: chrono::Duration) -> chrono::Duration {
+    if offset.abs() > threshold {
+        offset
+    } else {
+        chrono::Duration::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compensated_offset() {
+        let threshold = chrono::Duration::seconds(2);
+        assert!(compensated_offset(chrono::Duration::milliseconds(1500), threshold).is_zero());
+        assert!(compensated_offset(chrono::Duration::milliseconds(-1500), threshold).is_zero());
+        assert_eq!(
+            compensated_offset(chrono::Duration::seconds(-30), threshold),
+            chrono::Duration::seconds(-30)
+        );
+        assert_eq!(
+            compensated_offset(chrono::Duration::seconds(5), threshold),
+            chrono::Duration::seconds(5)
+        );
+    }
+}
@@ -1,14 +1,15 @@
 use colored::Colorize;
 use dkn_p2p::libp2p::{Multiaddr, PeerId};
 use dkn_utils::{
-    payloads::{HEARTBEAT_TOPIC, SPECS_TOPIC},
-    DriaMessage,
+    crypto::session::SessionKey,
+    payloads::{HEARTBEAT_TOPIC, SPECS_TOPIC, TASK_PROGRESS_TOPIC},
+    DriaMessage, DriaMessageEncoding,
 };
-use eyre::{eyre, Result};
+use eyre::{eyre, Context, Result};
 use std::time::Duration;
 use tokio_util::sync::CancellationToken;
 
-use crate::{reqres::HeartbeatRequester, DriaComputeNode};
+use crate::{utils::sign_messages, DriaComputeNode};
 
 impl DriaComputeNode {
     /// Runs the main loop of the compute node.
@@ -17,44 +18,68 @@ impl DriaComputeNode {
         // initialize the points client
         self.points_client.initialize().await;
 
-        /// Duration between refreshing for diagnostic prints.
-        const DIAGNOSTIC_REFRESH_INTERVAL_SECS: Duration = Duration::from_secs(45);
-        /// Duration between refreshing for points update.
-        const POINTS_REFRESH_INTERVAL_SECS: Duration = Duration::from_secs(180);
-        /// Duration between refreshing the available nodes.
-        const RPC_LIVENESS_REFRESH_INTERVAL_SECS: Duration = Duration::from_secs(2 * 60);
-        /// Duration between each specs update sent to the RPC.
-        const SPECS_INTERVAL_SECS: Duration = Duration::from_secs(60 * 5);
-
-        let mut diagnostic_refresh_interval =
-            tokio::time::interval(DIAGNOSTIC_REFRESH_INTERVAL_SECS);
+        // connect to the standby RPCs, so that we can fail over to them right away
+        self.handle_standby_rpcs_refresh().await;
+        self.write_status(self.health.checks(), true);
+
+        // the intervals of the main jobs are configurable, see `NodeIntervals`
+        let intervals = self.config.intervals;
+
+        /// Duration between refreshing the standby RPCs.
+        const RPC_STANDBY_REFRESH_INTERVAL_SECS: Duration = Duration::from_secs(2 * 60);
+        /// Duration between checks for models that went cold & need to be warmed up.
+        const MODEL_WARMUP_INTERVAL_SECS: Duration = Duration::from_secs(60);
+        /// Duration between checks for a newer release, which is downloaded if auto-update is enabled.
+        const UPDATE_CHECK_INTERVAL_SECS: Duration = Duration::from_secs(6 * 60 * 60);
+        /// Duration between checks of the local clock against NTP, the first one is at startup.
+        const CLOCK_CHECK_INTERVAL_SECS: Duration = Duration::from_secs(30 * 60);
+
+        let mut diagnostic_refresh_interval = tokio::time::interval(intervals.diagnostic_refresh);
         diagnostic_refresh_interval.tick().await; // move each one tick
         let mut rpc_liveness_refresh_interval =
-            tokio::time::interval(RPC_LIVENESS_REFRESH_INTERVAL_SECS);
+            tokio::time::interval(intervals.rpc_liveness_refresh);
         rpc_liveness_refresh_interval.tick().await; // move each one tick
+        rpc_liveness_refresh_interval.reset_after(intervals.diagnostic_refresh); // give the initial dial some time
+        let mut rpc_standby_refresh_interval =
+            tokio::time::interval(RPC_STANDBY_REFRESH_INTERVAL_SECS);
+        rpc_standby_refresh_interval.tick().await;
 
         // tick the first time a bit earlier
-        let mut points_refresh_interval = tokio::time::interval(POINTS_REFRESH_INTERVAL_SECS);
+        let mut points_refresh_interval = tokio::time::interval(intervals.points_refresh);
         points_refresh_interval.tick().await;
-        points_refresh_interval.reset_after(POINTS_REFRESH_INTERVAL_SECS / 12);
+        points_refresh_interval.reset_after(intervals.points_refresh / 12);
 
         // move one tick, and wait at least a third of the diagnostics
-        let mut heartbeat_interval = tokio::time::interval(HeartbeatRequester::HEARTBEAT_DEADLINE);
+        let mut heartbeat_interval = tokio::time::interval(intervals.heartbeat_deadline);
         heartbeat_interval.tick().await;
-        heartbeat_interval.reset_after(DIAGNOSTIC_REFRESH_INTERVAL_SECS / 3);
+        heartbeat_interval.reset_after(intervals.diagnostic_refresh / 3);
 
         // move one tick, and wait a little bit
-        let mut specs_interval = tokio::time::interval(SPECS_INTERVAL_SECS);
+        let mut specs_interval = tokio::time::interval(intervals.specs);
         specs_interval.tick().await;
-        specs_interval.reset_after(DIAGNOSTIC_REFRESH_INTERVAL_SECS / 6);
+        specs_interval.reset_after(intervals.diagnostic_refresh / 6);
+
+        // models are warmed up at startup, so we can skip the first tick
+        let mut model_warmup_interval = tokio::time::interval(MODEL_WARMUP_INTERVAL_SECS);
+        model_warmup_interval.tick().await;
 
+        // move one tick, and check a while after startup
+        let mut update_check_interval = tokio::time::interval(UPDATE_CHECK_INTERVAL_SECS);
+        update_check_interval.tick().await;
+        update_check_interval.reset_after(intervals.diagnostic_refresh * 2);
+
+        let mut clock_check_interval = tokio::time::interval(CLOCK_CHECK_INTERVAL_SECS);
+
+        let mut task_outputs = Vec::new();
         loop {
             tokio::select! {
                 // a task is completed by the worker & should be responded to the requesting peer
-                task_response_msg_opt = self.task_output_rx.recv() => {
-                    if let Some(task_response_msg) = task_response_msg_opt {
-                        if let Err(err) = self.send_task_output(task_response_msg).await {
-                            log::error!("Error responding to task: {err:?}");
+                // where the tasks that are completed together (e.g. of a batch) are responded to at once
+                num_outputs = self.task_output_rx.recv_many(&mut task_outputs, self.config.batch_size.max(1)) => {
+                    if num_outputs > 0 {
+                        if let Err(err) = self.send_task_outputs(std::mem::take(&mut task_outputs)).await {
+                            log::error!("Error responding to tasks: {err:?}");
+                            self.record_error(format!("could not respond to tasks: {err}"));
                         }
                     } else {
                         log::error!("task_output_rx channel closed unexpectedly, we still have {} batch and {} single tasks.", self.pending_tasks_batch.len(), self.pending_tasks_single.len());
@@ -62,10 +87,17 @@ impl DriaComputeNode {
                     }
                 },
 
+                // a task has made progress & it should be reported to the RPC
+                Some(task_progress) = self.task_progress_rx.recv() => {
+                    if let Err(err) = self.send_task_progress(task_progress).await {
+                        log::error!("Error sending {}: {err:?}", TASK_PROGRESS_TOPIC.cyan());
+                    }
+                },
+
                 // a Request or Response is received by the p2p client
                 reqres_msg_opt = self.reqres_rx.recv() => {
-                  if let Some((peer_id, message)) = reqres_msg_opt {
-                    self.handle_reqres(peer_id, message).await;
+                  if let Some((peer_id, version, message)) = reqres_msg_opt {
+                    self.handle_reqres(peer_id, &version, message).await;
                   } else {
                     log::error!("reqres_rx channel closed unexpectedly.");
                     break;
@@ -75,18 +107,36 @@ impl DriaComputeNode {
                 // check peer count every now and then
                 _ = diagnostic_refresh_interval.tick() => self.handle_diagnostic_refresh().await,
 
-                // check RPC, and get a new one if we are disconnected
+                // check RPC, and fail over or get a new one if we are disconnected
                 _ = rpc_liveness_refresh_interval.tick() => {
-                    let is_connected = self.handle_rpc_liveness_check().await;
-                    if !is_connected {
+                    if self.handle_rpc_liveness_check().await {
                         // make sure we reset the heartbeat and specs intervals so that
                         // we dont wait the entire duration for this new connection
                         log::info!("Connecting was re-attempted, resetting timers.");
                         heartbeat_interval.reset_after(Duration::from_secs(5));
                         specs_interval.reset_after(Duration::from_secs(5));
                     }
+                    self.handle_health_refresh().await;
+
+                    // restart once the tasks are drained for a staged update
+                    if self.is_update_drained() {
+                        log::warn!("Shutting down the node to restart with the update.");
+                        cancellation.cancel();
+                    }
                 },
 
+                // a command is received from the admin API
+                Some(admin_command) = self.admin_rx.recv() => {
+                    if self.handle_admin_command(admin_command).await {
+                        log::info!("Resetting timers for the admin command.");
+                        heartbeat_interval.reset_after(Duration::from_secs(5));
+                        specs_interval.reset_after(Duration::from_secs(5));
+                    }
+                },
+
+                // keep the connections with standby RPCs warm
+                _ = rpc_standby_refresh_interval.tick() => self.handle_standby_rpcs_refresh().await,
+
                 // log points every now and then
                 _ = points_refresh_interval.tick() => self.handle_points_refresh().await,
 
@@ -94,6 +144,7 @@ impl DriaComputeNode {
                 _ = heartbeat_interval.tick() => {
                   if let Err(e) = self.send_heartbeat().await {
                     log::error!("Error making {}: {:?}", HEARTBEAT_TOPIC.blue(), e);
+                    self.record_error(format!("could not make heartbeat: {e}"));
                   }
                 },
 
@@ -104,6 +155,15 @@ impl DriaComputeNode {
                   }
                 },
 
+                // warm up the models that were unloaded due to being idle
+                _ = model_warmup_interval.tick() => self.handle_model_warmup(),
+
+                // check if a newer version is released
+                _ = update_check_interval.tick() => self.handle_update_check().await,
+
+                // check the drift of the local clock, so that the deadlines can be compensated
+                _ = clock_check_interval.tick() => self.handle_clock_check().await,
+
                 // check if the cancellation token is cancelled
                 // this is expected to be cancelled by the main thread with signal handling
                 _ = cancellation.cancelled() => {
@@ -120,20 +180,103 @@ impl DriaComputeNode {
         if let Err(err) = self.shutdown().await {
             log::error!("Could not shutdown the node gracefully: {err:?}");
         }
+
+        // snapshot the final state, after the remaining tasks are responded to
+        if let Err(err) = self.save_state() {
+            log::error!("Could not save node state: {err:?}");
+        }
+        if let Err(err) = self.lifetime.save() {
+            log::error!("Could not save lifetime stats: {err:?}");
+        }
+
+        // the last status tells the monitors that the node is stopped, rather than stuck
+        self.write_status(self.health.checks(), false);
     }
 
-    /// Shorthand method to create a signed message with the given data and topic.
+    /// Shorthand method to create a message with the given data and topic, signed by the configured signer.
     ///
     /// Topic was previously used for GossipSub, but kept for verbosity.
-    #[inline(always)]
-    pub fn new_message(&self, data: impl AsRef<[u8]>, topic: impl ToString) -> DriaMessage {
-        DriaMessage::new_signed(
+    pub async fn new_message(
+        &self,
+        data: impl AsRef<[u8]>,
+        topic: impl ToString,
+    ) -> Result<DriaMessage> {
+        self.new_session_message(data, topic, None).await
+    }
+
+    /// Creates a new message like [`Self::new_message`], with its payload encrypted within the given session if any.
+    pub(crate) async fn new_session_message(
+        &self,
+        data: impl AsRef<[u8]>,
+        topic: impl ToString,
+        session: Option<&SessionKey>,
+    ) -> Result<DriaMessage> {
+        let mut message = self.new_unsigned_message(data, topic);
+        if let Some(session) = session {
+            message
+                .encrypt_payload(session)
+                .wrap_err("could not encrypt message")?;
+        }
+        let (signature, recovery_id) = self
+            .config
+            .signer
+            .sign(message.signing_digest())
+            .await
+            .wrap_err("could not sign message")?;
+        message.set_signature(&signature, &recovery_id);
+
+        Ok(message)
+    }
+
+    /// Creates a new unsigned message, with the TTL of the config if any.
+    fn new_unsigned_message(&self, data: impl AsRef<[u8]>, topic: impl ToString) -> DriaMessage {
+        let message = DriaMessage::new_unsigned(
             data,
             topic,
             self.p2p.protocol().name.clone(),
-            &self.config.secret_key,
             self.config.version,
-        )
+        );
+        match self.config.message_ttl {
+            Some(ttl) => message.with_ttl(ttl),
+            None => message,
+        }
+    }
+
+    /// Returns the encoding to send the requests to the given peer with, i.e. CBOR if the peer has sent
+    /// a request in CBOR, and JSON otherwise.
+    pub(crate) fn request_encoding(&self, peer_id: PeerId) -> DriaMessageEncoding {
+        self.peer_encodings
+            .get(&peer_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Creates a new message for each of the given data like [`Self::new_session_message`], where they are
+    /// hashed & signed at once.
+    pub(crate) async fn new_session_messages<'a>(
+        &self,
+        data: impl IntoIterator<Item = (impl AsRef<[u8]>, Option<&'a SessionKey>)>,
+        topic: impl ToString,
+    ) -> Result<Vec<DriaMessage>> {
+        let topic = topic.to_string();
+        let mut messages = data
+            .into_iter()
+            .map(|(data, session)| {
+                let mut message = self.new_unsigned_message(data, &topic);
+                if let Some(session) = session {
+                    message
+                        .encrypt_payload(session)
+                        .wrap_err("could not encrypt message")?;
+                }
+                Ok(message)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        sign_messages(self.config.signer.as_ref(), &mut messages)
+            .await
+            .wrap_err("could not sign messages")?;
+
+        Ok(messages)
     }
 
     /// Dial the given peer at the given address.
@@ -153,14 +296,32 @@ impl DriaComputeNode {
     /// Can be inlined as it is called only once from very few places.
     #[inline]
     pub async fn shutdown(&mut self) -> Result<()> {
-        log::debug!("Sending shutdown command to p2p client.");
-        self.p2p.shutdown().await?;
+        log::debug!("Closing reqres channel.");
+        self.reqres_rx.close();
+        // the queued requests are dropped along with their response channels
+        while self.reqres_rx.try_recv().is_ok() {}
 
         log::debug!("Closing task output channel.");
         self.task_output_rx.close();
+        // tasks that were completed right before shutdown are still responded to
+        let mut task_outputs = Vec::new();
+        while let Ok(task_output) = self.task_output_rx.try_recv() {
+            task_outputs.push(task_output);
+        }
+        if let Err(err) = self.send_task_outputs(task_outputs).await {
+            log::error!("Error responding to tasks: {err:?}");
+        }
 
-        log::debug!("Closing reqres channel.");
-        self.reqres_rx.close();
+        // the p2p client flushes the queued responses before returning
+        log::debug!("Sending shutdown command to p2p client.");
+        self.p2p.shutdown().await?;
+
+        if self.late_results.len() > 0 {
+            log::warn!(
+                "{} task results could not be delivered before shutdown, they are lost.",
+                self.late_results.len()
+            );
+        }
 
         Ok(())
     }
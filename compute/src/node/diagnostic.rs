@@ -1,11 +1,13 @@
 use colored::Colorize;
-use std::time::Duration;
+use dkn_p2p::libp2p::PeerId;
+use eyre::Result;
+use std::sync::Arc;
 
-use crate::{node::rpc::DriaRPC, DriaComputeNode, DRIA_COMPUTE_NODE_VERSION};
-
-/// Number of seconds such that if the last heartbeat ACK is older than this, the node is considered unreachable.
-/// This must be at least greated than the heartbeat interval duration, and the liveness check duration.
-const HEARTBEAT_LIVENESS_SECS: Duration = Duration::from_secs(4 * 60);
+use crate::{
+    node::{rpc::DriaRPC, DriaNodeEvent},
+    utils::{write_atomic, HealthChecks, NodeHealth},
+    DriaComputeNode, DRIA_COMPUTE_NODE_VERSION,
+};
 
 impl DriaComputeNode {
     /// Returns the task count within the channels, `single` and `batch`.
@@ -17,6 +19,15 @@ impl DriaComputeNode {
         ]
     }
 
+    /// Returns whether the node is within its quiet hours, if it has any.
+    #[inline]
+    pub fn is_quiet(&self) -> bool {
+        self.config
+            .quiet_hours
+            .as_ref()
+            .is_some_and(|quiet_hours| quiet_hours.is_quiet())
+    }
+
     /// Peer refresh simply reports the peer count to the user.
     pub(crate) async fn handle_diagnostic_refresh(&mut self) {
         let mut diagnostics = vec![format!("Diagnostics (v{}):", DRIA_COMPUTE_NODE_VERSION)];
@@ -42,8 +53,130 @@ impl DriaComputeNode {
                     "Disconnected".red()
                 }
             ));
+
+            // identify info of the RPC, e.g. to see which address it observes for us
+            if let Ok(Some(info)) = self.p2p.peer_info(self.dria_rpc.peer_id).await {
+                diagnostics.push(format!(
+                    "RPC Agent: {} (observes us at {})",
+                    info.agent_version, info.observed_addr
+                ));
+            }
+
+            for rpc in &self.standby_rpcs {
+                diagnostics.push(format!(
+                    "Standby RPC {}: {}",
+                    rpc.peer_id,
+                    if self.p2p.is_connected(rpc.peer_id).await.unwrap_or(false) {
+                        "Connected".green()
+                    } else {
+                        "Disconnected".red()
+                    }
+                ));
+            }
+        }
+
+        // print pending tasks, and whether we are shedding load
+        let [pending_single, pending_batch] = self.get_pending_task_count();
+        let pending = pending_single + pending_batch;
+        let pending_str = match self.config.max_pending_tasks {
+            Some(max_pending) => format!(
+                "Pending Tasks (single/batch): {pending_single} / {pending_batch} (max: {max_pending})"
+            ),
+            None => format!(
+                "Pending Tasks (single/batch): {pending_single} / {pending_batch} (high-water mark: {})",
+                self.config.pending_high_water_mark
+            ),
+        };
+        diagnostics.push(if self.config.pending_capacity_error(pending).is_some() {
+            pending_str.red().to_string()
+        } else {
+            pending_str
+        });
+        if self.shed_tasks != 0 {
+            diagnostics.push(format!(
+                "Rejected Tasks (overloaded): {}",
+                self.shed_tasks.to_string().yellow()
+            ));
+            self.shed_tasks = 0;
+        }
+
+        // print direct connection upgrades, which only happen for relayed connections
+        if let Ok(stats) = self.p2p.hole_punch_stats().await {
+            if stats.succeeded + stats.failed != 0 {
+                diagnostics.push(format!(
+                    "Direct Connection Upgrades (succeeded/failed): {} / {}",
+                    stats.succeeded, stats.failed
+                ));
+            }
+        }
+
+        // print the peers served by the relay server, if it is enabled
+        if self.config.p2p_config.relay_server {
+            if let Ok(stats) = self.p2p.relay_server_stats().await {
+                diagnostics.push(format!(
+                    "Relay Reservations (active/served): {} / {}, Circuits (active/served): {} / {}",
+                    stats.reservations.len(),
+                    stats.reservations_served,
+                    stats.circuits,
+                    stats.circuits_served
+                ));
+            }
+        }
+
+        // print the latency to the RPC, as measured by the pings
+        if let Ok(Some(rtt)) = self.p2p.rtt(self.dria_rpc.peer_id).await {
+            diagnostics.push(format!("RPC RTT: {}ms", rtt.as_millis()));
+        }
+
+        // print bandwidth usage, with a breakdown per transport protocol stack in debug
+        if let Ok(stats) = self.p2p.stats().await {
+            diagnostics.push(format!(
+                "Bandwidth (in/out): {} / {}",
+                format_bytes(stats.total.inbound),
+                format_bytes(stats.total.outbound)
+            ));
+            if log::log_enabled!(log::Level::Debug) {
+                for (protocols, usage) in &stats.protocols {
+                    diagnostics.push(format!(
+                        "  {protocols}: {} / {}",
+                        format_bytes(usage.inbound),
+                        format_bytes(usage.outbound)
+                    ));
+                }
+            }
+        }
+
+        // print the outcomes & latencies of each model
+        for (model, stats) in self.model_stats.models() {
+            let mut line = format!(
+                "Model {model}: {} completed, {} failed, {}ms avg latency",
+                stats.completed,
+                stats.num_failed(),
+                stats.avg_latency_ms()
+            );
+            if !stats.failed.is_empty() {
+                let failed = stats
+                    .failed
+                    .iter()
+                    .map(|(class, count)| format!("{class}: {count}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                line.push_str(&format!(" ({})", failed.red()));
+            }
+            diagnostics.push(line);
         }
 
+        // print the statistics across restarts
+        let lifetime = self.lifetime.current();
+        diagnostics.push(format!(
+            "Lifetime: {}h uptime over {} sessions since {}, {} tasks & {} tokens",
+            lifetime.uptime_secs / 3600,
+            lifetime.sessions,
+            lifetime.since.format("%Y-%m-%d"),
+            lifetime.tasks,
+            lifetime.tokens
+        ));
+
         // print peer id and address
         diagnostics.push(format!("Peer ID: {}", self.config.peer_id));
         diagnostics.push(format!("Address: 0x{}", self.config.address));
@@ -55,7 +188,8 @@ impl DriaComputeNode {
         ));
 
         // if we have not received pings for a while, we are considered offline
-        let is_offline = chrono::Utc::now() > self.last_heartbeat_at + HEARTBEAT_LIVENESS_SECS;
+        let is_offline =
+            chrono::Utc::now() > self.last_heartbeat_at + self.config.intervals.heartbeat_liveness;
 
         // if we have not yet received a heartbeat response, we are still connecting
         if self.num_heartbeats == 0 {
@@ -72,22 +206,61 @@ impl DriaComputeNode {
             ));
         }
 
+        // quiet hours are independent of connectivity
+        if self.is_quiet() {
+            diagnostics.push(format!(
+                "Availability: {} (not accepting tasks)",
+                "QUIET HOURS".yellow()
+            ));
+        }
+
         log::info!("{}", diagnostics.join("\n  "));
 
         // if offline, print this error message as well
         if is_offline {
             log::error!(
                 "Node has not received any pings for at least {} seconds & it may be unreachable!\nPlease restart your node!",
-                HEARTBEAT_LIVENESS_SECS.as_secs()
+                self.config.intervals.heartbeat_liveness.as_secs()
             );
         }
+
+        if let Err(err) = self.write_metrics().await {
+            log::error!("Could not write metrics: {err:?}");
+        }
+
+        // summarize the activity once a day, before the state is saved with the new period
+        self.handle_activity_summary();
+
+        if let Err(err) = self.save_state() {
+            log::error!("Could not save node state: {err:?}");
+        }
+        if let Err(err) = self.lifetime.save() {
+            log::error!("Could not save lifetime stats: {err:?}");
+        }
+    }
+
+    /// Writes the P2P metrics to the metrics path, if any.
+    async fn write_metrics(&self) -> Result<()> {
+        let Some(path) = &self.config.metrics_path else {
+            return Ok(());
+        };
+
+        let metrics = self.p2p.metrics().await?;
+
+        // written atomically, so that a scraper never reads a partial file
+        write_atomic(path, metrics)
     }
 
     /// Dials the existing RPC node if we are not connected to it.
     ///
-    /// If there is an error while doing that, it will try to get a new RPC node and dial it.
+    /// If we are not connected, it first fails over to a connected standby RPC, and if there is none
+    /// it will try to get a new RPC node and dial it, or dial the pinned RPC again. These attempts are
+    /// spaced out with an exponential backoff, which is reset once the RPC is connected.
+    ///
+    /// The node is considered partitioned if a re-attempt fails to reach an RPC while there are
+    /// no connections at all, see [`handle_partition_check`](DriaComputeNode::handle_partition_check).
     ///
-    /// Returns `true` if the RPC is connected, `false` otherwise.
+    /// Returns `true` if the RPC has changed or a connection was re-attempted, `false` otherwise.
     pub(crate) async fn handle_rpc_liveness_check(&mut self) -> bool {
         log::debug!("Checking RPC connections for diagnostics.");
 
@@ -97,16 +270,87 @@ impl DriaComputeNode {
             .is_connected(self.dria_rpc.peer_id)
             .await
             .unwrap_or(false);
+        if is_connected {
+            if self.rpc_backoff.attempts() != 0 {
+                log::info!("Connection with RPC {} is established.", self.dria_rpc.addr);
+                self.rpc_backoff.reset();
+            }
+            log::debug!("Connection with {} is intact.", self.dria_rpc.peer_id);
+            self.handle_partition_check(false);
+            return false;
+        }
+
+        // the disconnect is emitted once, before the first re-attempt
+        if self.rpc_backoff.attempts() == 0 {
+            self.emit_event(DriaNodeEvent::RpcDisconnected {
+                rpc: self.dria_rpc.addr.to_string(),
+            });
+        }
 
-        // if we are not connected, get a new RPC and dial it again
-        if !is_connected {
-            // if we also cannot dial it, get a new RPC node
+        // a connected standby is used right away
+        if self.handle_rpc_failover().await {
+            self.rpc_backoff.reset();
+            self.handle_partition_check(false);
+            return true;
+        }
+
+        // otherwise, wait for the backoff so that a flapping RPC is not hammered
+        if !self.rpc_backoff.is_ready() {
+            return false;
+        }
+        let delay = self.rpc_backoff.record_attempt();
+        let attempt = self.rpc_backoff.attempts();
+
+        let is_reachable = if self.config.pinned_rpc_addr.is_some() {
+            // a pinned RPC is never replaced, so we just dial it again
             log::warn!(
-                "Connection to RPC {} is lost, geting a new one!",
+                "Connection to pinned RPC {} is lost, dialing again (attempt {attempt}, next in {}s).",
                 self.dria_rpc.addr,
+                delay.as_secs()
             );
-            match DriaRPC::new_for_network(self.dria_rpc.network, &self.config.version).await {
-                Ok(new_rpc) => {
+            if let Err(err) = self
+                .dial_with_timeout(self.dria_rpc.peer_id, self.dria_rpc.addr.clone())
+                .await
+            {
+                log::error!("Could not dial the pinned RPC: {err:?}");
+                false
+            } else {
+                true
+            }
+        } else {
+            // if there is no standby to fail over, get a new RPC node and dial it
+            log::warn!(
+                "Connection to RPC {} is lost, geting a new one (attempt {attempt}, next in {}s).",
+                self.dria_rpc.addr,
+                delay.as_secs()
+            );
+            self.peer_store.record_failure(&self.dria_rpc);
+            let new_rpc = self.discover_rpcs().await.map(|rpcs| {
+                // prefer another RPC than the lost one, if there is any
+                let idx = rpcs
+                    .iter()
+                    .position(|rpc| rpc.peer_id != self.dria_rpc.peer_id)
+                    .unwrap_or_default();
+                rpcs.into_iter().nth(idx)
+            });
+            match new_rpc {
+                Ok(None) => {
+                    log::error!("Could not get a new RPC node: no RPCs are known");
+                    false
+                }
+                Ok(Some(new_rpc)) => {
+                    self.allow_rpc(new_rpc.peer_id).await;
+                    if new_rpc.peer_id != self.dria_rpc.peer_id {
+                        self.disallow_rpc(self.dria_rpc.peer_id).await;
+                    }
+                    self.standby_rpcs
+                        .retain(|rpc| rpc.peer_id != new_rpc.peer_id);
+                    if new_rpc.addr != self.dria_rpc.addr {
+                        self.emit_event(DriaNodeEvent::RpcChanged {
+                            previous: self.dria_rpc.addr.to_string(),
+                            rpc: new_rpc.addr.to_string(),
+                        });
+                    }
                     self.dria_rpc = new_rpc;
 
                     // now dial this new RPC again
@@ -114,35 +358,306 @@ impl DriaComputeNode {
                         .dial_with_timeout(self.dria_rpc.peer_id, self.dria_rpc.addr.clone())
                         .await
                     {
-                        // worst-case we cant dial this one too, just leave it for the next diagnostic
+                        // worst-case we cant dial this one too, just leave it for the next attempt
                         log::error!("Could not dial the new RPC: {err:?}");
+                        false
+                    } else {
+                        true
                     }
                 }
                 Err(err) => {
                     log::error!("Could not get a new RPC node: {err:?}");
+                    false
                 }
-            };
-        } else {
-            log::debug!("Connection with {} is intact.", self.dria_rpc.peer_id);
+            }
+        };
+
+        // the node is partitioned if it could not reach an RPC, and it has no other connections either
+        let num_peers = self
+            .p2p
+            .network_info()
+            .await
+            .map(|info| info.num_peers())
+            .unwrap_or_default();
+        self.handle_partition_check(!is_reachable && num_peers == 0);
+
+        true
+    }
+
+    /// Returns the health of the node, to be served by the health server.
+    pub fn health(&self) -> Arc<NodeHealth> {
+        self.health.clone()
+    }
+
+    /// Updates the readiness checks of the node, which are:
+    ///
+    /// - the node is connected to its RPC
+    /// - the last heartbeat was acknowledged within the liveness duration
+    /// - the task workers are still running, i.e. their channels are open
+    ///
+    /// An event is emitted when the heartbeats start failing.
+    pub(crate) async fn handle_health_refresh(&mut self) {
+        let rpc_connected = self
+            .p2p
+            .is_connected(self.dria_rpc.peer_id)
+            .await
+            .unwrap_or(false);
+        let heartbeats_acked =
+            chrono::Utc::now() <= self.last_heartbeat_at + self.config.intervals.heartbeat_liveness;
+        let workers_alive = [&self.task_request_batch_tx, &self.task_request_single_tx]
+            .into_iter()
+            .flatten()
+            .all(|tx| !tx.is_closed());
+
+        if !heartbeats_acked && !self.heartbeats_failing {
+            self.emit_event(DriaNodeEvent::HeartbeatFailed {
+                last_acked_at: self.last_heartbeat_at,
+            });
+        }
+        self.heartbeats_failing = !heartbeats_acked;
+
+        let checks = HealthChecks {
+            rpc_connected,
+            heartbeats_acked,
+            workers_alive,
+        };
+        if !checks.is_ready() {
+            log::debug!("Node is not ready: {checks:?}");
+        }
+        self.health.set_checks(checks);
+        self.write_status(checks, true);
+    }
+
+    /// Fails over to the first connected standby RPC if the primary RPC is not connected,
+    /// in which case the previous primary becomes a standby to be re-dialed later.
+    ///
+    /// This does not make any network requests besides the local connection checks, so it is cheap.
+    ///
+    /// Returns `true` if the primary RPC has changed.
+    async fn handle_rpc_failover(&mut self) -> bool {
+        if self.standby_rpcs.is_empty()
+            || self
+                .p2p
+                .is_connected(self.dria_rpc.peer_id)
+                .await
+                .unwrap_or(true)
+        {
+            return false;
+        }
+
+        for idx in 0..self.standby_rpcs.len() {
+            let peer_id = self.standby_rpcs[idx].peer_id;
+            if self.p2p.is_connected(peer_id).await.unwrap_or(false) {
+                let standby_rpc = self.standby_rpcs.remove(idx);
+                log::warn!(
+                    "Connection to RPC {} is lost, failing over to {}",
+                    self.dria_rpc.addr,
+                    standby_rpc.addr
+                );
+                let previous_rpc = std::mem::replace(&mut self.dria_rpc, standby_rpc);
+                self.emit_event(DriaNodeEvent::RpcChanged {
+                    previous: previous_rpc.addr.to_string(),
+                    rpc: self.dria_rpc.addr.to_string(),
+                });
+                self.standby_rpcs.push(previous_rpc);
+                return true;
+            }
         }
 
-        // return the connection status
-        is_connected
+        false
+    }
+
+    /// Keeps the connections with the standby RPCs warm, dropping the ones that can not be dialed
+    /// and getting new ones from the discovery API until there are `rpc_standby_count` of them.
+    ///
+    /// The health of the RPCs is recorded to the peer store as well.
+    pub(crate) async fn handle_standby_rpcs_refresh(&mut self) {
+        // record the health of the RPCs, so that the healthy ones are tried first after a restart
+        if self
+            .p2p
+            .is_connected(self.dria_rpc.peer_id)
+            .await
+            .unwrap_or(false)
+        {
+            self.peer_store.record_seen(&self.dria_rpc);
+        }
+
+        // re-dial the disconnected standbys, and drop the ones that we can not reach
+        let mut standby_rpcs = Vec::new();
+        for rpc in std::mem::take(&mut self.standby_rpcs) {
+            if rpc.peer_id == self.dria_rpc.peer_id
+                || standby_rpcs
+                    .iter()
+                    .any(|r: &DriaRPC| r.peer_id == rpc.peer_id)
+            {
+                continue;
+            }
+
+            if self.p2p.is_connected(rpc.peer_id).await.unwrap_or(false) {
+                self.peer_store.record_seen(&rpc);
+            } else {
+                self.peer_store.record_failure(&rpc);
+                if let Err(err) = self.dial_with_timeout(rpc.peer_id, rpc.addr.clone()).await {
+                    log::warn!("Dropping standby RPC {}: {err:?}", rpc.addr);
+                    self.disallow_rpc(rpc.peer_id).await;
+                    continue;
+                }
+            }
+            standby_rpcs.push(rpc);
+        }
+        self.standby_rpcs = standby_rpcs;
+        if let Err(err) = self.peer_store.save() {
+            log::warn!("Could not save peer store: {err:?}");
+        }
+
+        let num_missing = self
+            .config
+            .rpc_standby_count
+            .saturating_sub(self.standby_rpcs.len());
+        if num_missing == 0 {
+            return;
+        }
+
+        // get new standbys among the RPCs that we do not know yet
+        let candidates = match self.discover_rpcs().await {
+            Ok(candidates) => candidates,
+            Err(err) => {
+                log::warn!("Could not get standby RPCs: {err:?}");
+                return;
+            }
+        };
+        let candidates = candidates
+            .into_iter()
+            .filter(|rpc| {
+                rpc.peer_id != self.dria_rpc.peer_id
+                    && !self.standby_rpcs.iter().any(|r| r.peer_id == rpc.peer_id)
+            })
+            .take(num_missing)
+            .collect::<Vec<_>>();
+        for rpc in candidates {
+            self.allow_rpc(rpc.peer_id).await;
+            match self.dial_with_timeout(rpc.peer_id, rpc.addr.clone()).await {
+                Ok(()) => {
+                    log::info!("Using standby RPC {}", rpc.addr);
+                    self.standby_rpcs.push(rpc);
+                }
+                Err(err) => {
+                    log::warn!("Could not dial standby RPC {}: {err:?}", rpc.addr);
+                    self.disallow_rpc(rpc.peer_id).await;
+                }
+            }
+        }
+    }
+
+    /// Returns the RPCs from the discovery API, or the known ones within the peer store if
+    /// the discovery API is not available.
+    async fn discover_rpcs(&self) -> Result<Vec<DriaRPC>> {
+        match DriaRPC::candidates_for_network(
+            self.dria_rpc.network,
+            &self.config.version,
+            self.config.discovery_url.as_deref(),
+        )
+        .await
+        {
+            Ok(rpcs) => Ok(rpcs),
+            Err(err) => {
+                let rpcs = self.peer_store.candidates(self.dria_rpc.network);
+                if rpcs.is_empty() {
+                    return Err(err);
+                }
+
+                log::warn!("Could not use discovery API, using known RPCs instead: {err:?}");
+                Ok(rpcs)
+            }
+        }
+    }
+
+    /// Authorizes the requests & streams of the given RPC, allows it to connect in case only the RPC peers
+    /// are allowed, and keeps its connections alive if enabled.
+    async fn allow_rpc(&mut self, peer_id: PeerId) {
+        self.authorized_rpcs.insert(peer_id);
+        if let Err(err) = self.p2p.authorize_streams(peer_id, true).await {
+            log::error!("Could not authorize the streams of RPC {peer_id}: {err:?}");
+        }
+        if self.config.p2p_config.allowed_peers.is_some() {
+            if let Err(err) = self.p2p.allow_peer(peer_id).await {
+                log::error!("Could not allow RPC {peer_id}: {err:?}");
+            }
+        }
+        if self.config.rpc_keep_alive {
+            if let Err(err) = self.p2p.keep_alive(peer_id, true).await {
+                log::error!("Could not keep RPC {peer_id} alive: {err:?}");
+            }
+        }
+    }
+
+    /// Disallows the given RPC, in case only the RPC peers are allowed, unless it is allowed explicitly.
+    /// Its connections are no longer kept alive either, and its requests & streams are no longer authorized,
+    /// unless configured explicitly.
+    async fn disallow_rpc(&mut self, peer_id: PeerId) {
+        if !self.config.authorized_rpcs.contains(&peer_id) {
+            self.authorized_rpcs.remove(&peer_id);
+            if let Err(err) = self.p2p.authorize_streams(peer_id, false).await {
+                log::error!("Could not deauthorize the streams of RPC {peer_id}: {err:?}");
+            }
+        }
+        if let Some(allowed_peers) = &self.config.p2p_config.allowed_peers {
+            if !allowed_peers.contains(&peer_id) {
+                if let Err(err) = self.p2p.disallow_peer(peer_id).await {
+                    log::error!("Could not disallow RPC {peer_id}: {err:?}");
+                }
+            }
+        }
+        if !self.config.p2p_config.keep_alive_peers.contains(&peer_id) {
+            if let Err(err) = self.p2p.keep_alive(peer_id, false).await {
+                log::error!("Could not stop keeping RPC {peer_id} alive: {err:?}");
+            }
+        }
+    }
+
+    /// Warms up the models that went cold in a separate task, so that the main loop is not blocked
+    /// while the models are being loaded.
+    ///
+    /// If the previous warm-up is still running, this does nothing.
+    pub(crate) fn handle_model_warmup(&mut self) {
+        if self
+            .model_warmup_handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+        {
+            log::debug!("Previous model warm-up is still running, skipping.");
+            return;
+        }
+
+        let executors = self.config.executors.clone();
+        self.model_warmup_handle = Some(tokio::spawn(async move {
+            executors.warmup_cold_models().await;
+        }));
     }
 
     /// Updates the points for the given address.
     #[inline]
     pub(crate) async fn handle_points_refresh(&mut self) {
         // get points from the API
-        match self.points_client.get_points().await {
+        match self.points_client.refresh_points().await {
             Ok(steps) => {
+                let now = chrono::Utc::now();
+                let ledger = &self.points_client.ledger;
                 log::info!(
-                    "{}: {} total, {} earned in this run, within top {}%",
+                    "{}: {} total, {} earned in this run, {} in the last day, {} in the last week, within top {}%",
                     "$DRIA Points".purple(),
                     steps.score,
                     steps.score - self.points_client.initial,
+                    ledger
+                        .earned_since(now - chrono::Duration::days(1))
+                        .unwrap_or_default(),
+                    ledger
+                        .earned_since(now - chrono::Duration::weeks(1))
+                        .unwrap_or_default(),
                     steps.percentile
                 );
+                self.activity.record_points(steps.score);
+                self.points = Some(steps);
             }
             Err(err) => {
                 log::error!("Could not get $DRIA points info: {err:?}");
@@ -150,3 +665,33 @@ impl DriaComputeNode {
         }
     }
 }
+
+/// Formats the given number of bytes in a human-readable way, e.g. `1.5 MiB`.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
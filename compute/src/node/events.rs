@@ -0,0 +1,253 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+use crate::{node::ActivitySummary, DriaComputeNode};
+
+/// Buffer size for the broadcasted node events, per subscriber.
+///
+/// A subscriber that falls behind by more than this many events misses the oldest ones.
+pub(crate) const NODE_EVENTS_BUFSIZE: usize = 256;
+
+/// Timeout of a webhook call, so that an unreachable webhook does not pile up tasks.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Duration of the window that the task errors are counted within.
+pub(crate) const TASK_ERRORS_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// High-level events of the compute node, for applications to observe & react to the node state.
+///
+/// These are broadcasted to all subscribers, see [`DriaComputeNode::subscribe_events`], and are streamed
+/// by the admin API at `GET /events`. The operational events are sent to the webhook as JSON as well,
+/// see [`DriaNodeEvent::is_operational`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DriaNodeEvent {
+    /// The node has lost all of its connections, and could not reach an RPC either.
+    Partitioned { since: DateTime<Utc> },
+    /// The node is connected to the network again after a partition.
+    Recovered {
+        since: DateTime<Utc>,
+        duration_secs: i64,
+    },
+    /// The connection to the primary RPC is lost, the node fails over or looks for a new one.
+    RpcDisconnected { rpc: String },
+    /// No heartbeat was acknowledged by the RPC within the liveness duration.
+    HeartbeatFailed { last_acked_at: DateTime<Utc> },
+    /// The number of failed tasks within the window has reached the configured threshold.
+    TaskErrors { count: usize, window_secs: u64 },
+    /// A newer version of the compute node is released.
+    UpdateAvailable { current: String, latest: String },
+    /// A task is accepted & queued for its worker.
+    TaskStarted {
+        row_id: uuid::Uuid,
+        task_id: String,
+        model: String,
+    },
+    /// A task is executed successfully, and its result is sent to the RPC.
+    TaskCompleted {
+        row_id: uuid::Uuid,
+        task_id: String,
+        model: String,
+    },
+    /// A task has failed, and its error is sent to the RPC.
+    TaskFailed {
+        row_id: uuid::Uuid,
+        task_id: String,
+        model: String,
+        error: String,
+    },
+    /// A heartbeat is acknowledged by the RPC.
+    HeartbeatAcked { at: DateTime<Utc> },
+    /// The primary RPC is changed, due to a failover or a new RPC from the discovery API.
+    RpcChanged { previous: String, rpc: String },
+    /// The activity of the node over the last day.
+    DailySummary(ActivitySummary),
+}
+
+impl DriaNodeEvent {
+    /// Whether this is an operational event that may need attention, i.e. it is sent to the webhook.
+    ///
+    /// The lifecycle events of tasks & heartbeats are too frequent for a webhook, and are only broadcasted.
+    pub fn is_operational(&self) -> bool {
+        !matches!(
+            self,
+            DriaNodeEvent::TaskStarted { .. }
+                | DriaNodeEvent::TaskCompleted { .. }
+                | DriaNodeEvent::TaskFailed { .. }
+                | DriaNodeEvent::HeartbeatAcked { .. }
+        )
+    }
+
+    /// Returns the error that this event reports, if any.
+    pub(crate) fn error_message(&self) -> Option<String> {
+        match self {
+            DriaNodeEvent::Partitioned { .. } => {
+                Some("node is partitioned from the network".to_string())
+            }
+            DriaNodeEvent::RpcDisconnected { rpc } => {
+                Some(format!("connection with RPC {rpc} is lost"))
+            }
+            DriaNodeEvent::HeartbeatFailed { last_acked_at } => Some(format!(
+                "no heartbeat is acknowledged since {last_acked_at}"
+            )),
+            DriaNodeEvent::TaskFailed { task_id, error, .. } => {
+                Some(format!("task {task_id} failed: {error}"))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Counts the task errors within a sliding window, to alert once when they reach a threshold.
+#[derive(Debug)]
+pub(crate) struct TaskErrorTracker {
+    errors: VecDeque<Instant>,
+    window: Duration,
+    threshold: usize,
+    /// Whether the threshold is reached, so that it is not alerted again until the errors cool down.
+    is_alerted: bool,
+}
+
+impl TaskErrorTracker {
+    pub(crate) fn new(window: Duration, threshold: usize) -> Self {
+        Self {
+            errors: VecDeque::new(),
+            window,
+            threshold,
+            is_alerted: false,
+        }
+    }
+
+    /// Records a task error, and returns the number of errors within the window if the
+    /// threshold is reached for the first time since the errors were below it.
+    ///
+    /// A threshold of `0` disables the alert.
+    pub(crate) fn record_error(&mut self) -> Option<usize> {
+        let now = Instant::now();
+        while self
+            .errors
+            .front()
+            .is_some_and(|&at| now.duration_since(at) > self.window)
+        {
+            self.errors.pop_front();
+        }
+        if self.errors.len() < self.threshold {
+            self.is_alerted = false;
+        }
+        self.errors.push_back(now);
+
+        if self.threshold == 0 || self.is_alerted || self.errors.len() < self.threshold {
+            return None;
+        }
+        self.is_alerted = true;
+        Some(self.errors.len())
+    }
+}
+
+impl DriaComputeNode {
+    /// Returns a receiver of the node events, such as partitions.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<DriaNodeEvent> {
+        self.events_tx.subscribe()
+    }
+
+    /// Broadcasts the event to the subscribers, and sends it to the webhook if one is configured
+    /// and the event is operational.
+    ///
+    /// Partition events are sent to the partition webhook as well.
+    /// The errors are recorded for the status file as well.
+    pub(crate) fn emit_event(&mut self, event: DriaNodeEvent) {
+        if let Some(message) = event.error_message() {
+            self.record_error(message);
+        }
+
+        let mut urls = Vec::new();
+        if let Some(url) = self
+            .config
+            .webhook_url
+            .as_ref()
+            .filter(|_| event.is_operational())
+        {
+            urls.push(url.clone());
+        }
+        if matches!(
+            event,
+            DriaNodeEvent::Partitioned { .. } | DriaNodeEvent::Recovered { .. }
+        ) {
+            if let Some(url) = &self.config.partition_webhook {
+                if !urls.contains(url) {
+                    urls.push(url.clone());
+                }
+            }
+        }
+
+        if !urls.is_empty() {
+            let mut payload = serde_json::to_value(&event).unwrap_or_default();
+            payload["address"] = format!("0x{}", self.config.address).into();
+            tokio::spawn(async move {
+                let client = reqwest::Client::new();
+                for url in urls {
+                    let result = client
+                        .post(&url)
+                        .json(&payload)
+                        .timeout(WEBHOOK_TIMEOUT)
+                        .send()
+                        .await
+                        .and_then(|res| res.error_for_status());
+                    if let Err(err) = result {
+                        log::warn!("Could not call the webhook: {err:?}");
+                    }
+                }
+            });
+        }
+
+        // there may be no subscribers, which is fine
+        let _ = self.events_tx.send(event);
+    }
+
+    /// Records a failed task, and emits an event if the errors within the window reach the threshold.
+    pub(crate) fn handle_task_error(&mut self) {
+        if let Some(count) = self.task_errors.record_error() {
+            log::error!(
+                "{count} tasks have failed within the last {} minutes.",
+                TASK_ERRORS_WINDOW.as_secs() / 60
+            );
+            self.emit_event(DriaNodeEvent::TaskErrors {
+                count,
+                window_secs: TASK_ERRORS_WINDOW.as_secs(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_error_tracker() {
+        let mut tracker = TaskErrorTracker::new(Duration::from_millis(50), 3);
+        assert_eq!(tracker.record_error(), None);
+        assert_eq!(tracker.record_error(), None);
+        assert_eq!(tracker.record_error(), Some(3));
+        // alerted only once while the errors stay above the threshold
+        assert_eq!(tracker.record_error(), None);
+
+        // alerted again after the errors cool down
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(tracker.record_error(), None);
+        assert_eq!(tracker.record_error(), None);
+        assert_eq!(tracker.record_error(), Some(3));
+
+        let json = serde_json::to_value(DriaNodeEvent::TaskErrors {
+            count: 3,
+            window_secs: 600,
+        })
+        .unwrap();
+        assert_eq!(json["event"], "task_errors");
+        assert_eq!(json["count"], 3);
+        assert!(!DriaNodeEvent::HeartbeatAcked { at: Utc::now() }.is_operational());
+    }
+}
@@ -0,0 +1,405 @@
+use chrono::{DateTime, Utc};
+use dkn_utils::payloads::{TaskError, TaskResponsePayload};
+use eyre::{Context, Result};
+use rusqlite::{named_params, Connection, OpenFlags, Row};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::DriaComputeNode;
+
+/// Maximum number of tasks kept in the history, the oldest ones are deleted beyond this.
+const HISTORY_MAX_TASKS: usize = 100_000;
+/// Number of recorded tasks after which the old ones are deleted.
+const HISTORY_PRUNE_EVERY: usize = 256;
+/// Maximum number of tasks returned by a query.
+pub(crate) const HISTORY_MAX_LIMIT: usize = 1000;
+
+/// A completed or failed task, as recorded in the history.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskRecord {
+    pub row_id: Uuid,
+    pub task_id: String,
+    pub file_id: Uuid,
+    pub model: String,
+    pub success: bool,
+    /// Class of the error if the task has failed, e.g. `provider:server_busy` or `http`.
+    pub error_class: Option<String>,
+    pub error: Option<String>,
+    pub received_at: DateTime<Utc>,
+    /// The time that the result was responded at.
+    pub completed_at: DateTime<Utc>,
+    /// Time spent waiting for a worker, in milliseconds.
+    pub queue_ms: i64,
+    /// Time spent executing, in milliseconds.
+    pub execution_ms: i64,
+    /// Time from receiving to responding, in milliseconds.
+    pub total_ms: i64,
+    pub tokens: u64,
+    pub verification_tokens: u64,
+}
+
+impl From<&TaskResponsePayload> for TaskRecord {
+    fn from(payload: &TaskResponsePayload) -> Self {
+        let stats = &payload.stats;
+        let millis = |from: DateTime<Utc>, to: DateTime<Utc>| (to - from).num_milliseconds().max(0);
+        Self {
+            row_id: payload.row_id,
+            task_id: payload.task_id.clone(),
+            file_id: payload.file_id,
+            model: payload.model.clone(),
+            success: payload.error.is_none(),
+            error_class: payload.error.as_ref().map(error_class),
+            error: payload.error.as_ref().map(ToString::to_string),
+            received_at: stats.received_at,
+            completed_at: stats.published_at,
+            queue_ms: millis(stats.received_at, stats.execution_started_at),
+            execution_ms: millis(stats.execution_started_at, stats.execution_ended_at),
+            total_ms: millis(stats.received_at, stats.published_at),
+            tokens: stats.token_count as u64,
+            verification_tokens: stats.verification_token_count as u64,
+        }
+    }
+}
+
+/// Filters of a history query, the most recent tasks are returned first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryQuery {
+    pub model: Option<String>,
+    /// Only the completed (`true`) or failed (`false`) tasks, all if `None`.
+    pub success: Option<bool>,
+    /// Only the tasks completed at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Maximum number of tasks to return, at most [`HISTORY_MAX_LIMIT`].
+    pub limit: usize,
+}
+
+impl Default for HistoryQuery {
+    fn default() -> Self {
+        Self {
+            model: None,
+            success: None,
+            since: None,
+            limit: 100,
+        }
+    }
+}
+
+/// History of the completed & failed tasks in an embedded SQLite database, so that they can be
+/// inspected later via the admin API & the `history` command.
+///
+/// Tasks older than the retention are deleted, along with the oldest ones beyond 100k tasks.
+pub(crate) struct TaskHistory {
+    /// Connection to the database, behind a lock so that the node can be shared across threads.
+    conn: Mutex<Connection>,
+    retention: Duration,
+    /// Number of tasks recorded since the last prune.
+    num_recorded: usize,
+}
+
+impl TaskHistory {
+    /// Opens or creates the database at the given path, and deletes the tasks beyond the retention.
+    pub(crate) fn open(path: &Path, retention: Duration) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("could not create {}", parent.display()))?;
+        }
+
+        let conn = Connection::open(path)
+            .wrap_err_with(|| format!("could not open {}", path.display()))?;
+        // WAL lets the `history` command read while the node is writing
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tasks (
+                row_id TEXT PRIMARY KEY,
+                task_id TEXT NOT NULL,
+                file_id TEXT NOT NULL,
+                model TEXT NOT NULL,
+                success INTEGER NOT NULL,
+                error_class TEXT,
+                error TEXT,
+                received_at INTEGER NOT NULL,
+                completed_at INTEGER NOT NULL,
+                queue_ms INTEGER NOT NULL,
+                execution_ms INTEGER NOT NULL,
+                total_ms INTEGER NOT NULL,
+                tokens INTEGER NOT NULL,
+                verification_tokens INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS tasks_completed_at ON tasks (completed_at);",
+        )
+        .wrap_err("could not create task history table")?;
+
+        let mut history = Self {
+            conn: Mutex::new(conn),
+            retention,
+            num_recorded: 0,
+        };
+        history.prune(Utc::now())?;
+        Ok(history)
+    }
+
+    /// Opens the database at the given path without writing to it, e.g. while a node is running with it.
+    pub(crate) fn open_read_only(path: &Path) -> Result<Self> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .wrap_err_with(|| format!("could not open {}", path.display()))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            retention: Duration::MAX,
+            num_recorded: 0,
+        })
+    }
+
+    /// Records the task, replacing a previous record of the same task if any.
+    pub(crate) fn record(&mut self, record: &TaskRecord) -> Result<()> {
+        self.conn()
+            .execute(
+                "INSERT OR REPLACE INTO tasks VALUES (
+                    :row_id, :task_id, :file_id, :model, :success, :error_class, :error, :received_at,
+                    :completed_at, :queue_ms, :execution_ms, :total_ms, :tokens, :verification_tokens
+                )",
+                named_params! {
+                    ":row_id": record.row_id.to_string(),
+                    ":task_id": record.task_id,
+                    ":file_id": record.file_id.to_string(),
+                    ":model": record.model,
+                    ":success": record.success,
+                    ":error_class": record.error_class,
+                    ":error": record.error,
+                    ":received_at": record.received_at.timestamp_millis(),
+                    ":completed_at": record.completed_at.timestamp_millis(),
+                    ":queue_ms": record.queue_ms,
+                    ":execution_ms": record.execution_ms,
+                    ":total_ms": record.total_ms,
+                    ":tokens": record.tokens as i64,
+                    ":verification_tokens": record.verification_tokens as i64,
+                },
+            )
+            .wrap_err("could not record task")?;
+
+        self.num_recorded += 1;
+        if self.num_recorded >= HISTORY_PRUNE_EVERY {
+            self.prune(Utc::now())?;
+        }
+        Ok(())
+    }
+
+    /// Returns the tasks matching the query, the most recent ones first.
+    pub(crate) fn query(&self, query: &HistoryQuery) -> Result<Vec<TaskRecord>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare_cached(
+            "SELECT * FROM tasks
+            WHERE completed_at >= :since
+                AND (:model IS NULL OR model = :model)
+                AND (:success IS NULL OR success = :success)
+            ORDER BY completed_at DESC
+            LIMIT :limit",
+        )?;
+        let records = statement
+            .query_map(
+                named_params! {
+                    ":since": query.since.map_or(0, |since| since.timestamp_millis()),
+                    ":model": query.model,
+                    ":success": query.success,
+                    ":limit": query.limit.min(HISTORY_MAX_LIMIT) as i64,
+                },
+                read_record,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .wrap_err("could not query task history")?;
+
+        Ok(records)
+    }
+
+    /// Deletes the tasks that are older than the retention, and the oldest ones beyond the maximum.
+    fn prune(&mut self, now: DateTime<Utc>) -> Result<()> {
+        let oldest = chrono::Duration::from_std(self.retention)
+            .ok()
+            .and_then(|retention| now.checked_sub_signed(retention))
+            .map_or(0, |oldest| oldest.timestamp_millis());
+        let conn = self.conn();
+        let deleted = conn
+            .execute("DELETE FROM tasks WHERE completed_at < ?1", [oldest])
+            .wrap_err("could not prune task history")?
+            + conn
+                .execute(
+                    "DELETE FROM tasks WHERE row_id IN (
+                        SELECT row_id FROM tasks ORDER BY completed_at DESC LIMIT -1 OFFSET ?1
+                    )",
+                    [HISTORY_MAX_TASKS as i64],
+                )
+                .wrap_err("could not prune task history")?;
+        if deleted != 0 {
+            log::debug!("Deleted {deleted} tasks from the task history.");
+        }
+        drop(conn);
+
+        self.num_recorded = 0;
+        Ok(())
+    }
+
+    /// Locks the connection, which is only poisoned if a query had panicked.
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Reads a record from a row of the `tasks` table.
+fn read_record(row: &Row) -> rusqlite::Result<TaskRecord> {
+    let uuid = |idx: usize| -> rusqlite::Result<Uuid> {
+        let value = row.get::<_, String>(idx)?;
+        Uuid::parse_str(&value).map_err(|err| {
+            rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, err.into())
+        })
+    };
+    let datetime = |idx: usize| -> rusqlite::Result<DateTime<Utc>> {
+        Ok(DateTime::from_timestamp_millis(row.get(idx)?).unwrap_or_default())
+    };
+
+    Ok(TaskRecord {
+        row_id: uuid(0)?,
+        task_id: row.get(1)?,
+        file_id: uuid(2)?,
+        model: row.get(3)?,
+        success: row.get(4)?,
+        error_class: row.get(5)?,
+        error: row.get(6)?,
+        received_at: datetime(7)?,
+        completed_at: datetime(8)?,
+        queue_ms: row.get(9)?,
+        execution_ms: row.get(10)?,
+        total_ms: row.get(11)?,
+        tokens: row.get::<_, i64>(12)? as u64,
+        verification_tokens: row.get::<_, i64>(13)? as u64,
+    })
+}
+
+/// Returns a short class of the error, to group the failures by.
+pub(crate) fn error_class(error: &TaskError) -> String {
+    match error {
+        TaskError::ParseError(_) => "parse".to_string(),
+        TaskError::ProviderError { code, .. } => format!("provider:{code}"),
+        TaskError::HttpError(_) => "http".to_string(),
+        TaskError::ExecutorError(_) => "executor".to_string(),
+        TaskError::OutboundRequestError { code, .. } => format!("outbound:{code}"),
+        TaskError::Overloaded { .. } => "overloaded".to_string(),
+        TaskError::CapacityExceeded { .. } => "capacity".to_string(),
+        TaskError::Unavailable(_) => "unavailable".to_string(),
+        TaskError::Other(_) => "other".to_string(),
+    }
+}
+
+impl DriaComputeNode {
+    /// Records the responded task to the history, if any.
+    pub(crate) fn record_history(&mut self, payload: &TaskResponsePayload) {
+        let Some(history) = &mut self.history else {
+            return;
+        };
+
+        if let Err(err) = history.record(&TaskRecord::from(payload)) {
+            log::error!(
+                "Could not record task {} to history: {err:?}",
+                payload.row_id
+            );
+        }
+    }
+}
+
+/// Opens the task history at the given path if any, the node runs without it if it can not be opened.
+pub(crate) fn open_history(path: Option<&Path>, retention: Duration) -> Option<TaskHistory> {
+    TaskHistory::open(path?, retention)
+        .inspect_err(|err| log::error!("Could not open the task history: {err:?}"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dkn_utils::payloads::{SchemaVersion, TaskStats};
+
+    #[test]
+    fn test_task_history() {
+        let path = std::env::temp_dir().join(format!("dkn-history-{}.db", Uuid::now_v7()));
+        // times are stored in milliseconds
+        let now = DateTime::from_timestamp_millis(Utc::now().timestamp_millis()).unwrap();
+        let payload = |model: &str, error: Option<TaskError>, completed_at: DateTime<Utc>| {
+            TaskResponsePayload {
+                schema_version: SchemaVersion::CURRENT,
+                file_id: Uuid::now_v7(),
+                row_id: Uuid::now_v7(),
+                task_id: "task".to_string(),
+                model: model.to_string(),
+                stats: TaskStats {
+                    received_at: completed_at - chrono::Duration::seconds(3),
+                    execution_started_at: completed_at - chrono::Duration::seconds(2),
+                    execution_ended_at: completed_at,
+                    published_at: completed_at,
+                    token_count: 42,
+                    verification_token_count: 0,
+                },
+                result: error.is_none().then(|| "hello".to_string()),
+                error,
+                encrypted_result: None,
+                receipt_signature: None,
+            }
+        };
+
+        let mut history = TaskHistory::open(&path, Duration::from_secs(24 * 60 * 60)).unwrap();
+        let completed = TaskRecord::from(&payload("gemma3:4b", None, now));
+        assert_eq!(completed.queue_ms, 1000);
+        assert_eq!(completed.execution_ms, 2000);
+        history.record(&completed).unwrap();
+        let failed = TaskRecord::from(&payload(
+            "gpt-4o",
+            Some(TaskError::ProviderError {
+                code: "server_busy".to_string(),
+                message: "busy".to_string(),
+                provider: "ollama".to_string(),
+            }),
+            now - chrono::Duration::minutes(1),
+        ));
+        history.record(&failed).unwrap();
+        let old = TaskRecord::from(&payload("gemma3:4b", None, now - chrono::Duration::days(2)));
+        history.record(&old).unwrap();
+
+        let query = |query: HistoryQuery| history.query(&query).unwrap();
+        assert_eq!(
+            query(HistoryQuery::default()),
+            vec![completed.clone(), failed.clone(), old]
+        );
+        let failures = query(HistoryQuery {
+            success: Some(false),
+            ..Default::default()
+        });
+        assert_eq!(failures, vec![failed.clone()]);
+        assert_eq!(
+            failures[0].error_class.as_deref(),
+            Some("provider:server_busy")
+        );
+        assert_eq!(
+            query(HistoryQuery {
+                model: Some("gemma3:4b".to_string()),
+                limit: 1,
+                ..Default::default()
+            }),
+            vec![completed.clone()]
+        );
+
+        // the old task is deleted once the history is opened again
+        drop(history);
+        let history = TaskHistory::open(&path, Duration::from_secs(24 * 60 * 60)).unwrap();
+        assert_eq!(
+            history.query(&HistoryQuery::default()).unwrap(),
+            vec![completed, failed]
+        );
+
+        drop(history);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+}
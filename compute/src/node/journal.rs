@@ -0,0 +1,301 @@
+use chrono::{DateTime, Utc};
+use dkn_utils::payloads::LostTask;
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::{utils::write_atomic, DriaComputeNode};
+
+/// Number of entries after which the journal is compacted to its open tasks.
+const JOURNAL_COMPACT_ENTRIES: usize = 1024;
+
+/// A task that is accepted by the node, as written to the journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JournaledTask {
+    pub row_id: Uuid,
+    pub task_id: String,
+    pub file_id: Uuid,
+    pub model: String,
+    pub accepted_at: DateTime<Utc>,
+}
+
+/// An entry of the journal, one JSON object per line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+enum JournalEntry {
+    /// The task is accepted & queued for its worker.
+    Accepted(JournaledTask),
+    /// The task is responded to, with its result or error.
+    #[serde(rename_all = "camelCase")]
+    Completed { row_id: Uuid },
+    /// The task was lost by a crash, and this is reported to the RPC.
+    #[serde(rename_all = "camelCase")]
+    Reported { row_id: Uuid },
+}
+
+/// Write-ahead journal of the pending tasks, so that the tasks lost by an unclean exit can be reported
+/// to the RPC after a restart.
+///
+/// Each accepted & completed task is appended & synced to the file before the node moves on,
+/// and the tasks that were accepted but not completed are the lost ones when the journal is opened.
+pub(crate) struct TaskJournal {
+    path: PathBuf,
+    file: File,
+    /// Accepted tasks that are not completed nor reported yet, by their row id.
+    open: HashMap<Uuid, JournaledTask>,
+    /// Number of entries in the file, to compact it every once in a while.
+    num_entries: usize,
+}
+
+impl TaskJournal {
+    /// Opens the journal at the given path, and returns it along with the tasks lost by the previous run.
+    ///
+    /// The lost tasks are kept in the journal until they are reported, see [`Self::record_reported`].
+    pub(crate) fn open(path: &Path) -> Result<(Self, Vec<JournaledTask>)> {
+        let mut open = HashMap::new();
+        if path.exists() {
+            let file =
+                File::open(path).wrap_err_with(|| format!("could not read {}", path.display()))?;
+            for line in BufReader::new(file).lines() {
+                let line = line.wrap_err_with(|| format!("could not read {}", path.display()))?;
+                // a crash may have left a partial last line, which is skipped
+                match serde_json::from_str::<JournalEntry>(&line) {
+                    Ok(JournalEntry::Accepted(task)) => {
+                        open.insert(task.row_id, task);
+                    }
+                    Ok(JournalEntry::Completed { row_id } | JournalEntry::Reported { row_id }) => {
+                        open.remove(&row_id);
+                    }
+                    Err(err) => log::warn!("Skipping a malformed journal entry: {err}"),
+                }
+            }
+        }
+
+        let mut lost = open.values().cloned().collect::<Vec<_>>();
+        lost.sort_by_key(|task| task.accepted_at);
+
+        // the journal is compacted to its open tasks right away
+        let journal = Self {
+            path: path.to_path_buf(),
+            file: Self::create(path, &open)?,
+            num_entries: open.len(),
+            open,
+        };
+
+        Ok((journal, lost))
+    }
+
+    /// Appends an accepted task to the journal.
+    pub(crate) fn record_accepted(&mut self, task: JournaledTask) -> Result<()> {
+        self.append(&JournalEntry::Accepted(task.clone()))?;
+        self.open.insert(task.row_id, task);
+        Ok(())
+    }
+
+    /// Appends a completed task to the journal.
+    pub(crate) fn record_completed(&mut self, row_id: Uuid) -> Result<()> {
+        self.append(&JournalEntry::Completed { row_id })?;
+        self.open.remove(&row_id);
+        self.compact_if_needed()
+    }
+
+    /// Appends the lost tasks that are reported to the RPC, so that they are not reported again.
+    pub(crate) fn record_reported(
+        &mut self,
+        row_ids: impl IntoIterator<Item = Uuid>,
+    ) -> Result<()> {
+        for row_id in row_ids {
+            self.append(&JournalEntry::Reported { row_id })?;
+            self.open.remove(&row_id);
+        }
+        self.compact_if_needed()
+    }
+
+    /// Writes & syncs the entry, so that it survives a crash right after.
+    fn append(&mut self, entry: &JournalEntry) -> Result<()> {
+        let mut line =
+            serde_json::to_string(entry).wrap_err("could not serialize journal entry")?;
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .and_then(|_| self.file.sync_data())
+            .wrap_err_with(|| format!("could not write {}", self.path.display()))?;
+        self.num_entries += 1;
+        Ok(())
+    }
+
+    /// Rewrites the journal with only its open tasks, once it has grown enough.
+    fn compact_if_needed(&mut self) -> Result<()> {
+        if self.num_entries < JOURNAL_COMPACT_ENTRIES {
+            return Ok(());
+        }
+
+        self.file = Self::create(&self.path, &self.open)?;
+        self.num_entries = self.open.len();
+        Ok(())
+    }
+
+    /// Writes the given open tasks to a new journal at the given path, and returns it for appending.
+    ///
+    /// The file is written & synced to a temporary path first, so that a crash does not lose the open tasks.
+    fn create(path: &Path, open: &HashMap<Uuid, JournaledTask>) -> Result<File> {
+        let mut content = String::new();
+        for task in open.values() {
+            content.push_str(
+                &serde_json::to_string(&JournalEntry::Accepted(task.clone()))
+                    .wrap_err("could not serialize journal entry")?,
+            );
+            content.push('\n');
+        }
+        write_atomic(path, content)?;
+
+        OpenOptions::new()
+            .append(true)
+            .open(path)
+            .wrap_err_with(|| format!("could not open {}", path.display()))
+    }
+}
+
+impl DriaComputeNode {
+    /// Records the accepted task to the journal, if any, once it is pending for its worker.
+    pub(crate) fn journal_accepted(&mut self, row_id: Uuid) {
+        let Some(journal) = &mut self.journal else {
+            return;
+        };
+        let Some(metadata) = self
+            .pending_tasks_single
+            .get(&row_id)
+            .or_else(|| self.pending_tasks_batch.get(&row_id))
+        else {
+            return;
+        };
+
+        let task = JournaledTask {
+            row_id,
+            task_id: metadata.task_id.clone(),
+            file_id: metadata.file_id,
+            model: metadata.model.to_string(),
+            accepted_at: Utc::now(),
+        };
+        if let Err(err) = journal.record_accepted(task) {
+            log::error!("Could not journal the accepted task {row_id}: {err:?}");
+        }
+    }
+
+    /// Records the completed task to the journal, if any.
+    pub(crate) fn journal_completed(&mut self, row_id: Uuid) {
+        let Some(journal) = &mut self.journal else {
+            return;
+        };
+
+        if let Err(err) = journal.record_completed(row_id) {
+            log::error!("Could not journal the completed task {row_id}: {err:?}");
+        }
+    }
+
+    /// Marks the lost tasks as reported, once a heartbeat with them is acknowledged by the RPC.
+    pub(crate) fn handle_lost_tasks_reported(&mut self) {
+        if self.lost_tasks.is_empty() {
+            return;
+        }
+
+        log::info!(
+            "{} lost tasks are reported to the RPC.",
+            self.lost_tasks.len()
+        );
+        let row_ids = std::mem::take(&mut self.lost_tasks)
+            .into_iter()
+            .map(|task| task.row_id);
+        if let Some(journal) = &mut self.journal {
+            if let Err(err) = journal.record_reported(row_ids) {
+                log::error!("Could not journal the reported tasks: {err:?}");
+            }
+        }
+    }
+}
+
+/// Opens the task journal at the given path if any, and returns it along with the tasks lost by the previous run.
+///
+/// The node runs without a journal if it can not be opened.
+pub(crate) fn open_journal(path: Option<&PathBuf>) -> (Option<TaskJournal>, Vec<LostTask>) {
+    let Some(path) = path else {
+        return (None, Vec::new());
+    };
+
+    match TaskJournal::open(path) {
+        Ok((journal, lost)) => {
+            if !lost.is_empty() {
+                log::warn!(
+                    "{} tasks were lost by an unclean exit, they will be reported to the RPC: {:?}",
+                    lost.len(),
+                    lost.iter().map(|task| &task.task_id).collect::<Vec<_>>()
+                );
+            }
+            (
+                Some(journal),
+                lost.into_iter().map(LostTask::from).collect(),
+            )
+        }
+        Err(err) => {
+            log::error!("Could not open the task journal: {err:?}");
+            (None, Vec::new())
+        }
+    }
+}
+
+impl From<JournaledTask> for LostTask {
+    fn from(task: JournaledTask) -> Self {
+        Self {
+            row_id: task.row_id,
+            task_id: task.task_id,
+            file_id: task.file_id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_journal() {
+        let path = std::env::temp_dir().join(format!("dkn-journal-{}.jsonl", Uuid::now_v7()));
+        let task = |task_id: &str| JournaledTask {
+            row_id: Uuid::now_v7(),
+            task_id: task_id.to_string(),
+            file_id: Uuid::now_v7(),
+            model: "gpt-4o".to_string(),
+            accepted_at: Utc::now(),
+        };
+
+        let (mut journal, lost) = TaskJournal::open(&path).unwrap();
+        assert!(lost.is_empty());
+        let (first, second) = (task("first"), task("second"));
+        journal.record_accepted(first.clone()).unwrap();
+        journal.record_accepted(second.clone()).unwrap();
+        journal.record_completed(first.row_id).unwrap();
+
+        // the node "crashes" with a partial entry, & only the second task is lost
+        drop(journal);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"op\":\"acc").unwrap();
+        let (journal, lost) = TaskJournal::open(&path).unwrap();
+        assert_eq!(lost, vec![second.clone()]);
+
+        // the lost task is kept until it is reported
+        drop(journal);
+        let (mut journal, lost) = TaskJournal::open(&path).unwrap();
+        assert_eq!(lost, vec![second.clone()]);
+        journal.record_reported([second.row_id]).unwrap();
+        drop(journal);
+        let (_, lost) = TaskJournal::open(&path).unwrap();
+        assert!(lost.is_empty());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,306 @@
+use dkn_utils::payloads::{LateResultRequest, SchemaVersion, TaskResponsePayload};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::reqres::{late_session, LateResultRequester};
+use crate::DriaComputeNode;
+
+/// Maximum number of results that are not delivered yet, the oldest ones are dropped beyond this.
+const LATE_RESULTS_MAX: usize = 256;
+/// Duration to wait for a late result to be acknowledged, before it is sent again.
+const LATE_RESULT_ACK_TIMEOUT: Duration = Duration::from_secs(60);
+/// Maximum number of times a late result is sent without an acknowledgement, before it is dropped,
+/// e.g. as the RPC does not support late results.
+const LATE_RESULT_MAX_ATTEMPTS: usize = 5;
+
+/// A result to be delivered late, along with the session of its task request.
+#[derive(Debug, Clone)]
+pub(crate) struct LateResult {
+    pub(crate) request: LateResultRequest,
+    /// Session that the task request was encrypted within, so that its result is not sent in plaintext.
+    pub(crate) session_id: Option<Uuid>,
+    /// Number of times the result is sent without an acknowledgement.
+    attempts: usize,
+}
+
+/// Results of the tasks that could not be responded to because the RPC connection was lost mid-task,
+/// to be delivered as late results once the node is reconnected.
+#[derive(Debug, Default)]
+pub(crate) struct LateResults {
+    /// Results waiting to be sent, oldest first.
+    queued: VecDeque<LateResult>,
+    /// Results sent to the RPC & waiting for an acknowledgement, along with the time they were sent at.
+    in_flight: HashMap<Uuid, (Instant, LateResult)>,
+}
+
+impl LateResults {
+    /// Queues the result of a request within the given session, and returns the oldest undelivered one
+    /// if it is dropped to make room.
+    pub(crate) fn push(
+        &mut self,
+        result: TaskResponsePayload,
+        session_id: Option<Uuid>,
+    ) -> Option<LateResultRequest> {
+        let dropped = (self.len() >= LATE_RESULTS_MAX)
+            .then(|| self.drop_oldest())
+            .flatten();
+        self.queued.push_back(LateResult {
+            request: LateResultRequest {
+                schema_version: SchemaVersion::CURRENT,
+                late_id: Uuid::now_v7(),
+                completed_at: chrono::Utc::now(),
+                result,
+            },
+            session_id,
+            attempts: 0,
+        });
+
+        dropped
+    }
+
+    /// Drops the oldest undelivered result, whether it is queued or in flight.
+    fn drop_oldest(&mut self) -> Option<LateResultRequest> {
+        let oldest_in_flight = self
+            .in_flight
+            .values()
+            .map(|(_, late_result)| &late_result.request)
+            .min_by_key(|request| request.completed_at)
+            .map(|request| (request.late_id, request.completed_at));
+
+        match (self.queued.front(), oldest_in_flight) {
+            (Some(queued), Some((_, completed_at)))
+                if queued.request.completed_at <= completed_at =>
+            {
+                self.queued
+                    .pop_front()
+                    .map(|late_result| late_result.request)
+            }
+            (_, Some((late_id, _))) => self.ack(late_id),
+            (_, None) => self
+                .queued
+                .pop_front()
+                .map(|late_result| late_result.request),
+        }
+    }
+
+    /// Returns the results to be sent, i.e. the queued ones along with the ones that were not acknowledged
+    /// within the given timeout, which keep their id so that the RPC can deduplicate them.
+    ///
+    /// The results that are not acknowledged after [`LATE_RESULT_MAX_ATTEMPTS`] are dropped instead,
+    /// and returned separately.
+    pub(crate) fn take_due(
+        &mut self,
+        timeout: Duration,
+    ) -> (Vec<LateResult>, Vec<LateResultRequest>) {
+        let expired = self
+            .in_flight
+            .iter()
+            .filter(|(_, (sent_at, _))| sent_at.elapsed() >= timeout)
+            .map(|(late_id, _)| *late_id)
+            .collect::<Vec<_>>();
+
+        let (mut due, exhausted): (Vec<_>, Vec<_>) = expired
+            .into_iter()
+            .filter_map(|late_id| self.in_flight.remove(&late_id))
+            .map(|(_, late_result)| late_result)
+            .partition(|late_result| late_result.attempts < LATE_RESULT_MAX_ATTEMPTS);
+        due.sort_by_key(|late_result| late_result.request.completed_at);
+        due.extend(self.queued.drain(..));
+
+        let exhausted = exhausted
+            .into_iter()
+            .map(|late_result| late_result.request)
+            .collect();
+        (due, exhausted)
+    }
+
+    /// Marks the result as sent, to wait for its acknowledgement.
+    pub(crate) fn mark_sent(&mut self, mut late_result: LateResult) {
+        late_result.attempts += 1;
+        self.in_flight
+            .insert(late_result.request.late_id, (Instant::now(), late_result));
+    }
+
+    /// Puts the results that could not be sent back to the front of the queue.
+    pub(crate) fn requeue(&mut self, late_results: impl IntoIterator<Item = LateResult>) {
+        let mut late_results = late_results.into_iter().collect::<Vec<_>>();
+        late_results.extend(self.queued.drain(..));
+        self.queued = late_results.into();
+    }
+
+    /// Removes the acknowledged result, returns `None` if it is unknown.
+    pub(crate) fn ack(&mut self, late_id: Uuid) -> Option<LateResultRequest> {
+        self.in_flight
+            .remove(&late_id)
+            .map(|(_, late_result)| late_result.request)
+    }
+
+    /// Number of results that are not delivered yet.
+    pub(crate) fn len(&self) -> usize {
+        self.queued.len() + self.in_flight.len()
+    }
+}
+
+impl DriaComputeNode {
+    /// Buffers the result of a task that could not be responded to, to be delivered once reconnected.
+    ///
+    /// The result of a request that was encrypted within a session is only delivered within a session as well.
+    pub(crate) fn buffer_late_result(
+        &mut self,
+        result: TaskResponsePayload,
+        session_id: Option<Uuid>,
+    ) {
+        log::warn!(
+            "Could not respond to task {}, its result is buffered to be delivered once reconnected.",
+            result.row_id
+        );
+        if let Some(dropped) = self.late_results.push(result, session_id) {
+            log::error!(
+                "Too many undelivered results, dropping the result of task {}.",
+                dropped.result.row_id
+            );
+        }
+    }
+
+    /// Sends the buffered results to the RPC, called once the RPC is known to be reachable.
+    pub(crate) async fn send_late_results(&mut self) {
+        let (due, exhausted) = self.late_results.take_due(LATE_RESULT_ACK_TIMEOUT);
+        for request in exhausted {
+            log::error!(
+                "Late result of task {} was not acknowledged after {LATE_RESULT_MAX_ATTEMPTS} attempts, dropping it.",
+                request.result.row_id
+            );
+        }
+
+        // the results of the encrypted requests are held until the RPC adopts a session
+        let (due, held): (Vec<_>, Vec<_>) = due
+            .into_iter()
+            .partition(|late_result| late_session(self, late_result.session_id).is_some());
+        if !held.is_empty() {
+            log::info!(
+                "Holding {} late task results until the RPC adopts a session.",
+                held.len()
+            );
+            self.late_results.requeue(held);
+        }
+        if due.is_empty() {
+            return;
+        }
+
+        log::info!("Delivering {} late task results.", due.len());
+        let messages = match LateResultRequester::new_late_messages(self, &due).await {
+            Ok(messages) => messages,
+            Err(err) => {
+                log::error!("Could not sign late task results: {err:?}");
+                self.late_results.requeue(due);
+                return;
+            }
+        };
+
+        let peer_id = self.dria_rpc.peer_id;
+        let encoding = self.request_encoding(peer_id);
+        let mut due = due.into_iter().zip(messages);
+        while let Some((late_result, message)) = due.next() {
+            let sent = match message.to_bytes(encoding) {
+                Ok(data) => self.p2p.request(peer_id, data).await,
+                Err(err) => Err(err.into()),
+            };
+            match sent {
+                Ok(_) => self.late_results.mark_sent(late_result),
+                Err(err) => {
+                    log::error!("Could not send late task result: {err:?}");
+                    self.late_results.requeue(
+                        std::iter::once(late_result).chain(due.map(|(late_result, _)| late_result)),
+                    );
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dkn_utils::payloads::TaskStats;
+
+    #[test]
+    fn test_late_results() {
+        let result = |task_id: &str| TaskResponsePayload {
+            schema_version: SchemaVersion::CURRENT,
+            file_id: Uuid::now_v7(),
+            row_id: Uuid::now_v7(),
+            task_id: task_id.to_string(),
+            model: "gpt-4o".to_string(),
+            stats: TaskStats::default(),
+            result: Some("hello".to_string()),
+            error: None,
+            encrypted_result: None,
+            receipt_signature: None,
+        };
+
+        let session_id = Some(Uuid::now_v7());
+        let mut late_results = LateResults::default();
+        assert!(late_results.push(result("first"), session_id).is_none());
+        assert!(late_results.push(result("second"), None).is_none());
+
+        // the first one is sent, the second one could not be sent
+        let (mut due, exhausted) = late_results.take_due(LATE_RESULT_ACK_TIMEOUT);
+        assert_eq!(due.len(), 2);
+        assert!(exhausted.is_empty());
+        let second = due.pop().unwrap();
+        let first = due.pop().unwrap();
+        assert_eq!(first.session_id, session_id);
+        let first_id = first.request.late_id;
+        late_results.mark_sent(first);
+        late_results.requeue([second]);
+        assert_eq!(late_results.len(), 2);
+
+        // the first one is waiting for its ack, unless it is timed out
+        let (due, _) = late_results.take_due(LATE_RESULT_ACK_TIMEOUT);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].request.result.task_id, "second");
+        late_results.mark_sent(due.into_iter().next().unwrap());
+        let (due, _) = late_results.take_due(Duration::ZERO);
+        assert_eq!(due.len(), 2);
+        assert_eq!(due[0].request.late_id, first_id);
+        assert_eq!(due[0].session_id, session_id);
+        due.into_iter()
+            .for_each(|late_result| late_results.mark_sent(late_result));
+
+        assert!(late_results.ack(first_id).is_some());
+        assert!(late_results.ack(first_id).is_none());
+        assert_eq!(late_results.len(), 1);
+
+        // the results that are never acknowledged are dropped after the maximum attempts, second is sent twice so far
+        for _ in 2..LATE_RESULT_MAX_ATTEMPTS {
+            let (due, exhausted) = late_results.take_due(Duration::ZERO);
+            assert_eq!(due.len(), 1);
+            assert!(exhausted.is_empty());
+            due.into_iter()
+                .for_each(|late_result| late_results.mark_sent(late_result));
+        }
+        let (due, exhausted) = late_results.take_due(Duration::ZERO);
+        assert!(due.is_empty());
+        assert_eq!(exhausted.len(), 1);
+        assert_eq!(exhausted[0].result.task_id, "second");
+        assert_eq!(late_results.len(), 0);
+
+        // the oldest results are dropped beyond the limit, including the ones in flight
+        let mut late_results = LateResults::default();
+        assert!(late_results.push(result("oldest"), None).is_none());
+        let (due, _) = late_results.take_due(LATE_RESULT_ACK_TIMEOUT);
+        due.into_iter()
+            .for_each(|late_result| late_results.mark_sent(late_result));
+        for _ in 1..LATE_RESULTS_MAX {
+            assert!(late_results.push(result("task"), None).is_none());
+        }
+        let dropped = late_results.push(result("overflow"), None).unwrap();
+        assert_eq!(dropped.result.task_id, "oldest");
+        assert_eq!(late_results.len(), LATE_RESULTS_MAX);
+        let dropped = late_results.push(result("overflow"), None).unwrap();
+        assert_eq!(dropped.result.task_id, "task");
+        assert_eq!(late_results.len(), LATE_RESULTS_MAX);
+    }
+}
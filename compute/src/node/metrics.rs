@@ -0,0 +1,309 @@
+use eyre::{Context, Result};
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::{
+    counter::Counter,
+    family::Family,
+    gauge::Gauge,
+    histogram::{exponential_buckets, Histogram},
+};
+use prometheus_client::registry::Registry;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    node::AdminCommand,
+    utils::{read_request, write_response, HTTP_REQUEST_TIMEOUT},
+    workers::task::TaskWorkerOutput,
+    DriaComputeNode,
+};
+
+/// Terminator of the OpenMetrics text format, each encoded registry ends with it.
+const OPENMETRICS_EOF: &str = "# EOF\n";
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ModelLabels {
+    model: String,
+}
+
+impl ModelLabels {
+    fn new(model: impl ToString) -> Self {
+        Self {
+            model: model.to_string(),
+        }
+    }
+}
+
+type HistogramFamily = Family<ModelLabels, Histogram, fn() -> Histogram>;
+
+/// Metrics of the tasks, heartbeats & connections of the node, exported along with the P2P metrics.
+pub(crate) struct NodeMetrics {
+    registry: Registry,
+    tasks_received: Family<ModelLabels, Counter>,
+    tasks_completed: Family<ModelLabels, Counter>,
+    tasks_failed: Family<ModelLabels, Counter>,
+    execution_seconds: HistogramFamily,
+    tokens: Family<ModelLabels, Counter>,
+    heartbeat_rtt_seconds: Histogram,
+    connected_peers: Gauge,
+    rpc_connected: Gauge,
+}
+
+impl Default for NodeMetrics {
+    fn default() -> Self {
+        let mut registry = Registry::with_prefix("dria");
+
+        let tasks_received = Family::default();
+        registry.register(
+            "tasks_received",
+            "Number of tasks received by model",
+            tasks_received.clone(),
+        );
+        let tasks_completed = Family::default();
+        registry.register(
+            "tasks_completed",
+            "Number of tasks completed successfully by model",
+            tasks_completed.clone(),
+        );
+        let tasks_failed = Family::default();
+        registry.register(
+            "tasks_failed",
+            "Number of tasks failed by model",
+            tasks_failed.clone(),
+        );
+        let execution_seconds: HistogramFamily =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(0.25, 2.0, 12)));
+        registry.register(
+            "task_execution_seconds",
+            "Execution time of the completed tasks by model",
+            execution_seconds.clone(),
+        );
+        let tokens = Family::default();
+        registry.register(
+            "task_tokens",
+            "Number of tokens generated by the completed tasks by model",
+            tokens.clone(),
+        );
+        let heartbeat_rtt_seconds = Histogram::new(exponential_buckets(0.01, 2.0, 12));
+        registry.register(
+            "heartbeat_rtt_seconds",
+            "Round-trip time of the acknowledged heartbeats",
+            heartbeat_rtt_seconds.clone(),
+        );
+        let connected_peers = Gauge::default();
+        registry.register(
+            "p2p_connected_peers",
+            "Number of connected peers",
+            connected_peers.clone(),
+        );
+        let rpc_connected = Gauge::default();
+        registry.register(
+            "rpc_connected",
+            "Whether the node is connected to its RPC",
+            rpc_connected.clone(),
+        );
+
+        Self {
+            registry,
+            tasks_received,
+            tasks_completed,
+            tasks_failed,
+            execution_seconds,
+            tokens,
+            heartbeat_rtt_seconds,
+            connected_peers,
+            rpc_connected,
+        }
+    }
+}
+
+impl NodeMetrics {
+    pub(crate) fn record_task_received(&self, model: impl ToString) {
+        self.tasks_received
+            .get_or_create(&ModelLabels::new(model))
+            .inc();
+    }
+
+    pub(crate) fn record_task_output(&self, model: impl ToString, output: &TaskWorkerOutput) {
+        let labels = ModelLabels::new(model);
+        if output.result.is_err() {
+            self.tasks_failed.get_or_create(&labels).inc();
+            return;
+        }
+
+        self.tasks_completed.get_or_create(&labels).inc();
+        let stats = &output.stats;
+        let execution_time = stats.execution_ended_at - stats.execution_started_at;
+        if let Ok(execution_time) = execution_time.to_std() {
+            self.execution_seconds
+                .get_or_create(&labels)
+                .observe(execution_time.as_secs_f64());
+        }
+        self.tokens
+            .get_or_create(&labels)
+            .inc_by((stats.token_count + stats.verification_token_count) as u64);
+    }
+
+    pub(crate) fn record_heartbeat_rtt(&self, rtt: Duration) {
+        self.heartbeat_rtt_seconds.observe(rtt.as_secs_f64());
+    }
+
+    /// Encodes the metrics in the OpenMetrics text format, without the terminator
+    /// so that other metrics can be appended.
+    fn encode(&self) -> String {
+        let mut encoded = String::new();
+        if let Err(err) = prometheus_client::encoding::text::encode(&mut encoded, &self.registry) {
+            log::error!("Could not encode metrics: {err:?}");
+        }
+        encoded
+            .strip_suffix(OPENMETRICS_EOF)
+            .map(String::from)
+            .unwrap_or(encoded)
+    }
+}
+
+impl DriaComputeNode {
+    /// Returns the node & P2P metrics in the OpenMetrics text format, where the connection
+    /// state is updated right before.
+    pub(crate) async fn encode_metrics(&mut self) -> String {
+        let num_peers = self
+            .p2p
+            .network_info()
+            .await
+            .map(|info| info.num_peers())
+            .unwrap_or_default();
+        self.metrics.connected_peers.set(num_peers as i64);
+        let rpc_connected = self
+            .p2p
+            .is_connected(self.dria_rpc.peer_id)
+            .await
+            .unwrap_or(false);
+        self.metrics.rpc_connected.set(rpc_connected as i64);
+
+        let mut encoded = self.metrics.encode();
+        match self.p2p.metrics().await {
+            Ok(p2p_metrics) => encoded.push_str(&p2p_metrics),
+            Err(err) => {
+                log::warn!("Could not get P2P metrics: {err:?}");
+                encoded.push_str(OPENMETRICS_EOF);
+            }
+        }
+        encoded
+    }
+}
+
+/// Serves the metrics at `/metrics` in the OpenMetrics text format until cancelled, to be scraped by Prometheus.
+pub async fn serve_metrics(
+    addr: SocketAddr,
+    commander: mpsc::Sender<AdminCommand>,
+    cancellation: CancellationToken,
+) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .wrap_err_with(|| format!("could not bind metrics server to {addr}"))?;
+    log::info!("Serving metrics at http://{addr}/metrics");
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let commander = commander.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) = tokio::time::timeout(HTTP_REQUEST_TIMEOUT, handle_connection(stream, &commander)).await {
+                            log::debug!("Metrics request timed out: {err}");
+                        }
+                    });
+                }
+                Err(err) => log::warn!("Could not accept metrics connection: {err}"),
+            },
+            _ = cancellation.cancelled() => {
+                log::info!("Closing metrics server.");
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Reads a single request & writes its response, the connection is closed afterwards.
+async fn handle_connection(mut stream: TcpStream, commander: &mpsc::Sender<AdminCommand>) {
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+    if request.path != "/metrics" {
+        write_response(
+            &mut stream,
+            &request,
+            "404 Not Found",
+            "not found",
+            "text/plain",
+        )
+        .await;
+        return;
+    }
+
+    let (sender, receiver) = oneshot::channel();
+    let metrics = match commander.send(AdminCommand::Metrics { sender }).await {
+        Ok(()) => receiver.await.ok(),
+        Err(_) => None,
+    };
+    match metrics {
+        Some(metrics) => {
+            write_response(
+                &mut stream,
+                &request,
+                "200 OK",
+                &metrics,
+                "application/openmetrics-text; version=1.0.0; charset=utf-8",
+            )
+            .await
+        }
+        None => {
+            write_response(
+                &mut stream,
+                &request,
+                "503 Service Unavailable",
+                "node is not running",
+                "text/plain",
+            )
+            .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dkn_utils::payloads::TaskStats;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_node_metrics() {
+        let metrics = NodeMetrics::default();
+        metrics.record_task_received("gpt-4o");
+        metrics.record_task_received("gpt-4o");
+
+        let mut stats = TaskStats::new();
+        stats.execution_ended_at = stats.execution_started_at + chrono::Duration::seconds(2);
+        stats.token_count = 42;
+        metrics.record_task_output(
+            "gpt-4o",
+            &TaskWorkerOutput {
+                row_id: Uuid::now_v7(),
+                result: Ok("hello".to_string()),
+                stats,
+                batchable: true,
+            },
+        );
+        metrics.record_heartbeat_rtt(Duration::from_millis(30));
+
+        let encoded = metrics.encode();
+        assert!(encoded.contains(r#"dria_tasks_received_total{model="gpt-4o"} 2"#));
+        assert!(encoded.contains(r#"dria_tasks_completed_total{model="gpt-4o"} 1"#));
+        assert!(encoded.contains(r#"dria_task_tokens_total{model="gpt-4o"} 42"#));
+        assert!(encoded.contains(r#"dria_task_execution_seconds_sum{model="gpt-4o"} 2.0"#));
+        assert!(encoded.contains("dria_heartbeat_rtt_seconds_count 1"));
+        assert!(!encoded.ends_with(OPENMETRICS_EOF));
+    }
+}
@@ -2,34 +2,98 @@ use dkn_executor::Model;
 use dkn_p2p::{
     libp2p::PeerId, DriaP2PClient, DriaP2PCommander, DriaP2PProtocol, DriaReqResMessage,
 };
-use dkn_utils::{crypto::secret_to_keypair, payloads::SpecModelPerformance};
+use dkn_utils::payloads::SpecModelPerformance;
 use eyre::Result;
 use std::collections::{HashMap, HashSet};
-use tokio::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
 use uuid::Uuid;
 
 use crate::{
     config::*,
-    utils::{DriaPointsClient, SpecCollector},
-    workers::task::{TaskWorker, TaskWorkerInput, TaskWorkerMetadata, TaskWorkerOutput},
+    utils::{Backoff, DriaPoints, DriaPointsClient, NodeHealth, SpecCollector},
+    workers::task::{
+        TaskWorker, TaskWorkerInput, TaskWorkerMetadata, TaskWorkerOutput, TaskWorkerProgress,
+    },
 };
 
+mod admin;
+mod builder;
+mod clock;
+pub use builder::DriaComputeNodeBuilder;
 mod core;
 mod diagnostic;
+mod events;
+mod history;
+use history::open_history;
+pub(crate) use history::TaskHistory;
+pub use history::{HistoryQuery, TaskRecord};
+mod journal;
+mod late;
+use admin::ADMIN_COMMANDS_BUFSIZE;
+pub use admin::{serve_admin, AdminCommand, AdminStatus, ModelTaskCount};
+pub use events::DriaNodeEvent;
+use events::{TaskErrorTracker, NODE_EVENTS_BUFSIZE, TASK_ERRORS_WINDOW};
+use journal::{open_journal, TaskJournal};
+pub(crate) use late::LateResult;
+use late::LateResults;
+mod metrics;
+mod model_stats;
+pub use metrics::serve_metrics;
+use metrics::NodeMetrics;
+use model_stats::ModelStatsTracker;
+mod partition;
+use partition::PartitionTracker;
+mod peer_store;
 mod reqres;
+pub(crate) use peer_store::PeerStore;
 mod rpc;
-use rpc::DriaRPC;
+pub(crate) use rpc::DriaRPC;
+mod reload;
+pub use reload::{reload_config, NodeReconfig, ReloadHandle};
+mod replay;
+use replay::{ReplayGuard, REPLAY_CAPACITY, REPLAY_WINDOW};
+mod session;
+use session::{RpcSessions, SESSION_LIFETIME};
+mod state;
+mod stats;
+mod status;
+mod summary;
+use state::{load_state, STATE_MAX_AGE};
+pub(crate) use stats::read_stats;
+use stats::LifetimeStatsStore;
+use status::NodeError;
+use summary::ActivityAggregator;
+pub use summary::{ActivitySummary, ModelActivity};
+mod update;
+use update::StagedUpdate;
 
 /// Buffer size for message publishes.
 const PUBLISH_CHANNEL_BUFSIZE: usize = 1024;
+/// Delay before the first re-attempt of a lost RPC connection, doubled for each attempt.
+const RPC_BACKOFF_BASE: Duration = Duration::from_secs(5);
+/// Maximum delay between the re-attempts of a lost RPC connection.
+const RPC_BACKOFF_MAX: Duration = Duration::from_secs(5 * 60);
 
 pub struct DriaComputeNode {
     /// Compute node configuration.
     pub config: DriaComputeNodeConfig,
-    /// Chosen RPC node.
+    /// Chosen RPC node, which the node sends heartbeats to and accepts tasks from.
     pub dria_rpc: DriaRPC,
+    /// Standby RPC nodes with warm connections, the primary fails over to one of them when it drops.
+    pub(crate) standby_rpcs: Vec<DriaRPC>,
+    /// RPC peers whose requests are accepted, i.e. the primary & standby RPCs along with
+    /// the explicitly authorized ones.
+    pub(crate) authorized_rpcs: HashSet<PeerId>,
+    /// Known RPCs along with their health, persisted to disk if configured.
+    pub(crate) peer_store: PeerStore,
+    /// Backoff for re-attempting the RPC connection when it is lost.
+    pub(crate) rpc_backoff: Backoff,
     /// Peer-to-peer client commander to interact with the network.
     pub p2p: DriaP2PCommander,
+    /// Offset of the local clock from the NTP time, non-zero only if its drift exceeds the threshold.
+    pub(crate) clock_offset: chrono::Duration,
     /// The last time the node had an acknowledged heartbeat.
     /// If this is too much, we can say that the node is not reachable by RPC.
     pub(crate) last_heartbeat_at: chrono::DateTime<chrono::Utc>,
@@ -42,9 +106,15 @@ pub struct DriaComputeNode {
     /// This is used to track the specs, and their acknowledgements.
     pub(crate) specs_reqs: HashSet<Uuid>,
     /// Request-response message receiver, can have both a request or a response.
-    reqres_rx: mpsc::Receiver<(PeerId, DriaReqResMessage)>,
+    reqres_rx: mpsc::Receiver<(PeerId, String, DriaReqResMessage)>,
     /// Task response receiver, will respond to the request-response channel with the given result.
     task_output_rx: mpsc::Receiver<TaskWorkerOutput>,
+    /// Task progress receiver, progress updates are sent to the RPC.
+    task_progress_rx: mpsc::Receiver<TaskWorkerProgress>,
+    /// Task response transmitter, given to the workers spawned when the configuration is reloaded.
+    task_output_tx: mpsc::Sender<TaskWorkerOutput>,
+    /// Task progress transmitter, given to the workers along with tasks if progress is reported.
+    pub(crate) task_progress_tx: mpsc::Sender<TaskWorkerProgress>,
     /// Task worker transmitter to send batchable tasks.
     task_request_batch_tx: Option<mpsc::Sender<TaskWorkerInput>>,
     /// Task worker transmitter to send single tasks.
@@ -53,14 +123,66 @@ pub struct DriaComputeNode {
     pub pending_tasks_single: HashMap<Uuid, TaskWorkerMetadata>,
     // Batchable tasks, key is `row_id`, which has negligible probability of collision.
     pub pending_tasks_batch: HashMap<Uuid, TaskWorkerMetadata>,
+    /// Journal of the pending tasks, if configured.
+    journal: Option<TaskJournal>,
+    /// Tasks lost by an unclean exit of the previous run, reported to the RPC within the heartbeats.
+    pub(crate) lost_tasks: Vec<dkn_utils::payloads::LostTask>,
+    /// Results that could not be responded to due to a lost RPC connection, delivered once reconnected.
+    pub(crate) late_results: LateResults,
     /// Completed single tasks count
     completed_tasks_single: usize,
     /// Completed batch tasks count
     completed_tasks_batch: usize,
+    /// Completed tasks count per model.
+    completed_tasks_per_model: HashMap<Model, usize>,
+    /// Outcomes & latencies of the responded tasks per model, for the diagnostics.
+    pub(crate) model_stats: ModelStatsTracker,
+    /// Statistics across the restarts of the node.
+    lifetime: LifetimeStatsStore,
+    /// History of the completed & failed tasks, if enabled.
+    history: Option<TaskHistory>,
+    /// Activity within the current day, summarized once a day.
+    pub(crate) activity: ActivityAggregator,
+    /// Count of tasks that were rejected due to being overloaded, since the last diagnostic.
+    shed_tasks: usize,
     /// Specifications collector.
     spec_collector: SpecCollector,
     /// Points client.
     points_client: DriaPointsClient,
+    /// The latest points, if they could be fetched.
+    points: Option<DriaPoints>,
+    /// Handle to the background task that warms up cold models, if any.
+    model_warmup_handle: Option<tokio::task::JoinHandle<()>>,
+    /// Whether the node is partitioned from the network, and since when.
+    partition: PartitionTracker,
+    /// High-level node events, broadcasted to the subscribers.
+    events_tx: broadcast::Sender<DriaNodeEvent>,
+    /// Whether the heartbeats are failing, so that it is emitted once until they are acknowledged again.
+    heartbeats_failing: bool,
+    /// Recent task errors, to emit an event when they reach the threshold.
+    task_errors: TaskErrorTracker,
+    /// Ids of the recently handled requests, to reject the ones that are resent.
+    replay_guard: ReplayGuard,
+    /// End-to-end encrypted sessions with the RPC, offered only if enabled.
+    pub(crate) sessions: RpcSessions,
+    /// Message encodings of the peers, as of their latest requests, see [`Self::request_encoding`].
+    pub(crate) peer_encodings: HashMap<PeerId, dkn_utils::DriaMessageEncoding>,
+    /// The latest error, reported in the status file.
+    last_error: Option<NodeError>,
+    /// The latest version that an update event was emitted for, if any.
+    notified_version: Option<dkn_utils::SemanticVersion>,
+    /// A verified binary of a newer version to restart with, if auto-update is enabled.
+    staged_update: Option<StagedUpdate>,
+    /// Readiness of the node, shared with the health server.
+    health: Arc<NodeHealth>,
+    /// Metrics of the tasks, heartbeats & connections.
+    pub(crate) metrics: NodeMetrics,
+    /// Whether the task intake is paused by the admin API, new tasks are rejected if so.
+    is_paused: bool,
+    /// Admin command transmitter, given to the admin API.
+    admin_tx: mpsc::Sender<AdminCommand>,
+    /// Admin command receiver.
+    admin_rx: mpsc::Receiver<AdminCommand>,
 }
 
 impl DriaComputeNode {
@@ -76,34 +198,109 @@ impl DriaComputeNode {
         Option<TaskWorker>,
         Option<TaskWorker>,
     )> {
-        // create the keypair from secret key
-        let keypair = secret_to_keypair(&config.secret_key);
+        // keypair of the P2P identity, which may differ from the wallet
+        let keypair = config.p2p_keypair.clone();
+
+        // known RPCs from the previous runs, if any
+        let peer_store = PeerStore::load(config.peer_store_path.clone());
+
+        // state of the previous run, if it is recent enough
+        let state = load_state(config.state_path.as_ref(), STATE_MAX_AGE);
+
+        // tasks that were lost by a crash of the previous run, if journaled
+        let (journal, lost_tasks) = open_journal(config.journal_path.as_ref());
+
+        // statistics across the restarts, this starts a new session
+        let lifetime = LifetimeStatsStore::load(config.stats_path.clone());
+
+        // history of the tasks, if enabled
+        let history = open_history(config.history_path.as_deref(), config.history_retention);
 
         // dial the RPC node
-        let dria_rpc = if let Some(addr) = config.initial_rpc_addr.take() {
+        let dria_rpc = if let Some(addr) = config.pinned_rpc_addr.clone() {
+            log::info!("Using pinned RPC address: {addr}");
+            config.rpc_standby_count = 0;
+            DriaRPC::new(addr, config.network).expect("could not get RPC to connect to")
+        } else if let Some(addr) = config.initial_rpc_addr.take() {
             log::info!("Using initial RPC address: {addr}");
+            // an explicit RPC is meant for testing, so we do not connect to others
+            config.rpc_standby_count = 0;
             DriaRPC::new(addr, config.network).expect("could not get RPC to connect to")
         } else {
-            DriaRPC::new_for_network(config.network, &config.version)
-                .await
-                .expect("could not get RPC to connect to")
+            match DriaRPC::candidates_for_network(
+                config.network,
+                &config.version,
+                config.discovery_url.as_deref(),
+            )
+            .await
+            {
+                // the RPC of the previous run is preferred, if it is still available
+                Ok(rpcs) if !rpcs.is_empty() => {
+                    let last_rpc = state.as_ref().and_then(|state| state.last_rpc.as_ref());
+                    let idx = rpcs
+                        .iter()
+                        .position(|rpc| Some(&rpc.addr) == last_rpc)
+                        .unwrap_or_default();
+                    rpcs.into_iter().nth(idx).expect("index is within bounds")
+                }
+                Ok(_) => {
+                    log::warn!(
+                        "No RPCs were returned by discovery API, using a known RPC instead."
+                    );
+                    peer_store
+                        .candidates(config.network)
+                        .into_iter()
+                        .next()
+                        .expect("could not get RPC to connect to")
+                }
+                Err(err) => {
+                    // fallback to the healthiest known RPC, if the discovery API is down
+                    log::warn!("Could not use discovery API, using a known RPC instead: {err:?}");
+                    peer_store
+                        .candidates(config.network)
+                        .into_iter()
+                        .next()
+                        .expect("could not get RPC to connect to")
+                }
+            }
         };
 
         // we are using the major.minor version as the P2P version
-        // so that patch versions do not interfere with the protocol
-        let protocol = DriaP2PProtocol::new_major_minor(config.network.protocol_name());
+        // so that patch versions do not interfere with the protocol, unless a private one is given
+        let protocol = config
+            .protocol
+            .clone()
+            .unwrap_or_else(|| DriaP2PProtocol::new_major_minor(config.network.protocol_name()));
         log::info!("Using identity: {protocol}");
 
+        // the RPC is always allowed, in case only the RPC peers are allowed to connect
+        let mut p2p_config = config.p2p_config.clone();
+        if let Some(allowed_peers) = p2p_config.allowed_peers.as_mut() {
+            allowed_peers.push(dria_rpc.peer_id);
+            allowed_peers.extend(config.authorized_rpcs.iter().copied());
+        }
+        // and its connection is kept alive, so that it is not dropped while idle
+        if config.rpc_keep_alive {
+            p2p_config.keep_alive_peers.push(dria_rpc.peer_id);
+        }
+        // only the authorized RPCs can open streams
+        p2p_config.stream_peers.push(dria_rpc.peer_id);
+        p2p_config
+            .stream_peers
+            .extend(config.authorized_rpcs.iter().copied());
+
         // create p2p client
         let (p2p_client, p2p_commander, request_rx) = DriaP2PClient::new(
             keypair,
-            config.p2p_listen_addr.clone(),
+            config.p2p_listen_addrs.clone(),
             &dria_rpc.addr,
             protocol,
+            p2p_config,
         )?;
 
         // create channel for task executors, all workers use the same publish channel
         let (publish_tx, publish_rx) = mpsc::channel(PUBLISH_CHANNEL_BUFSIZE);
+        let (progress_tx, progress_rx) = mpsc::channel(PUBLISH_CHANNEL_BUFSIZE);
 
         // check if we should create a worker for batch executor
         let (task_batch_worker, task_batch_tx) =
@@ -117,14 +314,26 @@ impl DriaComputeNode {
         // check if we should create a worker for single executor
         let (task_single_worker, task_single_tx) =
             if config.executors.providers.keys().any(|p| !p.is_batchable()) {
-                let (worker, sender) = TaskWorker::new(publish_tx);
+                let (worker, sender) = TaskWorker::new(publish_tx.clone());
                 (Some(worker), Some(sender))
             } else {
                 (None, None)
             };
 
         let model_names = config.executors.get_model_names();
-        let points_client = DriaPointsClient::new(&config.address, &config.network)?;
+        let points_client = DriaPointsClient::new(&config.address, &config.network)?
+            .with_ledger(config.points_ledger_path.clone());
+
+        // requests are accepted from the RPC, and the explicitly authorized ones
+        let authorized_rpcs = config
+            .authorized_rpcs
+            .iter()
+            .copied()
+            .chain([dria_rpc.peer_id])
+            .collect();
+
+        let (admin_tx, admin_rx) = mpsc::channel(ADMIN_COMMANDS_BUFSIZE);
+        let task_errors = TaskErrorTracker::new(TASK_ERRORS_WINDOW, config.webhook_task_errors);
 
         let spec_collector = SpecCollector::new(
             model_names.clone(),
@@ -133,34 +342,68 @@ impl DriaComputeNode {
             config.exec_platform.clone(),
             p2p_client.peer_id,
         );
-        Ok((
-            DriaComputeNode {
-                config,
-                p2p: p2p_commander,
-                dria_rpc,
-                points_client,
-                // receivers
-                task_output_rx: publish_rx,
-                reqres_rx: request_rx,
-                // transmitters
-                task_request_batch_tx: task_batch_tx,
-                task_request_single_tx: task_single_tx,
-                // task trackers
-                pending_tasks_single: HashMap::new(),
-                pending_tasks_batch: HashMap::new(),
-                completed_tasks_single: 0,
-                completed_tasks_batch: 0,
-                // heartbeats
-                heartbeats_reqs: HashMap::new(),
-                last_heartbeat_at: chrono::Utc::now(),
-                num_heartbeats: 0,
-                // specs
-                specs_reqs: HashSet::new(),
-                spec_collector,
-            },
-            p2p_client,
-            task_batch_worker,
-            task_single_worker,
-        ))
+        let mut node = DriaComputeNode {
+            config,
+            p2p: p2p_commander,
+            dria_rpc,
+            standby_rpcs: Vec::new(),
+            authorized_rpcs,
+            peer_store,
+            rpc_backoff: Backoff::new(RPC_BACKOFF_BASE, RPC_BACKOFF_MAX),
+            points_client,
+            points: None,
+            // receivers
+            task_output_rx: publish_rx,
+            reqres_rx: request_rx,
+            task_progress_rx: progress_rx,
+            // transmitters
+            task_output_tx: publish_tx,
+            task_progress_tx: progress_tx,
+            task_request_batch_tx: task_batch_tx,
+            task_request_single_tx: task_single_tx,
+            // task trackers
+            pending_tasks_single: HashMap::new(),
+            pending_tasks_batch: HashMap::new(),
+            journal,
+            lost_tasks,
+            late_results: LateResults::default(),
+            completed_tasks_single: 0,
+            completed_tasks_batch: 0,
+            completed_tasks_per_model: HashMap::new(),
+            model_stats: ModelStatsTracker::default(),
+            lifetime,
+            history,
+            activity: ActivityAggregator::new(chrono::Utc::now()),
+            shed_tasks: 0,
+            // heartbeats
+            heartbeats_reqs: HashMap::new(),
+            last_heartbeat_at: chrono::Utc::now(),
+            clock_offset: chrono::Duration::zero(),
+            num_heartbeats: 0,
+            // specs
+            specs_reqs: HashSet::new(),
+            spec_collector,
+            model_warmup_handle: None,
+            partition: PartitionTracker::default(),
+            events_tx: broadcast::channel(NODE_EVENTS_BUFSIZE).0,
+            heartbeats_failing: false,
+            task_errors,
+            replay_guard: ReplayGuard::new(REPLAY_WINDOW, REPLAY_CAPACITY),
+            sessions: RpcSessions::new(SESSION_LIFETIME),
+            peer_encodings: HashMap::new(),
+            last_error: None,
+            notified_version: None,
+            staged_update: None,
+            health: Arc::new(NodeHealth::default()),
+            metrics: NodeMetrics::default(),
+            is_paused: false,
+            admin_tx,
+            admin_rx,
+        };
+        if let Some(state) = state {
+            node.restore_state(state);
+        }
+
+        Ok((node, p2p_client, task_batch_worker, task_single_worker))
     }
 }
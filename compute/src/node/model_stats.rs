@@ -0,0 +1,108 @@
+use dkn_utils::payloads::TaskResponsePayload;
+use std::collections::BTreeMap;
+
+use super::history::error_class;
+
+/// Task outcomes of a single model within this session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ModelStats {
+    pub completed: usize,
+    /// Failed tasks per error class, e.g. `provider:server_busy` or `http`.
+    pub failed: BTreeMap<String, usize>,
+    /// Sum of the latencies from receiving to responding, in milliseconds.
+    total_latency_ms: u64,
+}
+
+impl ModelStats {
+    /// Returns the number of failed tasks across all error classes.
+    pub(crate) fn num_failed(&self) -> usize {
+        self.failed.values().sum()
+    }
+
+    /// Returns the average latency from receiving to responding, in milliseconds.
+    pub(crate) fn avg_latency_ms(&self) -> u64 {
+        match self.completed + self.num_failed() {
+            0 => 0,
+            count => self.total_latency_ms / count as u64,
+        }
+    }
+}
+
+/// Per-model breakdown of the responded tasks, printed within the diagnostics.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ModelStatsTracker {
+    models: BTreeMap<String, ModelStats>,
+}
+
+impl ModelStatsTracker {
+    /// Records the outcome & latency of a responded task.
+    pub(crate) fn record(&mut self, payload: &TaskResponsePayload) {
+        let stats = self.models.entry(payload.model.clone()).or_default();
+        match &payload.error {
+            None => stats.completed += 1,
+            Some(err) => *stats.failed.entry(error_class(err)).or_default() += 1,
+        }
+
+        let latency = payload.stats.published_at - payload.stats.received_at;
+        stats.total_latency_ms += latency.num_milliseconds().max(0) as u64;
+    }
+
+    /// Returns the stats of each model that has responded to a task, sorted by model name.
+    pub(crate) fn models(&self) -> impl Iterator<Item = (&String, &ModelStats)> {
+        self.models.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dkn_utils::payloads::{SchemaVersion, TaskError, TaskStats};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_model_stats() {
+        let payload = |model: &str, error: Option<TaskError>, latency_ms: i64| {
+            let received_at = chrono::Utc::now();
+            TaskResponsePayload {
+                schema_version: SchemaVersion::CURRENT,
+                file_id: Uuid::now_v7(),
+                row_id: Uuid::now_v7(),
+                task_id: "task".to_string(),
+                model: model.to_string(),
+                stats: TaskStats {
+                    received_at,
+                    published_at: received_at + chrono::Duration::milliseconds(latency_ms),
+                    ..Default::default()
+                },
+                result: None,
+                error,
+                encrypted_result: None,
+                receipt_signature: None,
+            }
+        };
+
+        let mut tracker = ModelStatsTracker::default();
+        tracker.record(&payload("gemma3:4b", None, 1000));
+        tracker.record(&payload("gemma3:4b", None, 3000));
+        tracker.record(&payload(
+            "gemma3:4b",
+            Some(TaskError::HttpError("timeout".to_string())),
+            5000,
+        ));
+        tracker.record(&payload(
+            "gpt-4o",
+            Some(TaskError::HttpError("timeout".to_string())),
+            100,
+        ));
+
+        let models = tracker.models().collect::<Vec<_>>();
+        assert_eq!(models.len(), 2);
+        let (model, stats) = models[0];
+        assert_eq!(model, "gemma3:4b");
+        assert_eq!(stats.completed, 2);
+        assert_eq!(stats.failed, BTreeMap::from([("http".to_string(), 1)]));
+        assert_eq!(stats.avg_latency_ms(), 3000);
+        assert_eq!(models[1].1.num_failed(), 1);
+        assert_eq!(models[1].1.avg_latency_ms(), 100);
+    }
+}
@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+
+use crate::{node::DriaNodeEvent, DriaComputeNode};
+
+/// Tracks whether the node is partitioned from the network.
+#[derive(Debug, Default)]
+pub(crate) struct PartitionTracker {
+    /// The time that the current partition started at, if the node is partitioned.
+    since: Option<DateTime<Utc>>,
+}
+
+impl PartitionTracker {
+    /// Updates the state, and returns an event if the node got partitioned or has recovered.
+    pub(crate) fn update(&mut self, is_partitioned: bool) -> Option<DriaNodeEvent> {
+        match (self.since, is_partitioned) {
+            (None, true) => {
+                let since = Utc::now();
+                self.since = Some(since);
+                Some(DriaNodeEvent::Partitioned { since })
+            }
+            (Some(since), false) => {
+                self.since = None;
+                Some(DriaNodeEvent::Recovered {
+                    since,
+                    duration_secs: (Utc::now() - since).num_seconds(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The time that the current partition started at, if the node is partitioned.
+    pub(crate) fn since(&self) -> Option<DateTime<Utc>> {
+        self.since
+    }
+}
+
+impl DriaComputeNode {
+    /// Updates the partition state of the node, where a partition is logged as an error at each check
+    /// until recovery, and its start & end are emitted as events.
+    pub(crate) fn handle_partition_check(&mut self, is_partitioned: bool) {
+        let Some(event) = self.partition.update(is_partitioned) else {
+            if let Some(since) = self.partition.since() {
+                log::error!(
+                    "Node is still partitioned from the network, since {} seconds.",
+                    (Utc::now() - since).num_seconds()
+                );
+            }
+            return;
+        };
+
+        match &event {
+            DriaNodeEvent::Partitioned { .. } => {
+                log::error!("Node is partitioned from the network: there are no connections, and no RPC could be reached.")
+            }
+            DriaNodeEvent::Recovered { duration_secs, .. } => {
+                log::info!("Node has recovered from a partition after {duration_secs} seconds.")
+            }
+            _ => {}
+        }
+
+        self.emit_event(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition_tracker() {
+        let mut tracker = PartitionTracker::default();
+        assert_eq!(tracker.update(false), None);
+
+        let Some(DriaNodeEvent::Partitioned { since }) = tracker.update(true) else {
+            panic!("expected a partition event");
+        };
+        assert_eq!(tracker.update(true), None);
+        assert_eq!(tracker.since(), Some(since));
+
+        assert!(matches!(
+            tracker.update(false),
+            Some(DriaNodeEvent::Recovered { since: s, .. }) if s == since
+        ));
+        assert_eq!(tracker.since(), None);
+
+        let json = serde_json::to_value(DriaNodeEvent::Partitioned { since }).unwrap();
+        assert_eq!(json["event"], "partitioned");
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::{DateTime, Utc};
+use dkn_p2p::libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use dkn_utils::DriaNetwork;
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::rpc::DriaRPC;
+use crate::utils::write_atomic;
+
+/// A known RPC along with its last-seen health.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PeerRecord {
+    /// Address of the RPC, including its peer id.
+    addr: Multiaddr,
+    /// Network of the RPC, e.g. `mainnet`.
+    network: String,
+    /// The last time the RPC was connected.
+    last_seen_at: Option<DateTime<Utc>>,
+    /// Number of failed dials since it was last seen.
+    failures: u32,
+}
+
+impl PeerRecord {
+    /// Returns the peer id within the address.
+    fn peer_id(&self) -> Option<PeerId> {
+        self.addr.iter().find_map(|p| match p {
+            Protocol::P2p(peer_id) => Some(peer_id),
+            _ => None,
+        })
+    }
+}
+
+/// Known RPCs persisted to disk, so that a restarted node can reconnect even when the discovery API is down.
+///
+/// Without a path, the store is kept in memory only.
+#[derive(Debug, Default)]
+pub struct PeerStore {
+    path: Option<PathBuf>,
+    records: Vec<PeerRecord>,
+}
+
+impl PeerStore {
+    /// Maximum number of RPCs to remember, the least healthy ones are forgotten first.
+    const MAX_RECORDS: usize = 32;
+
+    /// Loads the store from the given path, starting with an empty one if the file
+    /// does not exist or can not be parsed.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let records = match &path {
+            Some(path) if path.exists() => std::fs::read_to_string(path)
+                .map_err(eyre::Report::from)
+                .and_then(|content| serde_json::from_str(&content).map_err(Into::into))
+                .unwrap_or_else(|err| {
+                    log::warn!("Could not read peer store {}: {err}", path.display());
+                    Vec::new()
+                }),
+            _ => Vec::new(),
+        };
+
+        Self { path, records }
+    }
+
+    /// Writes the store to its path, if any.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let content =
+            serde_json::to_string_pretty(&self.records).wrap_err("could not serialize peers")?;
+        write_atomic(path, content)
+    }
+
+    /// Records that the given RPC is connected.
+    pub fn record_seen(&mut self, rpc: &DriaRPC) {
+        let record = self.record_mut(rpc);
+        record.last_seen_at = Some(Utc::now());
+        record.failures = 0;
+    }
+
+    /// Records that the given RPC could not be connected.
+    pub fn record_failure(&mut self, rpc: &DriaRPC) {
+        let record = self.record_mut(rpc);
+        record.failures = record.failures.saturating_add(1);
+    }
+
+    /// Returns the known RPCs of the given network, the healthiest ones first.
+    pub fn candidates(&self, network: DriaNetwork) -> Vec<DriaRPC> {
+        let network_name = network.to_string();
+        let mut records = self
+            .records
+            .iter()
+            .filter(|record| record.network == network_name)
+            .collect::<Vec<_>>();
+        records.sort_by(|a, b| {
+            a.failures
+                .cmp(&b.failures)
+                .then(b.last_seen_at.cmp(&a.last_seen_at))
+        });
+
+        records
+            .into_iter()
+            .filter_map(|record| DriaRPC::new(record.addr.clone(), network).ok())
+            .collect()
+    }
+
+    /// Returns the record of the given RPC, creating it if needed.
+    fn record_mut(&mut self, rpc: &DriaRPC) -> &mut PeerRecord {
+        let idx = match self
+            .records
+            .iter()
+            .position(|record| record.peer_id() == Some(rpc.peer_id))
+        {
+            Some(idx) => {
+                // the address may have changed
+                self.records[idx].addr = rpc.addr.clone();
+                idx
+            }
+            None => {
+                if self.records.len() >= Self::MAX_RECORDS {
+                    self.forget_least_healthy();
+                }
+                self.records.push(PeerRecord {
+                    addr: rpc.addr.clone(),
+                    network: rpc.network.to_string(),
+                    last_seen_at: None,
+                    failures: 0,
+                });
+                self.records.len() - 1
+            }
+        };
+
+        &mut self.records[idx]
+    }
+
+    /// Removes the record with the most failures, the one seen the longest ago among them.
+    fn forget_least_healthy(&mut self) {
+        if let Some(idx) = self
+            .records
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| {
+                a.failures
+                    .cmp(&b.failures)
+                    .then(b.last_seen_at.cmp(&a.last_seen_at))
+            })
+            .map(|(idx, _)| idx)
+        {
+            self.records.remove(idx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_store() {
+        let rpc = |peer_id: &str| {
+            DriaRPC::new(
+                format!("/ip4/12.34.56.78/tcp/4001/p2p/{peer_id}")
+                    .parse()
+                    .unwrap(),
+                DriaNetwork::Mainnet,
+            )
+            .unwrap()
+        };
+        let healthy = rpc("16Uiu2HAmB5HGdwLNHX81u7ey1fvDx5Mr4ofa2PdSSVxFKrrcErAN");
+        let flapping = rpc("16Uiu2HAmG7qrpSh8kenjuYqyrwxgEVdzqRV4wM1hHAZRq4j25VBC");
+
+        let path = std::env::temp_dir().join(format!("dkn-peers-{}.json", uuid::Uuid::now_v7()));
+        let mut store = PeerStore::load(Some(path.clone()));
+        store.record_seen(&flapping);
+        store.record_failure(&flapping);
+        store.record_seen(&healthy);
+        store.save().unwrap();
+
+        let store = PeerStore::load(Some(path.clone()));
+        std::fs::remove_file(&path).unwrap();
+        let candidates = store.candidates(DriaNetwork::Mainnet);
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].peer_id, healthy.peer_id);
+        assert_eq!(candidates[1].peer_id, flapping.peer_id);
+        assert!(store.candidates(DriaNetwork::Testnet).is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+use dkn_executor::{DriaExecutorsManager, Model, ModelProvider};
+use dkn_utils::payloads::SpecModelPerformance;
+use eyre::{Context, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot, Notify};
+
+use crate::{
+    config::{parse_batch_size, provider_batch_sizes_from_env},
+    node::AdminCommand,
+    utils::{EnvFile, LogFilterHandle},
+    workers::task::TaskWorker,
+    DriaComputeNode,
+};
+
+/// Configuration that can be changed at runtime, without restarting the node.
+pub struct NodeReconfig {
+    /// Executors for the models, with their API keys.
+    pub executors: DriaExecutorsManager,
+    /// Performance of the models, as measured by the service checks.
+    pub model_perf: HashMap<Model, SpecModelPerformance>,
+    pub batch_size: usize,
+    pub provider_batch_sizes: HashMap<ModelProvider, usize>,
+}
+
+impl std::fmt::Debug for NodeReconfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeReconfig")
+            .field("models", &self.executors.get_model_names())
+            .field("batch_size", &self.batch_size)
+            .field("provider_batch_sizes", &self.provider_batch_sizes)
+            .finish()
+    }
+}
+
+/// Requests configuration reloads, e.g. on `SIGHUP` or from the admin API.
+///
+/// Concurrent requests are coalesced, where the last one decides the models.
+#[derive(Debug, Clone, Default)]
+pub struct ReloadHandle {
+    notify: Arc<Notify>,
+    /// Models to serve instead of `DKN_MODELS` in the requested reload, if any.
+    models: Arc<Mutex<Option<Vec<Model>>>>,
+}
+
+impl ReloadHandle {
+    /// Requests a reload from the environment file.
+    pub fn request(&self) {
+        *self.models.lock().unwrap_or_else(|err| err.into_inner()) = None;
+        self.notify.notify_one();
+    }
+
+    /// Requests a reload from the environment file, serving the given models instead of `DKN_MODELS`.
+    pub fn request_models(&self, models: Vec<Model>) {
+        *self.models.lock().unwrap_or_else(|err| err.into_inner()) = Some(models);
+        self.notify.notify_one();
+    }
+
+    /// Waits for the next request.
+    pub async fn requested(&self) {
+        self.notify.notified().await
+    }
+
+    /// Takes the models of the requested reload, if given.
+    fn take_models(&self) -> Option<Vec<Model>> {
+        self.models
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take()
+    }
+}
+
+impl DriaComputeNode {
+    /// Applies the given configuration, where the workers are replaced as needed and the p2p client is kept as is.
+    ///
+    /// A replaced worker completes its queued tasks before closing, so no task is lost.
+    pub(crate) fn handle_reconfigure(&mut self, reconfig: NodeReconfig) {
+        let NodeReconfig {
+            executors,
+            model_perf,
+            batch_size,
+            provider_batch_sizes,
+        } = reconfig;
+
+        let needs_batch = executors.providers.keys().any(|p| p.is_batchable());
+        if !needs_batch {
+            self.task_request_batch_tx = None;
+        } else if self.task_request_batch_tx.is_none()
+            || batch_size != self.config.batch_size
+            || provider_batch_sizes != self.config.provider_batch_sizes
+        {
+            log::info!("Spawning batch executor worker thread. (batch size {batch_size})");
+            let (mut worker, sender) = TaskWorker::new(self.task_output_tx.clone());
+            let worker_batch_sizes = provider_batch_sizes.clone();
+            tokio::spawn(async move { worker.run_batch(batch_size, worker_batch_sizes).await });
+            self.task_request_batch_tx = Some(sender);
+        }
+
+        let needs_single = executors.providers.keys().any(|p| !p.is_batchable());
+        if !needs_single {
+            self.task_request_single_tx = None;
+        } else if self.task_request_single_tx.is_none() {
+            log::info!("Spawning single executor worker thread.");
+            let (mut worker, sender) = TaskWorker::new(self.task_output_tx.clone());
+            tokio::spawn(async move { worker.run_series().await });
+            self.task_request_single_tx = Some(sender);
+        }
+
+        let model_names = executors.get_model_names();
+        log::info!(
+            "Reloaded configuration with models {} and batch size {batch_size}.",
+            model_names.join(", ")
+        );
+        self.spec_collector.set_models(model_names, model_perf);
+        self.config.executors = executors;
+        self.config.batch_size = batch_size;
+        self.config.provider_batch_sizes = provider_batch_sizes;
+    }
+}
+
+/// Re-reads the environment file & the variables within its selected profile, and reloads the log level, the models
+/// along with their API keys and the batch size of the node.
+///
+/// The models are checked once & applied to each of the given nodes, and the current configuration
+/// is kept if there is an error. The models given with [`ReloadHandle::request_models`] are served
+/// instead of `DKN_MODELS`, until the next reload from the environment file.
+pub async fn reload_config(
+    env_file: &EnvFile,
+    log_filter: &LogFilterHandle,
+    reload: &ReloadHandle,
+    commanders: &[mpsc::Sender<AdminCommand>],
+) -> Result<()> {
+    log::info!("Reloading configuration from {}", env_file.path);
+    if let Err(err) = env_file.reload() {
+        log::warn!(
+            "Could not load environment file from {}: {err:#}",
+            env_file.path
+        );
+    }
+    log_filter.reload();
+
+    let batch_size = parse_batch_size();
+    if batch_size > TaskWorker::MAX_BATCH_SIZE {
+        eyre::bail!(
+            "batch size {batch_size} is larger than {}",
+            TaskWorker::MAX_BATCH_SIZE
+        );
+    }
+    let provider_batch_sizes = provider_batch_sizes_from_env()?;
+
+    let models = match reload.take_models() {
+        Some(models) => models,
+        None => Model::from_csv(std::env::var("DKN_MODELS").unwrap_or_default())
+            .into_iter()
+            .collect(),
+    };
+    let mut executors = DriaExecutorsManager::new_from_env_for_models(models.into_iter())?;
+    let model_perf = executors.check_services().await;
+    if executors.models.is_empty() {
+        eyre::bail!("no valid models left after service checks");
+    }
+
+    for commander in commanders {
+        let (sender, receiver) = oneshot::channel();
+        commander
+            .send(AdminCommand::Reconfigure {
+                reconfig: Box::new(NodeReconfig {
+                    executors: executors.clone(),
+                    model_perf: model_perf.clone(),
+                    batch_size,
+                    provider_batch_sizes: provider_batch_sizes.clone(),
+                }),
+                sender,
+            })
+            .await
+            .wrap_err("node is not running")?;
+        receiver.await.wrap_err("node is not running")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,240 @@
+use chrono::{DateTime, Utc};
+use dkn_p2p::libp2p::PeerId;
+use dkn_utils::{crypto::sha256hash, DriaMessage};
+use eyre::Result;
+use std::collections::{HashSet, VecDeque};
+
+/// Window around the current time that the timestamps of the requests must be within.
+pub(crate) const REPLAY_WINDOW: chrono::Duration = chrono::Duration::minutes(5);
+
+/// Maximum number of message ids that are remembered, the oldest ones are forgotten beyond it.
+pub(crate) const REPLAY_CAPACITY: usize = 65_536;
+
+/// Returns the id of a message for the [`ReplayGuard`], i.e. the hash of its signed digest along with its signer.
+///
+/// The signature itself is not a part of it, as a message could be resent with another encoding of its signature.
+pub(crate) fn replay_id(message: &DriaMessage, signer: &PeerId) -> [u8; 32] {
+    sha256hash([message.signing_digest().as_slice(), &signer.to_bytes()].concat())
+}
+
+/// Remembers the ids of the recent messages, to reject the signed messages that are resent as is.
+///
+/// Messages with timestamps outside of the window are rejected as well, so that only the ids within
+/// the window are to be remembered; if there are more than the capacity, the oldest ones are forgotten first.
+///
+/// The messages without a TTL have no signed timestamp to be rejected by, so their ids are remembered
+/// regardless of their age, up to the capacity. Such a message can still be replayed once its id is forgotten,
+/// i.e. after as many other messages without a TTL, or after a restart; `DKN_REQUIRE_MESSAGE_TTL` closes this.
+#[derive(Debug)]
+pub(crate) struct ReplayGuard {
+    seen: HashSet<[u8; 32]>,
+    /// Ids in the order they are seen, along with the timestamps of their messages.
+    order: VecDeque<([u8; 32], DateTime<Utc>)>,
+    /// Ids of the messages without a TTL in the order they are seen, which are not forgotten by age.
+    legacy_order: VecDeque<[u8; 32]>,
+    window: chrono::Duration,
+    capacity: usize,
+}
+
+impl ReplayGuard {
+    pub(crate) fn new(window: chrono::Duration, capacity: usize) -> Self {
+        Self {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            legacy_order: VecDeque::new(),
+            window,
+            capacity,
+        }
+    }
+
+    /// Checks the message signed by the given signer at the given time, see [`Self::check`].
+    ///
+    /// The timestamp of a message is only signed along with its TTL, so a message without a TTL is checked
+    /// by [`Self::check_legacy`] instead; otherwise, its timestamp could be rewritten to be within the window again.
+    pub(crate) fn check_message(
+        &mut self,
+        message: &DriaMessage,
+        signer: &PeerId,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let id = replay_id(message, signer);
+        match message.ttl_secs {
+            Some(_) => self.check(id, message.timestamp, now),
+            None => self.check_legacy(id),
+        }
+    }
+
+    /// Checks the message of the given id that has no signed timestamp, and remembers it if it is accepted.
+    ///
+    /// Its id is remembered until there are more than the capacity of such ids, regardless of its age.
+    pub(crate) fn check_legacy(&mut self, id: [u8; 32]) -> Result<()> {
+        if !self.seen.insert(id) {
+            eyre::bail!("message without a TTL is a replay of a message that was handled already");
+        }
+        self.legacy_order.push_back(id);
+        while self.legacy_order.len() > self.capacity {
+            if let Some(id) = self.legacy_order.pop_front() {
+                self.seen.remove(&id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the message of the given id & timestamp at the given time, and remembers it if it is accepted.
+    ///
+    /// Returns an error if the timestamp is outside of the window, or the id is seen already.
+    pub(crate) fn check(
+        &mut self,
+        id: [u8; 32],
+        timestamp: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        if (now - timestamp).abs() > self.window {
+            eyre::bail!(
+                "message at {timestamp} is outside of the {}s window",
+                self.window.num_seconds()
+            );
+        }
+
+        // the ids out of the window are forgotten, as their messages are rejected by the timestamp anyways
+        while self
+            .order
+            .front()
+            .is_some_and(|(_, at)| now - *at > self.window)
+        {
+            self.forget_oldest();
+        }
+
+        if !self.seen.insert(id) {
+            eyre::bail!("message at {timestamp} is a replay of a message that was handled already");
+        }
+        self.order.push_back((id, timestamp));
+        while self.order.len() > self.capacity {
+            self.forget_oldest();
+        }
+
+        Ok(())
+    }
+
+    fn forget_oldest(&mut self) {
+        if let Some((id, _)) = self.order.pop_front() {
+            self.seen.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_guard() {
+        let now = Utc::now();
+        let mut guard = ReplayGuard::new(chrono::Duration::minutes(5), 2);
+
+        assert!(guard.check([1; 32], now, now).is_ok());
+        assert!(guard.check([1; 32], now, now).is_err());
+        assert!(guard.check([2; 32], now, now).is_ok());
+
+        // stale & far future messages are rejected regardless of their ids
+        let stale = now - chrono::Duration::minutes(10);
+        assert!(guard.check([3; 32], stale, now).is_err());
+        let future = now + chrono::Duration::minutes(10);
+        assert!(guard.check([3; 32], future, now).is_err());
+
+        // the oldest id is forgotten beyond the capacity
+        assert!(guard.check([3; 32], now, now).is_ok());
+        assert!(guard.check([2; 32], now, now).is_err());
+        assert!(guard.check([1; 32], now, now).is_ok());
+        assert_eq!(guard.seen.len(), 2);
+
+        // ids are forgotten once their messages are out of the window
+        let later = now + chrono::Duration::minutes(6);
+        assert!(guard.check([4; 32], later, later).is_ok());
+        assert_eq!(guard.seen.len(), 1);
+    }
+
+    #[test]
+    fn test_replay_resigned_message() {
+        let secret_key = libsecp256k1::SecretKey::parse(&[1u8; 32]).unwrap();
+        let message = DriaMessage::new_signed(
+            "{}",
+            "task",
+            "dria".to_string(),
+            &secret_key,
+            Default::default(),
+        );
+        let signer = message.recover_peer_id().unwrap();
+        let mut guard = ReplayGuard::new(REPLAY_WINDOW, REPLAY_CAPACITY);
+        let now = message.timestamp;
+        assert!(guard
+            .check(replay_id(&message, &signer), message.timestamp, now)
+            .is_ok());
+
+        // the same request with an upper-cased signature is rejected, and would be a replay regardless
+        let mut uppercased = message.clone();
+        uppercased.signature = uppercased.signature.to_uppercase();
+        assert!(uppercased.recover_peer_id().is_err());
+        assert!(guard
+            .check(replay_id(&uppercased, &signer), uppercased.timestamp, now)
+            .is_err());
+
+        // the same request with the high-s twin of its signature is rejected, and would be a replay regardless
+        let mut signature = libsecp256k1::Signature::parse_standard_slice(
+            &hex::decode(&message.signature).unwrap(),
+        )
+        .unwrap();
+        signature.s = -signature.s;
+        let mut malleated = message.clone();
+        malleated.signature = hex::encode(signature.serialize());
+        malleated.recovery_id ^= 1;
+        assert!(malleated.recover_peer_id().is_err());
+        assert!(guard
+            .check(replay_id(&malleated, &signer), malleated.timestamp, now)
+            .is_err());
+    }
+
+    #[test]
+    fn test_replay_unsigned_timestamp() {
+        let secret_key = libsecp256k1::SecretKey::parse(&[1u8; 32]).unwrap();
+        let message = DriaMessage::new_signed(
+            "{}",
+            "task",
+            "dria".to_string(),
+            &secret_key,
+            Default::default(),
+        );
+        let signer = message.recover_peer_id().unwrap();
+        let mut guard = ReplayGuard::new(REPLAY_WINDOW, REPLAY_CAPACITY);
+
+        // an unsigned timestamp is ignored, so a stale one does not matter on its own
+        let mut stale = message.clone();
+        stale.timestamp -= chrono::Duration::hours(1);
+        let now = message.timestamp;
+        assert!(guard.check_message(&stale, &signer, now).is_ok());
+
+        // rewriting the unsigned timestamp does not make it another message
+        let later = now + chrono::Duration::minutes(4);
+        let mut rewritten = message.clone();
+        rewritten.timestamp = later;
+        assert!(rewritten.recover_peer_id().is_ok());
+        assert!(guard.check_message(&rewritten, &signer, later).is_err());
+
+        // nor is it forgotten once it is out of the window, while the ones with a TTL are rejected by then
+        let much_later = now + chrono::Duration::hours(1);
+        let with_ttl = DriaMessage::new_unsigned("{}", "task", "dria".into(), Default::default())
+            .with_ttl(std::time::Duration::from_secs(60));
+        assert!(guard
+            .check_message(&with_ttl, &signer, with_ttl.timestamp)
+            .is_ok());
+        assert!(guard.check_message(&message, &signer, much_later).is_err());
+        assert!(guard.check_message(&with_ttl, &signer, much_later).is_err());
+
+        // but it is forgotten beyond the capacity
+        let mut guard = ReplayGuard::new(REPLAY_WINDOW, 1);
+        assert!(guard.check_message(&message, &signer, now).is_ok());
+        assert!(guard.check_legacy([1; 32]).is_ok());
+        assert!(guard.check_message(&message, &signer, now).is_ok());
+    }
+}
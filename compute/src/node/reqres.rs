@@ -1,18 +1,25 @@
 use colored::Colorize;
 use dkn_p2p::libp2p::{
-    request_response::{OutboundRequestId, ResponseChannel},
+    request_response::{InboundRequestId, OutboundRequestId, ResponseChannel},
     PeerId,
 };
 use dkn_p2p::DriaReqResMessage;
 use dkn_utils::{
-    payloads::{HEARTBEAT_TOPIC, SPECS_TOPIC, TASK_REQUEST_TOPIC},
-    DriaMessage,
+    payloads::{
+        SchemaVersion, TaskError, TaskProgress, TaskProgressRequest, HEARTBEAT_TOPIC,
+        LATE_RESULT_TOPIC, SPECS_TOPIC, TASK_PROGRESS_TOPIC, TASK_REQUEST_TOPIC,
+    },
+    DriaMessage, DriaMessageEncoding, SemanticVersion,
 };
-use eyre::Result;
+use eyre::{Context, Result};
 
-use crate::{reqres::*, workers::task::TaskWorkerOutput};
+use crate::{
+    reqres::*,
+    workers::task::{TaskWorkerMetadata, TaskWorkerOutput, TaskWorkerProgress},
+};
+use uuid::Uuid;
 
-use super::DriaComputeNode;
+use super::{DriaComputeNode, DriaNodeEvent};
 
 impl DriaComputeNode {
     /// Handles a generic request-response message received from the network.
@@ -20,8 +27,15 @@ impl DriaComputeNode {
     /// - Request is forwarded to [`handle_request`](DriaComputeNode::handle_request) method.
     /// - Response is forwarded to [`handle_response`](DriaComputeNode::handle_response) method.
     ///
+    /// The `version` is the request-response protocol version negotiated with the peer.
+    ///
     /// Does not return an error, but simply logs it to [`log::error`].
-    pub(crate) async fn handle_reqres(&mut self, peer_id: PeerId, message: DriaReqResMessage) {
+    pub(crate) async fn handle_reqres(
+        &mut self,
+        peer_id: PeerId,
+        version: &str,
+        message: DriaReqResMessage,
+    ) {
         match message {
             // make sure that the `channel` here is NOT DROPPED until a response is sent,
             // otherwise you will get an error
@@ -30,13 +44,16 @@ impl DriaComputeNode {
                 request_id,
                 channel,
             } => {
-                log::debug!("Received a request ({request_id}) from {peer_id}");
+                log::debug!("Received a request ({request_id}) from {peer_id} (v{version})");
 
                 // ensure that message is from the known RPCs
-                if self.dria_rpc.peer_id != peer_id {
+                if !self.authorized_rpcs.contains(&peer_id) {
                     log::warn!("Received request from unauthorized source: {peer_id}");
-                    log::debug!("Allowed source: {}", self.dria_rpc.peer_id);
-                } else if let Err(err) = self.handle_request(peer_id, &request, channel).await {
+                    log::debug!("Allowed sources: {:?}", self.authorized_rpcs);
+                } else if let Err(err) = self
+                    .handle_request(peer_id, version, &request, request_id, channel)
+                    .await
+                {
                     log::error!("Error handling request: {err:?}");
                 }
             }
@@ -45,7 +62,7 @@ impl DriaComputeNode {
                 response,
                 request_id,
             } => {
-                log::debug!("Received a response ({request_id}) from {peer_id}");
+                log::debug!("Received a response ({request_id}) from {peer_id} (v{version})");
                 if let Err(err) = self.handle_response(peer_id, request_id, response).await {
                     log::error!("Error handling response: {err:?}");
                 }
@@ -64,9 +81,9 @@ impl DriaComputeNode {
         request_id: OutboundRequestId,
         data: Vec<u8>,
     ) -> Result<()> {
-        if peer_id != self.dria_rpc.peer_id {
+        if !self.authorized_rpcs.contains(&peer_id) {
             log::warn!("Received response from unauthorized source: {peer_id}");
-            log::debug!("Allowed source: {}", self.dria_rpc.peer_id);
+            log::debug!("Allowed sources: {:?}", self.authorized_rpcs);
         }
 
         if let Ok(heartbeat_response) = HeartbeatRequester::try_parse_response(&data) {
@@ -81,6 +98,18 @@ impl DriaComputeNode {
                 SPECS_TOPIC.green(),
             );
             SpecRequester::handle_ack(self, spec_response).await
+        } else if let Ok(progress_response) = ProgressRequester::try_parse_response(&data) {
+            log::debug!(
+                "Received a {} response ({request_id}) from {peer_id}",
+                TASK_PROGRESS_TOPIC.cyan(),
+            );
+            ProgressRequester::handle_ack(progress_response).await
+        } else if let Ok(late_response) = LateResultRequester::try_parse_response(&data) {
+            log::debug!(
+                "Received a {} response ({request_id}) from {peer_id}",
+                LATE_RESULT_TOPIC.purple(),
+            );
+            LateResultRequester::handle_ack(self, late_response).await
         } else {
             Err(eyre::eyre!("Received unhandled request from {}", peer_id))
         }
@@ -90,24 +119,94 @@ impl DriaComputeNode {
     ///
     /// - Internally, the data is expected to be some JSON serialized data that is expected to be parsed and handled.
     /// - Can be inlined because it is only called by [`DriaComputeNode::handle_reqres`].
+    /// - Messages are checked against the negotiated `version`, so that peers on the previous protocol version are served as well.
+    /// - Messages can be encoded in JSON or CBOR, and are responded to with the same encoding.
+    /// - Messages must be signed by one of the authorized RPCs, which is checked before they are handled.
+    /// - Messages are handled only once, and must be recent, see [`ReplayGuard`](super::replay::ReplayGuard).
+    /// - Messages with a TTL must not be expired, where the TTL is signed along with the timestamp; messages without
+    ///   one are rejected if a TTL is required.
     async fn handle_request(
         &mut self,
         peer_id: PeerId,
+        version: &str,
         message_data: &[u8],
+        request_id: InboundRequestId,
         channel: ResponseChannel<Vec<u8>>,
     ) -> Result<()> {
-        let message = DriaMessage::from_slice_checked(
+        let encoding = DriaMessageEncoding::detect(message_data);
+        let mut message = DriaMessage::from_slice_checked(
             message_data,
             self.p2p.protocol().name.clone(),
-            self.config.version,
+            self.message_version(version),
         )?;
 
+        // the request must be signed by a known RPC, as the peer id of the connection alone does not
+        // tell who has created the message
+        let signer = message.recover_peer_id().map_err(|err| {
+            eyre::eyre!("Received a request with an invalid signature from {peer_id}: {err}")
+        })?;
+        if !self.authorized_rpcs.contains(&signer) {
+            eyre::bail!("Received a request from {peer_id} signed by unknown key {signer}");
+        }
+
+        // a request past its signed TTL is stale, e.g. delayed on the way, even if it is within the replay window
+        let now = self.network_now();
+        if message.is_expired(now) {
+            eyre::bail!(
+                "Received an expired request from {peer_id}, sent at {} with a TTL of {}s",
+                message.timestamp,
+                message.ttl_secs.unwrap_or_default()
+            );
+        }
+
+        // the timestamp is only signed along with a TTL, so it can not be trusted otherwise
+        if message.ttl_secs.is_none() && self.config.require_message_ttl {
+            eyre::bail!("Received a request from {peer_id} without a signed TTL");
+        }
+
+        // a signed request could be resent as is to duplicate the work, so each is handled only once
+        self.replay_guard
+            .check_message(&message, &signer, now)
+            .wrap_err_with(|| format!("Rejected a request from {peer_id}"))?;
+
+        // the peer is known to accept the encoding of its request, so the requests to it are encoded with it as well
+        self.peer_encodings.insert(peer_id, encoding);
+
+        // the payload is decrypted after the signature is verified, as the signature is over the ciphertext
+        let session_id = message.session_id;
+        if let Some(session_id) = session_id {
+            let session = self.sessions.adopt(session_id).ok_or_else(|| {
+                eyre::eyre!("Received a request from {peer_id} within unknown session {session_id}")
+            })?;
+            message
+                .decrypt_payload(session)
+                .wrap_err_with(|| format!("Could not decrypt a request from {peer_id}"))?;
+        }
+
         match message.topic.as_str() {
-            TASK_REQUEST_TOPIC => self.handle_task_request(peer_id, message, channel).await,
+            TASK_REQUEST_TOPIC => {
+                self.handle_task_request(
+                    peer_id, message, session_id, encoding, request_id, channel,
+                )
+                .await
+            }
             _ => Err(eyre::eyre!("Received unhandled request from {peer_id}")),
         }
     }
 
+    /// Returns the message version that is compatible with the given `major.minor` protocol version.
+    ///
+    /// Falls back to the version of the node if the protocol version is the current one or can not be parsed.
+    fn message_version(&self, version: &str) -> SemanticVersion {
+        if version == self.p2p.protocol().version {
+            return self.config.version;
+        }
+
+        format!("{version}.0")
+            .parse()
+            .unwrap_or(self.config.version)
+    }
+
     /// Handles a Task request received from the network.
     ///
     /// Based on the task type, the task is sent to the appropriate worker & metadata is stored in memory.
@@ -117,6 +216,9 @@ impl DriaComputeNode {
         &mut self,
         peer_id: PeerId,
         task_request: <TaskResponder as IsResponder>::Request,
+        session_id: Option<Uuid>,
+        encoding: DriaMessageEncoding,
+        request_id: InboundRequestId,
         channel: ResponseChannel<Vec<u8>>,
     ) -> Result<()> {
         log::info!(
@@ -124,8 +226,51 @@ impl DriaComputeNode {
             TASK_REQUEST_TOPIC.yellow()
         );
 
-        let (task_input, task_metadata) =
-            TaskResponder::parse_task_request(self, &task_request, channel).await?;
+        let (mut task_input, task_metadata) = TaskResponder::parse_task_request(
+            self,
+            &task_request,
+            session_id,
+            encoding,
+            request_id,
+            channel,
+        )
+        .await?;
+        let row_id = task_input.row_id;
+        self.metrics.record_task_received(task_metadata.model);
+
+        // reject tasks during quiet hours, the RPC should not have sent it anyways
+        if self.is_quiet() {
+            log::warn!("Rejecting {} {row_id} due to quiet hours.", "task".yellow());
+            let error = TaskError::Unavailable("node is in its quiet hours".to_string());
+            return TaskResponder::send_rejection(self, task_input, task_metadata, error).await;
+        }
+
+        // reject tasks while the intake is paused by the admin API
+        if self.is_paused {
+            log::warn!(
+                "Rejecting {} {row_id} as task intake is paused.",
+                "task".yellow()
+            );
+            let error = TaskError::Unavailable("node has paused accepting tasks".to_string());
+            return TaskResponder::send_rejection(self, task_input, task_metadata, error).await;
+        }
+
+        // shed the load if there are too many pending tasks, so that the RPC can reassign it
+        let pending = self.get_pending_task_count().iter().sum::<usize>();
+        if let Some(error) = self.config.pending_capacity_error(pending) {
+            log::warn!(
+                "Rejecting {} {row_id} as there are {pending} pending tasks.",
+                "task".yellow()
+            );
+            self.shed_tasks += 1;
+            return TaskResponder::send_rejection(self, task_input, task_metadata, error).await;
+        }
+        task_input.queued = tracing::info_span!(parent: &task_input.span, "queue");
+        let started = DriaNodeEvent::TaskStarted {
+            row_id,
+            task_id: task_metadata.task_id.clone(),
+            model: task_metadata.model.to_string(),
+        };
         if let Err(err) = match task_input.task.is_batchable() {
             // this is a batchable task, send it to batch worker
             // and keep track of the task id in pending tasks
@@ -150,12 +295,78 @@ impl DriaComputeNode {
             },
         } {
             log::error!("Could not send task to worker: {err:?}");
+        } else {
+            // journaled once it is queued, so that a crash at any point after this reports it as lost
+            self.journal_accepted(row_id);
+            self.emit_event(started);
+            if self.config.task_progress {
+                let queued = TaskWorkerProgress {
+                    row_id,
+                    progress: TaskProgress::Queued,
+                };
+                if let Err(err) = self.send_task_progress(queued).await {
+                    log::error!("Error sending {}: {err:?}", TASK_PROGRESS_TOPIC.cyan());
+                }
+            }
         };
 
         Ok(())
     }
 
-    pub(crate) async fn send_task_output(&mut self, task_response: TaskWorkerOutput) -> Result<()> {
+    /// Sends the progress of a pending task to the RPC.
+    ///
+    /// The progress is ignored if the task is no longer pending, i.e. it has been responded to already.
+    pub(crate) async fn send_task_progress(
+        &mut self,
+        task_progress: TaskWorkerProgress,
+    ) -> Result<()> {
+        let Some(task_metadata) = self
+            .pending_tasks_single
+            .get(&task_progress.row_id)
+            .or_else(|| self.pending_tasks_batch.get(&task_progress.row_id))
+        else {
+            return Ok(());
+        };
+
+        let progress_request = TaskProgressRequest {
+            schema_version: SchemaVersion::CURRENT,
+            progress_id: Uuid::now_v7(),
+            file_id: task_metadata.file_id,
+            row_id: task_progress.row_id,
+            task_id: task_metadata.task_id.clone(),
+            progress: task_progress.progress,
+        };
+        let peer_id = self.dria_rpc.peer_id;
+        let request_id = ProgressRequester::send_progress(self, peer_id, progress_request).await?;
+        log::debug!(
+            "Sending {} request ({request_id}) to {peer_id}",
+            TASK_PROGRESS_TOPIC.cyan()
+        );
+
+        Ok(())
+    }
+
+    /// Responds to the given completed tasks, e.g. of a batch, whose responses are signed at once.
+    pub(crate) async fn send_task_outputs(
+        &mut self,
+        task_responses: Vec<TaskWorkerOutput>,
+    ) -> Result<()> {
+        let mut outputs = Vec::with_capacity(task_responses.len());
+        for task_response in task_responses {
+            match self.complete_task(&task_response) {
+                Some(task_metadata) => outputs.push((task_response, task_metadata)),
+                None => {
+                    // totally unexpected case, wont happen at all
+                    log::error!("Metadata not found for {}", task_response.row_id);
+                }
+            }
+        }
+
+        TaskResponder::send_task_outputs(self, outputs).await
+    }
+
+    /// Removes the completed task from the pending tasks & records it, returning its metadata.
+    fn complete_task(&mut self, task_response: &TaskWorkerOutput) -> Option<TaskWorkerMetadata> {
         // remove the task from pending tasks, and get its metadata
         let task_metadata = match task_response.batchable {
             true => {
@@ -168,18 +379,46 @@ impl DriaComputeNode {
             }
         };
 
-        // respond to the response channel with the result
-        match task_metadata {
-            Some(task_metadata) => {
-                TaskResponder::send_task_output(self, task_response, task_metadata).await?;
+        let task_metadata = task_metadata?;
+        *self
+            .completed_tasks_per_model
+            .entry(task_metadata.model)
+            .or_default() += 1;
+        self.metrics
+            .record_task_output(task_metadata.model, task_response);
+        self.journal_completed(task_response.row_id);
+        let (row_id, task_id, model) = (
+            task_response.row_id,
+            task_metadata.task_id.clone(),
+            task_metadata.model.to_string(),
+        );
+        match &task_response.result {
+            Ok(_) => {
+                self.lifetime.record_task(task_response.stats.token_count);
+                self.activity.record_task(
+                    task_metadata.model,
+                    true,
+                    task_response.stats.token_count,
+                );
+                self.emit_event(DriaNodeEvent::TaskCompleted {
+                    row_id,
+                    task_id,
+                    model,
+                });
             }
-            None => {
-                // totally unexpected case, wont happen at all
-                eyre::bail!("Metadata not found for {}", task_response.row_id);
+            Err(err) => {
+                self.activity.record_task(task_metadata.model, false, 0);
+                self.emit_event(DriaNodeEvent::TaskFailed {
+                    row_id,
+                    task_id,
+                    model,
+                    error: err.to_string(),
+                });
+                self.handle_task_error();
             }
-        };
+        }
 
-        Ok(())
+        Some(task_metadata)
     }
 
     /// Sends a heartbeat request to the configured RPC node.
@@ -199,7 +438,16 @@ impl DriaComputeNode {
     #[inline]
     pub(crate) async fn send_specs(&mut self) -> Result<()> {
         let peer_id = self.dria_rpc.peer_id;
-        let specs = self.spec_collector.collect().await;
+        let model_states = self.config.executors.get_model_states().await;
+        let mut specs = self
+            .spec_collector
+            .collect(model_states, self.lifetime.current())
+            .await;
+        specs.key_rotation = self.config.active_key_rotation(chrono::Utc::now()).cloned();
+        #[cfg(feature = "bls")]
+        if let Some(secret_key) = &self.config.bls_secret_key {
+            specs.bls_public_key = Some(hex::encode(secret_key.sk_to_pk().compress()));
+        }
         let request_id = SpecRequester::send_specs(self, peer_id, specs).await?;
         log::info!(
             "Sending {} request ({request_id}) to {peer_id}",
@@ -32,21 +32,52 @@ impl DriaRPC {
 
     /// Creates a new RPC target for the given network type and version.
     pub async fn new_for_network(network: DriaNetwork, version: &SemanticVersion) -> Result<Self> {
-        let addr = get_rpc_for_network(&network, version).await?;
-        Self::new(addr, network)
+        Self::candidates_for_network(network, version, None)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_eyre("no RPCs were returned by discovery API")
+    }
+
+    /// Returns the RPC targets for the given network type and version in a random order,
+    /// so that the first ones can be used as the primary & standby RPCs.
+    ///
+    /// RPCs with invalid addresses are skipped. The discovery API of the network is used,
+    /// unless another one is given by its base URL.
+    pub async fn candidates_for_network(
+        network: DriaNetwork,
+        version: &SemanticVersion,
+        discovery_url: Option<&str>,
+    ) -> Result<Vec<Self>> {
+        let url = match discovery_url {
+            Some(base_url) => format!(
+                "{}/{}",
+                base_url.trim_end_matches('/'),
+                version.as_major_minor()
+            ),
+            None => network.discovery_url(version),
+        };
+        let addrs = get_rpcs_for_network(&url).await?;
+        Ok(addrs
+            .into_iter()
+            .filter_map(|addr| match Self::new(addr, network) {
+                Ok(rpc) => Some(rpc),
+                Err(err) => {
+                    log::warn!("Skipping RPC: {err:?}");
+                    None
+                }
+            })
+            .collect())
     }
 }
 
-/// Calls the DKN API to get an RPC address for the given network type.
+/// Calls the discovery API at the given URL to get the RPC addresses, in a random order.
 ///
 /// The peer id is expected to be within the multi-address.
-async fn get_rpc_for_network(
-    network: &DriaNetwork,
-    version: &SemanticVersion,
-) -> Result<Multiaddr> {
+async fn get_rpcs_for_network(url: &str) -> Result<Vec<Multiaddr>> {
     const MIN_MARGIN: usize = 150;
 
-    let response = reqwest::get(network.discovery_url(version)).await?;
+    let response = reqwest::get(url).await?;
     let rpcs_and_peer_counts = response
         .json::<Vec<(Multiaddr, usize)>>()
         .await
@@ -65,21 +96,18 @@ async fn get_rpc_for_network(
         .unwrap(); // safe to unwrap because we checked for empty earlier
 
     // choose the RPCs that have peers in range `[min_peer_count, min_peer_count + MIN_MARGIN]`
-    let rpcs_and_peer_counts: Vec<(Multiaddr, usize)> = rpcs_and_peer_counts
+    let mut rpcs: Vec<Multiaddr> = rpcs_and_peer_counts
         .into_iter()
         .filter(|(_, peer_count)| {
             (min_peer_count..=min_peer_count + MIN_MARGIN).contains(peer_count)
         })
+        .map(|(addr, _)| addr)
         .collect();
 
-    // pick a random RPC from the filtered list
-    let chosen_rpc = rpcs_and_peer_counts
-        .choose(&mut rand::thread_rng())
-        .cloned()
-        .map(|(addr, _)| addr)
-        .unwrap(); // safe to unwrap because we checked for empty earlier
+    // shuffle them, so that a random RPC is picked first
+    rpcs.shuffle(&mut rand::thread_rng());
 
-    Ok(chosen_rpc)
+    Ok(rpcs)
 }
 
 #[cfg(test)]
@@ -105,4 +133,43 @@ mod tests {
         assert_eq!(result[0].1, 1);
         assert_eq!(result[1].1, 4);
     }
+
+    #[tokio::test]
+    async fn test_custom_discovery_url() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 1024];
+            let len = stream.read(&mut request).await.unwrap();
+
+            let body = r#"[["/ip4/12.34.56.78/tcp/4001/p2p/16Uiu2HAmG7qrpSh8kenjuYqyrwxgEVdzqRV4wM1hHAZRq4j25VBC", 1]]"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request[..len]).to_string()
+        });
+
+        let version = SemanticVersion {
+            major: 1,
+            minor: 2,
+            patch: 3,
+        };
+        let rpcs = DriaRPC::candidates_for_network(
+            DriaNetwork::Testnet,
+            &version,
+            Some(&format!("http://{addr}/available-nodes/")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(rpcs.len(), 1);
+        assert_eq!(rpcs[0].network, DriaNetwork::Testnet);
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /available-nodes/1.2 "));
+    }
 }
@@ -0,0 +1,167 @@
+use dkn_p2p::libp2p::PeerId;
+use dkn_utils::crypto::session::{peer_id_to_public_key, SessionKey};
+use dkn_utils::payloads::SessionOffer;
+use libsecp256k1::{PublicKey, SecretKey};
+use uuid::Uuid;
+
+/// Lifetime of a session, after which a new one is offered even if the RPC stays the same.
+pub(crate) const SESSION_LIFETIME: chrono::Duration = chrono::Duration::hours(1);
+
+#[derive(Debug)]
+struct RpcSession {
+    rpc: PeerId,
+    key: SessionKey,
+    offer: SessionOffer,
+    started_at: chrono::DateTime<chrono::Utc>,
+    /// Whether the RPC has sent a request within this session, i.e. it has derived the key.
+    adopted: bool,
+}
+
+/// Sessions with the RPC that the payloads are encrypted within, see [`dkn_utils::crypto::session`].
+///
+/// A new session is started with a new ephemeral key whenever the RPC changes or the current session
+/// is past its lifetime. The previous session is kept as well, so that the requests that are in flight
+/// during the rekeying can still be decrypted.
+#[derive(Debug)]
+pub(crate) struct RpcSessions {
+    current: Option<RpcSession>,
+    previous: Option<RpcSession>,
+    lifetime: chrono::Duration,
+}
+
+impl RpcSessions {
+    pub(crate) fn new(lifetime: chrono::Duration) -> Self {
+        Self {
+            current: None,
+            previous: None,
+            lifetime,
+        }
+    }
+
+    /// Returns the session offer for the given RPC, starting a new session if needed.
+    ///
+    /// Returns `None` if the peer id of the RPC does not have a secp256k1 key to derive a session with.
+    pub(crate) fn offer(
+        &mut self,
+        rpc: PeerId,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Option<SessionOffer> {
+        let is_valid = self
+            .current
+            .as_ref()
+            .is_some_and(|session| session.rpc == rpc && now - session.started_at < self.lifetime);
+        if !is_valid {
+            let rpc_public_key = peer_id_to_public_key(&rpc)?;
+            let ephemeral_key = SecretKey::random(&mut rand::thread_rng());
+            let session_id = Uuid::now_v7();
+            let key = SessionKey::derive(session_id, &ephemeral_key, &rpc_public_key).ok()?;
+
+            log::debug!("Starting session {session_id} with RPC {rpc}");
+            let session = RpcSession {
+                rpc,
+                key,
+                offer: SessionOffer {
+                    session_id,
+                    public_key: hex::encode(
+                        PublicKey::from_secret_key(&ephemeral_key).serialize_compressed(),
+                    ),
+                },
+                started_at: now,
+                adopted: false,
+            };
+            self.previous = self.current.replace(session);
+        }
+
+        self.current.as_ref().map(|session| session.offer.clone())
+    }
+
+    /// Returns the key of the session with the given id for a request of the RPC, if it is the current
+    /// or the previous one.
+    ///
+    /// The session is marked as adopted by the RPC, as it is used by one of its requests.
+    pub(crate) fn adopt(&mut self, session_id: Uuid) -> Option<&SessionKey> {
+        [self.current.as_mut(), self.previous.as_mut()]
+            .into_iter()
+            .flatten()
+            .find(|session| session.offer.session_id == session_id)
+            .map(|session| {
+                session.adopted = true;
+                &session.key
+            })
+    }
+
+    /// Returns the key of the session with the given id, if it is the current or the previous one.
+    pub(crate) fn get(&self, session_id: Uuid) -> Option<&SessionKey> {
+        [self.current.as_ref(), self.previous.as_ref()]
+            .into_iter()
+            .flatten()
+            .find(|session| session.offer.session_id == session_id)
+            .map(|session| &session.key)
+    }
+
+    /// Returns the key of the session with the given id if it is with the given RPC, and it is the current
+    /// or the previous one.
+    pub(crate) fn get_with(&self, rpc: PeerId, session_id: Uuid) -> Option<&SessionKey> {
+        [self.current.as_ref(), self.previous.as_ref()]
+            .into_iter()
+            .flatten()
+            .find(|session| session.rpc == rpc && session.offer.session_id == session_id)
+            .map(|session| &session.key)
+    }
+
+    /// Returns the key of the current session if the RPC has adopted it, for the messages that are
+    /// not a response to a request of the RPC.
+    pub(crate) fn adopted(&self) -> Option<&SessionKey> {
+        self.current
+            .as_ref()
+            .filter(|session| session.adopted)
+            .map(|session| &session.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dkn_utils::crypto::public_key_to_peer_id;
+
+    #[test]
+    fn test_rpc_sessions() {
+        let rpc_secret_key = SecretKey::parse(&[1u8; 32]).unwrap();
+        let rpc = public_key_to_peer_id(&PublicKey::from_secret_key(&rpc_secret_key));
+        let now = chrono::Utc::now();
+        let mut sessions = RpcSessions::new(SESSION_LIFETIME);
+
+        // the same session is offered within its lifetime
+        let offer = sessions.offer(rpc, now).unwrap();
+        assert_eq!(sessions.offer(rpc, now).unwrap(), offer);
+        assert!(sessions.adopted().is_none());
+
+        // the RPC derives the same key from the offer
+        let public_key =
+            PublicKey::parse_slice(&hex::decode(&offer.public_key).unwrap(), None).unwrap();
+        let rpc_key = SessionKey::derive(offer.session_id, &rpc_secret_key, &public_key).unwrap();
+        let ciphertext = rpc_key.encrypt(b"prompt").unwrap();
+        assert!(sessions.get(offer.session_id).is_some());
+        assert!(sessions.adopted().is_none());
+        let key = sessions.adopt(offer.session_id).unwrap();
+        assert_eq!(key.decrypt(&ciphertext).unwrap(), b"prompt");
+        assert!(sessions.adopted().is_some());
+
+        // rekeyed after the lifetime, where the previous session is still known
+        let rekeyed = sessions.offer(rpc, now + SESSION_LIFETIME).unwrap();
+        assert_ne!(rekeyed.session_id, offer.session_id);
+        assert!(sessions.adopted().is_none());
+        assert!(sessions.get(offer.session_id).is_some());
+
+        // rekeyed for another RPC, and the oldest session is forgotten
+        let other_rpc = public_key_to_peer_id(&PublicKey::from_secret_key(
+            &SecretKey::parse(&[2u8; 32]).unwrap(),
+        ));
+        let other = sessions.offer(other_rpc, now).unwrap();
+        assert_ne!(other.session_id, rekeyed.session_id);
+        assert!(sessions.get(offer.session_id).is_none());
+        assert!(sessions.get(rekeyed.session_id).is_some());
+        assert!(sessions.get_with(rpc, rekeyed.session_id).is_some());
+        assert!(sessions.get_with(other_rpc, rekeyed.session_id).is_none());
+    }
+}
@@ -0,0 +1,171 @@
+use chrono::{DateTime, Utc};
+use dkn_executor::Model;
+use dkn_p2p::libp2p::Multiaddr;
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::{node::ActivityAggregator, utils::write_atomic, DriaComputeNode};
+
+/// Maximum age of a state snapshot to be restored, an older one starts a new session.
+pub(crate) const STATE_MAX_AGE: chrono::Duration = chrono::Duration::hours(1);
+
+/// Snapshot of the node state, persisted to disk so that a restarted node continues its session
+/// instead of resetting its diagnostics & accounting.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NodeState {
+    /// The time that the snapshot was taken at.
+    pub saved_at: DateTime<Utc>,
+    pub completed_tasks_single: usize,
+    pub completed_tasks_batch: usize,
+    /// Completed tasks count per model name, unknown models are ignored when restored.
+    pub completed_tasks_per_model: BTreeMap<String, usize>,
+    /// Points at the start of the session, so that the points earned are not reset by a restart.
+    pub points_baseline: Option<f64>,
+    /// Address of the primary RPC, preferred on startup if it is still available.
+    pub last_rpc: Option<Multiaddr>,
+    /// Row ids of the tasks that were pending, these are lost by a restart.
+    pub pending_task_ids: Vec<uuid::Uuid>,
+    /// Number of acknowledged heartbeats.
+    pub num_heartbeats: u64,
+    /// Activity within the current day, so that the daily summary is not reset by a restart.
+    #[serde(default)]
+    pub activity: Option<ActivityAggregator>,
+}
+
+impl NodeState {
+    /// Loads the snapshot from the given path, returns `None` if it does not exist or can not be parsed.
+    pub(crate) fn load(path: &Path) -> Option<Self> {
+        if !path.exists() {
+            return None;
+        }
+
+        std::fs::read_to_string(path)
+            .map_err(eyre::Report::from)
+            .and_then(|content| serde_json::from_str(&content).map_err(Into::into))
+            .inspect_err(|err| log::warn!("Could not read node state {}: {err}", path.display()))
+            .ok()
+    }
+
+    /// Writes the snapshot to the given path.
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        let content =
+            serde_json::to_string_pretty(self).wrap_err("could not serialize node state")?;
+        write_atomic(path, content)
+    }
+}
+
+impl DriaComputeNode {
+    /// Returns a snapshot of the current node state.
+    pub(crate) fn snapshot_state(&self) -> NodeState {
+        NodeState {
+            saved_at: Utc::now(),
+            completed_tasks_single: self.completed_tasks_single,
+            completed_tasks_batch: self.completed_tasks_batch,
+            completed_tasks_per_model: self
+                .completed_tasks_per_model
+                .iter()
+                .map(|(model, count)| (model.to_string(), *count))
+                .collect(),
+            points_baseline: Some(self.points_client.initial),
+            last_rpc: Some(self.dria_rpc.addr.clone()),
+            pending_task_ids: self
+                .pending_tasks_single
+                .keys()
+                .chain(self.pending_tasks_batch.keys())
+                .copied()
+                .collect(),
+            num_heartbeats: self.num_heartbeats,
+            activity: Some(self.activity.clone()),
+        }
+    }
+
+    /// Restores the counters & the points baseline from the given snapshot.
+    pub(crate) fn restore_state(&mut self, state: NodeState) {
+        log::info!(
+            "Restoring node state from {} ({} single & {} batch tasks completed).",
+            state.saved_at,
+            state.completed_tasks_single,
+            state.completed_tasks_batch
+        );
+        if !state.pending_task_ids.is_empty() {
+            log::warn!(
+                "{} tasks were pending when the node stopped, they are lost: {:?}",
+                state.pending_task_ids.len(),
+                state.pending_task_ids
+            );
+        }
+
+        self.completed_tasks_single = state.completed_tasks_single;
+        self.completed_tasks_batch = state.completed_tasks_batch;
+        self.completed_tasks_per_model = state
+            .completed_tasks_per_model
+            .into_iter()
+            .filter_map(|(model, count)| Model::try_from(model).ok().map(|model| (model, count)))
+            .collect();
+        if let Some(points_baseline) = state.points_baseline {
+            self.points_client.restore(points_baseline);
+        }
+        self.num_heartbeats = state.num_heartbeats;
+        if let Some(activity) = state.activity {
+            self.activity = activity;
+        }
+    }
+
+    /// Writes the state snapshot to the state path, if any.
+    pub(crate) fn save_state(&self) -> Result<()> {
+        let Some(path) = &self.config.state_path else {
+            return Ok(());
+        };
+
+        self.snapshot_state().save(path)
+    }
+}
+
+/// Loads the state snapshot from the given path if any, ignoring it if it is older than the given age.
+pub(crate) fn load_state(path: Option<&PathBuf>, max_age: chrono::Duration) -> Option<NodeState> {
+    let state = NodeState::load(path?)?;
+    if Utc::now() - state.saved_at > max_age {
+        log::info!(
+            "Ignoring node state from {}, a new session is started.",
+            state.saved_at
+        );
+        return None;
+    }
+
+    Some(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_state() {
+        let path = std::env::temp_dir().join(format!("dkn-state-{}.json", uuid::Uuid::now_v7()));
+        assert_eq!(load_state(Some(&path), chrono::Duration::hours(1)), None);
+
+        let state = NodeState {
+            saved_at: Utc::now(),
+            completed_tasks_single: 3,
+            completed_tasks_per_model: BTreeMap::from([("gpt-4o".to_string(), 3)]),
+            points_baseline: Some(12.5),
+            last_rpc: Some("/ip4/12.34.56.78/tcp/4001".parse().unwrap()),
+            pending_task_ids: vec![uuid::Uuid::now_v7()],
+            num_heartbeats: 42,
+            ..Default::default()
+        };
+        state.save(&path).unwrap();
+        assert_eq!(
+            load_state(Some(&path), chrono::Duration::hours(1)),
+            Some(state)
+        );
+
+        // a stale state starts a new session
+        assert_eq!(load_state(Some(&path), chrono::Duration::zero()), None);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::Utc;
+use dkn_utils::payloads::LifetimeStats;
+use eyre::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::utils::write_atomic;
+
+/// Keeps the statistics of the node across its restarts, persisted to disk if configured.
+///
+/// Unlike the state snapshot, these are never reset, so that the long-term contribution of a node can be verified.
+#[derive(Debug)]
+pub(crate) struct LifetimeStatsStore {
+    path: Option<PathBuf>,
+    stats: LifetimeStats,
+    /// Uptime of the previous sessions, the current session is added to it.
+    uptime_before: u64,
+    started_at: Instant,
+}
+
+impl LifetimeStatsStore {
+    /// Loads the statistics from the given path if any, and starts a new session.
+    pub(crate) fn load(path: Option<PathBuf>) -> Self {
+        let mut stats = path
+            .as_deref()
+            .and_then(read_stats)
+            .unwrap_or_else(|| LifetimeStats {
+                since: Utc::now(),
+                ..Default::default()
+            });
+        stats.sessions += 1;
+
+        Self {
+            path,
+            uptime_before: stats.uptime_secs,
+            stats,
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Records a completed task, along with the tokens it has generated.
+    pub(crate) fn record_task(&mut self, tokens: usize) {
+        self.stats.tasks += 1;
+        self.stats.tokens += tokens as u64;
+    }
+
+    /// Returns the statistics, including the uptime of the current session.
+    pub(crate) fn current(&self) -> LifetimeStats {
+        LifetimeStats {
+            uptime_secs: self.uptime_before + self.started_at.elapsed().as_secs(),
+            ..self.stats.clone()
+        }
+    }
+
+    /// Writes the statistics to the path, if any.
+    pub(crate) fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let content = serde_json::to_string_pretty(&self.current())
+            .wrap_err("could not serialize lifetime stats")?;
+        write_atomic(path, content)
+    }
+}
+
+/// Reads the statistics from the given path, returns `None` if it does not exist or can not be parsed.
+pub(crate) fn read_stats(path: &Path) -> Option<LifetimeStats> {
+    if !path.exists() {
+        return None;
+    }
+
+    std::fs::read_to_string(path)
+        .map_err(eyre::Report::from)
+        .and_then(|content| serde_json::from_str(&content).map_err(Into::into))
+        .inspect_err(|err| log::warn!("Could not read lifetime stats {}: {err}", path.display()))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifetime_stats() {
+        let path = std::env::temp_dir().join(format!("dkn-stats-{}.json", uuid::Uuid::now_v7()));
+
+        let mut store = LifetimeStatsStore::load(Some(path.clone()));
+        store.record_task(120);
+        store.record_task(30);
+        store.save().unwrap();
+        let since = store.current().since;
+
+        // a restart continues the statistics in a new session
+        let mut store = LifetimeStatsStore::load(Some(path.clone()));
+        store.record_task(50);
+        let stats = store.current();
+        assert_eq!(stats.since, since);
+        assert_eq!(stats.sessions, 2);
+        assert_eq!(stats.tasks, 3);
+        assert_eq!(stats.tokens, 200);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use chrono::{DateTime, Utc};
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{
+    utils::{write_atomic, HealthChecks},
+    DriaComputeNode, DRIA_COMPUTE_NODE_VERSION,
+};
+
+/// Compact status of the node, written to the status file for external monitors such as shell scripts,
+/// so that the node health can be checked without an HTTP server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NodeStatus {
+    /// The time that the status was written at, a stale one means that the node is stuck or killed.
+    pub updated_at: DateTime<Utc>,
+    pub version: String,
+    /// Whether the node is running, this is `false` for the last status written at shutdown.
+    pub running: bool,
+    /// Whether the node is ready to receive tasks, as per its health checks.
+    pub ready: bool,
+    /// Address of the primary RPC.
+    pub rpc: String,
+    pub rpc_connected: bool,
+    pub last_heartbeat_at: DateTime<Utc>,
+    /// Seconds since the last acknowledged heartbeat.
+    pub heartbeat_age_secs: i64,
+    /// Whether the task intake is paused.
+    pub paused: bool,
+    /// Pending tasks, as `[single, batch]`.
+    pub pending_tasks: [usize; 2],
+    /// Completed tasks, as `[single, batch]`.
+    pub completed_tasks: [usize; 2],
+    pub last_error: Option<NodeError>,
+}
+
+/// The latest error of the node, such as a failed task or a lost RPC connection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NodeError {
+    pub at: DateTime<Utc>,
+    pub message: String,
+}
+
+impl NodeStatus {
+    /// Writes the status to the given path as a single line of JSON.
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        // written atomically, so that a monitor never reads a partial file
+        let content = serde_json::to_string(self).wrap_err("could not serialize node status")?;
+        write_atomic(path, content + "\n")
+    }
+}
+
+impl DriaComputeNode {
+    /// Records the latest error, to be reported in the status file.
+    pub(crate) fn record_error(&mut self, message: impl Into<String>) {
+        self.last_error = Some(NodeError {
+            at: Utc::now(),
+            message: message.into(),
+        });
+    }
+
+    /// Returns the current status of the node, with the given health checks.
+    pub(crate) fn status(&self, checks: HealthChecks, running: bool) -> NodeStatus {
+        let now = Utc::now();
+        NodeStatus {
+            updated_at: now,
+            version: DRIA_COMPUTE_NODE_VERSION.to_string(),
+            running,
+            ready: running && checks.is_ready(),
+            rpc: self.dria_rpc.addr.to_string(),
+            rpc_connected: checks.rpc_connected,
+            last_heartbeat_at: self.last_heartbeat_at,
+            heartbeat_age_secs: (now - self.last_heartbeat_at).num_seconds(),
+            paused: self.is_paused,
+            pending_tasks: [
+                self.pending_tasks_single.len(),
+                self.pending_tasks_batch.len(),
+            ],
+            completed_tasks: [self.completed_tasks_single, self.completed_tasks_batch],
+            last_error: self.last_error.clone(),
+        }
+    }
+
+    /// Writes the status to the status file, if any.
+    pub(crate) fn write_status(&self, checks: HealthChecks, running: bool) {
+        let Some(path) = &self.config.status_path else {
+            return;
+        };
+
+        if let Err(err) = self.status(checks, running).save(path) {
+            log::warn!("Could not write the status file: {err:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_status() {
+        let path = std::env::temp_dir().join(format!("dkn-status-{}.json", uuid::Uuid::now_v7()));
+        let status = NodeStatus {
+            updated_at: Utc::now(),
+            version: DRIA_COMPUTE_NODE_VERSION.to_string(),
+            running: true,
+            ready: false,
+            rpc: "/ip4/12.34.56.78/tcp/4001".to_string(),
+            rpc_connected: true,
+            last_heartbeat_at: Utc::now(),
+            heartbeat_age_secs: 12,
+            paused: false,
+            pending_tasks: [1, 2],
+            completed_tasks: [3, 4],
+            last_error: Some(NodeError {
+                at: Utc::now(),
+                message: "task failed".to_string(),
+            }),
+        };
+        status.save(&path).unwrap();
+
+        // the status is a single line, so that it is easy to use from shell scripts
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 1);
+        let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(json["heartbeatAgeSecs"], 12);
+        assert_eq!(json["lastError"]["message"], "task failed");
+        assert_eq!(serde_json::from_value::<NodeStatus>(json).unwrap(), status);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,213 @@
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use dkn_executor::Model;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{node::DriaNodeEvent, DriaComputeNode};
+
+/// Duration of the period that the activity is summarized for.
+const SUMMARY_PERIOD: chrono::Duration = chrono::Duration::days(1);
+
+/// Tasks & tokens of a model within a summary period.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelActivity {
+    pub completed: usize,
+    pub failed: usize,
+    pub tokens: u64,
+}
+
+/// Summary of the activity of the node over a day, logged & sent to the webhook.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivitySummary {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    /// Activity of each model that has served a task within the period.
+    pub models: BTreeMap<String, ModelActivity>,
+    pub completed: usize,
+    pub failed: usize,
+    /// Ratio of the completed tasks to all tasks, if there were any.
+    pub success_ratio: Option<f64>,
+    pub tokens: u64,
+    /// Estimated cost of the generated tokens in USD, if the token prices are given by `DKN_TOKEN_PRICES`.
+    pub estimated_cost: Option<f64>,
+    /// Points earned within the period, if they could be fetched.
+    pub points_earned: Option<f64>,
+}
+
+/// Aggregates the activity of the node within the current period, persisted within the state snapshot
+/// so that a restart does not reset the day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ActivityAggregator {
+    /// Start of the current period.
+    pub since: DateTime<Utc>,
+    pub models: BTreeMap<String, ModelActivity>,
+    /// Points at the start of the period, if they could be fetched.
+    pub points_start: Option<f64>,
+    /// The latest points, if they could be fetched.
+    pub points_latest: Option<f64>,
+}
+
+impl ActivityAggregator {
+    pub(crate) fn new(since: DateTime<Utc>) -> Self {
+        Self {
+            since,
+            models: BTreeMap::new(),
+            points_start: None,
+            points_latest: None,
+        }
+    }
+
+    /// Records a completed or failed task of the given model.
+    pub(crate) fn record_task(&mut self, model: Model, success: bool, tokens: usize) {
+        let activity = self.models.entry(model.to_string()).or_default();
+        if success {
+            activity.completed += 1;
+            activity.tokens += tokens as u64;
+        } else {
+            activity.failed += 1;
+        }
+    }
+
+    /// Records the latest points, the first ones of a period are its start.
+    pub(crate) fn record_points(&mut self, score: f64) {
+        self.points_start.get_or_insert(score);
+        self.points_latest = Some(score);
+    }
+
+    /// Returns the summary of the period if it is over at the given time, and starts a new one.
+    ///
+    /// The cost is estimated with the given prices in USD per million tokens of each model.
+    pub(crate) fn take_summary(
+        &mut self,
+        now: DateTime<Utc>,
+        token_prices: &HashMap<Model, f64>,
+    ) -> Option<ActivitySummary> {
+        if now - self.since < SUMMARY_PERIOD {
+            return None;
+        }
+
+        let completed = self.models.values().map(|a| a.completed).sum::<usize>();
+        let failed = self.models.values().map(|a| a.failed).sum::<usize>();
+        let estimated_cost = (!token_prices.is_empty()).then(|| {
+            self.models
+                .iter()
+                .filter_map(|(model, activity)| {
+                    let price = token_prices.get(&Model::try_from(model.as_str()).ok()?)?;
+                    Some(activity.tokens as f64 * price / 1_000_000.0)
+                })
+                .sum()
+        });
+        let summary = ActivitySummary {
+            since: self.since,
+            until: now,
+            completed,
+            failed,
+            success_ratio: (completed + failed != 0)
+                .then(|| completed as f64 / (completed + failed) as f64),
+            tokens: self.models.values().map(|a| a.tokens).sum(),
+            estimated_cost,
+            points_earned: self
+                .points_start
+                .zip(self.points_latest)
+                .map(|(start, latest)| latest - start),
+            models: std::mem::take(&mut self.models),
+        };
+
+        // the next period starts from the latest points
+        *self = Self {
+            points_start: self.points_latest,
+            points_latest: self.points_latest,
+            ..Self::new(now)
+        };
+
+        Some(summary)
+    }
+}
+
+impl DriaComputeNode {
+    /// Logs the activity summary & sends it to the webhook once a day.
+    pub(crate) fn handle_activity_summary(&mut self) {
+        let Some(summary) = self
+            .activity
+            .take_summary(Utc::now(), &self.config.token_prices)
+        else {
+            return;
+        };
+
+        let mut lines = vec![format!(
+            "{} since {}: {} completed, {} failed, {} tokens",
+            "Daily Summary".bold(),
+            summary.since.format("%Y-%m-%d %H:%M UTC"),
+            summary.completed,
+            summary.failed,
+            summary.tokens
+        )];
+        if let Some(ratio) = summary.success_ratio {
+            lines.push(format!("Success Ratio: {:.1}%", ratio * 100.0));
+        }
+        for (model, activity) in &summary.models {
+            lines.push(format!(
+                "{model}: {} completed, {} failed, {} tokens",
+                activity.completed, activity.failed, activity.tokens
+            ));
+        }
+        if let Some(cost) = summary.estimated_cost {
+            lines.push(format!("Estimated Cost: ${cost:.4}"));
+        }
+        if let Some(points) = summary.points_earned {
+            lines.push(format!("{} Earned: {points}", "$DRIA Points".purple()));
+        }
+        log::info!("{}", lines.join("\n  "));
+
+        self.emit_event(DriaNodeEvent::DailySummary(summary));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activity_summary() {
+        let since = Utc::now();
+        let mut activity = ActivityAggregator::new(since);
+        activity.record_points(100.0);
+        activity.record_task(Model::Wasm, true, 300);
+        activity.record_task(Model::Wasm, true, 700);
+        activity.record_task(Model::Wasm, false, 0);
+        activity.record_task(Model::External, true, 1000);
+        activity.record_points(125.0);
+
+        let prices = HashMap::from([(Model::Wasm, 2.0)]);
+        assert_eq!(activity.take_summary(since, &prices), None);
+
+        let until = since + SUMMARY_PERIOD;
+        let summary = activity.take_summary(until, &prices).unwrap();
+        assert_eq!(summary.completed, 3);
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.success_ratio, Some(0.75));
+        assert_eq!(summary.tokens, 2000);
+        // only the models with a price are counted
+        assert_eq!(summary.estimated_cost, Some(0.002));
+        assert_eq!(summary.points_earned, Some(25.0));
+        assert_eq!(summary.models[&Model::Wasm.to_string()].failed, 1);
+        let json = serde_json::to_value(DriaNodeEvent::DailySummary(summary)).unwrap();
+        assert_eq!(json["event"], "daily_summary");
+        assert_eq!(json["pointsEarned"], 25.0);
+
+        // a new period starts from the latest points
+        assert_eq!(activity.since, until);
+        assert!(activity.models.is_empty());
+        activity.record_points(130.0);
+        let summary = activity
+            .take_summary(until + SUMMARY_PERIOD, &HashMap::new())
+            .unwrap();
+        assert_eq!(summary.points_earned, Some(5.0));
+        assert_eq!(summary.success_ratio, None);
+        assert_eq!(summary.estimated_cost, None);
+    }
+}
@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::{
+    node::DriaNodeEvent,
+    utils::{download_update, get_latest_release},
+    DriaComputeNode,
+};
+
+/// Maximum duration to wait for the pending tasks to be completed before restarting for an update.
+const UPDATE_DRAIN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// A verified binary of a newer version, which the node restarts with once its tasks are drained.
+#[derive(Debug)]
+pub(crate) struct StagedUpdate {
+    path: PathBuf,
+    /// The time after which the node restarts even if there are pending tasks.
+    drain_deadline: Instant,
+}
+
+impl DriaComputeNode {
+    /// Checks if a newer version is released, in which case it is logged & an event is emitted once per version.
+    ///
+    /// If auto-update is enabled, the new binary is downloaded & verified, and the task intake is paused
+    /// so that the node can restart with it once the pending tasks are completed.
+    pub(crate) async fn handle_update_check(&mut self) {
+        if self.staged_update.is_some() {
+            return;
+        }
+
+        let release = match get_latest_release().await {
+            Ok(release) => release,
+            Err(err) => {
+                log::debug!("Could not check for updates: {err:?}");
+                return;
+            }
+        };
+        let latest = match release.version() {
+            Ok(latest) => latest,
+            Err(err) => {
+                log::debug!("Could not check for updates: {err:?}");
+                return;
+            }
+        };
+
+        let current = self.config.version;
+        let is_newer = (latest.major, latest.minor, latest.patch)
+            > (current.major, current.minor, current.patch);
+        if !is_newer {
+            return;
+        }
+
+        if self.notified_version != Some(latest) {
+            log::warn!(
+                "A new version v{latest} is available (running v{current}), please update your node!"
+            );
+            self.notified_version = Some(latest);
+            self.emit_event(DriaNodeEvent::UpdateAvailable {
+                current: current.to_string(),
+                latest: latest.to_string(),
+            });
+        }
+
+        let Some(public_key) = self.config.auto_update else {
+            return;
+        };
+        if !latest.is_compatible(&current) {
+            // the network protocol is versioned by major.minor, so staying behind isolates the node
+            log::warn!("v{latest} uses a newer protocol version than v{current}, updating to stay compatible with the network.");
+        }
+
+        log::info!("Downloading v{latest} for auto-update.");
+        match download_update(&release, &public_key).await {
+            Ok(path) => {
+                log::warn!(
+                    "Downloaded & verified v{latest}, pausing new tasks to restart once the pending ones are completed."
+                );
+                self.is_paused = true;
+                self.staged_update = Some(StagedUpdate {
+                    path,
+                    drain_deadline: Instant::now() + UPDATE_DRAIN_TIMEOUT,
+                });
+            }
+            Err(err) => log::error!("Could not download v{latest}: {err:?}"),
+        }
+    }
+
+    /// Returns whether the node should restart for a staged update, i.e. its pending tasks are
+    /// completed or the drain has timed out.
+    pub(crate) fn is_update_drained(&self) -> bool {
+        let Some(update) = &self.staged_update else {
+            return false;
+        };
+
+        let num_pending = self.get_pending_task_count().iter().sum::<usize>();
+        if num_pending == 0 {
+            return true;
+        }
+        if Instant::now() >= update.drain_deadline {
+            log::warn!("{num_pending} tasks are still pending, restarting for the update anyways.");
+            return true;
+        }
+
+        false
+    }
+
+    /// Returns the path of the verified binary to restart with, if an update is staged.
+    pub fn staged_update(&self) -> Option<PathBuf> {
+        self.staged_update
+            .as_ref()
+            .map(|update| update.path.clone())
+    }
+}
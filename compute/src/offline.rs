@@ -0,0 +1,264 @@
+use colored::Colorize;
+use dkn_executor::{DriaExecutorsManager, Model, TaskBody};
+use dkn_utils::payloads::{SchemaVersion, TaskRequestPayload, TaskResponsePayload, TaskStats};
+use eyre::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::reqres::map_prompt_error_to_task_error;
+use crate::workers::task::{TaskWorker, TaskWorkerInput};
+
+/// Returns whether the node should run in offline mode, as per `DKN_OFFLINE`.
+pub fn is_offline_from_env() -> bool {
+    std::env::var("DKN_OFFLINE")
+        .map(|offline| matches!(offline.trim(), "1" | "true"))
+        .unwrap_or(false)
+}
+
+/// Reads the tasks within the given file.
+///
+/// The file can have a single task or an array of tasks, and each task can either be
+/// a task request as sent by the RPC (with `fileId`, `rowId`, `taskId` and `input`),
+/// or just the task body itself, for which the identifiers are generated.
+pub fn read_offline_tasks(path: &Path) -> Result<Vec<TaskRequestPayload<TaskBody>>> {
+    let content = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("could not read {}", path.display()))?;
+    let value: serde_json::Value = serde_json::from_str(&content)
+        .wrap_err_with(|| format!("could not parse {}", path.display()))?;
+
+    let values = match value {
+        serde_json::Value::Array(values) => values,
+        value => vec![value],
+    };
+
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            let task = if value.get("input").is_some() {
+                serde_json::from_value(value)
+            } else {
+                serde_json::from_value(value).map(|input| TaskRequestPayload {
+                    schema_version: SchemaVersion::CURRENT,
+                    file_id: Uuid::nil(),
+                    row_id: Uuid::now_v7(),
+                    task_id: format!("{}#{idx}", path.display()),
+                    input,
+                    result_public_keys: Vec::new(),
+                })
+            };
+            task.wrap_err_with(|| format!("could not parse task #{idx} in {}", path.display()))
+        })
+        .collect()
+}
+
+/// Runs the tasks in the given files through the task pipeline without joining the network,
+/// printing a response payload to `stdout` for each task, as a line of JSON.
+///
+/// This is meant for operators to validate their provider setup, and the executors are
+/// expected to be checked beforehand.
+///
+/// Returns the number of failed tasks.
+pub async fn run_offline(
+    executors: &DriaExecutorsManager,
+    paths: &[PathBuf],
+    cancellation: CancellationToken,
+) -> Result<usize> {
+    let mut tasks = Vec::new();
+    for path in paths {
+        tasks.extend(read_offline_tasks(path)?);
+    }
+    log::info!("Running {} tasks offline.", tasks.len());
+
+    let mut num_failed = 0;
+    for task in tasks {
+        if cancellation.is_cancelled() {
+            log::info!("Offline run cancelled, exiting.");
+            break;
+        }
+
+        let task_id = task.task_id.clone();
+        match execute_offline_task(executors, task).await {
+            Ok(payload) => {
+                if payload.error.is_some() {
+                    num_failed += 1;
+                }
+                println!(
+                    "{}",
+                    serde_json::to_string(&payload).wrap_err("could not serialize payload")?
+                );
+            }
+            Err(err) => {
+                log::error!("Task {task_id} can not be executed: {err:#}");
+                num_failed += 1;
+            }
+        }
+    }
+
+    Ok(num_failed)
+}
+
+/// Executes a single task through the task pipeline, and returns the response payload
+/// that would be sent to the network.
+///
+/// Returns an error if the task can not be executed at all, e.g. its model is not available.
+pub async fn execute_offline_task(
+    executors: &DriaExecutorsManager,
+    task: TaskRequestPayload<TaskBody>,
+) -> Result<TaskResponsePayload> {
+    let model = task.input.model;
+    log::info!(
+        "Executing {} {} with model {}",
+        "task".yellow(),
+        task.task_id,
+        model.to_string().yellow()
+    );
+
+    let input = prepare_task_input(executors, task.row_id, task.input).await?;
+
+    // the worker is used directly, so that the task goes through the same pipeline
+    let (publish_tx, mut publish_rx) = mpsc::channel(1);
+    TaskWorker::execute((input, &publish_tx)).await;
+    let output = publish_rx
+        .recv()
+        .await
+        .ok_or_else(|| eyre::eyre!("task output channel closed"))?;
+
+    let (result, error) = match output.result {
+        Ok(result) => (Some(result), None),
+        Err(err) => {
+            log::error!("Task {} failed: {err:#}", task.task_id);
+            (
+                None,
+                Some(map_prompt_error_to_task_error(model.provider(), err)),
+            )
+        }
+    };
+    let token_count = result.as_ref().map(String::len).unwrap_or_default();
+
+    Ok(TaskResponsePayload {
+        schema_version: SchemaVersion::CURRENT,
+        file_id: task.file_id,
+        row_id: output.row_id,
+        task_id: task.task_id,
+        model: model.to_string(),
+        stats: output
+            .stats
+            .record_published_at()
+            .record_token_count(token_count),
+        result,
+        error,
+        encrypted_result: None,
+        receipt_signature: None,
+    })
+}
+
+/// Runs the `task run <file> [--model <model>]` command, which executes the tasks in the given file
+/// with the real executors and prints their response payloads, to debug tasks before they hit the network.
+///
+/// If a model is given, it overrides the models of the tasks. Service checks are skipped so that a task
+/// can be tried quickly, and the executors are created from the environment as usual.
+pub async fn run_task_command(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let mut model = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--model" | "-m" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| eyre::eyre!("missing model name after {arg}"))?;
+                model = Some(Model::try_from(name.as_str()).map_err(|err| eyre::eyre!(err))?);
+            }
+            arg if path.is_none() => path = Some(PathBuf::from(arg)),
+            arg => eyre::bail!("unexpected argument {arg}"),
+        }
+    }
+    let path = path.ok_or_else(|| eyre::eyre!("usage: task run <file> [--model <model>]"))?;
+
+    let mut tasks = read_offline_tasks(&path)?;
+    if let Some(model) = model {
+        for task in tasks.iter_mut() {
+            task.input.model = model;
+        }
+    }
+
+    let executors =
+        DriaExecutorsManager::new_from_env_for_models(tasks.iter().map(|task| task.input.model))?;
+    for task in tasks {
+        let payload = execute_offline_task(&executors, task).await?;
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&payload).wrap_err("could not serialize payload")?
+        );
+    }
+
+    Ok(())
+}
+
+/// Prepares the worker input for a task, similar to a task request from the network.
+async fn prepare_task_input(
+    executors: &DriaExecutorsManager,
+    row_id: Uuid,
+    task: TaskBody,
+) -> Result<TaskWorkerInput> {
+    let executor = executors.get_executor(&task.model).await?;
+
+    let mut quorum_executors = HashMap::new();
+    if let Some(quorum) = &task.quorum {
+        for model in quorum.required_models() {
+            quorum_executors.insert(*model, executors.get_executor(model).await?);
+        }
+    }
+
+    Ok(TaskWorkerInput {
+        row_id,
+        executor,
+        task,
+        quorum_executors,
+        stats: TaskStats::new().record_received_at(),
+        progress_tx: None,
+        span: tracing::Span::none(),
+        queued: tracing::Span::none(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_offline_tasks() {
+        let path = std::env::temp_dir().join(format!("dkn-offline-{}.json", Uuid::now_v7()));
+        let row_id = Uuid::now_v7();
+        std::fs::write(
+            &path,
+            serde_json::json!([
+                { "model": "gemma3:4b", "messages": [{ "role": "user", "content": "What is 2 + 2?" }] },
+                {
+                    "fileId": Uuid::nil(),
+                    "rowId": row_id,
+                    "taskId": "my-task",
+                    "input": {
+                        "model": "qwen3:8b",
+                        "messages": [{ "role": "user", "content": "What is 3 + 3?" }]
+                    }
+                }
+            ])
+            .to_string(),
+        )
+        .unwrap();
+
+        let tasks = read_offline_tasks(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].input.model, Model::Gemma3_4b);
+        assert!(tasks[0].task_id.ends_with("#0"));
+        assert_eq!(tasks[1].row_id, row_id);
+        assert_eq!(tasks[1].task_id, "my-task");
+        assert_eq!(tasks[1].input.model, Model::Qwen3_8b);
+    }
+}
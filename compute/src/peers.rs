@@ -0,0 +1,49 @@
+use colored::Colorize;
+use dkn_utils::{DriaNetwork, SemanticVersion};
+use eyre::{Context, Result};
+use std::env;
+use std::path::PathBuf;
+
+use crate::{
+    config::parse_discovery_url,
+    node::{DriaRPC, PeerStore},
+    DRIA_COMPUTE_NODE_VERSION,
+};
+
+/// Runs the `peers` command, which prints the RPCs of the network in `DKN_NETWORK`: the ones known
+/// from the peer store at `DKN_PEER_STORE_PATH` healthiest first, and the ones from the discovery API
+/// (or the one at `DKN_DISCOVERY_URL`).
+pub async fn run_peers_command() -> Result<()> {
+    let network = env::var("DKN_NETWORK")
+        .ok()
+        .and_then(|network| DriaNetwork::try_from(network.as_str()).ok())
+        .unwrap_or(DriaNetwork::Mainnet);
+    let version = DRIA_COMPUTE_NODE_VERSION
+        .parse::<SemanticVersion>()
+        .map_err(|err| eyre::eyre!("could not parse version: {err}"))?;
+
+    let peer_store_path = env::var("DKN_PEER_STORE_PATH")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+        .map(|path| PathBuf::from(path.trim()));
+    if peer_store_path.is_some() {
+        let known = PeerStore::load(peer_store_path).candidates(network);
+        println!("{} ({network}):", "Known RPCs".purple());
+        for rpc in &known {
+            println!("  {}", rpc.addr);
+        }
+        if known.is_empty() {
+            println!("  none yet");
+        }
+    }
+
+    println!("{} ({network}):", "Discovered RPCs".purple());
+    for rpc in DriaRPC::candidates_for_network(network, &version, parse_discovery_url().as_deref())
+        .await
+        .wrap_err("could not discover RPCs")?
+    {
+        println!("  {}", rpc.addr);
+    }
+
+    Ok(())
+}
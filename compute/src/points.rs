@@ -0,0 +1,164 @@
+use colored::Colorize;
+use dkn_utils::{crypto::public_key_to_address, DriaNetwork};
+use eyre::{Context, Result};
+use libsecp256k1::{PublicKey, SecretKey};
+use serde::Serialize;
+use std::env;
+use std::path::PathBuf;
+
+use crate::utils::{DriaPointsClient, Keystore, PointsLedger};
+
+/// Number of recent days shown from the local history.
+const HISTORY_DAYS: usize = 7;
+
+/// Points of a node along with its recent history, as printed by the `points` command.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PointsReport {
+    pub address: String,
+    pub network: String,
+    pub score: f64,
+    pub percentile: usize,
+    /// Points earned in the last day & week, if there is a local history.
+    pub earned_last_day: Option<f64>,
+    pub earned_last_week: Option<f64>,
+    /// Points earned on each of the recent days, as `YYYY-MM-DD`, oldest first.
+    pub history: Vec<(String, f64)>,
+}
+
+/// Runs the `points [<address>] [--network <network>] [--ledger <file>] [--json]` command,
+/// which prints the points & percentile of a node without starting it.
+///
+/// The address defaults to the one of `DKN_WALLET_SECRET_KEY`, and the recent history is read from
+/// the ledger that the node keeps at `DKN_POINTS_LEDGER_PATH`, if any.
+pub async fn run_points_command(args: &[String]) -> Result<()> {
+    let mut address = None;
+    let mut network = env::var("DKN_NETWORK")
+        .ok()
+        .and_then(|network| DriaNetwork::try_from(network.as_str()).ok())
+        .unwrap_or(DriaNetwork::Mainnet);
+    let mut ledger_path = env::var("DKN_POINTS_LEDGER_PATH")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+        .map(|path| PathBuf::from(path.trim()));
+    let mut as_json = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--network" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| eyre::eyre!("missing value after {arg}"))?;
+                network = DriaNetwork::try_from(value.as_str())
+                    .map_err(|_| eyre::eyre!("unknown network {value}"))?;
+            }
+            "--ledger" => {
+                ledger_path = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| eyre::eyre!("missing value after {arg}"))?,
+                ))
+            }
+            "--json" => as_json = true,
+            arg if arg.starts_with("--") => eyre::bail!("unexpected argument {arg}"),
+            arg => address = Some(arg.trim_start_matches("0x").to_lowercase()),
+        }
+    }
+    let address = match address {
+        Some(address) => address,
+        None => address_from_env()?,
+    };
+
+    let points = DriaPointsClient::new(&address, &network)?
+        .get_points()
+        .await
+        .wrap_err("could not get points")?;
+
+    let ledger = ledger_path.as_deref().map(PointsLedger::read);
+    let now = chrono::Utc::now();
+    let report = PointsReport {
+        address: format!("0x{address}"),
+        network: network.to_string(),
+        score: points.score,
+        percentile: points.percentile,
+        earned_last_day: ledger
+            .as_ref()
+            .and_then(|ledger| ledger.earned_since(now - chrono::Duration::days(1))),
+        earned_last_week: ledger
+            .as_ref()
+            .and_then(|ledger| ledger.earned_since(now - chrono::Duration::weeks(1))),
+        history: ledger
+            .map(|ledger| {
+                let daily = ledger.daily_earned();
+                daily
+                    .iter()
+                    .skip(daily.len().saturating_sub(HISTORY_DAYS))
+                    .map(|(day, earned)| (day.to_string(), *earned))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    if as_json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).wrap_err("could not serialize points")?
+        );
+    } else {
+        print_report(&report);
+    }
+
+    Ok(())
+}
+
+/// Returns the address of the wallet given by `DKN_KEYSTORE_PATH` or `DKN_WALLET_SECRET_KEY`,
+/// hex encoded without `0x`.
+fn address_from_env() -> Result<String> {
+    // the keystore has the address in plaintext, so that it is not decrypted
+    if let Some(path) = env::var("DKN_KEYSTORE_PATH")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+    {
+        return Keystore::read(path.trim().as_ref())?
+            .address
+            .map(|address| address.trim_start_matches("0x").to_lowercase())
+            .ok_or_else(|| eyre::eyre!("keystore does not have an address"));
+    }
+
+    let secret_key = env::var("DKN_WALLET_SECRET_KEY")
+        .wrap_err("an address must be given, or DKN_WALLET_SECRET_KEY must be set")?;
+    let secret_key = hex::decode(secret_key.trim().trim_start_matches("0x"))
+        .ok()
+        .and_then(|secret_key| SecretKey::parse_slice(&secret_key).ok())
+        .ok_or_else(|| eyre::eyre!("could not parse DKN_WALLET_SECRET_KEY"))?;
+
+    Ok(hex::encode(public_key_to_address(
+        &PublicKey::from_secret_key(&secret_key),
+    )))
+}
+
+/// Prints the report in a human-readable way.
+fn print_report(report: &PointsReport) {
+    println!(
+        "{} of {} ({})",
+        "$DRIA Points".purple(),
+        report.address,
+        report.network
+    );
+    println!("  Total:      {}", report.score.to_string().bold());
+    println!("  Percentile: top {}%", report.percentile);
+
+    if report.history.is_empty() {
+        println!("  No local history, the node records it at DKN_POINTS_LEDGER_PATH if given.");
+        return;
+    }
+    println!(
+        "  Earned:     {} in the last day, {} in the last week",
+        report.earned_last_day.unwrap_or_default(),
+        report.earned_last_week.unwrap_or_default()
+    );
+    println!("  History:");
+    for (day, earned) in &report.history {
+        println!("    {day}  {}", format!("+{earned}").green());
+    }
+}
@@ -1,16 +1,15 @@
 use colored::Colorize;
 use dkn_p2p::libp2p::{request_response::OutboundRequestId, PeerId};
 use dkn_utils::{
-    payloads::{HeartbeatRequest, HeartbeatResponse, HEARTBEAT_TOPIC},
-    DriaMessage,
+    payloads::{HeartbeatRequest, HeartbeatResponse, SchemaVersion, HEARTBEAT_TOPIC},
+    DriaMessage, DriaMessageEncoding,
 };
 use eyre::{eyre, Result};
-use std::time::Duration;
 use uuid::Uuid;
 
 use super::IsResponder;
 
-use crate::DriaComputeNode;
+use crate::{node::DriaNodeEvent, DriaComputeNode};
 
 pub struct HeartbeatRequester;
 
@@ -20,28 +19,57 @@ impl IsResponder for HeartbeatRequester {
 }
 
 impl HeartbeatRequester {
-    /// Any acknowledged heartbeat that is older than this duration is considered dead.
-    pub const HEARTBEAT_DEADLINE: Duration = Duration::from_secs(60);
+    /// Sends a heartbeat, where any acknowledged heartbeat that is past its deadline is considered dead.
+    ///
+    /// The deadline is given by the heartbeat deadline within [`NodeIntervals`](crate::NodeIntervals).
     pub(crate) async fn send_heartbeat(
         node: &mut DriaComputeNode,
         peer_id: PeerId,
     ) -> Result<OutboundRequestId> {
         let uuid = Uuid::now_v7();
-        let deadline = chrono::Utc::now() + Self::HEARTBEAT_DEADLINE;
+        // the deadline is checked by the RPC, so it is compensated for the clock drift
+        let deadline = node.network_now() + node.config.intervals.heartbeat_deadline;
+        let rtt = node.p2p.rtt(peer_id).await.ok().flatten();
 
         let heartbeat_request = HeartbeatRequest {
+            schema_version: SchemaVersion::CURRENT,
             heartbeat_id: uuid,
             deadline,
             pending_batch: node.pending_tasks_batch.len(),
             pending_single: node.pending_tasks_single.len(),
-            batch_size: node.config.batch_size,
+            // no capacity is advertised during quiet hours
+            batch_size: if node.is_quiet() {
+                0
+            } else {
+                node.config.batch_size
+            },
+            rtt_ms: rtt.map(|rtt| rtt.as_millis() as u64),
+            lost_tasks: node.lost_tasks.clone(),
+            key_rotation: node.config.active_key_rotation(chrono::Utc::now()).cloned(),
+            // a new session is offered once the RPC changes or the current one expires
+            session: match node.config.session_encryption {
+                true => node.sessions.offer(peer_id, chrono::Utc::now()),
+                false => None,
+            },
+            encodings: DriaMessageEncoding::ALL
+                .iter()
+                .map(|encoding| encoding.name().to_string())
+                .collect(),
         };
 
-        let heartbeat_message = node.new_message(
-            serde_json::to_vec(&heartbeat_request).expect("should be serializable"),
-            HEARTBEAT_TOPIC,
-        );
-        let request_id = node.p2p.request(peer_id, heartbeat_message).await?;
+        let heartbeat_message = node
+            .new_message(
+                serde_json::to_vec(&heartbeat_request).expect("should be serializable"),
+                HEARTBEAT_TOPIC,
+            )
+            .await?;
+        let request_id = node
+            .p2p
+            .request(
+                peer_id,
+                heartbeat_message.to_bytes(node.request_encoding(peer_id))?,
+            )
+            .await?;
 
         // add it to local heartbeats set
         node.heartbeats_reqs.insert(uuid, deadline);
@@ -65,9 +93,23 @@ impl HeartbeatRequester {
                 // acknowledge heartbeat
                 node.last_heartbeat_at = chrono::Utc::now();
                 node.num_heartbeats += 1;
+                node.emit_event(DriaNodeEvent::HeartbeatAcked {
+                    at: node.last_heartbeat_at,
+                });
+                node.handle_lost_tasks_reported();
+
+                // the RPC is reachable, so the results buffered while disconnected can be delivered
+                node.send_late_results().await;
+
+                // the deadline is set relative to the time the heartbeat was sent
+                let now = node.network_now();
+                let sent_at = deadline - node.config.intervals.heartbeat_deadline;
+                if let Ok(rtt) = (now - sent_at).to_std() {
+                    node.metrics.record_heartbeat_rtt(rtt);
+                }
 
                 // for diagnostics, we can check if the heartbeat was past its deadline as well
-                if chrono::Utc::now() > deadline {
+                if now > deadline {
                     log::warn!(
                         "Acknowledged {} was past its deadline.",
                         HEARTBEAT_TOPIC.blue()
@@ -0,0 +1,102 @@
+use colored::Colorize;
+use dkn_utils::crypto::session::SessionKey;
+use dkn_utils::{
+    payloads::{LateResultResponse, LATE_RESULT_TOPIC},
+    DriaMessage,
+};
+use eyre::{eyre, Result};
+use uuid::Uuid;
+
+use super::IsResponder;
+
+use crate::node::LateResult;
+use crate::DriaComputeNode;
+
+pub struct LateResultRequester;
+
+impl IsResponder for LateResultRequester {
+    type Request = DriaMessage; // LateResultRequest;
+    type Response = LateResultResponse;
+}
+
+impl LateResultRequester {
+    /// Creates the messages of the late results, which are signed at once as there may be many of them
+    /// after a reconnection.
+    ///
+    /// Returns an error if the result of an encrypted request has no session to be encrypted within,
+    /// see [`late_session`].
+    pub(crate) async fn new_late_messages(
+        node: &DriaComputeNode,
+        late_results: &[LateResult],
+    ) -> Result<Vec<DriaMessage>> {
+        let data = late_results
+            .iter()
+            .map(|late_result| {
+                let session = late_session(node, late_result.session_id).ok_or_else(|| {
+                    eyre!(
+                        "no session for the late result of task {}",
+                        late_result.request.result.row_id
+                    )
+                })?;
+                let data =
+                    serde_json::to_vec(&late_result.request).expect("should be serializable");
+                Ok((data, session))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        node.new_session_messages(data, LATE_RESULT_TOPIC).await
+    }
+
+    /// Handles the late result acknowledgement by RPC.
+    ///
+    /// A result that is not accepted is not sent again, as the RPC has decided on it.
+    pub(crate) async fn handle_ack(
+        node: &mut DriaComputeNode,
+        res: LateResultResponse,
+    ) -> Result<()> {
+        let Some(late_request) = node.late_results.ack(res.late_id) else {
+            return Err(eyre!(
+                "Received an unknown {} response with id {}.",
+                LATE_RESULT_TOPIC.purple(),
+                res.late_id
+            ));
+        };
+
+        match res.error {
+            Some(err) => Err(eyre!(
+                "{} for task {} was not accepted: {}",
+                LATE_RESULT_TOPIC.purple(),
+                late_request.result.row_id,
+                err
+            )),
+            None => {
+                log::info!(
+                    "Delivered {} for task {}.",
+                    LATE_RESULT_TOPIC.purple(),
+                    late_request.result.row_id
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the session to encrypt a late result within, for a request that was encrypted within the given session.
+///
+/// The session of the request is kept if it is with the current RPC, otherwise the result is encrypted within the
+/// session that the current RPC has adopted. Returns `None` if there is no such session yet, where the result is
+/// to be held rather than sent in plaintext; the result of a request in plaintext is sent within the adopted
+/// session if any, and in plaintext otherwise.
+pub(crate) fn late_session(
+    node: &DriaComputeNode,
+    session_id: Option<Uuid>,
+) -> Option<Option<&SessionKey>> {
+    match session_id {
+        Some(session_id) => node
+            .sessions
+            .get_with(node.dria_rpc.peer_id, session_id)
+            .or(node.sessions.adopted())
+            .map(Some),
+        None => Some(node.sessions.adopted()),
+    }
+}
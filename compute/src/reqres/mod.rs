@@ -1,5 +1,6 @@
 //! Request-response handlers.
 
+use dkn_utils::DriaMessageEncoding;
 use eyre::Context;
 use serde::{de::DeserializeOwned, Serialize};
 
@@ -7,14 +8,23 @@ mod specs;
 pub use specs::SpecRequester;
 
 mod task;
+pub(crate) use task::map_prompt_error_to_task_error;
 pub use task::TaskResponder;
 
 mod heartbeat;
 pub use heartbeat::HeartbeatRequester;
 
+mod progress;
+pub use progress::ProgressRequester;
+
+mod late;
+pub(crate) use late::late_session;
+pub use late::LateResultRequester;
+
 /// A responder should implement a request & response type, both serializable.
 ///
-/// The `try_parse_request` is automatically implemented using `serde-json` for a byte slice.
+/// The `try_parse_request` is automatically implemented using `serde-json` for a byte slice, and
+/// the `try_parse_response` for a byte slice in either of the [`DriaMessageEncoding`]s.
 pub trait IsResponder {
     type Request: DeserializeOwned;
     type Response: Serialize + DeserializeOwned;
@@ -24,7 +34,7 @@ pub trait IsResponder {
     }
 
     fn try_parse_response(data: &[u8]) -> eyre::Result<Self::Response> {
-        serde_json::from_slice(data).wrap_err("could not parse response")
+        DriaMessageEncoding::decode(data).wrap_err("could not parse response")
     }
 }
 
@@ -0,0 +1,54 @@
+use colored::Colorize;
+use dkn_p2p::libp2p::{request_response::OutboundRequestId, PeerId};
+use dkn_utils::{
+    payloads::{TaskProgressRequest, TaskProgressResponse, TASK_PROGRESS_TOPIC},
+    DriaMessage,
+};
+use eyre::{eyre, Result};
+
+use super::IsResponder;
+
+use crate::DriaComputeNode;
+
+pub struct ProgressRequester;
+
+impl IsResponder for ProgressRequester {
+    type Request = DriaMessage; // TaskProgressRequest;
+    type Response = TaskProgressResponse;
+}
+
+impl ProgressRequester {
+    pub(crate) async fn send_progress(
+        node: &mut DriaComputeNode,
+        peer_id: PeerId,
+        progress_request: TaskProgressRequest,
+    ) -> Result<OutboundRequestId> {
+        let progress_message = node
+            .new_session_message(
+                serde_json::to_vec(&progress_request).expect("should be serializable"),
+                TASK_PROGRESS_TOPIC,
+                node.sessions.adopted(),
+            )
+            .await?;
+
+        let encoding = node.request_encoding(peer_id);
+        node.p2p
+            .request(peer_id, progress_message.to_bytes(encoding)?)
+            .await
+    }
+
+    /// Handles the progress acknowledgement by RPC.
+    ///
+    /// Progress updates are not tracked, so an acknowledgement only matters if it has an error.
+    pub(crate) async fn handle_ack(res: TaskProgressResponse) -> Result<()> {
+        match res.error {
+            Some(err) => Err(eyre!(
+                "{} {} was not acknowledged: {}",
+                TASK_PROGRESS_TOPIC.cyan(),
+                res.progress_id,
+                err
+            )),
+            None => Ok(()),
+        }
+    }
+}
@@ -4,7 +4,7 @@ use super::IsResponder;
 use colored::Colorize;
 use dkn_p2p::libp2p::{request_response::OutboundRequestId, PeerId};
 use dkn_utils::{
-    payloads::{Specs, SpecsRequest, SpecsResponse, SPECS_TOPIC},
+    payloads::{SchemaVersion, Specs, SpecsRequest, SpecsResponse, SPECS_TOPIC},
     DriaMessage,
 };
 use eyre::{eyre, Result};
@@ -25,16 +25,25 @@ impl SpecRequester {
     ) -> Result<OutboundRequestId> {
         let uuid = Uuid::now_v7();
         let specs_request = SpecsRequest {
+            schema_version: SchemaVersion::CURRENT,
             specs_id: uuid,
             specs,
             address: node.config.address.clone(),
         };
 
-        let specs_message = node.new_message(
-            serde_json::to_vec(&specs_request).expect("should be serializable"),
-            SPECS_TOPIC,
-        );
-        let request_id = node.p2p.request(peer_id, specs_message).await?;
+        let specs_message = node
+            .new_message(
+                serde_json::to_vec(&specs_request).expect("should be serializable"),
+                SPECS_TOPIC,
+            )
+            .await?;
+        let request_id = node
+            .p2p
+            .request(
+                peer_id,
+                specs_message.to_bytes(node.request_encoding(peer_id))?,
+            )
+            .await?;
 
         // add it to local specs set
         node.specs_reqs.insert(uuid);
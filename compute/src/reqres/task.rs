@@ -1,11 +1,18 @@
 use colored::Colorize;
 use dkn_executor::{CompletionError, ModelProvider, PromptError, TaskBody};
-use dkn_p2p::libp2p::request_response::ResponseChannel;
+use dkn_p2p::libp2p::request_response::{InboundRequestId, ResponseChannel};
 use dkn_utils::payloads::{
-    TaskError, TaskRequestPayload, TaskResponsePayload, TaskStats, TASK_RESULT_TOPIC,
+    SchemaVersion, TaskError, TaskRequestPayload, TaskResponsePayload, TaskStats, TASK_RESULT_TOPIC,
+};
+use dkn_utils::{
+    crypto::{encrypt_bytes, session::SessionKey},
+    DriaMessage, DriaMessageEncoding,
 };
-use dkn_utils::DriaMessage;
 use eyre::{Context, Result};
+use libsecp256k1::PublicKey;
+use std::collections::HashMap;
+use tracing::Instrument;
+use uuid::Uuid;
 
 use crate::workers::task::*;
 use crate::DriaComputeNode;
@@ -21,14 +28,30 @@ impl TaskResponder {
     pub(crate) async fn parse_task_request(
         node: &mut DriaComputeNode,
         compute_message: &DriaMessage,
+        session_id: Option<Uuid>,
+        encoding: DriaMessageEncoding,
+        request_id: InboundRequestId,
         channel: ResponseChannel<Vec<u8>>,
     ) -> Result<(TaskWorkerInput, TaskWorkerMetadata)> {
         // parse this in two-steps so that if something goes wrong we know the task id
         let task = compute_message
             .parse_payload::<TaskRequestPayload<serde_json::Value>>()
             .wrap_err("could not parse task request payload")?;
-        let task_body = match serde_json::from_value::<TaskBody>(task.input) {
-            Ok(task_body) => task_body,
+        // a task of an incompatible schema could be misread, so it is rejected as a parsing error
+        let parsed = match task.schema_version.is_compatible() {
+            true => Ok(()),
+            false => Err(format!(
+                "unsupported schema version {} (node is at {})",
+                task.schema_version,
+                SchemaVersion::CURRENT
+            )),
+        }
+        .and_then(|_| serde_json::from_value::<TaskBody>(task.input).map_err(|err| err.to_string()))
+        .and_then(|task_body| {
+            parse_public_keys(&task.result_public_keys).map(|keys| (task_body, keys))
+        });
+        let (task_body, result_public_keys) = match parsed {
+            Ok(parsed) => parsed,
             Err(err) => {
                 log::error!(
                     "Task {}/{} failed due to parsing error: {err}",
@@ -38,24 +61,35 @@ impl TaskResponder {
 
                 // prepare error payload
                 let error_payload = TaskResponsePayload {
+                    schema_version: SchemaVersion::CURRENT,
                     result: None,
-                    error: Some(TaskError::ParseError(err.to_string())),
+                    error: Some(TaskError::ParseError(err.clone())),
                     row_id: task.row_id,
                     file_id: task.file_id,
                     task_id: task.task_id,
                     model: "<n/a>".to_string(), // no model available due to parsing error
                     stats: TaskStats::new(),
+                    encrypted_result: None,
+                    receipt_signature: None,
                 };
 
                 let error_payload_str = serde_json::to_string(&error_payload)
                     .wrap_err("could not serialize payload")?;
 
                 // respond through the channel to notify about the parsing error
-                let response = node.new_message(error_payload_str, TASK_RESULT_TOPIC);
-                node.p2p.respond(response.into(), channel).await?;
+                let response = node
+                    .new_session_message(
+                        error_payload_str,
+                        TASK_RESULT_TOPIC,
+                        response_session(node, session_id),
+                    )
+                    .await?;
+                node.p2p
+                    .respond(request_id, response.to_bytes(encoding)?, channel)
+                    .await?;
 
                 // return with error
-                eyre::bail!("could not parse task body: {err}")
+                eyre::bail!("could not parse task request: {err}")
             }
         };
 
@@ -67,33 +101,192 @@ impl TaskResponder {
             task_body.model.to_string().yellow()
         );
 
-        // check if the model is available in this node, if so
-        // it will return an executor that can run this model
-        let executor = node.config.executors.get_executor(&task_body.model).await?;
+        let span = tracing::info_span!(
+            "task",
+            task_id = %task.task_id,
+            row_id = %task.row_id,
+            model = %task_body.model,
+            provider = %task_body.model.provider(),
+        );
+
+        let (executor, quorum_executors) = async {
+            // check if the model is available in this node, if so
+            // it will return an executor that can run this model
+            let executor = node.config.executors.get_executor(&task_body.model).await?;
+
+            // a quorum task requires all of its models to be available as well
+            let mut quorum_executors = HashMap::new();
+            if let Some(quorum) = &task_body.quorum {
+                for model in quorum.required_models() {
+                    let quorum_executor = node.config.executors.get_executor(model).await?;
+                    quorum_executors.insert(*model, quorum_executor);
+                }
+            }
+
+            Ok::<_, eyre::Report>((executor, quorum_executors))
+        }
+        .instrument(tracing::info_span!(parent: &span, "intake"))
+        .await?;
 
         let task_metadata = TaskWorkerMetadata {
             task_id: task.task_id,
             file_id: task.file_id,
             model: task_body.model,
+            result_public_keys,
+            session_id,
+            encoding,
+            request_id,
             channel,
+            span: span.clone(),
         };
         let task_input = TaskWorkerInput {
             executor,
             task: task_body,
+            quorum_executors,
+            progress_tx: node
+                .config
+                .task_progress
+                .then(|| node.task_progress_tx.clone()),
             row_id: task.row_id,
             stats,
+            span,
+            // the queue starts once the task is sent to a worker
+            queued: tracing::Span::none(),
         };
 
         Ok((task_input, task_metadata))
     }
 
-    /// Handles the result of a task.
-    pub(crate) async fn send_task_output(
+    /// Responds with the given error without executing the task, e.g. when the node is overloaded.
+    pub(crate) async fn send_rejection(
         node: &mut DriaComputeNode,
-        task_output: TaskWorkerOutput,
+        task_input: TaskWorkerInput,
         task_metadata: TaskWorkerMetadata,
+        error: TaskError,
+    ) -> Result<()> {
+        let error_payload = TaskResponsePayload {
+            schema_version: SchemaVersion::CURRENT,
+            result: None,
+            error: Some(error),
+            row_id: task_input.row_id,
+            file_id: task_metadata.file_id,
+            task_id: task_metadata.task_id,
+            model: task_metadata.model.to_string(),
+            stats: task_input.stats.record_published_at(),
+            encrypted_result: None,
+            receipt_signature: None,
+        };
+        let error_payload_str =
+            serde_json::to_string(&error_payload).wrap_err("could not serialize payload")?;
+
+        let response = node
+            .new_session_message(
+                error_payload_str,
+                TASK_RESULT_TOPIC,
+                response_session(node, task_metadata.session_id),
+            )
+            .await?;
+        node.p2p
+            .respond(
+                task_metadata.request_id,
+                response.to_bytes(task_metadata.encoding)?,
+                task_metadata.channel,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Handles the results of the tasks that are completed together, e.g. of a batch, whose responses
+    /// are signed at once.
+    ///
+    /// If a response channel is closed, e.g. the RPC connection was lost while the task was executed,
+    /// the result is buffered to be delivered as a late result once reconnected.
+    pub(crate) async fn send_task_outputs(
+        node: &mut DriaComputeNode,
+        outputs: Vec<(TaskWorkerOutput, TaskWorkerMetadata)>,
     ) -> Result<()> {
-        let response = match task_output.result {
+        if outputs.is_empty() {
+            return Ok(());
+        }
+
+        let (payloads, metadatas): (Vec<_>, Vec<_>) = outputs
+            .into_iter()
+            .map(|(task_output, task_metadata)| {
+                let span = tracing::info_span!(
+                    parent: &task_metadata.span,
+                    "response",
+                    success = task_output.result.is_ok()
+                );
+                let payload =
+                    span.in_scope(|| Self::task_payload(node, task_output, &task_metadata));
+                (payload, (task_metadata, span))
+            })
+            .unzip();
+
+        let payload_strs = payloads
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()
+            .wrap_err("could not serialize payload")?;
+        // a remote signer may be unavailable for a while, so the results are kept to be retried later
+        let responses = match node
+            .new_session_messages(
+                payload_strs
+                    .iter()
+                    .zip(&metadatas)
+                    .map(|(payload_str, (task_metadata, _))| {
+                        (
+                            payload_str,
+                            response_session(node, task_metadata.session_id),
+                        )
+                    }),
+                TASK_RESULT_TOPIC,
+            )
+            .await
+        {
+            Ok(responses) => responses,
+            Err(err) => {
+                log::error!(
+                    "Could not sign the results of {} tasks: {err:?}",
+                    payloads.len()
+                );
+                for (payload, (task_metadata, _)) in payloads.into_iter().zip(metadatas) {
+                    node.buffer_late_result(payload, task_metadata.session_id);
+                }
+                return Ok(());
+            }
+        };
+
+        // respond through the channels, with the encoding of each request
+        for ((response, payload), (task_metadata, span)) in
+            responses.into_iter().zip(payloads).zip(metadatas)
+        {
+            if let Err(err) = node
+                .p2p
+                .respond(
+                    task_metadata.request_id,
+                    response.to_bytes(task_metadata.encoding)?,
+                    task_metadata.channel,
+                )
+                .instrument(span)
+                .await
+            {
+                log::debug!("Could not respond to task {}: {err:?}", payload.row_id);
+                node.buffer_late_result(payload, task_metadata.session_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates the response payload of a task from its result, and records it.
+    fn task_payload(
+        node: &mut DriaComputeNode,
+        task_output: TaskWorkerOutput,
+        task_metadata: &TaskWorkerMetadata,
+    ) -> TaskResponsePayload {
+        let payload = match task_output.result {
             Ok(result) => {
                 // prepare signed and encrypted payload
                 log::info!(
@@ -105,22 +298,36 @@ impl TaskResponder {
 
                 // TODO: will get better token count from `TaskWorkerOutput`
                 let token_count = result.len();
-                let payload = TaskResponsePayload {
-                    result: Some(result),
-                    error: None,
+                let (result, encrypted_result, error) =
+                    match task_metadata.result_public_keys.is_empty() {
+                        true => (Some(result), None, None),
+                        // the result is never sent in plaintext if it is to be encrypted
+                        false => match encrypt_bytes(&result, &task_metadata.result_public_keys) {
+                            Ok(encrypted) => (None, Some(encrypted), None),
+                            Err(err) => (
+                                None,
+                                None,
+                                Some(TaskError::Other(format!(
+                                    "could not encrypt the result: {err}"
+                                ))),
+                            ),
+                        },
+                    };
+                TaskResponsePayload {
+                    schema_version: SchemaVersion::CURRENT,
+                    result,
+                    error,
+                    encrypted_result,
+                    receipt_signature: None,
                     file_id: task_metadata.file_id,
-                    task_id: task_metadata.task_id,
+                    task_id: task_metadata.task_id.clone(),
                     row_id: task_output.row_id,
                     model: task_metadata.model.to_string(),
                     stats: task_output
                         .stats
                         .record_published_at()
                         .record_token_count(token_count),
-                };
-                let payload_str =
-                    serde_json::to_string(&payload).wrap_err("could not serialize payload")?;
-
-                node.new_message(payload_str, TASK_RESULT_TOPIC)
+                }
             }
             Err(err) => {
                 // use pretty display string for error logging with causes
@@ -132,7 +339,8 @@ impl TaskResponder {
                 );
 
                 // prepare error payload
-                let error_payload = TaskResponsePayload {
+                TaskResponsePayload {
+                    schema_version: SchemaVersion::CURRENT,
                     result: None,
                     error: Some(map_prompt_error_to_task_error(
                         task_metadata.model.provider(),
@@ -140,31 +348,61 @@ impl TaskResponder {
                     )),
                     row_id: task_output.row_id,
                     file_id: task_metadata.file_id,
-                    task_id: task_metadata.task_id,
+                    task_id: task_metadata.task_id.clone(),
                     model: task_metadata.model.to_string(),
                     stats: task_output
                         .stats
                         .record_published_at()
                         .record_token_count(0),
-                };
-                let error_payload_str = serde_json::to_string(&error_payload)
-                    .wrap_err("could not serialize payload")?;
-
-                node.new_message(error_payload_str, TASK_RESULT_TOPIC)
+                    encrypted_result: None,
+                    receipt_signature: None,
+                }
             }
         };
+        #[cfg(feature = "bls")]
+        let payload = match &node.config.bls_secret_key {
+            Some(secret_key) => {
+                let signature = dkn_utils::crypto::bls::sign_task_receipt(secret_key, &payload);
+                TaskResponsePayload {
+                    receipt_signature: Some(hex::encode(signature.compress())),
+                    ..payload
+                }
+            }
+            None => payload,
+        };
+        node.model_stats.record(&payload);
+        node.record_history(&payload);
 
-        // respond through the channel
-        node.p2p
-            .respond(response.into(), task_metadata.channel)
-            .await?;
-
-        Ok(())
+        payload
     }
 }
 
+/// Returns the session to encrypt the response within, for a request that was encrypted within the given session.
+///
+/// If the session is rekeyed twice while the task is executed, the current one is used as the RPC has adopted it;
+/// a request in plaintext is responded to in plaintext.
+fn response_session(node: &DriaComputeNode, session_id: Option<Uuid>) -> Option<&SessionKey> {
+    session_id.and_then(|id| node.sessions.get(id).or(node.sessions.adopted()))
+}
+
+/// Parses the hex encoded public keys that the result is to be encrypted for.
+fn parse_public_keys(public_keys: &[String]) -> Result<Vec<PublicKey>, String> {
+    public_keys
+        .iter()
+        .map(|public_key| {
+            hex::decode(public_key.trim().trim_start_matches("0x"))
+                .ok()
+                .and_then(|bytes| PublicKey::parse_slice(&bytes, None).ok())
+                .ok_or_else(|| format!("invalid result public key {public_key}"))
+        })
+        .collect()
+}
+
 /// Maps a [`PromptError`] to a [`TaskError`] with respect to the given provider.
-fn map_prompt_error_to_task_error(provider: ModelProvider, err: PromptError) -> TaskError {
+pub(crate) fn map_prompt_error_to_task_error(
+    provider: ModelProvider,
+    err: PromptError,
+) -> TaskError {
     match &err {
         // if the error is a provider error, we can try to parse it
         PromptError::CompletionError(CompletionError::ProviderError(err_inner)) => {
@@ -266,6 +504,10 @@ fn map_prompt_error_to_task_error(provider: ModelProvider, err: PromptError) ->
                             }
                         },
                     ),
+                // wasm & external errors are not JSON, e.g. a module running out of fuel
+                ModelProvider::Wasm | ModelProvider::External => Ok(TaskError::ExecutorError(
+                    format!("{provider} executor error: {err_inner}"),
+                )),
             }
             // if we couldn't parse it, just return a generic prompt error
             .unwrap_or(TaskError::ExecutorError(format!(
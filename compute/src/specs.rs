@@ -0,0 +1,36 @@
+use dkn_executor::{DriaExecutorsManager, Model};
+use eyre::{Context, Result};
+use std::collections::HashMap;
+use std::env;
+
+use crate::{node::read_stats, utils::SpecCollector, DriaComputeNodeConfig};
+
+/// Runs the `specs` command, which prints the specs of this machine along with the models in `DKN_MODELS`
+/// as they are sent to the RPC, without checking the models.
+pub async fn run_specs_command() -> Result<()> {
+    let models = Model::from_csv(env::var("DKN_MODELS").unwrap_or_default());
+    let executors = DriaExecutorsManager::new_from_env_for_models(models.into_iter())?;
+    let config = DriaComputeNodeConfig::new(executors);
+
+    let lifetime = config
+        .stats_path
+        .as_deref()
+        .and_then(read_stats)
+        .unwrap_or_default();
+    let mut spec_collector = SpecCollector::new(
+        config.executors.get_model_names(),
+        HashMap::new(),
+        config.version,
+        config.exec_platform.clone(),
+        config.peer_id,
+    );
+    let specs = spec_collector
+        .collect(config.executors.get_model_states().await, lifetime)
+        .await;
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&specs).wrap_err("could not serialize specs")?
+    );
+    Ok(())
+}
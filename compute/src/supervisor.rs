@@ -0,0 +1,187 @@
+//! Supervisor mode, where one process runs a node for each of several identities, e.g. for operators
+//! running many nodes on one machine.
+//!
+//! The identities are given by a JSON file at `DKN_IDENTITIES_FILE`, each with its own wallet & listen
+//! address, while the models & their providers are checked once and shared by all nodes:
+//!
+//! ```json
+//! [
+//!   { "name": "first", "secret_key": "0x...", "listen_addr": "/ip4/0.0.0.0/tcp/4001" },
+//!   { "name": "second", "secret_key": "0x...", "listen_addr": "/ip4/0.0.0.0/tcp/4002", "health_addr": "127.0.0.1:8082" }
+//! ]
+//! ```
+//!
+//! The health, metrics & admin servers along with the state & peer store files are configured per identity,
+//! as they can not be shared; the ones in the environment are not used.
+
+use eyre::{Context, Result};
+use libsecp256k1::SecretKey;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use crate::{config::parse_addrs, DriaComputeNodeConfig};
+
+/// An identity to run a node with, as given in the identities file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeIdentity {
+    /// Name of the identity, used in the logs.
+    pub name: String,
+    /// Wallet secret key, hex encoded.
+    secret_key: String,
+    /// P2P listen addresses as a comma-separated list, must be distinct for each identity.
+    listen_addr: String,
+    #[serde(default)]
+    state_path: Option<PathBuf>,
+    #[serde(default)]
+    peer_store_path: Option<PathBuf>,
+    #[serde(default)]
+    stats_path: Option<PathBuf>,
+    #[serde(default)]
+    journal_path: Option<PathBuf>,
+    #[serde(default)]
+    status_path: Option<PathBuf>,
+    #[serde(default)]
+    points_ledger_path: Option<PathBuf>,
+    #[serde(default)]
+    history_path: Option<PathBuf>,
+    #[serde(default)]
+    health_addr: Option<SocketAddr>,
+    #[serde(default)]
+    metrics_addr: Option<SocketAddr>,
+    /// Address of the admin API, which requires `admin_token` as well.
+    #[serde(default)]
+    admin_addr: Option<SocketAddr>,
+    #[serde(default)]
+    admin_token: Option<String>,
+}
+
+impl NodeIdentity {
+    /// Returns the configuration of the node for this identity, based on the given shared configuration.
+    pub fn apply(&self, mut config: DriaComputeNodeConfig) -> Result<DriaComputeNodeConfig> {
+        let secret_key = hex::decode(self.secret_key.trim().trim_start_matches("0x"))
+            .ok()
+            .and_then(|secret_key| SecretKey::parse_slice(&secret_key).ok())
+            .ok_or_else(|| eyre::eyre!("could not parse the secret key of {}", self.name))?;
+        config.set_secret_key(secret_key);
+        config.p2p_listen_addrs = parse_addrs(&self.listen_addr)
+            .wrap_err_with(|| format!("could not parse the listen address of {}", self.name))?;
+
+        config.state_path = self.state_path.clone();
+        config.peer_store_path = self.peer_store_path.clone();
+        config.stats_path = self.stats_path.clone();
+        config.journal_path = self.journal_path.clone();
+        config.status_path = self.status_path.clone();
+        config.points_ledger_path = self.points_ledger_path.clone();
+        config.history_path = self.history_path.clone();
+        config.metrics_path = None;
+        config.health_addr = self.health_addr;
+        config.metrics_addr = self.metrics_addr;
+        config.admin_api = match (self.admin_addr, &self.admin_token) {
+            (None, _) => None,
+            (Some(addr), Some(token)) if !token.trim().is_empty() => {
+                Some((addr, token.trim().to_string()))
+            }
+            (Some(_), _) => eyre::bail!(
+                "admin_token of {} must be given for its admin API",
+                self.name
+            ),
+        };
+        // a node restarting for an update would stop the others as well
+        config.auto_update = None;
+
+        Ok(config)
+    }
+}
+
+/// Reads the identities from the given file, and checks that their wallets & addresses are distinct.
+pub fn load_identities(path: &Path) -> Result<Vec<NodeIdentity>> {
+    let content = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("could not read {}", path.display()))?;
+    let identities: Vec<NodeIdentity> = serde_json::from_str(&content)
+        .wrap_err_with(|| format!("could not parse {}", path.display()))?;
+    if identities.is_empty() {
+        eyre::bail!("no identities are given in {}", path.display());
+    }
+
+    let mut seen = HashSet::new();
+    for identity in &identities {
+        for (kind, value) in [
+            ("name", identity.name.clone()),
+            (
+                "secret key",
+                identity
+                    .secret_key
+                    .trim()
+                    .trim_start_matches("0x")
+                    .to_lowercase(),
+            ),
+            ("listen address", identity.listen_addr.trim().to_string()),
+        ] {
+            if !seen.insert((kind, value)) {
+                eyre::bail!("{kind} of {} is used by another identity", identity.name);
+            }
+        }
+        for addr in [
+            identity.health_addr,
+            identity.metrics_addr,
+            identity.admin_addr,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if !seen.insert(("address", addr.to_string())) {
+                eyre::bail!(
+                    "address {addr} of {} is used by another identity",
+                    identity.name
+                );
+            }
+        }
+    }
+
+    Ok(identities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_identities() {
+        let path =
+            std::env::temp_dir().join(format!("dkn-identities-{}.json", uuid::Uuid::now_v7()));
+        let key = |byte: u8| hex::encode([byte; 32]);
+
+        std::fs::write(
+            &path,
+            serde_json::json!([
+                { "name": "first", "secret_key": key(1), "listen_addr": "/ip4/0.0.0.0/tcp/4001" },
+                { "name": "second", "secret_key": format!("0x{}", key(2)), "listen_addr": "/ip4/0.0.0.0/tcp/4002", "health_addr": "127.0.0.1:8082" }
+            ])
+            .to_string(),
+        )
+        .unwrap();
+        let identities = load_identities(&path).unwrap();
+        assert_eq!(identities.len(), 2);
+        assert_eq!(
+            identities[1].health_addr,
+            Some("127.0.0.1:8082".parse().unwrap())
+        );
+
+        // a wallet can not be used twice
+        std::fs::write(
+            &path,
+            serde_json::json!([
+                { "name": "first", "secret_key": key(1), "listen_addr": "/ip4/0.0.0.0/tcp/4001" },
+                { "name": "second", "secret_key": format!("0x{}", key(1)), "listen_addr": "/ip4/0.0.0.0/tcp/4002" }
+            ])
+            .to_string(),
+        )
+        .unwrap();
+        assert!(load_identities(&path).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,299 @@
+//! Terminal UI dashboard for interactive operators, enabled with `--tui`.
+//!
+//! The dashboard polls the node status over the admin commands, and shows the logs that are
+//! written to a [`LogBuffer`] instead of the terminal.
+
+use chrono::Utc;
+use eyre::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+
+use crate::node::{AdminCommand, AdminStatus};
+
+/// Maximum number of log lines kept for the log view.
+const MAX_LOG_LINES: usize = 1000;
+/// Duration between redraws & input checks.
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+/// Duration between node status refreshes.
+const STATUS_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Log lines written by the logger, shown by the dashboard.
+///
+/// While the dashboard is not attached, e.g. during startup & shutdown, logs are written to
+/// `stderr` as well so that they are not lost.
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer {
+    inner: Arc<Mutex<LogBufferInner>>,
+}
+
+#[derive(Debug, Default)]
+struct LogBufferInner {
+    lines: VecDeque<String>,
+    /// The last line, if it is not yet terminated.
+    partial: String,
+    is_attached: bool,
+}
+
+impl LogBuffer {
+    fn lock(&self) -> std::sync::MutexGuard<'_, LogBufferInner> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Returns the number of complete lines.
+    fn len(&self) -> usize {
+        self.lock().lines.len()
+    }
+
+    /// Returns up to `count` lines that end `offset` lines before the last one.
+    fn lines(&self, count: usize, offset: usize) -> Vec<String> {
+        let inner = self.lock();
+        let end = inner.lines.len().saturating_sub(offset);
+        let start = end.saturating_sub(count);
+        inner.lines.range(start..end).cloned().collect()
+    }
+
+    fn set_attached(&self, is_attached: bool) {
+        self.lock().is_attached = is_attached;
+    }
+}
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut inner = self.lock();
+        if !inner.is_attached {
+            io::stderr().write_all(buf)?;
+        }
+
+        inner.partial.push_str(&String::from_utf8_lossy(buf));
+        while let Some(idx) = inner.partial.find('\n') {
+            let line = inner.partial.drain(..=idx).collect::<String>();
+            inner.lines.push_back(line.trim_end().to_string());
+        }
+        while inner.lines.len() > MAX_LOG_LINES {
+            inner.lines.pop_front();
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// State of the dashboard.
+#[derive(Debug, Default)]
+struct TuiState {
+    /// The latest status of the node, `None` if it could not be fetched yet.
+    status: Option<AdminStatus>,
+    /// Number of lines that the log view is scrolled up by, `0` to follow the logs.
+    scroll: usize,
+}
+
+/// Runs the dashboard until the user quits or the cancellation occurs, where quitting
+/// cancels the given token so that the node shuts down as well.
+pub async fn run_tui(
+    commander: mpsc::Sender<AdminCommand>,
+    logs: LogBuffer,
+    cancellation: CancellationToken,
+) -> Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    logs.set_attached(true);
+
+    let result = run(&mut terminal, &commander, &logs, &cancellation).await;
+
+    logs.set_attached(false);
+    ratatui::try_restore()?;
+    result
+}
+
+async fn run(
+    terminal: &mut DefaultTerminal,
+    commander: &mpsc::Sender<AdminCommand>,
+    logs: &LogBuffer,
+    cancellation: &CancellationToken,
+) -> Result<()> {
+    let mut state = TuiState::default();
+    let mut tick_interval = tokio::time::interval(TICK_INTERVAL);
+    let mut status_interval = tokio::time::interval(STATUS_REFRESH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = tick_interval.tick() => {
+                while event::poll(Duration::ZERO)? {
+                    if let Event::Key(key) = event::read()? {
+                        if key.kind != KeyEventKind::Press {
+                            continue;
+                        }
+                        match key.code {
+                            KeyCode::Char('q') | KeyCode::Esc => cancellation.cancel(),
+                            // raw mode swallows the signal, so it is handled here instead
+                            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => cancellation.cancel(),
+                            KeyCode::Up => state.scroll = (state.scroll + 1).min(logs.len()),
+                            KeyCode::Down => state.scroll = state.scroll.saturating_sub(1),
+                            KeyCode::PageUp => state.scroll = (state.scroll + 10).min(logs.len()),
+                            KeyCode::PageDown => state.scroll = state.scroll.saturating_sub(10),
+                            KeyCode::End => state.scroll = 0,
+                            _ => {}
+                        }
+                    }
+                }
+                terminal.draw(|frame| draw(frame, &state, logs))?;
+            }
+            _ = status_interval.tick() => {
+                let (sender, receiver) = oneshot::channel();
+                if commander.send(AdminCommand::Status { sender }).await.is_ok() {
+                    if let Ok(Ok(status)) = tokio::time::timeout(STATUS_REFRESH_INTERVAL, receiver).await {
+                        state.status = Some(status);
+                    }
+                }
+            }
+            _ = cancellation.cancelled() => return Ok(()),
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, state: &TuiState, logs: &LogBuffer) {
+    let [header_area, middle_area, logs_area] = Layout::vertical([
+        Constraint::Length(6),
+        Constraint::Length(10),
+        Constraint::Min(5),
+    ])
+    .areas(frame.area());
+    let [peers_area, models_area] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .areas(middle_area);
+
+    let title = Line::from(" Dria Compute Node ".bold()).centered();
+    let Some(status) = &state.status else {
+        frame.render_widget(
+            Paragraph::new("Waiting for the node...").block(Block::bordered().title(title)),
+            header_area,
+        );
+        draw_logs(frame, state, logs, logs_area);
+        return;
+    };
+
+    // node & RPC status
+    let heartbeat_age = (Utc::now() - status.last_heartbeat_at).num_seconds();
+    let rpc_status = match status.rpc_connected {
+        true => "connected".green(),
+        false => "disconnected".red(),
+    };
+    let intake = match status.paused {
+        true => "paused".yellow(),
+        false => "accepting".green(),
+    };
+    let points = match (status.points, status.points_earned) {
+        (Some(points), Some(earned)) => format!("{points:.2} ({earned:+.2} in this run)"),
+        _ => "unknown".to_string(),
+    };
+    let header = Paragraph::new(vec![
+        Line::from(format!(
+            "v{}  {}  {}",
+            status.version, status.address, status.peer_id
+        )),
+        Line::from(vec![
+            Span::raw(format!("RPC {} ", status.rpc)),
+            rpc_status,
+            Span::raw(format!(", {} standby", status.standby_rpcs.len())),
+        ]),
+        Line::from(vec![
+            Span::raw(format!("Last heartbeat {heartbeat_age}s ago, tasks ")),
+            intake,
+        ]),
+        Line::from(format!("$DRIA Points: {points}")),
+    ])
+    .block(Block::bordered().title(title));
+    frame.render_widget(header, header_area);
+
+    // connected peers, the RPCs are highlighted
+    let peers = List::new(status.connected_peers.iter().map(|peer_id| {
+        let is_rpc = status.rpc.ends_with(peer_id.as_str())
+            || status.standby_rpcs.iter().any(|rpc| rpc.ends_with(peer_id));
+        match is_rpc {
+            true => Line::from(peer_id.as_str().cyan()),
+            false => Line::from(peer_id.as_str()),
+        }
+    }))
+    .block(Block::bordered().title(format!(" Peers ({}) ", status.connected_peers.len())));
+    frame.render_widget(peers, peers_area);
+
+    // tasks per model
+    let models = Table::new(
+        status.models.iter().map(|(model, count)| {
+            Row::new([
+                model.clone(),
+                count.pending.to_string(),
+                count.completed.to_string(),
+            ])
+        }),
+        [
+            Constraint::Fill(1),
+            Constraint::Length(8),
+            Constraint::Length(10),
+        ],
+    )
+    .header(Row::new(["Model", "Pending", "Completed"]).style(Style::new().bold()))
+    .block(Block::bordered().title(format!(
+        " Tasks ({} pending, {} completed) ",
+        status.pending_tasks.iter().sum::<usize>(),
+        status.completed_tasks.iter().sum::<usize>()
+    )));
+    frame.render_widget(models, models_area);
+
+    draw_logs(frame, state, logs, logs_area);
+}
+
+fn draw_logs(frame: &mut Frame, state: &TuiState, logs: &LogBuffer, area: ratatui::layout::Rect) {
+    let height = area.height.saturating_sub(2) as usize;
+    let title = match state.scroll {
+        0 => " Logs (q to quit, ↑/↓ to scroll) ".to_string(),
+        scroll => format!(" Logs ({scroll} lines up, End to follow) "),
+    };
+    let lines = logs
+        .lines(height, state.scroll)
+        .into_iter()
+        .map(Line::from)
+        .collect::<Vec<_>>();
+    frame.render_widget(
+        Paragraph::new(lines)
+            .style(Style::new().fg(Color::Gray))
+            .block(Block::bordered().title(title)),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_buffer() {
+        let mut logs = LogBuffer::default();
+        logs.set_attached(true);
+
+        write!(logs, "first\nsec").unwrap();
+        assert_eq!(logs.len(), 1);
+        write!(logs, "ond\nthird\n").unwrap();
+        assert_eq!(logs.lines(10, 0), vec!["first", "second", "third"]);
+        assert_eq!(logs.lines(1, 1), vec!["second"]);
+        assert!(logs.lines(10, 5).is_empty());
+
+        for i in 0..MAX_LOG_LINES {
+            writeln!(logs, "{i}").unwrap();
+        }
+        assert_eq!(logs.len(), MAX_LOG_LINES);
+        assert_eq!(logs.lines(1, MAX_LOG_LINES - 1), vec!["0"]);
+    }
+}
@@ -0,0 +1,74 @@
+use chrono::{DateTime, Local, TimeZone};
+use cron::Schedule;
+use eyre::{Context, Result};
+use std::str::FromStr;
+
+/// Quiet hours of the node, during which it stays connected but does not advertise capacity
+/// nor accept tasks, e.g. for machines that are shared with daytime workloads.
+///
+/// Given as a cron expression in local time, where every matching moment is considered quiet.
+/// The standard 5-field form `min hour day-of-month month day-of-week` is accepted, as well as the
+/// 6 or 7-field form with seconds (and years).
+///
+/// ## Example
+///
+/// `* 9-17 * * Mon-Fri` is quiet during working hours on weekdays.
+#[derive(Debug, Clone)]
+pub struct QuietHours {
+    schedule: Schedule,
+}
+
+impl FromStr for QuietHours {
+    type Err = eyre::Report;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim();
+
+        // the cron parser expects seconds as well, which is not needed for quiet hours
+        let expression = match value.split_whitespace().count() {
+            5 => format!("* {value}"),
+            _ => value.to_string(),
+        };
+
+        let schedule = Schedule::from_str(&expression)
+            .wrap_err_with(|| format!("could not parse quiet hours {value}"))?;
+
+        Ok(Self { schedule })
+    }
+}
+
+impl QuietHours {
+    /// Returns whether the given time is within quiet hours.
+    pub fn is_quiet_at<Tz: TimeZone>(&self, time: DateTime<Tz>) -> bool {
+        self.schedule.includes(time)
+    }
+
+    /// Returns whether the node is within quiet hours now.
+    #[inline]
+    pub fn is_quiet(&self) -> bool {
+        self.is_quiet_at(Local::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_quiet_hours() {
+        let quiet_hours = QuietHours::from_str("* 9-17 * * Mon-Fri").unwrap();
+
+        // Wednesday
+        let noon = Utc.with_ymd_and_hms(2025, 1, 1, 12, 30, 0).unwrap();
+        assert!(quiet_hours.is_quiet_at(noon));
+        let night = Utc.with_ymd_and_hms(2025, 1, 1, 22, 0, 0).unwrap();
+        assert!(!quiet_hours.is_quiet_at(night));
+
+        // Saturday
+        let weekend_noon = Utc.with_ymd_and_hms(2025, 1, 4, 12, 30, 0).unwrap();
+        assert!(!quiet_hours.is_quiet_at(weekend_noon));
+
+        assert!(QuietHours::from_str("not a cron").is_err());
+    }
+}
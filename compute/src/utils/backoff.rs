@@ -0,0 +1,89 @@
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+/// Exponential backoff with jitter, to space out the retries of a failing operation such as dialing an RPC.
+///
+/// The `n`-th retry waits for `base * 2^(n-1)` capped at `max`, where a random jitter of up to half of
+/// the delay is subtracted so that many nodes do not retry at the same time.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    /// Number of attempts since the last reset.
+    attempts: u32,
+    /// The earliest time for the next attempt, `None` if there were no attempts yet.
+    next_attempt_at: Option<Instant>,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max,
+            attempts: 0,
+            next_attempt_at: None,
+        }
+    }
+
+    /// Number of attempts since the last reset.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns whether an attempt can be made now.
+    pub fn is_ready(&self) -> bool {
+        self.next_attempt_at
+            .is_none_or(|next_attempt_at| Instant::now() >= next_attempt_at)
+    }
+
+    /// Records an attempt, and returns the delay until the next one.
+    pub fn record_attempt(&mut self) -> Duration {
+        self.attempts = self.attempts.saturating_add(1);
+
+        let delay = self.delay(self.attempts);
+        let jitter = rand::thread_rng().gen_range(Duration::ZERO..=delay / 2);
+        let delay = delay - jitter;
+
+        self.next_attempt_at = Some(Instant::now() + delay);
+        delay
+    }
+
+    /// Resets the backoff, e.g. after the operation has succeeded.
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+        self.next_attempt_at = None;
+    }
+
+    /// Returns the delay after the given attempt without jitter.
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base.saturating_mul(factor).min(self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_secs(5), Duration::from_secs(60));
+        assert!(backoff.is_ready());
+        assert_eq!(backoff.delay(1), Duration::from_secs(5));
+        assert_eq!(backoff.delay(3), Duration::from_secs(20));
+        assert_eq!(backoff.delay(5), Duration::from_secs(60));
+        assert_eq!(backoff.delay(100), Duration::from_secs(60));
+
+        for attempt in 1..=10 {
+            let delay = backoff.record_attempt();
+            let max_delay = backoff.delay(attempt);
+            assert!(delay >= max_delay / 2 && delay <= max_delay);
+            assert!(!backoff.is_ready());
+        }
+        assert_eq!(backoff.attempts(), 10);
+
+        backoff.reset();
+        assert!(backoff.is_ready());
+        assert_eq!(backoff.attempts(), 0);
+    }
+}
@@ -0,0 +1,21 @@
+use eyre::{eyre, Result};
+use rsntp::{AsyncSntpClient, Config};
+use std::time::Duration;
+
+/// Timeout of an NTP query.
+const NTP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Queries the given NTP server, and returns the offset of the local clock from it,
+/// i.e. the duration to add to the local time to get the NTP time.
+pub async fn measure_clock_offset(server: &str) -> Result<chrono::Duration> {
+    let client = AsyncSntpClient::with_config(Config::default().timeout(NTP_TIMEOUT));
+    let result = client
+        .synchronize(server)
+        .await
+        .map_err(|err| eyre!("could not query NTP server {server}: {err}"))?;
+
+    result
+        .clock_offset()
+        .into_chrono_duration()
+        .map_err(|err| eyre!("could not convert clock offset: {err}"))
+}
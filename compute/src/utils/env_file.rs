@@ -0,0 +1,153 @@
+use eyre::{Context, Result};
+use std::io::Cursor;
+
+/// An environment file with optional named profiles, where each profile is a section that starts
+/// with a `[profile.<name>]` line and overrides the variables above the first section:
+///
+/// ```sh
+/// DKN_MODELS=gemma3:4b
+///
+/// [profile.test]
+/// DKN_NETWORK=testnet
+/// DKN_SPECS_INTERVAL_SECS=60
+/// ```
+///
+/// The variables that are already set in the process environment take precedence over both,
+/// and the overrides, e.g. given by the command-line flags, take precedence over all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvFile {
+    pub path: String,
+    /// The selected profile, if any.
+    pub profile: Option<String>,
+    /// Variables that are set after each load.
+    pub overrides: Vec<(String, String)>,
+}
+
+impl EnvFile {
+    pub fn new(path: impl Into<String>, profile: Option<String>) -> Self {
+        Self {
+            path: path.into(),
+            profile,
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Sets the variables that override the ones of the environment & the file.
+    pub fn with_overrides(mut self, overrides: Vec<(String, String)>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Loads the variables of the file & its selected profile, without overriding the existing ones.
+    pub fn load(&self) -> Result<()> {
+        // the overrides are applied even if the file can not be read
+        self.apply_overrides();
+        let (base, profile) = self.read_sections()?;
+
+        // the first value of a variable is kept, so the profile is loaded before the base
+        if let Some(profile) = profile {
+            dotenvy::from_read(Cursor::new(profile)).wrap_err("could not parse profile")?;
+        }
+        dotenvy::from_read(Cursor::new(base)).wrap_err("could not parse environment file")
+    }
+
+    /// Loads the variables of the file & its selected profile, overriding the existing ones
+    /// except for the overrides.
+    pub fn reload(&self) -> Result<()> {
+        let (base, profile) = self.read_sections()?;
+
+        dotenvy::from_read_override(Cursor::new(base))
+            .wrap_err("could not parse environment file")?;
+        if let Some(profile) = profile {
+            dotenvy::from_read_override(Cursor::new(profile))
+                .wrap_err("could not parse profile")?;
+        }
+        self.apply_overrides();
+        Ok(())
+    }
+
+    fn apply_overrides(&self) {
+        for (key, value) in &self.overrides {
+            std::env::set_var(key, value);
+        }
+    }
+
+    /// Reads the file, and returns its base section along with the section of the selected profile.
+    fn read_sections(&self) -> Result<(String, Option<String>)> {
+        let content = std::fs::read_to_string(&self.path)
+            .wrap_err_with(|| format!("could not read {}", self.path))?;
+
+        split_profiles(&content, self.profile.as_deref())
+    }
+}
+
+/// Splits the content into the base section & the section of the given profile, if any.
+fn split_profiles(content: &str, profile: Option<&str>) -> Result<(String, Option<String>)> {
+    let mut base = String::new();
+    let mut selected = None;
+    // the profile of the current section, `None` within the base section
+    let mut section: Option<&str> = None;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            let name = trimmed[1..trimmed.len() - 1]
+                .trim()
+                .strip_prefix("profile.")
+                .filter(|name| !name.is_empty())
+                .ok_or_else(|| {
+                    eyre::eyre!("unexpected section {trimmed}, expected [profile.<name>]")
+                })?;
+            if Some(name) == profile {
+                selected.get_or_insert_with(String::new);
+            }
+            section = Some(name);
+            continue;
+        }
+
+        // a profile can be given in several sections, they are merged
+        let target = match section {
+            None => &mut base,
+            Some(name) if Some(name) == profile => selected.get_or_insert_with(String::new),
+            Some(_) => continue,
+        };
+        target.push_str(line);
+        target.push('\n');
+    }
+
+    match (profile, selected) {
+        (Some(profile), None) => eyre::bail!("profile {profile} is not defined"),
+        (_, selected) => Ok((base, selected)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_file_profiles() {
+        let content = r#"
+DKN_MODELS=gemma3:4b
+DKN_NETWORK=mainnet
+
+[profile.test]
+DKN_NETWORK=testnet
+DKN_SPECS_INTERVAL_SECS=60
+
+[profile.pro]
+DKN_MODELS=gpt-4o
+"#;
+
+        let (base, profile) = split_profiles(content, None).unwrap();
+        assert!(base.contains("DKN_NETWORK=mainnet") && !base.contains("testnet"));
+        assert_eq!(profile, None);
+
+        let (_, profile) = split_profiles(content, Some("test")).unwrap();
+        assert_eq!(
+            profile.unwrap().trim(),
+            "DKN_NETWORK=testnet\nDKN_SPECS_INTERVAL_SECS=60"
+        );
+        assert!(split_profiles(content, Some("nope")).is_err());
+        assert!(split_profiles("[network]\nA=1", None).is_err());
+    }
+}
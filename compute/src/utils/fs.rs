@@ -0,0 +1,57 @@
+use eyre::{Context, Result};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Writes the content to the given path atomically, creating its parent directories if needed.
+///
+/// The content is written & synced to a temporary file next to it first, which then replaces the file,
+/// so that a crash never leaves a partial file and a reader never sees one.
+pub fn write_atomic(path: &Path, content: impl AsRef<[u8]>) -> Result<()> {
+    let parent = match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(parent) => {
+            std::fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("could not create {}", parent.display()))?;
+            parent
+        }
+        None => Path::new("."),
+    };
+
+    let tmp_path = path.with_extension("tmp");
+    File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(content.as_ref())?;
+            file.sync_all()
+        })
+        .wrap_err_with(|| format!("could not write {}", tmp_path.display()))?;
+    std::fs::rename(&tmp_path, path)
+        .wrap_err_with(|| format!("could not write {}", path.display()))?;
+
+    // the rename itself is durable only once the directory is synced
+    #[cfg(unix)]
+    File::open(parent)
+        .and_then(|dir| dir.sync_all())
+        .wrap_err_with(|| format!("could not sync {}", parent.display()))?;
+    #[cfg(not(unix))]
+    let _ = parent;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = std::env::temp_dir().join(format!("dkn-fs-{}", uuid::Uuid::now_v7()));
+        let path = dir.join("nested").join("file.json");
+
+        write_atomic(&path, "first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+        assert!(!path.with_extension("tmp").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,165 @@
+use eyre::{Context, Result};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+
+use super::http::{read_request, write_response, HTTP_REQUEST_TIMEOUT};
+
+/// Readiness checks of the node, updated by the node periodically.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct HealthChecks {
+    /// Whether the node is connected to its RPC.
+    pub rpc_connected: bool,
+    /// Whether the heartbeats are acknowledged by the RPC recently.
+    pub heartbeats_acked: bool,
+    /// Whether the task workers are still running.
+    pub workers_alive: bool,
+}
+
+impl HealthChecks {
+    /// Returns whether the node is ready to receive tasks, i.e. all checks pass.
+    pub fn is_ready(&self) -> bool {
+        self.rpc_connected && self.heartbeats_acked && self.workers_alive
+    }
+}
+
+/// Health of the node, shared between the node & the health server.
+#[derive(Debug, Default)]
+pub struct NodeHealth {
+    checks: RwLock<HealthChecks>,
+}
+
+impl NodeHealth {
+    /// Returns the latest readiness checks.
+    pub fn checks(&self) -> HealthChecks {
+        *self.checks.read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Updates the readiness checks.
+    pub fn set_checks(&self, checks: HealthChecks) {
+        *self.checks.write().unwrap_or_else(|err| err.into_inner()) = checks;
+    }
+}
+
+/// Serves the health endpoints at the given address until cancelled:
+///
+/// - `/healthz` is always `200 OK` while the process is up.
+/// - `/readyz` is `200 OK` if the node is ready as per its [`HealthChecks`], `503 Service Unavailable` otherwise.
+pub async fn serve_health(
+    addr: SocketAddr,
+    health: Arc<NodeHealth>,
+    cancellation: CancellationToken,
+) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .wrap_err_with(|| format!("could not bind health server to {addr}"))?;
+    log::info!("Serving health endpoints at http://{addr}");
+
+    run_health_server(listener, health, cancellation).await;
+    Ok(())
+}
+
+async fn run_health_server(
+    listener: TcpListener,
+    health: Arc<NodeHealth>,
+    cancellation: CancellationToken,
+) {
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let health = health.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) = tokio::time::timeout(HTTP_REQUEST_TIMEOUT, handle_connection(stream, &health)).await {
+                            log::debug!("Health request timed out: {err}");
+                        }
+                    });
+                }
+                Err(err) => log::warn!("Could not accept health connection: {err}"),
+            },
+            _ = cancellation.cancelled() => {
+                log::info!("Closing health server.");
+                return;
+            }
+        }
+    }
+}
+
+/// Reads a single request & writes its response, the connection is closed afterwards.
+async fn handle_connection(mut stream: TcpStream, health: &NodeHealth) {
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+
+    let (status, body) = route(&request.path, &health.checks());
+    write_response(&mut stream, &request, status, &body, "application/json").await;
+}
+
+/// Returns the status line & JSON body of the response for the given path.
+fn route(path: &str, checks: &HealthChecks) -> (&'static str, String) {
+    match path {
+        "/healthz" => ("200 OK", r#"{"status":"ok"}"#.to_string()),
+        "/readyz" => {
+            let (status, ready) = if checks.is_ready() {
+                ("200 OK", "ready")
+            } else {
+                ("503 Service Unavailable", "not ready")
+            };
+            let body = serde_json::json!({ "status": ready, "checks": checks });
+            (status, body.to_string())
+        }
+        _ => ("404 Not Found", r#"{"status":"not found"}"#.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_health_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let health = Arc::new(NodeHealth::default());
+        let cancellation = CancellationToken::new();
+        let server = tokio::spawn(run_health_server(
+            listener,
+            health.clone(),
+            cancellation.clone(),
+        ));
+
+        async fn get(addr: SocketAddr, path: &str) -> String {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes())
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        }
+
+        assert!(get(addr, "/healthz").await.starts_with("HTTP/1.1 200 OK"));
+        assert!(get(addr, "/readyz")
+            .await
+            .starts_with("HTTP/1.1 503 Service Unavailable"));
+        assert!(get(addr, "/nope")
+            .await
+            .starts_with("HTTP/1.1 404 Not Found"));
+
+        health.set_checks(HealthChecks {
+            rpc_connected: true,
+            heartbeats_acked: true,
+            workers_alive: true,
+        });
+        let response = get(addr, "/readyz?verbose").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains(r#""rpc_connected":true"#));
+
+        cancellation.cancel();
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+//! A minimal HTTP/1.1 responder for the local endpoints of the node, where each connection
+//! serves a single request.
+
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Maximum size of a request head to be read, the rest is ignored.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+/// Timeout for reading a request & writing its response.
+pub(crate) const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Request line & headers of a request, the body is ignored.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct HttpRequest {
+    pub method: String,
+    /// Path of the request, without the query.
+    pub path: String,
+    /// Query parameters of the request, decoded.
+    pub query: Vec<(String, String)>,
+    /// Headers of the request, names are lowercased.
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpRequest {
+    /// Parses the request head, unknown or malformed parts are left empty.
+    fn parse(head: &str) -> Self {
+        let mut lines = head.lines();
+        let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+        let method = request_line.next().unwrap_or_default().to_string();
+        let target = request_line.next().unwrap_or_default();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let path = path.to_string();
+        let query = query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let (name, value) = param.split_once('=').unwrap_or((param, ""));
+                (decode_query(name), decode_query(value))
+            })
+            .collect();
+        let headers = lines
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+
+        Self {
+            method,
+            path,
+            query,
+            headers,
+        }
+    }
+
+    /// Returns the value of the query parameter with the given name.
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the value of the header with the given lowercase name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Decodes a query component, where `+` is a space as well.
+fn decode_query(component: &str) -> String {
+    let component = component.replace('+', " ");
+    urlencoding::decode(&component)
+        .map(|decoded| decoded.into_owned())
+        .unwrap_or(component)
+}
+
+/// Reads the head of a request, returns `None` if it could not be read.
+pub(crate) async fn read_request(stream: &mut TcpStream) -> Option<HttpRequest> {
+    let mut buf = vec![0u8; MAX_REQUEST_SIZE];
+    let mut len = 0;
+    while len < buf.len() {
+        match stream.read(&mut buf[len..]).await {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) => {
+                log::debug!("Could not read HTTP request: {err}");
+                return None;
+            }
+        }
+        if buf[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+            break;
+        }
+    }
+
+    Some(HttpRequest::parse(&String::from_utf8_lossy(&buf[..len])))
+}
+
+/// Writes a response with the given status line & content type, and closes the connection.
+///
+/// The body is omitted for `HEAD` requests.
+pub(crate) async fn write_response(
+    stream: &mut TcpStream,
+    request: &HttpRequest,
+    status: &str,
+    body: &str,
+    content_type: &str,
+) {
+    let mut response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    if request.method != "HEAD" {
+        response.push_str(body);
+    }
+
+    if let Err(err) = stream.write_all(response.as_bytes()).await {
+        log::debug!("Could not write HTTP response: {err}");
+    }
+    let _ = stream.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let request = HttpRequest::parse(
+            "POST /tasks/pause?now HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer abc\r\n\r\nbody: ignored",
+        );
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/tasks/pause");
+        assert_eq!(request.query_param("now"), Some(""));
+        assert_eq!(request.header("authorization"), Some("Bearer abc"));
+        assert_eq!(request.header("body"), None);
+
+        assert_eq!(HttpRequest::parse(""), HttpRequest::default());
+
+        let request = HttpRequest::parse(
+            "GET /history?model=gemma3%3A4b&since=2025-01-01T00:00:00%2B00:00&status=failed HTTP/1.1\r\n\r\n",
+        );
+        assert_eq!(request.path, "/history");
+        assert_eq!(request.query_param("model"), Some("gemma3:4b"));
+        assert_eq!(
+            request.query_param("since"),
+            Some("2025-01-01T00:00:00+00:00")
+        );
+        assert_eq!(request.query_param("limit"), None);
+    }
+}
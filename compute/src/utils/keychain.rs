@@ -0,0 +1,121 @@
+use eyre::Result;
+use std::env;
+
+/// Variables of the secrets that can be read from the OS keychain.
+const KEYCHAIN_SECRETS: [&str; 7] = [
+    "DKN_WALLET_SECRET_KEY",
+    "DKN_KEYSTORE_PASSWORD",
+    "DKN_REMOTE_SIGNER_TOKEN",
+    "DKN_ADMIN_TOKEN",
+    "OPENAI_API_KEY",
+    "GEMINI_API_KEY",
+    "OPENROUTER_API_KEY",
+];
+
+/// Reads the secrets that are not given in the environment from the OS keychain (macOS Keychain,
+/// Windows Credential Manager or Secret Service), if `DKN_KEYCHAIN_SERVICE` is given.
+///
+/// Each secret is the password of the keychain entry of that service, with the name of its variable
+/// as the account (e.g. `DKN_WALLET_SECRET_KEY`), and is exported so that the config is loaded as usual.
+/// The node must be built with the `keychain` feature.
+pub fn apply_keychain_secrets() -> Result<()> {
+    let Some(service) = env::var("DKN_KEYCHAIN_SERVICE")
+        .ok()
+        .filter(|service| !service.trim().is_empty())
+    else {
+        return Ok(());
+    };
+    let service = service.trim();
+
+    let loaded = load_secrets(&KEYCHAIN_SECRETS, |var| read_secret(service, var))?;
+    if loaded.is_empty() {
+        log::warn!("No secrets were found in the keychain of {service}");
+    } else {
+        log::info!("Read {} from the keychain of {service}", loaded.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Exports the secrets that are missing in the environment with the given reader,
+/// returning the variables that were read.
+fn load_secrets<'a>(
+    vars: &[&'a str],
+    read: impl Fn(&str) -> Result<Option<String>>,
+) -> Result<Vec<&'a str>> {
+    let mut loaded = Vec::new();
+    for var in vars {
+        // the environment takes precedence, where an empty value (as in .env.example) is missing
+        if env::var(var).is_ok_and(|value| !value.trim().is_empty()) {
+            continue;
+        }
+
+        if let Some(secret) = read(var)? {
+            env::set_var(var, secret);
+            loaded.push(*var);
+        }
+    }
+
+    Ok(loaded)
+}
+
+/// Reads the secret of the given variable from the keychain, `None` if there is no such entry.
+#[cfg(feature = "keychain")]
+fn read_secret(service: &str, var: &str) -> Result<Option<String>> {
+    use eyre::Context;
+
+    let entry = keyring::Entry::new(service, var)
+        .wrap_err_with(|| format!("could not open keychain entry for {var}"))?;
+    match entry.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(err).wrap_err_with(|| format!("could not read {var} from the keychain")),
+    }
+}
+
+#[cfg(not(feature = "keychain"))]
+fn read_secret(_service: &str, _var: &str) -> Result<Option<String>> {
+    eyre::bail!(
+        "DKN_KEYCHAIN_SERVICE is given but the node is built without the `keychain` feature"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_secrets() {
+        env::set_var("DKN_TEST_KEYCHAIN_GIVEN", "from-env");
+        env::set_var("DKN_TEST_KEYCHAIN_EMPTY", "");
+        env::remove_var("DKN_TEST_KEYCHAIN_MISSING");
+        env::remove_var("DKN_TEST_KEYCHAIN_UNKNOWN");
+
+        let loaded = load_secrets(
+            &[
+                "DKN_TEST_KEYCHAIN_GIVEN",
+                "DKN_TEST_KEYCHAIN_EMPTY",
+                "DKN_TEST_KEYCHAIN_MISSING",
+                "DKN_TEST_KEYCHAIN_UNKNOWN",
+            ],
+            |var| Ok((var != "DKN_TEST_KEYCHAIN_UNKNOWN").then(|| "from-keychain".to_string())),
+        )
+        .unwrap();
+
+        // the ones given in the environment are kept
+        assert_eq!(
+            loaded,
+            vec!["DKN_TEST_KEYCHAIN_EMPTY", "DKN_TEST_KEYCHAIN_MISSING"]
+        );
+        assert_eq!(env::var("DKN_TEST_KEYCHAIN_GIVEN").unwrap(), "from-env");
+        assert_eq!(
+            env::var("DKN_TEST_KEYCHAIN_EMPTY").unwrap(),
+            "from-keychain"
+        );
+        assert_eq!(
+            env::var("DKN_TEST_KEYCHAIN_MISSING").unwrap(),
+            "from-keychain"
+        );
+        assert!(env::var("DKN_TEST_KEYCHAIN_UNKNOWN").is_err());
+    }
+}
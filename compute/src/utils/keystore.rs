@@ -0,0 +1,208 @@
+use aes::cipher::{KeyIvInit, StreamCipher};
+use dkn_utils::crypto::{keccak256hash, public_key_to_address};
+use eyre::{Context, Result};
+use libsecp256k1::{PublicKey, SecretKey};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io::IsTerminal;
+use std::path::Path;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// Scrypt cost of the created keystores, same as the Ethereum clients use.
+const SCRYPT_LOG_N: u8 = 18;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// An encrypted wallet in the Ethereum keystore (v3) format, with a scrypt key derivation & AES-128-CTR.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    /// Address of the wallet, without `0x`.
+    #[serde(default)]
+    pub address: Option<String>,
+    pub crypto: KeystoreCrypto,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeystoreCrypto {
+    pub cipher: String,
+    pub cipherparams: CipherParams,
+    /// Encrypted secret key, hex encoded.
+    pub ciphertext: String,
+    pub kdf: String,
+    pub kdfparams: ScryptParams,
+    /// Keccak256 of the second half of the derived key & the ciphertext, hex encoded.
+    pub mac: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CipherParams {
+    pub iv: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScryptParams {
+    pub dklen: usize,
+    pub n: u64,
+    pub r: u32,
+    pub p: u32,
+    pub salt: String,
+}
+
+impl Keystore {
+    /// Reads the keystore at the given path.
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("could not read {}", path.display()))?;
+        let keystore: Self = serde_json::from_str(&content)
+            .wrap_err_with(|| format!("could not parse keystore {}", path.display()))?;
+        if keystore.version != 3 {
+            eyre::bail!("unsupported keystore version {}", keystore.version);
+        }
+
+        Ok(keystore)
+    }
+
+    /// Encrypts the secret key with the given password.
+    pub fn encrypt(secret_key: &SecretKey, password: &str) -> Result<Self> {
+        Self::encrypt_with_cost(secret_key, password, SCRYPT_LOG_N)
+    }
+
+    fn encrypt_with_cost(secret_key: &SecretKey, password: &str, log_n: u8) -> Result<Self> {
+        let mut salt = [0u8; 32];
+        let mut iv = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut iv);
+
+        let kdfparams = ScryptParams {
+            dklen: 32,
+            n: 1 << log_n,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+            salt: hex::encode(salt),
+        };
+        let derived_key = derive_key(password, &kdfparams)?;
+
+        let mut ciphertext = secret_key.serialize();
+        Aes128Ctr::new(derived_key[..16].into(), &iv.into()).apply_keystream(&mut ciphertext);
+        let mac = keccak256hash([&derived_key[16..32], &ciphertext[..]].concat());
+
+        Ok(Self {
+            version: 3,
+            address: Some(hex::encode(public_key_to_address(
+                &PublicKey::from_secret_key(secret_key),
+            ))),
+            crypto: KeystoreCrypto {
+                cipher: "aes-128-ctr".to_string(),
+                cipherparams: CipherParams {
+                    iv: hex::encode(iv),
+                },
+                ciphertext: hex::encode(ciphertext),
+                kdf: "scrypt".to_string(),
+                kdfparams,
+                mac: hex::encode(mac),
+            },
+        })
+    }
+
+    /// Decrypts the secret key with the given password, failing if the password is wrong.
+    pub fn decrypt(&self, password: &str) -> Result<SecretKey> {
+        let crypto = &self.crypto;
+        if crypto.kdf != "scrypt" {
+            eyre::bail!(
+                "unsupported keystore kdf {}, only scrypt is supported",
+                crypto.kdf
+            );
+        }
+        if crypto.cipher != "aes-128-ctr" {
+            eyre::bail!("unsupported keystore cipher {}", crypto.cipher);
+        }
+
+        let derived_key = derive_key(password, &crypto.kdfparams)?;
+        let mut ciphertext =
+            hex::decode(&crypto.ciphertext).wrap_err("could not decode keystore ciphertext")?;
+        let mac = keccak256hash([&derived_key[16..32], &ciphertext[..]].concat());
+        if hex::encode(mac) != crypto.mac.trim_start_matches("0x").to_lowercase() {
+            eyre::bail!("wrong keystore password");
+        }
+
+        let iv = hex::decode(&crypto.cipherparams.iv)
+            .ok()
+            .filter(|iv| iv.len() == 16)
+            .ok_or_else(|| eyre::eyre!("could not decode keystore iv"))?;
+        Aes128Ctr::new(derived_key[..16].into(), iv.as_slice().into())
+            .apply_keystream(&mut ciphertext);
+
+        SecretKey::parse_slice(&ciphertext).wrap_err("could not parse keystore secret key")
+    }
+}
+
+/// Derives the encryption key from the password with scrypt.
+fn derive_key(password: &str, params: &ScryptParams) -> Result<Vec<u8>> {
+    if !params.n.is_power_of_two() || params.dklen < 32 {
+        eyre::bail!("invalid keystore scrypt parameters");
+    }
+    let salt = hex::decode(&params.salt).wrap_err("could not decode keystore salt")?;
+    let scrypt_params = scrypt::Params::new(
+        params.n.trailing_zeros() as u8,
+        params.r,
+        params.p,
+        params.dklen,
+    )
+    .map_err(|err| eyre::eyre!("invalid keystore scrypt parameters: {err}"))?;
+
+    let mut derived_key = vec![0u8; params.dklen];
+    scrypt::scrypt(password.as_bytes(), &salt, &scrypt_params, &mut derived_key)
+        .map_err(|err| eyre::eyre!("could not derive keystore key: {err}"))?;
+    Ok(derived_key)
+}
+
+/// Decrypts the keystore at `DKN_KEYSTORE_PATH` if given, with the password in `DKN_KEYSTORE_PASSWORD`
+/// or the one prompted from the terminal.
+pub fn secret_key_from_keystore_env() -> Result<Option<SecretKey>> {
+    let Some(path) = env::var("DKN_KEYSTORE_PATH")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+    else {
+        return Ok(None);
+    };
+    let keystore = Keystore::read(path.trim().as_ref())?;
+
+    let password = match env::var("DKN_KEYSTORE_PASSWORD") {
+        Ok(password) => password,
+        Err(_) if std::io::stdin().is_terminal() => {
+            rpassword::prompt_password(format!("Password for keystore {}: ", path.trim()))
+                .wrap_err("could not read keystore password")?
+        }
+        Err(_) => eyre::bail!(
+            "DKN_KEYSTORE_PASSWORD must be set to decrypt the keystore without a terminal"
+        ),
+    };
+
+    keystore.decrypt(&password).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystore() {
+        let secret_key = SecretKey::random(&mut rand::thread_rng());
+        // a lower cost, so that the test is fast in debug builds
+        let keystore = Keystore::encrypt_with_cost(&secret_key, "hunter2", 10).unwrap();
+        assert_eq!(
+            keystore.address,
+            Some(hex::encode(public_key_to_address(
+                &PublicKey::from_secret_key(&secret_key)
+            )))
+        );
+
+        let json = serde_json::to_string(&keystore).unwrap();
+        let keystore: Keystore = serde_json::from_str(&json).unwrap();
+        assert_eq!(keystore.decrypt("hunter2").unwrap(), secret_key);
+        assert!(keystore.decrypt("hunter3").is_err());
+    }
+}
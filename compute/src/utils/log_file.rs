@@ -0,0 +1,263 @@
+use chrono::{DateTime, Utc};
+use eyre::{Context, Result};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Default maximum size of a log file before it is rotated, in megabytes.
+const DEFAULT_MAX_SIZE_MB: u64 = 50;
+/// Default number of rotated log files to keep.
+const DEFAULT_KEEP: usize = 5;
+
+/// Time period after which the log file is rotated, regardless of its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+    Hourly,
+    Daily,
+}
+
+impl LogRotation {
+    /// Returns the period that the given time falls into.
+    fn period(&self, time: DateTime<Utc>) -> String {
+        match self {
+            Self::Hourly => time.format("%Y-%m-%d-%H").to_string(),
+            Self::Daily => time.format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+/// Configuration of the log file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFileConfig {
+    pub path: PathBuf,
+    /// Maximum size of the log file in bytes, it is rotated once this is exceeded.
+    pub max_size: u64,
+    /// Time period to rotate the log file at, if any.
+    pub rotation: Option<LogRotation>,
+    /// Number of rotated files to keep, the older ones are deleted.
+    pub keep: usize,
+}
+
+impl LogFileConfig {
+    /// Reads the log file configuration from the environment:
+    ///
+    /// - `DKN_LOG_FILE`: path of the log file, logs are not written to a file if not given.
+    /// - `DKN_LOG_FILE_MAX_SIZE`: maximum size of the log file in megabytes, 50 by default.
+    /// - `DKN_LOG_FILE_ROTATION`: `hourly` or `daily` to rotate the log file periodically as well.
+    /// - `DKN_LOG_FILE_KEEP`: number of rotated files to keep, 5 by default.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(path) = env::var("DKN_LOG_FILE")
+            .ok()
+            .map(|path| path.trim().to_string())
+            .filter(|path| !path.is_empty())
+        else {
+            return Ok(None);
+        };
+
+        let max_size_mb = match env::var("DKN_LOG_FILE_MAX_SIZE") {
+            Ok(size) if !size.trim().is_empty() => size
+                .trim()
+                .parse::<u64>()
+                .wrap_err("could not parse DKN_LOG_FILE_MAX_SIZE")?,
+            _ => DEFAULT_MAX_SIZE_MB,
+        };
+        let rotation = match env::var("DKN_LOG_FILE_ROTATION")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "none" => None,
+            "hourly" => Some(LogRotation::Hourly),
+            "daily" => Some(LogRotation::Daily),
+            other => {
+                eyre::bail!("unknown DKN_LOG_FILE_ROTATION: {other}, expected hourly or daily")
+            }
+        };
+        let keep = match env::var("DKN_LOG_FILE_KEEP") {
+            Ok(keep) if !keep.trim().is_empty() => keep
+                .trim()
+                .parse::<usize>()
+                .wrap_err("could not parse DKN_LOG_FILE_KEEP")?,
+            _ => DEFAULT_KEEP,
+        };
+
+        Ok(Some(Self {
+            path: PathBuf::from(path),
+            max_size: max_size_mb.saturating_mul(1024 * 1024),
+            rotation,
+            keep,
+        }))
+    }
+}
+
+/// A log file that is rotated by its size, and by time if configured.
+///
+/// Rotated files are suffixed with their order, e.g. `node.log.1` is the most recent one.
+/// Colors are stripped from the logs, as they are only meant for terminals.
+#[derive(Debug)]
+pub struct RotatingFile {
+    config: LogFileConfig,
+    file: File,
+    size: u64,
+    /// The rotation period that the file was opened in.
+    period: Option<String>,
+    /// Whether the last write has ended a line, so that lines are not split across files.
+    at_line_start: bool,
+    /// Whether an ANSI escape sequence is being stripped.
+    in_escape: bool,
+}
+
+impl RotatingFile {
+    pub fn open(config: LogFileConfig) -> Result<Self> {
+        if let Some(parent) = config.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("could not create {}", parent.display()))?;
+        }
+        let file = open_append(&config.path)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or_default();
+
+        Ok(Self {
+            period: config.rotation.map(|r| r.period(Utc::now())),
+            config,
+            file,
+            size,
+            at_line_start: true,
+            in_escape: false,
+        })
+    }
+
+    /// Returns whether the file should be rotated before the next line.
+    fn should_rotate(&self) -> bool {
+        if self.size >= self.config.max_size {
+            return true;
+        }
+
+        match (self.config.rotation, &self.period) {
+            (Some(rotation), Some(period)) => rotation.period(Utc::now()) != *period,
+            _ => false,
+        }
+    }
+
+    /// Shifts the rotated files by one, deleting the oldest, and starts a new file.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let rotated = |idx: usize| {
+            let mut path = self.config.path.clone().into_os_string();
+            path.push(format!(".{idx}"));
+            PathBuf::from(path)
+        };
+        if self.config.keep == 0 {
+            fs::remove_file(&self.config.path)?;
+        } else {
+            let _ = fs::remove_file(rotated(self.config.keep));
+            for idx in (1..self.config.keep).rev() {
+                let from = rotated(idx);
+                if from.exists() {
+                    fs::rename(from, rotated(idx + 1))?;
+                }
+            }
+            fs::rename(&self.config.path, rotated(1))?;
+        }
+
+        self.file = open_append(&self.config.path).map_err(io::Error::other)?;
+        self.size = 0;
+        self.period = self.config.rotation.map(|r| r.period(Utc::now()));
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.at_line_start && self.should_rotate() {
+            self.rotate()?;
+        }
+
+        // strip the ANSI escape sequences, i.e. `ESC [ ... <letter>`
+        let mut stripped = Vec::with_capacity(buf.len());
+        for &byte in buf {
+            match (self.in_escape, byte) {
+                (false, 0x1b) => self.in_escape = true,
+                (false, _) => stripped.push(byte),
+                (true, b'a'..=b'z' | b'A'..=b'Z') => self.in_escape = false,
+                (true, _) => {}
+            }
+        }
+
+        self.file.write_all(&stripped)?;
+        self.size += stripped.len() as u64;
+        if let Some(&last) = buf.last() {
+            self.at_line_start = last == b'\n';
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .wrap_err_with(|| format!("could not open log file {}", path.display()))
+}
+
+/// Writes the logs to all of the given writers, e.g. the terminal and a log file.
+pub struct LogTee(pub Vec<Box<dyn Write + Send>>);
+
+impl Write for LogTee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // a failing writer should not prevent the others from getting the logs
+        for writer in &mut self.0 {
+            let _ = writer.write_all(buf);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        for writer in &mut self.0 {
+            let _ = writer.flush();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = env::temp_dir().join(format!("dkn-logs-{}", uuid::Uuid::now_v7()));
+        let path = dir.join("node.log");
+        let mut file = RotatingFile::open(LogFileConfig {
+            path: path.clone(),
+            max_size: 10,
+            rotation: None,
+            keep: 2,
+        })
+        .unwrap();
+
+        // a line is not split even if it exceeds the size, and colors are stripped
+        write!(file, "\x1b[32mfirst\x1b[0m ").unwrap();
+        writeln!(file, "line").unwrap();
+        writeln!(file, "second").unwrap();
+        writeln!(file, "third").unwrap();
+        writeln!(file, "fourth").unwrap();
+        file.flush().unwrap();
+
+        let read = |suffix: &str| fs::read_to_string(dir.join(format!("node.log{suffix}"))).ok();
+        assert_eq!(read("").as_deref(), Some("fourth\n"));
+        assert_eq!(read(".1").as_deref(), Some("second\nthird\n"));
+        assert_eq!(read(".2").as_deref(), Some("first line\n"));
+        assert_eq!(read(".3"), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
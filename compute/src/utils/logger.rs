@@ -0,0 +1,79 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::sync::{Arc, RwLock};
+
+/// A logger whose filter can be reloaded at runtime, e.g. when `RUST_LOG` is changed.
+///
+/// Records are filtered here & formatted by the wrapped logger, which lets everything through.
+struct ReloadableLogger {
+    inner: env_logger::Logger,
+    filter: Arc<RwLock<env_filter::Filter>>,
+}
+
+impl Log for ReloadableLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter
+            .read()
+            .is_ok_and(|filter| filter.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        if self
+            .filter
+            .read()
+            .is_ok_and(|filter| filter.matches(record))
+        {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Handle to reload the log filter of the logger.
+#[derive(Clone)]
+pub struct LogFilterHandle {
+    filter: Arc<RwLock<env_filter::Filter>>,
+}
+
+impl LogFilterHandle {
+    /// Reloads the log filter from the `RUST_LOG` variable.
+    pub fn reload(&self) {
+        let filter = log_filter();
+        log::set_max_level(filter.filter());
+        if let Ok(mut current) = self.filter.write() {
+            *current = filter;
+        }
+    }
+}
+
+/// Returns the default log levels of the node, overridden by the `RUST_LOG` variable.
+fn log_filter() -> env_filter::Filter {
+    env_filter::Builder::new()
+        .filter(None, LevelFilter::Off)
+        .filter_module("dkn_compute", LevelFilter::Info)
+        .filter_module("dkn_p2p", LevelFilter::Info)
+        .filter_module("dkn_utils", LevelFilter::Info)
+        .filter_module("dkn_executor", LevelFilter::Info)
+        .filter_module("libp2p", LevelFilter::Error)
+        .parse(&std::env::var("RUST_LOG").unwrap_or_default())
+        .build()
+}
+
+/// Initializes the global logger with the given builder for formatting & output, where the
+/// log levels are given by [`LogFilterHandle`] instead.
+pub fn init_logger(builder: &mut env_logger::Builder) -> eyre::Result<LogFilterHandle> {
+    let filter = log_filter();
+    let max_level = filter.filter();
+    let filter = Arc::new(RwLock::new(filter));
+
+    let logger = ReloadableLogger {
+        inner: builder.filter_level(LevelFilter::Trace).build(),
+        filter: filter.clone(),
+    };
+    log::set_boxed_logger(Box::new(logger))?;
+    log::set_max_level(max_level);
+
+    Ok(LogFilterHandle { filter })
+}
@@ -3,3 +3,48 @@ pub use specs::*;
 
 mod points;
 pub use points::*;
+
+mod availability;
+pub use availability::*;
+
+mod backoff;
+pub use backoff::*;
+
+mod clock;
+pub use clock::*;
+
+mod health;
+pub use health::*;
+
+mod env_file;
+pub use env_file::*;
+
+mod keystore;
+pub use keystore::*;
+
+mod signer;
+pub use signer::*;
+
+mod keychain;
+pub use keychain::*;
+
+mod log_file;
+pub use log_file::*;
+
+mod logger;
+pub use logger::*;
+
+mod telemetry;
+pub use telemetry::*;
+
+mod release;
+pub use release::*;
+
+mod proxy;
+pub use proxy::*;
+
+mod http;
+pub(crate) use http::*;
+
+mod fs;
+pub use fs::*;
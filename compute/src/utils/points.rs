@@ -1,14 +1,29 @@
+use chrono::{DateTime, NaiveDate, Utc};
 use dkn_utils::DriaNetwork;
 use eyre::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::write_atomic;
+
+/// Maximum age of the points snapshots kept in the ledger.
+const POINTS_LEDGER_MAX_AGE: chrono::Duration = chrono::Duration::days(30);
 
 pub struct DriaPointsClient {
     pub url: String,
     client: reqwest::Client,
     /// The total number of points you have accumulated at the start of the run.
     pub initial: f64,
+    /// Whether the initial points are set, either fetched or restored.
+    is_initialized: bool,
+    /// History of the points, to compute the points earned over a day or a week.
+    pub ledger: PointsLedger,
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 pub struct DriaPoints {
     /// Indicates in which top percentile your points are.
     pub percentile: usize,
@@ -44,14 +59,46 @@ impl DriaPointsClient {
             url,
             client,
             initial: 0.0,
+            is_initialized: false,
+            ledger: PointsLedger::default(),
         })
     }
 
+    /// Keeps the history of the points at the given path, along with the snapshots of the previous runs.
+    pub fn with_ledger(mut self, path: Option<PathBuf>) -> Self {
+        self.ledger = PointsLedger::load(path);
+        self
+    }
+
     /// Sets the initial points to the current points.
     ///
-    /// If there is an error, it sets to 0.0.
+    /// If there is an error, it sets to 0.0. Does nothing if the initial points are restored.
     pub async fn initialize(&mut self) {
-        self.initial = self.get_points().await.map(|p| p.score).unwrap_or_default();
+        if self.is_initialized {
+            return;
+        }
+        self.initial = self
+            .refresh_points()
+            .await
+            .map(|p| p.score)
+            .unwrap_or_default();
+        self.is_initialized = true;
+    }
+
+    /// Restores the initial points of a previous run, so that the session continues.
+    pub fn restore(&mut self, initial: f64) {
+        self.initial = initial;
+        self.is_initialized = true;
+    }
+
+    /// Gets the points, and records them to the ledger.
+    pub async fn refresh_points(&mut self) -> eyre::Result<DriaPoints> {
+        let points = self.get_points().await?;
+        if let Err(err) = self.ledger.record(Utc::now(), points.score) {
+            log::warn!("Could not record points to the ledger: {err:?}");
+        }
+
+        Ok(points)
     }
 
     pub async fn get_points(&self) -> eyre::Result<DriaPoints> {
@@ -67,10 +114,217 @@ impl DriaPointsClient {
     }
 }
 
+/// A snapshot of the total points at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PointsSnapshot {
+    pub at: DateTime<Utc>,
+    pub score: f64,
+}
+
+/// Time series of the points snapshots, persisted as JSON lines if a path is given,
+/// so that the points earned over a day or a week are known across restarts.
+///
+/// Snapshots older than 30 days are dropped when the ledger is loaded.
+#[derive(Debug, Default)]
+pub struct PointsLedger {
+    path: Option<PathBuf>,
+    snapshots: VecDeque<PointsSnapshot>,
+}
+
+impl PointsLedger {
+    /// Loads the snapshots from the given path if any, skipping the malformed & the old ones.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let mut ledger = Self {
+            path,
+            snapshots: VecDeque::new(),
+        };
+        let Some(path) = &ledger.path else {
+            return ledger;
+        };
+        if !path.exists() {
+            return ledger;
+        }
+
+        let Some((snapshots, num_lines)) = read_snapshots(path) else {
+            return ledger;
+        };
+        ledger.snapshots = snapshots;
+
+        // the file is rewritten if some snapshots are dropped, so that it does not grow forever
+        if ledger.snapshots.len() != num_lines {
+            if let Err(err) = ledger.rewrite() {
+                log::warn!("Could not compact points ledger: {err:?}");
+            }
+        }
+
+        ledger
+    }
+
+    /// Reads the snapshots from the given path without ever writing to it, e.g. while a node is running with it.
+    pub fn read(path: &Path) -> Self {
+        Self {
+            path: None,
+            snapshots: read_snapshots(path)
+                .map(|(snapshots, _)| snapshots)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns the snapshots, oldest first.
+    pub fn snapshots(&self) -> impl Iterator<Item = &PointsSnapshot> {
+        self.snapshots.iter()
+    }
+
+    /// Records the score at the given time, appending it to the file if any.
+    pub fn record(&mut self, at: DateTime<Utc>, score: f64) -> eyre::Result<()> {
+        let snapshot = PointsSnapshot { at, score };
+        self.snapshots.push_back(snapshot);
+
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("could not create {}", parent.display()))?;
+        }
+        let mut line = serde_json::to_string(&snapshot).wrap_err("could not serialize points")?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .wrap_err_with(|| format!("could not write {}", path.display()))
+    }
+
+    /// Returns the points earned from the given time until the latest snapshot.
+    ///
+    /// If the history does not go back that far, it is counted from the oldest snapshot instead.
+    /// Returns `None` if there are no snapshots.
+    pub fn earned_since(&self, since: DateTime<Utc>) -> Option<f64> {
+        let latest = self.snapshots.back()?;
+        let baseline = self
+            .snapshots
+            .iter()
+            .rev()
+            .find(|snapshot| snapshot.at <= since)
+            .or(self.snapshots.front())?;
+
+        Some(latest.score - baseline.score)
+    }
+
+    /// Returns the points earned on each day (in UTC) of the history, oldest first.
+    ///
+    /// The points of a day are counted from the last snapshot of the previous day, if there is one.
+    pub fn daily_earned(&self) -> Vec<(NaiveDate, f64)> {
+        let mut days = Vec::new();
+        let mut previous: Option<f64> = None;
+        let mut snapshots = self.snapshots.iter().peekable();
+        while let Some(snapshot) = snapshots.next() {
+            let day = snapshot.at.date_naive();
+            let start = previous.unwrap_or(snapshot.score);
+            let mut last = snapshot.score;
+            while let Some(next) = snapshots.next_if(|next| next.at.date_naive() == day) {
+                last = next.score;
+            }
+            days.push((day, last - start));
+            previous = Some(last);
+        }
+
+        days
+    }
+
+    /// Writes the snapshots to a new file, via a temporary file so that a crash does not lose them.
+    fn rewrite(&self) -> eyre::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut content = String::new();
+        for snapshot in &self.snapshots {
+            content
+                .push_str(&serde_json::to_string(snapshot).wrap_err("could not serialize points")?);
+            content.push('\n');
+        }
+        write_atomic(path, content)
+    }
+}
+
+/// Reads the recent snapshots from the given path, along with the number of lines in the file.
+///
+/// Returns `None` if the file does not exist or can not be read.
+fn read_snapshots(path: &Path) -> Option<(VecDeque<PointsSnapshot>, usize)> {
+    if !path.exists() {
+        return None;
+    }
+
+    let content = std::fs::read_to_string(path)
+        .inspect_err(|err| log::warn!("Could not read points ledger {}: {err}", path.display()))
+        .ok()?;
+    let oldest = Utc::now() - POINTS_LEDGER_MAX_AGE;
+    let snapshots = content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<PointsSnapshot>(line).ok())
+        .filter(|snapshot| snapshot.at >= oldest)
+        .collect();
+
+    Some((snapshots, content.lines().count()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_points_ledger() {
+        let path = std::env::temp_dir().join(format!("dkn-points-{}.jsonl", uuid::Uuid::now_v7()));
+        let now = Utc::now();
+
+        let mut ledger = PointsLedger::load(Some(path.clone()));
+        assert_eq!(ledger.earned_since(now), None);
+        ledger
+            .record(now - chrono::Duration::days(40), 1.0)
+            .unwrap();
+        ledger
+            .record(now - chrono::Duration::days(8), 10.0)
+            .unwrap();
+        ledger
+            .record(now - chrono::Duration::days(2), 50.0)
+            .unwrap();
+        ledger
+            .record(now - chrono::Duration::hours(12), 70.0)
+            .unwrap();
+        ledger.record(now, 100.0).unwrap();
+
+        // the ledger continues after a restart, without the old snapshots
+        let ledger = PointsLedger::load(Some(path.clone()));
+        assert_eq!(ledger.snapshots.len(), 4);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 4);
+        assert_eq!(
+            ledger.earned_since(now - chrono::Duration::days(1)),
+            Some(50.0)
+        );
+        assert_eq!(
+            ledger.earned_since(now - chrono::Duration::weeks(1)),
+            Some(90.0)
+        );
+        // a longer period than the history is counted from the oldest snapshot
+        assert_eq!(
+            ledger.earned_since(now - chrono::Duration::days(20)),
+            Some(90.0)
+        );
+
+        // each day is counted from the end of the previous one
+        let daily = PointsLedger::read(&path).daily_earned();
+        assert_eq!(
+            daily.first(),
+            Some(&((now - chrono::Duration::days(8)).date_naive(), 0.0))
+        );
+        assert_eq!(daily.iter().map(|(_, earned)| earned).sum::<f64>(), 90.0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
     #[tokio::test]
     async fn test_get_points() {
         let client = DriaPointsClient::new(
@@ -0,0 +1,313 @@
+//! Releases of the compute node, used to check for a newer version & to update the binary in place.
+//!
+//! Each release has a binary per platform named `dkn-compute-binary-{os}-{arch}`, along with its
+//! SHA256 checksum at `{binary}.sha256` and its secp256k1 signature at `{binary}.sig`, where the signature
+//! is hex-encoded in its compact form. The signature is over the SHA256 of the statement
+//! `{binary}|{version}|{os}-{arch}|{checksum}`, so that a signed binary can not be served for another
+//! release or platform, e.g. to downgrade the node.
+
+use dkn_utils::{crypto::sha256hash, SemanticVersion};
+use eyre::{Context, OptionExt, Result};
+use libsecp256k1::{Message, PublicKey, Signature};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Releases API of the compute node repository.
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/firstbatchxyz/dkn-compute-node/releases/latest";
+/// Timeout of the release metadata requests.
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Timeout of the binary download.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// The latest release of the compute node.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Release {
+    /// Tag of the release, like `v0.1.0`.
+    tag_name: String,
+    assets: Vec<ReleaseAsset>,
+}
+
+impl Release {
+    /// Returns the version of the release, parsed from its tag.
+    pub fn version(&self) -> Result<SemanticVersion> {
+        self.tag_name
+            .trim_start_matches('v')
+            .parse()
+            .map_err(|err| eyre::eyre!("could not parse release tag {}: {err}", self.tag_name))
+    }
+
+    /// Returns the download URL of the asset with the given name.
+    fn asset_url(&self, name: &str) -> Result<&str> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .map(|asset| asset.browser_download_url.as_str())
+            .ok_or_else(|| eyre::eyre!("release {} has no asset {name}", self.tag_name))
+    }
+}
+
+/// Returns the latest release.
+pub async fn get_latest_release() -> Result<Release> {
+    client()?
+        .get(LATEST_RELEASE_URL)
+        .timeout(RELEASE_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Release>()
+        .await
+        .wrap_err("could not parse release")
+}
+
+/// Returns the name of the release binary for this platform, if there is one.
+pub fn binary_asset_name() -> Option<String> {
+    let target = binary_target()?;
+    let ext = if cfg!(windows) { ".exe" } else { "" };
+
+    Some(format!("dkn-compute-binary-{target}{ext}"))
+}
+
+/// Returns the platform of the release binaries as `{os}-{arch}`, if there is one for this platform.
+fn binary_target() -> Option<String> {
+    let os = match std::env::consts::OS {
+        "linux" => "linux",
+        "macos" => "macOS",
+        "windows" => "windows",
+        _ => return None,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        _ => return None,
+    };
+
+    Some(format!("{os}-{arch}"))
+}
+
+/// Downloads the binary of the release for this platform, verifies its checksum & signature
+/// with the given public key, and stages it next to the running binary.
+///
+/// The release must be newer than the running version, which is signed along with the checksum.
+///
+/// Returns the path of the staged binary, see [`install_update_and_restart`].
+pub async fn download_update(release: &Release, public_key: &PublicKey) -> Result<PathBuf> {
+    let name = binary_asset_name().ok_or_eyre("there is no release binary for this platform")?;
+    let target = binary_target().ok_or_eyre("there is no release binary for this platform")?;
+    let version = release.version()?;
+    let current = SemanticVersion::from_crate_version();
+    if (version.major, version.minor, version.patch)
+        <= (current.major, current.minor, current.patch)
+    {
+        eyre::bail!("release v{version} is not newer than the running v{current}");
+    }
+
+    let client = client()?;
+    let download = |url: String, timeout: Duration| {
+        let client = client.clone();
+        async move {
+            client
+                .get(&url)
+                .timeout(timeout)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await
+                .wrap_err_with(|| format!("could not download {url}"))
+        }
+    };
+
+    let checksum = download(
+        release.asset_url(&format!("{name}.sha256"))?.to_string(),
+        RELEASE_TIMEOUT,
+    )
+    .await?;
+    let signature = download(
+        release.asset_url(&format!("{name}.sig"))?.to_string(),
+        RELEASE_TIMEOUT,
+    )
+    .await?;
+    let binary = download(release.asset_url(&name)?.to_string(), DOWNLOAD_TIMEOUT).await?;
+
+    verify_binary(
+        &binary,
+        &release_statement(
+            &name,
+            &version,
+            &target,
+            &String::from_utf8_lossy(&checksum),
+        )?,
+        &String::from_utf8_lossy(&signature),
+        public_key,
+    )?;
+
+    // the binary is written to a temporary file first, so that a partially written one is never staged
+    let staged_path = staged_binary_path()?;
+    let temp_path = staged_path.with_extension("update.tmp");
+    std::fs::write(&temp_path, &binary)
+        .wrap_err_with(|| format!("could not write {}", temp_path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&temp_path, std::fs::Permissions::from_mode(0o755))
+            .wrap_err("could not make the binary executable")?;
+    }
+    std::fs::rename(&temp_path, &staged_path)
+        .wrap_err_with(|| format!("could not stage {}", staged_path.display()))?;
+
+    Ok(staged_path)
+}
+
+/// Returns the statement of the release binary with the given name that its signature is over,
+/// see the [module docs](self).
+///
+/// The checksum is expected in the `sha256sum` format, i.e. the hex digest optionally followed by the file name.
+fn release_statement(
+    name: &str,
+    version: &SemanticVersion,
+    target: &str,
+    checksum: &str,
+) -> Result<String> {
+    let checksum = checksum
+        .split_whitespace()
+        .next()
+        .ok_or_eyre("checksum is empty")?
+        .to_lowercase();
+
+    Ok(format!("{name}|{version}|{target}|{checksum}"))
+}
+
+/// Verifies that the binary matches the checksum within the statement, and that the statement is signed by
+/// the given public key.
+fn verify_binary(
+    binary: &[u8],
+    statement: &str,
+    signature: &str,
+    public_key: &PublicKey,
+) -> Result<()> {
+    let expected = statement.rsplit('|').next().unwrap_or_default();
+    if hex::encode(sha256hash(binary)) != expected {
+        eyre::bail!("checksum mismatch, expected {expected}");
+    }
+
+    let signature = hex::decode(signature.trim().trim_start_matches("0x"))
+        .wrap_err("could not decode signature")?;
+    // a recovery id may be appended to the signature, which is not needed here
+    let signature = Signature::parse_standard_slice(&signature[..signature.len().min(64)])
+        .map_err(|err| eyre::eyre!("could not parse signature: {err}"))?;
+    if !libsecp256k1::verify(
+        &Message::parse(&sha256hash(statement)),
+        &signature,
+        public_key,
+    ) {
+        eyre::bail!("signature is not valid for {statement} with the configured public key");
+    }
+
+    Ok(())
+}
+
+/// Replaces the running binary with the staged one, and restarts the node with the same arguments.
+///
+/// On Unix the process is replaced in place, so this only returns if it fails; elsewhere the new
+/// binary is started as a new process and this process exits.
+pub fn install_update_and_restart(staged_path: &Path) -> Result<()> {
+    let current_exe = std::env::current_exe().wrap_err("could not get the running binary")?;
+
+    // a running binary can not be overwritten on Windows, but it can be renamed
+    let previous_path = current_exe.with_extension("old");
+    let _ = std::fs::remove_file(&previous_path);
+    std::fs::rename(&current_exe, &previous_path)
+        .wrap_err_with(|| format!("could not move {}", current_exe.display()))?;
+    if let Err(err) = std::fs::rename(staged_path, &current_exe) {
+        // put the previous binary back, so that the node can still be started
+        let _ = std::fs::rename(&previous_path, &current_exe);
+        return Err(err).wrap_err_with(|| format!("could not replace {}", current_exe.display()));
+    }
+    log::info!(
+        "Restarting with the updated binary at {}",
+        current_exe.display()
+    );
+
+    let mut command = std::process::Command::new(&current_exe);
+    command.args(std::env::args_os().skip(1));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        Err(command.exec()).wrap_err("could not restart the node")
+    }
+
+    #[cfg(not(unix))]
+    {
+        command.spawn().wrap_err("could not restart the node")?;
+        std::process::exit(0);
+    }
+}
+
+/// Returns the path that a downloaded binary is staged at, next to the running binary.
+fn staged_binary_path() -> Result<PathBuf> {
+    let current_exe = std::env::current_exe().wrap_err("could not get the running binary")?;
+    Ok(current_exe.with_extension("update"))
+}
+
+fn client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .build()
+        .wrap_err("could not create release client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libsecp256k1::SecretKey;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_verify_binary() {
+        let secret_key = SecretKey::random(&mut rand::thread_rng());
+        let public_key = PublicKey::from_secret_key(&secret_key);
+
+        let name = "dkn-compute-binary-linux-amd64";
+        let version = SemanticVersion::from_str("0.7.0").unwrap();
+        let binary = b"a new binary";
+        let checksum = format!(
+            "{}  {name}\n",
+            hex::encode(sha256hash(binary)).to_uppercase()
+        );
+        let statement = release_statement(name, &version, "linux-amd64", &checksum).unwrap();
+        assert_eq!(
+            statement,
+            format!(
+                "{name}|0.7.0|linux-amd64|{}",
+                hex::encode(sha256hash(binary))
+            )
+        );
+        let (signature, recid) =
+            libsecp256k1::sign(&Message::parse(&sha256hash(&statement)), &secret_key);
+        let mut signature_bytes = signature.serialize().to_vec();
+        signature_bytes.push(recid.serialize());
+        let signature = hex::encode(signature_bytes);
+
+        assert!(verify_binary(binary, &statement, &signature, &public_key).is_ok());
+        // a tampered binary fails the checksum
+        assert!(verify_binary(b"another binary", &statement, &signature, &public_key).is_err());
+        // a checksum signed by another key fails the signature
+        let other_key = PublicKey::from_secret_key(&SecretKey::random(&mut rand::thread_rng()));
+        assert!(verify_binary(binary, &statement, &signature, &other_key).is_err());
+        // the signature of a binary is not valid for another version or platform
+        for (version, target) in [("0.6.0", "linux-amd64"), ("0.7.0", "linux-arm64")] {
+            let version = SemanticVersion::from_str(version).unwrap();
+            let statement = release_statement(name, &version, target, &checksum).unwrap();
+            assert!(verify_binary(binary, &statement, &signature, &public_key).is_err());
+        }
+    }
+}
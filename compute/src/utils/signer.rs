@@ -0,0 +1,238 @@
+use async_trait::async_trait;
+use dkn_utils::crypto::{sha256hash_many, sign_digests};
+use dkn_utils::DriaMessage;
+use eyre::{Context, Result};
+use libsecp256k1::{Message, PublicKey, RecoveryId, SecretKey, Signature};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::time::Duration;
+
+/// Signs the messages of the node with the wallet, so that the wallet key can be kept elsewhere.
+#[async_trait]
+pub trait Signer: Send + Sync {
+    /// Returns the public key of the wallet.
+    fn public_key(&self) -> PublicKey;
+
+    /// Signs the given 32-byte digest, returning the signature along with its recovery id.
+    async fn sign(&self, digest: [u8; 32]) -> Result<(Signature, RecoveryId)>;
+
+    /// Signs each of the given digests, e.g. for many results at once.
+    ///
+    /// Signs them one by one by default, which signers with a cheaper batch should override.
+    async fn sign_many(&self, digests: &[[u8; 32]]) -> Result<Vec<(Signature, RecoveryId)>> {
+        let mut signatures = Vec::with_capacity(digests.len());
+        for digest in digests {
+            signatures.push(self.sign(*digest).await?);
+        }
+        Ok(signatures)
+    }
+}
+
+/// Signs the given messages at once, over the digests of their signed data, i.e. their payloads along with
+/// their timestamps & TTLs if any, see [`DriaMessage::signed_data`].
+pub async fn sign_messages(signer: &dyn Signer, messages: &mut [DriaMessage]) -> Result<()> {
+    let digests = sha256hash_many(messages.iter().map(DriaMessage::signed_data));
+    let signatures = signer.sign_many(&digests).await?;
+    for (message, (signature, recovery_id)) in messages.iter_mut().zip(signatures) {
+        message.set_signature(&signature, &recovery_id);
+    }
+
+    Ok(())
+}
+
+/// Signs with a secret key in memory.
+#[derive(Clone)]
+pub struct LocalSigner {
+    secret_key: SecretKey,
+    public_key: PublicKey,
+}
+
+impl LocalSigner {
+    pub fn new(secret_key: SecretKey) -> Self {
+        Self {
+            public_key: PublicKey::from_secret_key(&secret_key),
+            secret_key,
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for LocalSigner {
+    fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    async fn sign(&self, digest: [u8; 32]) -> Result<(Signature, RecoveryId)> {
+        Ok(libsecp256k1::sign(
+            &Message::parse(&digest),
+            &self.secret_key,
+        ))
+    }
+
+    async fn sign_many(&self, digests: &[[u8; 32]]) -> Result<Vec<(Signature, RecoveryId)>> {
+        Ok(sign_digests(&self.secret_key, digests))
+    }
+}
+
+/// Signs through a remote signer service, e.g. one in front of a hardware wallet.
+///
+/// The digest is posted to the URL as `{"digest": "0x..."}`, and the service responds with
+/// `{"signature": "0x...", "recoveryId": 0}` where the signature is the 64-byte `r || s`.
+#[derive(Clone)]
+pub struct RemoteSigner {
+    url: String,
+    /// Bearer token sent to the service, if any.
+    token: Option<String>,
+    public_key: PublicKey,
+    client: reqwest::Client,
+}
+
+#[derive(Serialize)]
+struct SignRequest {
+    digest: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignResponse {
+    signature: String,
+    recovery_id: u8,
+}
+
+impl RemoteSigner {
+    /// Time allowed for the service to sign, which may include a confirmation on the device.
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new(url: String, token: Option<String>, public_key: PublicKey) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Self::TIMEOUT)
+            .build()
+            .wrap_err("could not create remote signer client")?;
+
+        Ok(Self {
+            url,
+            token,
+            public_key,
+            client,
+        })
+    }
+
+    /// Creates the remote signer given by `DKN_REMOTE_SIGNER_URL`, with the wallet public key
+    /// in `DKN_REMOTE_SIGNER_PUBLIC_KEY` & an optional `DKN_REMOTE_SIGNER_TOKEN`.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(url) = env::var("DKN_REMOTE_SIGNER_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+        else {
+            return Ok(None);
+        };
+
+        let public_key = env::var("DKN_REMOTE_SIGNER_PUBLIC_KEY")
+            .wrap_err("DKN_REMOTE_SIGNER_PUBLIC_KEY must be set for the remote signer")?;
+        let public_key = hex::decode(public_key.trim().trim_start_matches("0x"))
+            .ok()
+            .and_then(|public_key| PublicKey::parse_slice(&public_key, None).ok())
+            .ok_or_else(|| eyre::eyre!("could not parse DKN_REMOTE_SIGNER_PUBLIC_KEY"))?;
+        let token = env::var("DKN_REMOTE_SIGNER_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty());
+
+        Self::new(url.trim().to_string(), token, public_key).map(Some)
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    async fn sign(&self, digest: [u8; 32]) -> Result<(Signature, RecoveryId)> {
+        let mut request = self.client.post(&self.url).json(&SignRequest {
+            digest: format!("0x{}", hex::encode(digest)),
+        });
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response: SignResponse = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .wrap_err("could not reach the remote signer")?
+            .json()
+            .await
+            .wrap_err("could not parse the remote signer response")?;
+
+        let mut signature = hex::decode(response.signature.trim_start_matches("0x"))
+            .ok()
+            .and_then(|signature| Signature::parse_standard_slice(&signature).ok())
+            .ok_or_else(|| eyre::eyre!("remote signer returned an invalid signature"))?;
+        let mut recovery_id = RecoveryId::parse(response.recovery_id)
+            .map_err(|_| eyre::eyre!("remote signer returned an invalid recovery id"))?;
+
+        // high-s signatures are rejected by the RPC, so they are normalized to their low-s twin
+        if signature.s.is_high() {
+            signature.s = -signature.s;
+            recovery_id = RecoveryId::parse(recovery_id.serialize() ^ 1)
+                .expect("recovery id is 0 or 1 after flipping");
+        }
+
+        // a signature of another key would be rejected by the RPC, so it is caught here instead
+        let recovered = libsecp256k1::recover(&Message::parse(&digest), &signature, &recovery_id)
+            .map_err(|_| eyre::eyre!("could not recover the remote signature"))?;
+        if recovered != self.public_key {
+            eyre::bail!("remote signer signed with another key than DKN_REMOTE_SIGNER_PUBLIC_KEY");
+        }
+
+        Ok((signature, recovery_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dkn_utils::SemanticVersion;
+
+    #[tokio::test]
+    async fn test_local_signer() {
+        let secret_key = SecretKey::random(&mut rand::thread_rng());
+        let signer = LocalSigner::new(secret_key);
+
+        let version = SemanticVersion {
+            major: 0,
+            minor: 1,
+            patch: 0,
+        };
+        let mut message = DriaMessage::new_unsigned(b"hello", "topic", "dria".to_string(), version);
+        let (signature, recovery_id) = signer.sign(message.signing_digest()).await.unwrap();
+        message.set_signature(&signature, &recovery_id);
+
+        // same as signing with the key directly
+        let expected =
+            DriaMessage::new_signed(b"hello", "topic", "dria".to_string(), &secret_key, version);
+        assert_eq!(message.signature, expected.signature);
+        assert_eq!(message.recover_public_key().unwrap(), signer.public_key());
+
+        // a batch is signed the same way
+        let signatures = signer.sign_many(&[message.signing_digest()]).await.unwrap();
+        assert_eq!(signatures, vec![(signature, recovery_id)]);
+    }
+
+    #[tokio::test]
+    async fn test_sign_messages_with_ttl() {
+        let signer = LocalSigner::new(SecretKey::random(&mut rand::thread_rng()));
+        let mut messages = ["a", "b", "c"]
+            .map(|data| {
+                DriaMessage::new_unsigned(data, "topic", "dria".to_string(), Default::default())
+                    .with_ttl(Duration::from_secs(60))
+            })
+            .to_vec();
+        sign_messages(&signer, &mut messages).await.unwrap();
+
+        // the signatures are over the timestamps & the TTLs as well
+        for message in &messages {
+            assert_eq!(message.recover_public_key().unwrap(), signer.public_key());
+        }
+    }
+}
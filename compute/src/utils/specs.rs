@@ -1,7 +1,7 @@
 use dkn_executor::Model;
 use dkn_p2p::libp2p::PeerId;
 use dkn_utils::{
-    payloads::{SpecModelPerformance, Specs},
+    payloads::{LifetimeStats, SpecModelPerformance, SpecModelState, Specs},
     SemanticVersion,
 };
 use std::collections::HashMap;
@@ -52,6 +52,19 @@ impl SpecCollector {
         }
     }
 
+    /// Updates the models & their performances, e.g. when the configuration is reloaded.
+    pub fn set_models(
+        &mut self,
+        models: Vec<String>,
+        model_perf: HashMap<Model, SpecModelPerformance>,
+    ) {
+        self.models = models;
+        self.model_perf = model_perf
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+    }
+
     /// Returns the selected refresh kinds. It is important to ignore
     /// process values here because it will consume a lot of file-descriptors.
     #[inline(always)]
@@ -61,7 +74,12 @@ impl SpecCollector {
             .with_memory(MemoryRefreshKind::everything())
     }
 
-    pub async fn collect(&mut self) -> Specs {
+    /// Collects the specs, along with the given load states of the models.
+    pub async fn collect(
+        &mut self,
+        model_state: HashMap<Model, SpecModelState>,
+        lifetime: LifetimeStats,
+    ) -> Specs {
         self.system.refresh_specifics(Self::get_refresh_specifics());
 
         Specs {
@@ -77,6 +95,15 @@ impl SpecCollector {
             model_perf: self.model_perf.clone(),
             exec_platform: Some(self.exec_platform.clone()),
             peer_id: Some(self.peer_id.clone()),
+            model_state: Some(
+                model_state
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v))
+                    .collect(),
+            ),
+            lifetime: Some(lifetime),
+            key_rotation: None,
+            bls_public_key: None,
             // gpus: self.gpus.clone(),
         }
     }
@@ -101,7 +128,12 @@ mod tests {
             "testing".to_string(),
             PeerId::random(),
         );
-        let specs = spec_collector.collect().await;
+        let specs = spec_collector
+            .collect(
+                HashMap::from_iter([(Model::Gemma3_4b, SpecModelState::Warm)]),
+                LifetimeStats::default(),
+            )
+            .await;
         assert!(specs.total_mem > 0);
         assert!(specs.free_mem > 0);
         assert!(specs.num_cpus.is_some());
@@ -113,6 +145,10 @@ mod tests {
         assert_eq!(specs.model_perf.len(), 2);
         assert_eq!(specs.version, "4.5.1");
         assert_eq!(specs.exec_platform, Some("testing".to_string()));
+        assert_eq!(
+            specs.model_state.as_ref().unwrap().get("gemma3:4b"),
+            Some(&SpecModelState::Warm)
+        );
 
         // should be serializable to JSON
         assert!(serde_json::to_string_pretty(&specs).is_ok())
@@ -0,0 +1,76 @@
+//! Export of the task spans over OpenTelemetry (OTLP/HTTP), enabled with the `otlp` feature.
+//!
+//! Each task has a `task` span with its `task_id`, `row_id`, `model` and `provider`, and its
+//! stages `intake`, `queue`, `execution` and `response` are recorded as child spans, so that
+//! the slow stages can be seen in a trace viewer like Jaeger.
+//!
+//! Without a configured endpoint the spans are not recorded at all.
+
+use eyre::Result;
+
+/// Keeps the span exporter alive, and flushes the remaining spans when dropped.
+#[must_use = "spans are not exported once the guard is dropped"]
+pub struct TelemetryGuard {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::TracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(err) = provider.shutdown() {
+                log::warn!("Could not flush the remaining spans: {err:?}");
+            }
+        }
+    }
+}
+
+/// Initializes the export of the spans to the given OTLP/HTTP endpoint, e.g. `http://localhost:4318`,
+/// if any.
+///
+/// Must be called within a Tokio runtime, as spans are exported in batches in the background.
+#[cfg(feature = "otlp")]
+pub fn init_telemetry(endpoint: Option<&str>) -> Result<TelemetryGuard> {
+    use eyre::Context;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let Some(endpoint) = endpoint else {
+        return Ok(TelemetryGuard { provider: None });
+    };
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()
+        .wrap_err("could not create span exporter")?;
+    let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_resource(opentelemetry_sdk::Resource::new([
+            KeyValue::new("service.name", "dkn-compute"),
+            KeyValue::new("service.version", crate::DRIA_COMPUTE_NODE_VERSION),
+        ]))
+        .build();
+
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("dkn-compute"));
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))
+        .wrap_err("could not set tracing subscriber")?;
+    log::info!("Exporting task spans to {endpoint}");
+
+    Ok(TelemetryGuard {
+        provider: Some(provider),
+    })
+}
+
+/// Initializes the export of the spans, which requires the `otlp` feature.
+#[cfg(not(feature = "otlp"))]
+pub fn init_telemetry(endpoint: Option<&str>) -> Result<TelemetryGuard> {
+    if endpoint.is_some() {
+        log::warn!("Exporting spans requires the `otlp` feature, which is not enabled.");
+    }
+
+    Ok(TelemetryGuard {})
+}
@@ -1,8 +1,16 @@
 use colored::Colorize;
-use dkn_executor::{DriaExecutor, Model, TaskBody};
-use dkn_p2p::libp2p::request_response::ResponseChannel;
-use dkn_utils::payloads::TaskStats;
+use dkn_executor::quorum::{
+    judge_task, majority_vote, parse_judge_verdict, QuorumStrategy, TaskQuorum,
+};
+use dkn_executor::verify::verification_task;
+use dkn_executor::{DriaExecutor, Model, ModelProvider, PromptError, TaskBody};
+use dkn_p2p::libp2p::request_response::{InboundRequestId, ResponseChannel};
+use dkn_utils::payloads::{TaskProgress, TaskStats};
+use dkn_utils::DriaMessageEncoding;
+use std::collections::{HashMap, VecDeque};
 use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tracing::Instrument;
 use uuid::Uuid;
 
 /// A metadata object that is kept aside while the worker is doing its job.
@@ -12,10 +20,20 @@ pub struct TaskWorkerMetadata {
     pub model: Model,
     pub task_id: String,
     pub file_id: Uuid,
+    /// Public keys that the result is encrypted for, given by the request; it is in plaintext if empty.
+    pub result_public_keys: Vec<libsecp256k1::PublicKey>,
+    /// Session that the request was encrypted within, which the response is encrypted within as well.
+    pub session_id: Option<Uuid>,
+    /// Encoding of the request, which the response is encoded with as well.
+    pub encoding: DriaMessageEncoding,
+    /// Request that is responded to through `channel`.
+    pub request_id: InboundRequestId,
     /// If for any reason this object is dropped before `channel` is responded to,
     /// the task will be lost and the channel will be abruptly closed, causing an error on
     /// both the responder and the requester side, likely with an `OmissionError`.
     pub channel: ResponseChannel<Vec<u8>>,
+    /// Span of the task, which its stages are recorded under.
+    pub span: tracing::Span,
 }
 
 pub struct TaskWorkerInput {
@@ -24,8 +42,57 @@ pub struct TaskWorkerInput {
     // actual consumed input
     pub executor: DriaExecutor,
     pub task: TaskBody,
+    /// Executors for the additional models of a quorum task (including its judge), empty otherwise.
+    pub quorum_executors: HashMap<Model, DriaExecutor>,
     // piggybacked metadata
     pub stats: TaskStats,
+    /// Progress updates are sent here, if the node reports them.
+    pub progress_tx: Option<mpsc::Sender<TaskWorkerProgress>>,
+    /// Span of the task, which its stages are recorded under.
+    pub span: tracing::Span,
+    /// Span of the task waiting for the worker, closed once its execution starts.
+    pub queued: tracing::Span,
+}
+
+/// A progress update of a task, sent by the worker while executing it.
+pub struct TaskWorkerProgress {
+    // used as identifier for metadata
+    pub row_id: Uuid,
+    pub progress: TaskProgress,
+}
+
+/// Reports the progress of a single task, counting its steps.
+struct TaskProgressReporter {
+    row_id: Uuid,
+    progress_tx: Option<mpsc::Sender<TaskWorkerProgress>>,
+    current_step: usize,
+    total_steps: usize,
+}
+
+impl TaskProgressReporter {
+    /// Sends the progress without waiting, progress updates are dropped if the channel is full.
+    fn report(&self, progress: TaskProgress) {
+        if let Some(progress_tx) = &self.progress_tx {
+            let update = TaskWorkerProgress {
+                row_id: self.row_id,
+                progress,
+            };
+            if let Err(err) = progress_tx.try_send(update) {
+                log::debug!("Could not report progress of {}: {err}", self.row_id);
+            }
+        }
+    }
+
+    /// Marks a step as completed, reported only for tasks with several steps.
+    fn step(&mut self) {
+        self.current_step += 1;
+        if self.total_steps > 1 {
+            self.report(TaskProgress::Step {
+                current: self.current_step,
+                total: self.total_steps,
+            });
+        }
+    }
 }
 
 pub struct TaskWorkerOutput {
@@ -47,6 +114,56 @@ pub struct TaskWorker {
     // TODO: batch size must be defined here
 }
 
+/// Limits on the number of tasks within a batch, overall & for each provider.
+struct BatchLimits {
+    /// Number of tasks in a batch, which is the largest of the limits.
+    total: usize,
+    /// Limit of the providers without an override.
+    default: usize,
+    providers: HashMap<ModelProvider, usize>,
+}
+
+impl BatchLimits {
+    fn new(batch_size: usize, provider_batch_sizes: HashMap<ModelProvider, usize>) -> Self {
+        Self {
+            total: provider_batch_sizes
+                .values()
+                .copied()
+                .fold(batch_size, usize::max),
+            default: batch_size,
+            providers: provider_batch_sizes,
+        }
+    }
+
+    /// Returns the limit of the given provider.
+    fn of(&self, provider: &ModelProvider) -> usize {
+        self.providers
+            .get(provider)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Moves the candidates into the batch while their providers are within their limits,
+    /// the rest are deferred to the next batches in the same order.
+    fn fill<T>(
+        &self,
+        batch: &mut Vec<T>,
+        candidates: impl IntoIterator<Item = T>,
+        deferred: &mut VecDeque<T>,
+        provider_of: impl Fn(&T) -> ModelProvider,
+    ) {
+        for candidate in candidates {
+            let provider = provider_of(&candidate);
+            let count = batch.iter().filter(|t| provider_of(t) == provider).count();
+            if batch.len() < self.total && count < self.of(&provider) {
+                batch.push(candidate);
+            } else {
+                deferred.push_back(candidate);
+            }
+        }
+    }
+}
+
 /// Buffer size for task channels (per worker).
 const TASK_RX_CHANNEL_BUFSIZE: usize = 1024;
 
@@ -100,31 +217,47 @@ impl TaskWorker {
     /// It is suitable for task streams that make use of API calls, unlike Ollama-like
     /// tasks that consumes local resources and would not make sense to run in parallel.
     ///
-    /// Batch size must NOT be larger than `MAX_BATCH_SIZE`, otherwise will panic.
-    pub async fn run_batch(&mut self, batch_size: usize) {
+    /// A provider within `provider_batch_sizes` has at most that many tasks in a batch instead of `batch_size`,
+    /// and its tasks beyond that are deferred to the next batches.
+    ///
+    /// Batch sizes must NOT be larger than `MAX_BATCH_SIZE`, otherwise will panic.
+    pub async fn run_batch(
+        &mut self,
+        batch_size: usize,
+        provider_batch_sizes: HashMap<ModelProvider, usize>,
+    ) {
+        let limits = BatchLimits::new(batch_size, provider_batch_sizes);
         assert!(
-            batch_size <= Self::MAX_BATCH_SIZE,
+            limits.total <= Self::MAX_BATCH_SIZE,
             "Batch size must not be larger than {}",
             Self::MAX_BATCH_SIZE
         );
+        let provider_of = |input: &TaskWorkerInput| input.task.model.provider();
+        let mut deferred = VecDeque::new();
 
         loop {
             let mut tasks = Vec::new();
 
+            // tasks deferred by the previous batches come first
+            let pending = std::mem::take(&mut deferred);
+            limits.fill(&mut tasks, pending, &mut deferred, provider_of);
+
             // get tasks in batch from the channel, we enter the loop if:
             // (1) there are no tasks, or,
             // (2) there are tasks less than the batch size and the channel is not empty
-            while tasks.is_empty() || (tasks.len() < batch_size && !self.task_rx.is_empty()) {
+            while tasks.is_empty() || (tasks.len() < limits.total && !self.task_rx.is_empty()) {
                 log::info!(
                     "Worker is waiting for tasks ({} < {})",
                     tasks.len(),
-                    batch_size
+                    limits.total
                 );
-                let limit = batch_size - tasks.len();
-                match self.task_rx.recv_many(&mut tasks, limit).await {
+                let mut received = Vec::new();
+                let limit = limits.total - tasks.len();
+                match self.task_rx.recv_many(&mut received, limit).await {
                     // 0 tasks returned means that the channel is closed
                     0 => return self.shutdown(),
                     _ => {
+                        limits.fill(&mut tasks, received, &mut deferred, provider_of);
                         // wait a small amount of time to allow for more tasks to be sent into the channel
                         tokio::time::sleep(std::time::Duration::from_millis(256)).await;
                     }
@@ -134,7 +267,7 @@ impl TaskWorker {
             // process the batch
             let num_tasks = tasks.len();
             debug_assert!(
-                num_tasks <= batch_size,
+                num_tasks <= limits.total,
                 "number of tasks cant be larger than batch size"
             );
             debug_assert!(num_tasks != 0, "number of tasks cant be zero");
@@ -223,9 +356,68 @@ impl TaskWorker {
     pub async fn execute(
         (mut input, publish_tx): (TaskWorkerInput, &mpsc::Sender<TaskWorkerOutput>),
     ) {
+        drop(std::mem::replace(&mut input.queued, tracing::Span::none()));
+        let span = tracing::info_span!(parent: &input.span, "execution");
+        TaskWorker::execute_task(input, publish_tx)
+            .instrument(span)
+            .await
+    }
+
+    async fn execute_task(mut input: TaskWorkerInput, publish_tx: &mpsc::Sender<TaskWorkerOutput>) {
         let batchable = input.task.is_batchable();
+        let verification = input.task.verify.then(|| input.task.clone());
+        let mut progress = TaskProgressReporter {
+            row_id: input.row_id,
+            progress_tx: input.progress_tx,
+            current_step: 0,
+            total_steps: input.task.num_executions(),
+        };
+        progress.report(TaskProgress::Executing);
+
         input.stats = input.stats.record_execution_started_at();
-        let result = input.executor.execute(input.task).await;
+        let result = match input.task.quorum.take() {
+            Some(quorum) => {
+                TaskWorker::execute_quorum(
+                    input.executor.clone(),
+                    input.quorum_executors,
+                    input.task,
+                    quorum,
+                    &mut progress,
+                )
+                .await
+            }
+            None => {
+                let result = input.executor.execute(input.task).await;
+                progress.step();
+                result
+            }
+        };
+        let result = match (result, verification) {
+            (Ok(output), Some(task)) => {
+                // verification is done by the model of the task, even for quorums
+                let verified = input
+                    .executor
+                    .execute(verification_task(&task, &output))
+                    .await;
+                progress.step();
+                match verified {
+                    Ok(verified) if !verified.trim().is_empty() => {
+                        // TODO: will get better token count from the executor
+                        input.stats = input.stats.record_verification_token_count(verified.len());
+                        Ok(verified)
+                    }
+                    Ok(_) => {
+                        log::warn!("Verification returned an empty output, using the original.");
+                        Ok(output)
+                    }
+                    Err(err) => {
+                        log::warn!("Verification failed, using the original output: {err}");
+                        Ok(output)
+                    }
+                }
+            }
+            (result, _) => result,
+        };
         input.stats = input.stats.record_execution_ended_at();
 
         let output = TaskWorkerOutput {
@@ -239,6 +431,103 @@ impl TaskWorker {
             log::error!("Error sending task result: {err}");
         }
     }
+
+    /// Executes the task on its own model and the quorum models, and merges the successful outputs
+    /// into one w.r.t the quorum strategy.
+    ///
+    /// The models of batchable providers are executed concurrently, while the others (e.g. Ollama)
+    /// are executed one after another meanwhile, as they can not handle parallel requests.
+    ///
+    /// Fails only if all models fail, in which case the first error is returned.
+    async fn execute_quorum(
+        executor: DriaExecutor,
+        mut quorum_executors: HashMap<Model, DriaExecutor>,
+        task: TaskBody,
+        quorum: TaskQuorum,
+        progress: &mut TaskProgressReporter,
+    ) -> Result<String, PromptError> {
+        // fan-out to all models, keeping track of their order
+        let mut executions = JoinSet::new();
+        let mut sequential = Vec::new();
+        let models = std::iter::once(task.model).chain(quorum.models.iter().copied());
+        for (idx, model) in models.enumerate() {
+            let executor = match idx {
+                0 => executor.clone(),
+                _ => match quorum_executors.get(&model) {
+                    Some(executor) => executor.clone(),
+                    None => {
+                        log::warn!("No executor for quorum model {model}, skipping it.");
+                        continue;
+                    }
+                },
+            };
+            let mut task = task.clone();
+            task.model = model;
+            if task.is_batchable() {
+                executions.spawn(async move { (idx, executor.execute(task).await) });
+            } else {
+                sequential.push((idx, executor, task));
+            }
+        }
+
+        let mut results = Vec::new();
+        for (idx, executor, task) in sequential {
+            results.push((idx, executor.execute(task).await));
+            progress.step();
+        }
+        while let Some(result) = executions.join_next().await {
+            match result {
+                Ok(result) => results.push(result),
+                Err(err) => log::error!("Quorum execution panicked: {err}"),
+            }
+            progress.step();
+        }
+        results.sort_by_key(|(idx, _)| *idx);
+
+        let mut outputs = Vec::new();
+        let mut first_err = None;
+        for (_, result) in results {
+            match result {
+                Ok(output) => outputs.push(output),
+                Err(err) => {
+                    log::warn!("Quorum execution failed: {err}");
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+        if outputs.is_empty() {
+            return Err(first_err.expect("quorum has at least one model"));
+        }
+
+        // aggregate the outputs into one
+        let majority_idx = majority_vote(&outputs).expect("outputs are not empty");
+        let chosen_idx = match quorum.strategy {
+            QuorumStrategy::Majority => majority_idx,
+            QuorumStrategy::Judge { model } => {
+                let Some(judge) = quorum_executors.remove(&model) else {
+                    log::warn!("No executor for judge model {model}, using majority vote.");
+                    return Ok(outputs.swap_remove(majority_idx));
+                };
+
+                let verdict = judge.execute(judge_task(&task, model, &outputs)).await;
+                progress.step();
+                match verdict {
+                    Ok(verdict) => {
+                        parse_judge_verdict(&verdict, outputs.len()).unwrap_or_else(|| {
+                            log::warn!("Could not parse judge verdict, using majority vote.");
+                            majority_idx
+                        })
+                    }
+                    Err(err) => {
+                        log::warn!("Judge failed, using majority vote: {err}");
+                        majority_idx
+                    }
+                }
+            }
+        };
+
+        Ok(outputs.swap_remove(chosen_idx))
+    }
 }
 
 #[cfg(test)]
@@ -246,6 +535,36 @@ mod tests {
     use super::*;
     use dkn_executor::{DriaExecutor, Model};
 
+    #[test]
+    fn test_batch_limits() {
+        let limits = BatchLimits::new(3, HashMap::from([(ModelProvider::Wasm, 1)]));
+        assert_eq!(limits.total, 3);
+
+        // tasks beyond the limit of their provider are deferred in order
+        let mut batch = Vec::new();
+        let mut deferred = VecDeque::new();
+        let candidates = [
+            (1, ModelProvider::Wasm),
+            (2, ModelProvider::Wasm),
+            (3, ModelProvider::External),
+            (4, ModelProvider::Wasm),
+        ];
+        limits.fill(&mut batch, candidates, &mut deferred, |(_, p)| *p);
+        assert_eq!(
+            batch,
+            vec![(1, ModelProvider::Wasm), (3, ModelProvider::External)]
+        );
+        assert_eq!(
+            deferred,
+            vec![(2, ModelProvider::Wasm), (4, ModelProvider::Wasm)]
+        );
+
+        // an override larger than the default one increases the total
+        let limits = BatchLimits::new(2, HashMap::from([(ModelProvider::Wasm, 6)]));
+        assert_eq!(limits.total, 6);
+        assert_eq!(limits.of(&ModelProvider::External), 2);
+    }
+
     /// Tests the worker with a single task sent within a batch.
     ///
     /// ## Run command
@@ -267,13 +586,13 @@ mod tests {
 
         // create batch worker
         let worker_handle = tokio::spawn(async move {
-            worker.run_batch(4).await;
+            worker.run_batch(4, HashMap::new()).await;
         });
 
         let num_tasks = 4;
         let model = Model::Llama3_2_1bInstructQ4Km;
         let executor = DriaExecutor::new_from_env(model.provider()).unwrap();
-        let task = TaskBody::new_prompt("Write a poem about Julius Caesar.", model.clone());
+        let task = TaskBody::new_prompt("Write a poem about Julius Caesar.", model);
 
         for i in 0..num_tasks {
             log::info!("Sending task {}", i + 1);
@@ -281,9 +600,13 @@ mod tests {
             let task_input = TaskWorkerInput {
                 executor: executor.clone(),
                 task: task.clone(),
+                quorum_executors: HashMap::new(),
+                progress_tx: None,
                 // dummy variables
                 row_id: Uuid::now_v7(),
                 stats: TaskStats::default(),
+                span: tracing::Span::none(),
+                queued: tracing::Span::none(),
             };
 
             // send task to worker
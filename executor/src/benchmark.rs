@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+/// Measurements of a single task execution, used for benchmarking.
+///
+/// Only the latency is always available, the rest depends on what the provider reports.
+#[derive(Debug, Clone, Default)]
+pub struct BenchmarkSample {
+    /// Total time from sending the task until the whole output is received.
+    pub latency: Duration,
+    /// Time from sending the task until the first token is received.
+    pub time_to_first_token: Option<Duration>,
+    /// Number of tokens generated.
+    pub output_tokens: Option<u64>,
+    /// Time spent generating the output tokens, excluding the prompt evaluation.
+    pub generation_time: Option<Duration>,
+}
+
+impl BenchmarkSample {
+    /// Returns the output tokens per second, if the provider reports the token count.
+    ///
+    /// Uses the generation time if available, otherwise the latency.
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        let tokens = self.output_tokens?;
+        let secs = self.generation_time.unwrap_or(self.latency).as_secs_f64();
+        (secs > 0.0).then(|| tokens as f64 / secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_per_second() {
+        let mut sample = BenchmarkSample {
+            latency: Duration::from_secs(4),
+            ..Default::default()
+        };
+        assert_eq!(sample.tokens_per_second(), None);
+
+        sample.output_tokens = Some(100);
+        assert_eq!(sample.tokens_per_second(), Some(25.0));
+
+        sample.generation_time = Some(Duration::from_secs(2));
+        assert_eq!(sample.tokens_per_second(), Some(50.0));
+    }
+}
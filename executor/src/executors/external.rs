@@ -0,0 +1,288 @@
+use dkn_utils::payloads::SpecModelPerformance;
+use eyre::{eyre, Context, Result};
+use rig::completion::{CompletionError, PromptError};
+use rig::message::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+
+use crate::task::message_text;
+use crate::{Model, TaskBody};
+
+/// Default timeout for a single request to the external process.
+const DEFAULT_EXTERNAL_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A request written to the external process, as a single line of JSON.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ExternalRequest<'a> {
+    /// Liveness check, any non-error response is accepted.
+    Ping { id: u64 },
+    /// A task to be executed.
+    Task {
+        id: u64,
+        messages: Vec<ExternalMessage<'a>>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct ExternalMessage<'a> {
+    role: &'a str,
+    content: String,
+}
+
+/// A response read from the external process, as a single line of JSON.
+#[derive(Debug, Deserialize)]
+struct ExternalResponse {
+    id: u64,
+    #[serde(default)]
+    result: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// A running external process along with its standard I/O.
+struct ExternalProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+/// Executes tasks with an external executable that is registered by the operator, so that
+/// custom task types can be served without recompiling the node.
+///
+/// The process is spawned lazily & kept alive across tasks, and it talks JSON-over-stdio,
+/// one object per line:
+///
+/// - The node writes `{ "type": "task", "id": number, "messages": { role: string, content: string }[] }`
+///   to its `stdin`, or `{ "type": "ping", "id": number }` to check that it is alive.
+/// - The process writes `{ "id": number, "result": string }` or `{ "id": number, "error": string }` to its `stdout`.
+///
+/// Requests are sent one at a time. If the process exits, or it does not respond within the timeout,
+/// it is killed and a new one is spawned for the next request. Its `stderr` is inherited by the node.
+#[derive(Clone)]
+pub struct ExternalClient {
+    /// Program to execute.
+    program: String,
+    /// Arguments to the program.
+    args: Vec<String>,
+    /// Timeout for a single request.
+    timeout: Duration,
+    /// The running process, shared among the clones of this client.
+    process: Arc<Mutex<Option<ExternalProcess>>>,
+    /// Request id counter, to match the responses with requests.
+    next_id: Arc<AtomicU64>,
+}
+
+impl ExternalClient {
+    /// Creates a new external client for the given program & arguments.
+    pub fn new(program: impl Into<String>, args: Vec<String>, timeout: Duration) -> Self {
+        Self {
+            program: program.into(),
+            args,
+            timeout,
+            process: Arc::new(Mutex::new(None)),
+            next_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Looks at the environment variables for the command and the timeout.
+    ///
+    /// The command is required, and is split by whitespace into the program & its arguments.
+    /// If the timeout is not found, defaults to `DEFAULT_EXTERNAL_TIMEOUT`.
+    pub fn from_env() -> Result<Self, std::env::VarError> {
+        let command = env::var("EXTERNAL_EXECUTOR_COMMAND")?;
+        let mut command = command.split_whitespace().map(String::from);
+        let program = command.next().ok_or(std::env::VarError::NotPresent)?;
+
+        let timeout = env::var("EXTERNAL_EXECUTOR_TIMEOUT_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_EXTERNAL_TIMEOUT);
+
+        Ok(Self::new(program, command.collect(), timeout))
+    }
+
+    pub async fn execute(&self, task: TaskBody) -> Result<String, PromptError> {
+        let mut messages = Vec::new();
+        if let Some(preamble) = task.preamble {
+            messages.push(ExternalMessage {
+                role: "system",
+                content: preamble,
+            });
+        }
+        for message in task
+            .chat_history
+            .iter()
+            .chain(std::iter::once(&task.prompt))
+        {
+            messages.push(ExternalMessage {
+                role: match message {
+                    Message::User { .. } => "user",
+                    Message::Assistant { .. } => "assistant",
+                },
+                content: message_text(message),
+            });
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.request(&ExternalRequest::Task { id, messages }, id)
+            .await
+            .map_err(|err| {
+                PromptError::CompletionError(CompletionError::ProviderError(format!("{err:#}")))
+            })
+    }
+
+    /// Checks that the process can be spawned & responds to a ping.
+    pub async fn check(
+        &self,
+        models: &mut HashSet<Model>,
+    ) -> Result<HashMap<Model, SpecModelPerformance>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.request(&ExternalRequest::Ping { id }, id)
+            .await
+            .wrap_err_with(|| format!("could not ping {}", self.program))?;
+
+        Ok(models
+            .iter()
+            .map(|model| (*model, SpecModelPerformance::Passed))
+            .collect())
+    }
+
+    /// Sends a request to the process & waits for its response, spawning the process if needed.
+    ///
+    /// On timeout or I/O failure, the process is killed so that a fresh one is used for the next request.
+    async fn request(&self, request: &ExternalRequest<'_>, id: u64) -> Result<String> {
+        let mut process = self.process.lock().await;
+        if process.is_none() {
+            *process = Some(self.spawn()?);
+        }
+        let running = process.as_mut().expect("process is spawned");
+
+        let result = tokio::time::timeout(self.timeout, Self::roundtrip(running, request, id))
+            .await
+            .unwrap_or_else(|_| Err(eyre!("timed out after {:?}", self.timeout)));
+
+        // an error response from the process itself does not require a restart
+        match result {
+            Ok(response) => response.map_err(|err| eyre!("{} error: {err}", self.program)),
+            Err(err) => {
+                if let Some(mut failed) = process.take() {
+                    if let Err(err) = failed.child.kill().await {
+                        log::warn!("Could not kill {}: {err}", self.program);
+                    }
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Writes the request & reads lines until the response with the matching id.
+    ///
+    /// The outer result is for I/O failures, the inner one is the response of the process.
+    async fn roundtrip(
+        process: &mut ExternalProcess,
+        request: &ExternalRequest<'_>,
+        id: u64,
+    ) -> Result<Result<String, String>> {
+        let mut line = serde_json::to_string(request).wrap_err("could not serialize request")?;
+        line.push('\n');
+        process
+            .stdin
+            .write_all(line.as_bytes())
+            .await
+            .wrap_err("could not write request")?;
+        process.stdin.flush().await?;
+
+        loop {
+            let line = process
+                .stdout
+                .next_line()
+                .await
+                .wrap_err("could not read response")?
+                .ok_or_else(|| eyre!("process exited"))?;
+
+            match serde_json::from_str::<ExternalResponse>(&line) {
+                Ok(response) if response.id == id => {
+                    return Ok(match response.error {
+                        Some(err) => Err(err),
+                        None => Ok(response.result.unwrap_or_default()),
+                    });
+                }
+                Ok(response) => log::warn!("Ignoring response with unexpected id {}", response.id),
+                Err(err) => log::warn!("Ignoring invalid response line: {err}"),
+            }
+        }
+    }
+
+    /// Spawns the process with piped `stdin` & `stdout`.
+    fn spawn(&self) -> Result<ExternalProcess> {
+        log::info!("Spawning external executor: {}", self.program);
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .wrap_err_with(|| format!("could not spawn {}", self.program))?;
+
+        let stdin = child.stdin.take().ok_or_else(|| eyre!("no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| eyre!("no stdout"))?;
+
+        Ok(ExternalProcess {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Responds with the number of lines it has read so far.
+    const COUNTER_SCRIPT: &str = r#"n=0
+while IFS= read -r line; do
+  n=$((n+1))
+  id=$(echo "$line" | sed 's/.*"id":\([0-9]*\).*/\1/')
+  echo "{\"id\":$id,\"result\":\"$n\"}"
+done"#;
+
+    fn shell_client(script: &str, timeout: Duration) -> ExternalClient {
+        ExternalClient::new("sh", vec!["-c".into(), script.into()], timeout)
+    }
+
+    #[tokio::test]
+    async fn test_external_roundtrip() {
+        let client = shell_client(COUNTER_SCRIPT, Duration::from_secs(5));
+        let mut models = HashSet::from_iter([Model::External]);
+        client.check(&mut models).await.expect("should ping");
+
+        // the same process is reused across tasks
+        let task = TaskBody::new_prompt("hello", Model::External);
+        let result = client.execute(task.clone()).await.unwrap();
+        assert_eq!(result, "2");
+        let result = client.execute(task).await.unwrap();
+        assert_eq!(result, "3");
+    }
+
+    #[tokio::test]
+    async fn test_external_timeout_restart() {
+        let client = shell_client("read -r line; sleep 10", Duration::from_millis(200));
+        let task = TaskBody::new_prompt("hello", Model::External);
+
+        let err = client.execute(task).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert!(client.process.lock().await.is_none());
+    }
+}
@@ -1,10 +1,18 @@
+use crate::benchmark::BenchmarkSample;
 use crate::{Model, ModelProvider, TaskBody};
-use dkn_utils::payloads::SpecModelPerformance;
+use dkn_utils::payloads::{SpecModelPerformance, SpecModelState};
 use rig::completion::PromptError;
 use std::collections::{HashMap, HashSet};
+use std::time::Instant;
 
 mod ollama;
-use ollama::OllamaClient;
+pub use ollama::OllamaClient;
+
+mod wasm;
+pub use wasm::WasmClient;
+
+mod external;
+pub use external::ExternalClient;
 
 // mod openai;
 // use openai::OpenAIClient;
@@ -19,6 +27,8 @@ use ollama::OllamaClient;
 #[derive(Clone)]
 pub enum DriaExecutor {
     Ollama(OllamaClient),
+    Wasm(WasmClient),
+    External(ExternalClient),
     // OpenAI(OpenAIClient),
     // Gemini(GeminiClient),
     // OpenRouter(OpenRouterClient),
@@ -26,19 +36,32 @@ pub enum DriaExecutor {
 
 impl DriaExecutor {
     /// Creates a new executor for the given provider using the API key in the environment variables.
-    pub fn new_from_env(provider: ModelProvider) -> Result<Self, std::env::VarError> {
+    pub fn new_from_env(provider: ModelProvider) -> eyre::Result<Self> {
         match provider {
-            ModelProvider::Ollama => OllamaClient::from_env().map(DriaExecutor::Ollama),
+            ModelProvider::Ollama => Ok(OllamaClient::from_env().map(DriaExecutor::Ollama)?),
+            ModelProvider::Wasm => WasmClient::from_env().map(DriaExecutor::Wasm),
+            ModelProvider::External => Ok(ExternalClient::from_env().map(DriaExecutor::External)?),
             // ModelProvider::OpenAI => OpenAIClient::from_env().map(DriaExecutor::OpenAI),
             // ModelProvider::Gemini => GeminiClient::from_env().map(DriaExecutor::Gemini),
             // ModelProvider::OpenRouter => OpenRouterClient::from_env().map(DriaExecutor::OpenRouter),
         }
     }
 
+    /// Returns the provider of this executor.
+    pub fn provider(&self) -> ModelProvider {
+        match self {
+            DriaExecutor::Ollama(_) => ModelProvider::Ollama,
+            DriaExecutor::Wasm(_) => ModelProvider::Wasm,
+            DriaExecutor::External(_) => ModelProvider::External,
+        }
+    }
+
     /// Executes the given task using the appropriate provider.
     pub async fn execute(&self, task: TaskBody) -> Result<String, PromptError> {
         match self {
             DriaExecutor::Ollama(provider) => provider.execute(task).await,
+            DriaExecutor::Wasm(provider) => provider.execute(task).await,
+            DriaExecutor::External(provider) => provider.execute(task).await,
             // DriaExecutor::OpenAI(provider) => provider.execute(task).await,
             // DriaExecutor::Gemini(provider) => provider.execute(task).await,
             // DriaExecutor::OpenRouter(provider) => provider.execute(task).await,
@@ -54,15 +77,90 @@ impl DriaExecutor {
     ) -> eyre::Result<HashMap<Model, SpecModelPerformance>> {
         match self {
             DriaExecutor::Ollama(provider) => provider.check(models).await,
+            DriaExecutor::Wasm(provider) => provider.check(models).await,
+            DriaExecutor::External(provider) => provider.check(models).await,
             // DriaExecutor::OpenAI(provider) => provider.check(models).await,
             // DriaExecutor::Gemini(provider) => provider.check(models).await,
             // DriaExecutor::OpenRouter(provider) => provider.check(models).await,
         }
     }
 
+    /// Loads the given models into memory, so that the first task for each does not have to wait.
+    ///
+    /// Only applies to locally-hosted providers, failures are logged and ignored.
+    pub async fn warmup(&self, models: impl Iterator<Item = &Model>) {
+        match self {
+            DriaExecutor::Ollama(provider) => {
+                for model in models {
+                    if let Err(err) = provider.warmup(model).await {
+                        log::warn!("Could not warm up {model}: {err:?}");
+                    }
+                }
+            }
+            // wasm modules are compiled per task, nothing to warm up
+            DriaExecutor::Wasm(_) => {}
+            // external processes manage their own state
+            DriaExecutor::External(_) => {}
+        }
+    }
+
+    /// Executes the task like [`Self::execute`] while measuring its performance, see [`BenchmarkSample`].
+    ///
+    /// Ollama reports its token counts & durations along with the output, other providers are timed around
+    /// their execution.
+    pub async fn benchmark(&self, task: TaskBody) -> Result<BenchmarkSample, PromptError> {
+        match self {
+            DriaExecutor::Ollama(provider) => provider
+                .execute_measured(task)
+                .await
+                .map(|(_, sample)| sample),
+            DriaExecutor::Wasm(_) | DriaExecutor::External(_) => {
+                let started_at = Instant::now();
+                self.execute(task).await?;
+                Ok(BenchmarkSample {
+                    latency: started_at.elapsed(),
+                    ..Default::default()
+                })
+            }
+        }
+    }
+
+    /// Returns the memory used by the given model in bytes, if the provider reports it.
+    pub async fn model_memory(&self, model: &Model) -> Option<u64> {
+        match self {
+            DriaExecutor::Ollama(provider) => match provider.model_memory(model).await {
+                Ok(memory) => memory,
+                Err(err) => {
+                    log::warn!("Could not get memory usage of {model}: {err:#}");
+                    None
+                }
+            },
+            // wasm modules run within the node, and external processes are opaque
+            DriaExecutor::Wasm(_) | DriaExecutor::External(_) => None,
+        }
+    }
+
+    /// Returns the load state of the given model, which is cold if it could not be checked.
+    pub async fn model_state(&self, model: &Model) -> SpecModelState {
+        match self {
+            DriaExecutor::Ollama(provider) => match provider.is_warm(model).await {
+                Ok(true) => SpecModelState::Warm,
+                Ok(false) => SpecModelState::Cold,
+                Err(err) => {
+                    log::warn!("Could not get load state of {model}: {err:#}");
+                    SpecModelState::Cold
+                }
+            },
+            // API-based providers, wasm & external processes are always warm
+            DriaExecutor::Wasm(_) | DriaExecutor::External(_) => SpecModelState::Warm,
+        }
+    }
+
     pub fn name(&self) -> String {
         match self {
             DriaExecutor::Ollama(_) => ModelProvider::Ollama.to_string(),
+            DriaExecutor::Wasm(_) => ModelProvider::Wasm.to_string(),
+            DriaExecutor::External(_) => ModelProvider::External.to_string(),
             // DriaExecutor::OpenAI(_) => ModelProvider::OpenAI.to_string(),
             // DriaExecutor::Gemini(_) => ModelProvider::Gemini.to_string(),
             // DriaExecutor::OpenRouter(_) => ModelProvider::OpenRouter.to_string(),
@@ -1,12 +1,13 @@
 use dkn_utils::payloads::SpecModelPerformance;
 use eyre::{Context, Result};
 use ollama_rs::generation::completion::request::GenerationRequest;
-use rig::completion::{Chat, PromptError};
+use rig::completion::{AssistantContent, Completion, CompletionError, PromptError};
 use rig::providers::ollama;
 use std::collections::HashMap;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{collections::HashSet, env};
 
+use crate::benchmark::BenchmarkSample;
 use crate::{Model, TaskBody};
 
 const DEFAULT_OLLAMA_HOST: &str = "http://127.0.0.1";
@@ -17,6 +18,13 @@ const PERFORMANCE_TIMEOUT: Duration = Duration::from_secs(600);
 /// Minimum tokens per second (TPS) for checking model performance during a generation.
 const PERFORMANCE_MIN_TPS: f64 = 0.0;
 
+/// A model loaded in memory, as reported by Ollama.
+#[derive(serde::Deserialize)]
+struct RunningModel {
+    name: String,
+    size: u64,
+}
+
 /// Ollama-specific configurations.
 #[derive(Clone)]
 pub struct OllamaClient {
@@ -41,6 +49,11 @@ impl OllamaClient {
         }
     }
 
+    /// Creates a new Ollama client at `DEFAULT_OLLAMA_HOST` and `DEFAULT_OLLAMA_PORT`, with auto-pull enabled.
+    pub fn with_defaults() -> Self {
+        Self::new(DEFAULT_OLLAMA_HOST, DEFAULT_OLLAMA_PORT, true)
+    }
+
     /// Looks at the environment variables for Ollama host and port.
     ///
     /// If not found, defaults to `DEFAULT_OLLAMA_HOST` and `DEFAULT_OLLAMA_PORT`.
@@ -69,14 +82,111 @@ impl OllamaClient {
     }
 
     pub async fn execute(&self, task: TaskBody) -> Result<String, PromptError> {
+        self.execute_measured(task).await.map(|(output, _)| output)
+    }
+
+    /// Executes the task along with its measurements, where the token counts & durations are as reported
+    /// by Ollama, see [`BenchmarkSample`].
+    ///
+    /// The output is not streamed, so the time to first token is the time until the prompt is evaluated,
+    /// including the load of the model.
+    pub async fn execute_measured(
+        &self,
+        task: TaskBody,
+    ) -> Result<(String, BenchmarkSample), PromptError> {
         let mut model = self.client.agent(&task.model.to_string());
+        if let Some(params) = task.additional_params() {
+            model = model.additional_params(params);
+        }
         if let Some(preamble) = task.preamble {
             model = model.preamble(&preamble);
         }
+        if let Some(temperature) = task.temperature {
+            model = model.temperature(temperature);
+        }
+        if let Some(max_tokens) = task.max_tokens {
+            model = model.max_tokens(max_tokens);
+        }
 
         let agent = model.build();
 
-        agent.chat(task.prompt, task.chat_history).await
+        let started_at = Instant::now();
+        let response = agent
+            .completion(task.prompt, task.chat_history)
+            .await?
+            .send()
+            .await?;
+        let latency = started_at.elapsed();
+        // there are no tools, so the output is the text of the response as for `Chat`
+        let output = match response.choice.first() {
+            AssistantContent::Text(text) => text.text,
+            AssistantContent::ToolCall(tool_call) => {
+                return Err(PromptError::CompletionError(
+                    CompletionError::ResponseError(format!(
+                        "unexpected tool call {}",
+                        tool_call.function.name
+                    )),
+                ))
+            }
+        };
+
+        let raw = response.raw_response;
+        let sample = BenchmarkSample {
+            latency,
+            time_to_first_token: raw
+                .prompt_eval_duration
+                .map(|eval| Duration::from_nanos(raw.load_duration.unwrap_or_default() + eval)),
+            output_tokens: raw.eval_count,
+            generation_time: raw.eval_duration.map(Duration::from_nanos),
+        };
+
+        Ok((output, sample))
+    }
+
+    /// Returns the model if it is loaded in memory, as reported by Ollama with `/api/ps`,
+    /// which accounts for its keep-alive & the models it evicted to make room for others.
+    async fn running_model(&self, model: &Model) -> Result<Option<RunningModel>> {
+        #[derive(serde::Deserialize)]
+        struct RunningModels {
+            models: Vec<RunningModel>,
+        }
+
+        let url = format!("{}api/ps", self.ollama_rs_client.url_str());
+        let running = reqwest::get(&url)
+            .await
+            .wrap_err("could not get running models")?
+            .json::<RunningModels>()
+            .await
+            .wrap_err("could not parse running models")?;
+
+        Ok(running
+            .models
+            .into_iter()
+            .find(|running| running.name == model.to_string()))
+    }
+
+    /// Returns the memory used by the model if it is loaded, as reported by Ollama.
+    pub async fn model_memory(&self, model: &Model) -> Result<Option<u64>> {
+        Ok(self.running_model(model).await?.map(|running| running.size))
+    }
+
+    /// Loads the model into memory with an empty generation, so that the first task
+    /// does not have to wait for the model to be loaded.
+    pub async fn warmup(&self, model: &Model) -> Result<()> {
+        log::info!("Warming up Ollama for {model}");
+
+        // an empty prompt makes Ollama load the model without generating anything
+        self.ollama_rs_client
+            .generate(GenerationRequest::new(model.to_string(), ""))
+            .await
+            .wrap_err("could not warm up model")?;
+
+        Ok(())
+    }
+
+    /// Returns whether the model is loaded in memory, as reported by Ollama.
+    pub async fn is_warm(&self, model: &Model) -> Result<bool> {
+        Ok(self.running_model(model).await?.is_some())
     }
 
     /// Check if requested models exist in Ollama & test them using a dummy prompt.
@@ -126,6 +236,11 @@ impl OllamaClient {
                 }
             }
 
+            // load the model into memory so that the first task does not pay for it
+            if let Err(err) = self.warmup(model).await {
+                log::warn!("Could not warm up {model}: {err:?}");
+            }
+
             // test its performance
             // let perf = self.measure_tps_with_warmup(model).await;
             let perf = SpecModelPerformance::PassedWithTPS(100.0);
@@ -0,0 +1,327 @@
+use dkn_utils::payloads::SpecModelPerformance;
+use eyre::{eyre, Context, Result};
+use rig::completion::{CompletionError, PromptError};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::time::Duration;
+use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::task::message_text;
+use crate::{Model, TaskBody};
+
+/// Default amount of fuel given to a module per task, roughly one unit per instruction.
+const DEFAULT_WASM_FUEL_LIMIT: u64 = 1_000_000_000;
+/// Default maximum linear memory a module can use, in bytes.
+const DEFAULT_WASM_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+/// Maximum number of elements in the table of a module, which is not limited by the memory limit.
+const WASM_TABLE_ELEMENTS_LIMIT: usize = 10_000;
+/// Timeout of compiling & running a module, where a running module is interrupted once it is reached.
+const WASM_TIMEOUT: Duration = Duration::from_secs(60);
+/// Interval of the epoch ticks, i.e. the granularity of the timeout of a running module.
+const WASM_EPOCH_TICK: Duration = Duration::from_secs(1);
+/// Maximum size of a module, as its compilation is not limited by the fuel nor the timeout.
+const WASM_MODULE_SIZE_LIMIT: usize = 8 * 1024 * 1024;
+/// Maximum size of the output of a module, in bytes.
+const WASM_OUTPUT_SIZE_LIMIT: usize = 4 * 1024 * 1024;
+
+/// A trivial module used to check that the engine can compile & run modules,
+/// given as WebAssembly text format.
+const WASM_CHECK_MODULE: &str = r#"(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32) i32.const 0)
+  (func (export "run") (param i32 i32) (result i64)
+    local.get 1
+    i64.extend_i32_u))"#;
+
+/// Executes WebAssembly modules within a sandbox.
+///
+/// The modules are not given any imports, so they have no access to the host (e.g. filesystem or network),
+/// and they run with limited fuel, memory & time, so that a malicious or buggy module can not hog the node.
+///
+/// A module can be given in binary or text format, and must export the following:
+///
+/// - `memory`: its linear memory.
+/// - `alloc(len: i32) -> i32`: allocates `len` bytes and returns a pointer to them, where the input will be written.
+/// - `run(ptr: i32, len: i32) -> i64`: runs over the input, and returns the output pointer & length packed
+///   as `(ptr << 32) | len`, where the output is expected to be UTF-8.
+#[derive(Clone)]
+pub struct WasmClient {
+    /// Underlying engine, shared among the clones of this client.
+    engine: Engine,
+    /// Amount of fuel given to each task.
+    fuel_limit: u64,
+    /// Maximum linear memory for each task, in bytes.
+    memory_limit: usize,
+    /// Maximum duration that a module can run for, before it is interrupted.
+    timeout: Duration,
+}
+
+impl WasmClient {
+    /// Creates a new WASM client with the given limits.
+    ///
+    /// The epoch of the engine is ticked by a background thread, which stops once the engine is dropped.
+    pub fn new(fuel_limit: u64, memory_limit: usize) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true).epoch_interruption(true);
+        let engine = Engine::new(&config).map_err(wasm_err("could not create WASM engine"))?;
+
+        let engine_weak = engine.weak();
+        std::thread::Builder::new()
+            .name("wasm-epoch".to_string())
+            .spawn(move || loop {
+                std::thread::sleep(WASM_EPOCH_TICK);
+                match engine_weak.upgrade() {
+                    Some(engine) => engine.increment_epoch(),
+                    None => break,
+                }
+            })
+            .wrap_err("could not spawn WASM epoch thread")?;
+
+        Ok(Self {
+            engine,
+            fuel_limit,
+            memory_limit,
+            timeout: WASM_TIMEOUT,
+        })
+    }
+
+    /// Creates a new WASM client with `DEFAULT_WASM_FUEL_LIMIT` and `DEFAULT_WASM_MEMORY_LIMIT`.
+    pub fn with_defaults() -> Result<Self> {
+        Self::new(DEFAULT_WASM_FUEL_LIMIT, DEFAULT_WASM_MEMORY_LIMIT)
+    }
+
+    /// Looks at the environment variables for the fuel & memory limits.
+    ///
+    /// If not found, defaults to `DEFAULT_WASM_FUEL_LIMIT` and `DEFAULT_WASM_MEMORY_LIMIT`.
+    pub fn from_env() -> Result<Self> {
+        let fuel_limit = env::var("WASM_FUEL_LIMIT")
+            .ok()
+            .and_then(|fuel| fuel.parse().ok())
+            .unwrap_or(DEFAULT_WASM_FUEL_LIMIT);
+        let memory_limit = env::var("WASM_MEMORY_LIMIT")
+            .ok()
+            .and_then(|memory| memory.parse().ok())
+            .unwrap_or(DEFAULT_WASM_MEMORY_LIMIT);
+
+        Self::new(fuel_limit, memory_limit)
+    }
+
+    pub async fn execute(&self, task: TaskBody) -> Result<String, PromptError> {
+        let module = task.module.ok_or_else(|| {
+            PromptError::CompletionError(CompletionError::RequestError(
+                "no module given for the task".into(),
+            ))
+        })?;
+        let input = message_text(&task.prompt);
+
+        self.run_blocking(module, input.into_bytes())
+            .await
+            .map_err(|err| {
+                PromptError::CompletionError(CompletionError::ProviderError(format!("{err:#}")))
+            })
+    }
+
+    /// Compiles & runs the given module over the input like [`Self::run`], in a blocking thread as
+    /// both are CPU-bound.
+    ///
+    /// The module itself is interrupted at the timeout, this only stops waiting for the thread
+    /// in case the compilation takes longer.
+    async fn run_blocking(&self, module: Vec<u8>, input: Vec<u8>) -> Result<String> {
+        let client = self.clone();
+        let handle = tokio::task::spawn_blocking(move || client.run(&module, &input));
+        tokio::time::timeout(self.timeout + WASM_EPOCH_TICK, handle)
+            .await
+            .map_err(|_| eyre!("module timed out after {}s", self.timeout.as_secs()))?
+            .wrap_err("module thread failed")?
+    }
+
+    /// Compiles & runs the given module over the input, within the limits of this client.
+    fn run(&self, module: &[u8], input: &[u8]) -> Result<String> {
+        if module.len() > WASM_MODULE_SIZE_LIMIT {
+            eyre::bail!(
+                "module of {} bytes exceeds the maximum of {WASM_MODULE_SIZE_LIMIT} bytes",
+                module.len()
+            );
+        }
+        let module =
+            Module::new(&self.engine, module).map_err(wasm_err("could not compile module"))?;
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.memory_limit)
+            .instances(1)
+            .memories(1)
+            .tables(1)
+            .table_elements(WASM_TABLE_ELEMENTS_LIMIT)
+            .build();
+        let mut store: Store<StoreLimits> = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store
+            .set_fuel(self.fuel_limit)
+            .map_err(wasm_err("could not set fuel"))?;
+        // the module traps once the epoch is ticked past the deadline
+        store.set_epoch_deadline(
+            (self.timeout.as_millis() / WASM_EPOCH_TICK.as_millis()).max(1) as u64,
+        );
+
+        // an empty linker, any module with imports will fail to instantiate
+        let instance = Linker::new(&self.engine)
+            .instantiate(&mut store, &module)
+            .map_err(wasm_err("could not instantiate module"))?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| eyre!("module does not export memory"))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "alloc")
+            .map_err(wasm_err("could not find alloc"))?;
+        let run = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, "run")
+            .map_err(wasm_err("could not find run"))?;
+
+        // write input to the module memory
+        let input_len = i32::try_from(input.len()).wrap_err("input too large")?;
+        let input_ptr = alloc
+            .call(&mut store, input_len)
+            .map_err(wasm_err("could not allocate input"))?;
+        memory
+            .write(&mut store, input_ptr as u32 as usize, input)
+            .wrap_err("could not write input")?;
+
+        // run & read the output from the module memory
+        let packed = run
+            .call(&mut store, (input_ptr, input_len))
+            .map_err(wasm_err("could not run module"))? as u64;
+        let (output_ptr, output_len) = ((packed >> 32) as usize, packed as u32 as usize);
+        if output_len > WASM_OUTPUT_SIZE_LIMIT {
+            eyre::bail!(
+                "output of {output_len} bytes exceeds the maximum of {WASM_OUTPUT_SIZE_LIMIT} bytes"
+            );
+        }
+        let output = output_ptr
+            .checked_add(output_len)
+            .and_then(|output_end| memory.data(&store).get(output_ptr..output_end))
+            .ok_or_else(|| eyre!("output is out of the module memory"))?;
+
+        String::from_utf8(output.to_vec()).wrap_err("output is not UTF-8")
+    }
+
+    /// Checks that the engine can run modules; there is no performance to measure as
+    /// each module has its own workload.
+    pub async fn check(
+        &self,
+        models: &mut HashSet<Model>,
+    ) -> Result<HashMap<Model, SpecModelPerformance>> {
+        let output = self
+            .run_blocking(WASM_CHECK_MODULE.as_bytes().to_vec(), b"ok".to_vec())
+            .await
+            .wrap_err("could not run the check module")?;
+        if output != "ok" {
+            eyre::bail!("check module returned unexpected output: {output}");
+        }
+
+        Ok(models
+            .iter()
+            .map(|model| (*model, SpecModelPerformance::Passed))
+            .collect())
+    }
+}
+
+/// Returns a mapper from [`wasmtime::Error`] to [`eyre::Report`] with the given context.
+fn wasm_err(context: &'static str) -> impl Fn(wasmtime::Error) -> eyre::Report {
+    move |err| eyre!("{context}: {err:#}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Echoes the input back.
+    const ECHO_MODULE: &str = r#"(module
+      (memory (export "memory") 1)
+      (func (export "alloc") (param i32) (result i32) i32.const 1024)
+      (func (export "run") (param $ptr i32) (param $len i32) (result i64)
+        local.get $ptr
+        i64.extend_i32_u
+        i64.const 32
+        i64.shl
+        local.get $len
+        i64.extend_i32_u
+        i64.or))"#;
+
+    /// Loops forever.
+    const LOOP_MODULE: &str = r#"(module
+      (memory (export "memory") 1)
+      (func (export "alloc") (param i32) (result i32) i32.const 0)
+      (func (export "run") (param i32 i32) (result i64)
+        (loop $l (br $l))
+        i64.const 0))"#;
+
+    /// Requests more memory than allowed at instantiation.
+    const LARGE_MEMORY_MODULE: &str = r#"(module
+      (memory (export "memory") 2048)
+      (func (export "alloc") (param i32) (result i32) i32.const 0)
+      (func (export "run") (param i32 i32) (result i64) i64.const 0))"#;
+
+    /// Returns an output of the maximum length.
+    const LARGE_OUTPUT_MODULE: &str = r#"(module
+      (memory (export "memory") 1)
+      (func (export "alloc") (param i32) (result i32) i32.const 0)
+      (func (export "run") (param i32 i32) (result i64) i64.const 4294967295))"#;
+
+    /// Returns an output that ends past its memory, at `ptr = 65530` with `len = 100`.
+    const OUT_OF_BOUNDS_MODULE: &str = r#"(module
+      (memory (export "memory") 1)
+      (func (export "alloc") (param i32) (result i32) i32.const 0)
+      (func (export "run") (param i32 i32) (result i64) i64.const 281449206906980))"#;
+
+    /// Requests a larger table than allowed at instantiation.
+    const LARGE_TABLE_MODULE: &str = r#"(module
+      (memory (export "memory") 1)
+      (table 1000000 funcref)
+      (func (export "alloc") (param i32) (result i32) i32.const 0)
+      (func (export "run") (param i32 i32) (result i64) i64.const 0))"#;
+
+    #[tokio::test]
+    async fn test_wasm_echo() {
+        let client = WasmClient::with_defaults().unwrap();
+        let mut task = TaskBody::new_prompt("hello wasm", Model::Wasm);
+        task.module = Some(ECHO_MODULE.as_bytes().to_vec());
+
+        let output = client.execute(task).await.expect("should execute");
+        assert_eq!(output, "hello wasm");
+    }
+
+    #[test]
+    fn test_wasm_limits() {
+        let client = WasmClient::new(10_000, DEFAULT_WASM_MEMORY_LIMIT).unwrap();
+
+        // runs out of fuel
+        let err = client.run(LOOP_MODULE.as_bytes(), b"").unwrap_err();
+        assert!(format!("{err:#}").contains("fuel"));
+
+        // exceeds the memory limit (2048 pages = 128 MiB)
+        let err = client.run(LARGE_MEMORY_MODULE.as_bytes(), b"").unwrap_err();
+        assert!(format!("{err:#}").contains("instantiate"));
+
+        // exceeds the table elements limit
+        let err = client.run(LARGE_TABLE_MODULE.as_bytes(), b"").unwrap_err();
+        assert!(format!("{err:#}").contains("instantiate"));
+
+        // returns an output larger than allowed, or out of its memory
+        let err = client.run(LARGE_OUTPUT_MODULE.as_bytes(), b"").unwrap_err();
+        assert!(format!("{err:#}").contains("exceeds"));
+        let err = client
+            .run(OUT_OF_BOUNDS_MODULE.as_bytes(), b"")
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("out of the module memory"));
+    }
+
+    #[tokio::test]
+    async fn test_wasm_timeout() {
+        let mut client = WasmClient::new(u64::MAX, DEFAULT_WASM_MEMORY_LIMIT).unwrap();
+        client.timeout = Duration::from_secs(1);
+
+        // the module is interrupted, rather than left running in its thread
+        let err = client.run(LOOP_MODULE.as_bytes(), b"").unwrap_err();
+        assert!(format!("{err:#}").contains("interrupt"));
+    }
+}
@@ -1,5 +1,5 @@
 mod executors;
-pub use executors::DriaExecutor;
+pub use executors::{DriaExecutor, ExternalClient, OllamaClient, WasmClient};
 
 mod manager;
 pub use manager::DriaExecutorsManager;
@@ -10,6 +10,12 @@ pub use models::{Model, ModelProvider};
 mod task;
 pub use task::{TaskBody, TaskResult};
 
+pub mod quorum;
+
+pub mod verify;
+
+pub mod benchmark;
+
 pub use rig::completion::CompletionModel;
 pub use rig::completion::{CompletionError, PromptError};
 
@@ -1,9 +1,9 @@
-use dkn_utils::payloads::SpecModelPerformance;
+use dkn_utils::payloads::{SpecModelPerformance, SpecModelState};
 
 use crate::{executors::DriaExecutor, Model, ModelProvider};
 use std::collections::{HashMap, HashSet};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct DriaExecutorsManager {
     /// List of all models supported by this node.
     ///
@@ -59,6 +59,35 @@ impl DriaExecutorsManager {
         })
     }
 
+    /// Adds the given executor for the given models, without looking at the environment variables.
+    ///
+    /// Models of another provider are ignored with a warning, and an executor that is already
+    /// added for the same provider is replaced, along with its models.
+    pub fn with_executor(
+        mut self,
+        executor: DriaExecutor,
+        models: impl IntoIterator<Item = Model>,
+    ) -> Self {
+        let provider = executor.provider();
+        let models = models
+            .into_iter()
+            .filter(|model| {
+                let is_supported = model.provider() == provider;
+                if !is_supported {
+                    log::warn!("{model} is not served by {provider}, it will not be supported.");
+                }
+                is_supported
+            })
+            .collect::<HashSet<_>>();
+
+        if let Some((_, previous)) = self.providers.remove(&provider) {
+            self.models.retain(|model| !previous.contains(model));
+        }
+        self.models.extend(models.iter().copied());
+        self.providers.insert(provider, (executor, models));
+        self
+    }
+
     /// Given the model, returns a _cloned_ executor for it.
     ///
     /// If the model's provider is not supported, an error is returned.
@@ -92,6 +121,30 @@ impl DriaExecutorsManager {
         self.models.iter().map(|m| m.to_string()).collect()
     }
 
+    /// Returns the load state of each model in the manager.
+    pub async fn get_model_states(&self) -> HashMap<Model, SpecModelState> {
+        let mut model_states = HashMap::new();
+        for (executor, models) in self.providers.values() {
+            for model in models {
+                model_states.insert(*model, executor.model_state(model).await);
+            }
+        }
+        model_states
+    }
+
+    /// Warms up the models that are cold, e.g. those that were evicted by Ollama after being idle.
+    pub async fn warmup_cold_models(&self) {
+        for (executor, models) in self.providers.values() {
+            let mut cold_models = Vec::new();
+            for model in models {
+                if executor.model_state(model).await == SpecModelState::Cold {
+                    cold_models.push(model);
+                }
+            }
+            executor.warmup(cold_models.into_iter()).await;
+        }
+    }
+
     /// Check if the required compute services are running.
     ///
     /// - If Ollama models are used the task is tested with a simple task with timeout.
@@ -32,6 +32,14 @@ pub enum Model {
     /// [Alibaba's Qwen3 8b](https://ollama.com/library/qwen3:8b)
     #[serde(rename = "qwen3:8b")]
     Qwen3_8b,
+    // WASM
+    /// A custom WebAssembly module that is shipped along with the task, see [`TaskBody::module`](crate::TaskBody::module).
+    #[serde(rename = "wasm")]
+    Wasm,
+    // External
+    /// A custom task type served by the external executable of the operator.
+    #[serde(rename = "external")]
+    External,
     // // OpenAI models
     // /// [OpenAI's GPT-4o](https://platform.openai.com/docs/models#gpt-4o)
     // #[serde(rename = "gpt-4o")]
@@ -138,6 +146,10 @@ impl TryFrom<&str> for Model {
 pub enum ModelProvider {
     #[serde(rename = "ollama")]
     Ollama,
+    #[serde(rename = "wasm")]
+    Wasm,
+    #[serde(rename = "external")]
+    External,
     // #[serde(rename = "openai")]
     // OpenAI,
     // #[serde(rename = "gemini")]
@@ -165,6 +177,10 @@ impl ModelProvider {
         match self {
             // ollama models are not batchable
             ModelProvider::Ollama => false,
+            // wasm modules run in blocking threads of their own
+            ModelProvider::Wasm => true,
+            // external processes handle one request at a time
+            ModelProvider::External => false,
             // // api-based providers are batchable
             // ModelProvider::OpenAI => true,
             // ModelProvider::Gemini => true,
@@ -192,6 +208,10 @@ impl From<&Model> for ModelProvider {
             Model::MistralNemo12b => ModelProvider::Ollama,
             Model::Qwen3_8b => ModelProvider::Ollama,
             Model::Qwen3_32b => ModelProvider::Ollama,
+            // wasm
+            Model::Wasm => ModelProvider::Wasm,
+            // external
+            Model::External => ModelProvider::External,
             // // openai
             // Model::GPT4o => ModelProvider::OpenAI,
             // Model::GPT4oMini => ModelProvider::OpenAI,
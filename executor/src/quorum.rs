@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{Model, TaskBody};
+
+/// Maximum number of models a task can be executed on at once, including its own model.
+pub const MAX_QUORUM_SIZE: usize = 5;
+
+/// A task option to execute the same task on several models, and merge their outputs into one.
+///
+/// The task is executed on its own model along with the models here.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskQuorum {
+    /// Additional models to execute the task on.
+    pub models: Vec<Model>,
+    /// How the outputs are merged.
+    pub strategy: QuorumStrategy,
+}
+
+/// Strategy to pick a single output among the outputs of a quorum.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum QuorumStrategy {
+    /// The most common output wins, ignoring case & surrounding whitespace.
+    /// Ties are broken in favor of the output that came first.
+    Majority,
+    /// A judge model is prompted to pick the best output among the candidates.
+    Judge { model: Model },
+}
+
+impl TaskQuorum {
+    /// Returns all models that are needed to execute the quorum, including the judge.
+    pub fn required_models(&self) -> impl Iterator<Item = &Model> {
+        let judge = match &self.strategy {
+            QuorumStrategy::Majority => None,
+            QuorumStrategy::Judge { model } => Some(model),
+        };
+        self.models.iter().chain(judge)
+    }
+}
+
+/// Returns the index of the most common output, ignoring case & surrounding whitespace.
+///
+/// Returns `None` if there are no outputs.
+pub fn majority_vote(outputs: &[String]) -> Option<usize> {
+    let mut votes: HashMap<String, (usize, usize)> = HashMap::new();
+    for (idx, output) in outputs.iter().enumerate() {
+        votes
+            .entry(output.trim().to_lowercase())
+            .or_insert((idx, 0))
+            .1 += 1;
+    }
+
+    votes
+        .into_values()
+        .max_by(|(idx_a, count_a), (idx_b, count_b)| {
+            // more votes first, earlier index on ties
+            count_a.cmp(count_b).then(idx_b.cmp(idx_a))
+        })
+        .map(|(idx, _)| idx)
+}
+
+/// Creates a task for the judge model that asks for the best output among the candidates,
+/// see [`parse_judge_verdict`] for its answer.
+pub fn judge_task(task: &TaskBody, judge: Model, outputs: &[String]) -> TaskBody {
+    let conversation = task.transcript();
+
+    let mut prompt = format!(
+        "Below is a conversation, followed by {} candidate answers to its last message.\n\n{conversation}",
+        outputs.len()
+    );
+    for (idx, output) in outputs.iter().enumerate() {
+        prompt.push_str(&format!("\n### Candidate {}\n{output}\n", idx + 1));
+    }
+    prompt.push_str("\nReply with the number of the best candidate only.");
+
+    let mut judge_task = TaskBody::new_prompt(prompt, judge);
+    judge_task.preamble = Some(
+        "You are an impartial judge that picks the most correct & helpful answer.".to_string(),
+    );
+    judge_task
+}
+
+/// Parses the answer of the judge model, returning the index of the chosen output.
+///
+/// Returns `None` if the answer does not contain a valid candidate number.
+pub fn parse_judge_verdict(verdict: &str, num_outputs: usize) -> Option<usize> {
+    verdict
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|number| number.parse::<usize>().ok())
+        .find(|number| (1..=num_outputs).contains(number))
+        .map(|number| number - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_majority_vote() {
+        let outputs = ["Paris", "paris ", "Lyon", "Lyon", "PARIS"].map(String::from);
+        assert_eq!(majority_vote(&outputs), Some(0));
+
+        // ties are broken by the first output
+        let outputs = ["Lyon", "Paris", "Paris", "Lyon"].map(String::from);
+        assert_eq!(majority_vote(&outputs), Some(0));
+
+        assert_eq!(majority_vote(&[]), None);
+    }
+
+    #[test]
+    fn test_judge_verdict() {
+        assert_eq!(parse_judge_verdict("2", 3), Some(1));
+        assert_eq!(parse_judge_verdict("Candidate 3 is the best.", 3), Some(2));
+        assert_eq!(parse_judge_verdict("Candidate 7, or rather 1.", 3), Some(0));
+        assert_eq!(parse_judge_verdict("None of them.", 3), None);
+    }
+
+    #[test]
+    fn test_quorum_deserialization() {
+        let quorum: TaskQuorum = serde_json::from_value(serde_json::json!({
+            "models": ["gemma3:4b", "qwen3:8b"],
+            "strategy": { "type": "judge", "model": "gemma3:12b" }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            quorum.required_models().collect::<Vec<_>>(),
+            vec![&Model::Gemma3_4b, &Model::Qwen3_8b, &Model::Gemma3_12b]
+        );
+    }
+}
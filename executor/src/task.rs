@@ -1,9 +1,11 @@
+use base64::prelude::*;
 use rig::{
     completion::{CompletionRequest, PromptError},
-    message::Message,
+    message::{AssistantContent, Message, UserContent},
 };
 use serde::{Deserialize, Deserializer};
 
+use crate::quorum::{TaskQuorum, MAX_QUORUM_SIZE};
 use crate::{Model, ModelProvider};
 
 /// A future that represents the result of a task execution, of any provider.
@@ -24,6 +26,17 @@ pub type TaskResult = Result<String, PromptError>;
 /// - If the first message is a system message, it will be stored in the `preamble` field.
 /// - The last message must be a user message, and it will be stored in the `prompt` field.
 /// - All other intermediate messages will be stored in the `chat_history` field.
+///
+/// For the `wasm` model, a base64-encoded `"module": string` is expected as well, which is executed
+/// with the contents of the prompt as its input.
+///
+/// An optional `"quorum": { models: string[], strategy: { type: "majority" } | { type: "judge", model: string } }`
+/// can be given to execute the task on several models & merge their outputs, see [`TaskQuorum`].
+/// An optional `"seed": number` is passed to the providers that support seeded sampling, and an optional
+/// `"verify": boolean` makes the model verify & repair its own output before responding.
+///
+/// Generation can be controlled per task with the optional `"temperature": number`, `"top_p": number`,
+/// `"max_tokens": number` and `"stop": string[]` fields, which are applied w.r.t the provider.
 #[derive(Debug, Clone)]
 pub struct TaskBody {
     /// An optional system prompt.
@@ -34,6 +47,23 @@ pub struct TaskBody {
     pub chat_history: Vec<Message>,
     /// The model to use for the task.
     pub model: Model,
+    /// The WebAssembly module to execute, only given for [`Model::Wasm`].
+    pub module: Option<Vec<u8>>,
+    /// Additional models to execute this task on, along with how to merge their outputs.
+    pub quorum: Option<TaskQuorum>,
+    /// Seed for sampling, so that the output can be reproduced by providers that support it.
+    pub seed: Option<u64>,
+    /// Whether the model should verify & repair its own output with a second pass,
+    /// see [`verification_task`](crate::verify::verification_task).
+    pub verify: bool,
+    /// Sampling temperature, the provider default is used if `None`.
+    pub temperature: Option<f64>,
+    /// Nucleus sampling probability mass, the provider default is used if `None`.
+    pub top_p: Option<f64>,
+    /// Maximum number of tokens to generate, the provider default is used if `None`.
+    pub max_tokens: Option<u64>,
+    /// Sequences at which the generation stops.
+    pub stop: Vec<String>,
 }
 
 impl TaskBody {
@@ -44,26 +74,99 @@ impl TaskBody {
             prompt: Message::user(prompt),
             chat_history: Vec::default(),
             model,
+            module: None,
+            quorum: None,
+            seed: None,
+            verify: false,
+            temperature: None,
+            top_p: None,
+            max_tokens: None,
+            stop: Vec::default(),
         }
     }
 
     /// Returns whether this task can be executed in parallel, w.r.t to its model.
     pub fn is_batchable(&self) -> bool {
-        self.model.provider() != ModelProvider::Ollama
+        self.model.provider().is_batchable()
+    }
+
+    /// Returns the number of model executions this task takes, including its quorum & verification.
+    pub fn num_executions(&self) -> usize {
+        let quorum_executions = self
+            .quorum
+            .as_ref()
+            .map(|quorum| quorum.required_models().count())
+            .unwrap_or_default();
+        let verify_executions = usize::from(self.verify);
+
+        1 + quorum_executions + verify_executions
+    }
+
+    /// Returns the whole conversation of this task as plain text, one `Role: content` line per message.
+    ///
+    /// Used to embed the task within another prompt, e.g. for judging or verifying its outputs.
+    pub(crate) fn transcript(&self) -> String {
+        let mut transcript = String::new();
+        if let Some(preamble) = &self.preamble {
+            transcript.push_str(&format!("System: {preamble}\n"));
+        }
+        for message in self
+            .chat_history
+            .iter()
+            .chain(std::iter::once(&self.prompt))
+        {
+            let role = match message {
+                Message::User { .. } => "User",
+                Message::Assistant { .. } => "Assistant",
+            };
+            transcript.push_str(&format!("{role}: {}\n", message_text(message)));
+        }
+
+        transcript
+    }
+
+    /// Returns the provider-specific parameters for this task, such as seeding
+    /// and the generation parameters that are not covered by [`CompletionRequest`], if any.
+    pub fn additional_params(&self) -> Option<serde_json::Value> {
+        let mut params = serde_json::Map::new();
+        match self.model.provider() {
+            // Ollama reuses the prompt cache of a loaded model by itself,
+            // and these params are merged into its `options`
+            ModelProvider::Ollama => {
+                if let Some(seed) = self.seed {
+                    params.insert("seed".to_string(), seed.into());
+                }
+                if let Some(top_p) = self.top_p {
+                    params.insert("top_p".to_string(), top_p.into());
+                }
+                // Ollama ignores `max_tokens`, it is called `num_predict` instead
+                if let Some(max_tokens) = self.max_tokens {
+                    params.insert("num_predict".to_string(), max_tokens.into());
+                }
+                if !self.stop.is_empty() {
+                    params.insert("stop".to_string(), self.stop.clone().into());
+                }
+            }
+            // wasm modules are not prompted at all, and external processes receive the messages as is
+            ModelProvider::Wasm | ModelProvider::External => {}
+        }
+
+        (!params.is_empty()).then_some(serde_json::Value::Object(params))
     }
 }
 
 impl From<TaskBody> for CompletionRequest {
     fn from(task_body: TaskBody) -> Self {
+        let additional_params = task_body.additional_params();
         CompletionRequest {
             prompt: task_body.prompt,
             preamble: task_body.preamble,
             chat_history: task_body.chat_history,
             documents: Vec::default(),
             tools: Vec::default(),
-            temperature: None,
-            max_tokens: None,
-            additional_params: None,
+            temperature: task_body.temperature,
+            max_tokens: task_body.max_tokens,
+            additional_params,
         }
     }
 }
@@ -85,6 +188,22 @@ impl<'de> Deserialize<'de> for TaskBody {
         struct RawTaskBody {
             model: String,
             messages: Vec<RawMessage>,
+            #[serde(default)]
+            module: Option<String>,
+            #[serde(default)]
+            quorum: Option<TaskQuorum>,
+            #[serde(default)]
+            seed: Option<u64>,
+            #[serde(default)]
+            verify: bool,
+            #[serde(default)]
+            temperature: Option<f64>,
+            #[serde(default)]
+            top_p: Option<f64>,
+            #[serde(default)]
+            max_tokens: Option<u64>,
+            #[serde(default)]
+            stop: Vec<String>,
         }
 
         let raw = RawTaskBody::deserialize(deserializer)?;
@@ -94,6 +213,42 @@ impl<'de> Deserialize<'de> for TaskBody {
             Error::custom(format!("Model {err_model} is not supported by this node."))
         })?;
 
+        // parse module, which is required only for wasm tasks
+        let module = match (model, raw.module) {
+            (Model::Wasm, Some(module)) => Some(
+                BASE64_STANDARD
+                    .decode(module)
+                    .map_err(|err| Error::custom(format!("Invalid module encoding: {err}")))?,
+            ),
+            (Model::Wasm, None) => return Err(Error::custom("No module found in the task body")),
+            (_, Some(_)) => {
+                return Err(Error::custom(format!(
+                    "Model {model} does not accept a module"
+                )))
+            }
+            (_, None) => None,
+        };
+
+        // ensure the generation parameters are within limits
+        if raw.temperature.is_some_and(|t| !(0.0..=2.0).contains(&t)) {
+            return Err(Error::custom("Temperature must be within [0, 2]"));
+        }
+        if raw.top_p.is_some_and(|p| !(p > 0.0 && p <= 1.0)) {
+            return Err(Error::custom("Top-p must be within (0, 1]"));
+        }
+
+        // ensure the quorum is within limits
+        if let Some(quorum) = &raw.quorum {
+            if quorum.models.len() + 1 > MAX_QUORUM_SIZE {
+                return Err(Error::custom(format!(
+                    "Quorum can have at most {MAX_QUORUM_SIZE} models"
+                )));
+            }
+            if module.is_some() {
+                return Err(Error::custom("Quorum is not supported for modules"));
+            }
+        }
+
         // ensure there are messages
         if raw.messages.is_empty() {
             return Err(Error::custom("No messages found in the task body"));
@@ -135,10 +290,38 @@ impl<'de> Deserialize<'de> for TaskBody {
             prompt,
             chat_history: messages,
             model,
+            module,
+            quorum: raw.quorum,
+            seed: raw.seed,
+            verify: raw.verify,
+            temperature: raw.temperature,
+            top_p: raw.top_p,
+            max_tokens: raw.max_tokens,
+            stop: raw.stop,
         })
     }
 }
 
+/// Returns the concatenated text contents of a message.
+pub(crate) fn message_text(message: &Message) -> String {
+    match message {
+        Message::User { content } => content
+            .iter()
+            .filter_map(|content| match content {
+                UserContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect(),
+        Message::Assistant { content } => content
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -165,4 +348,81 @@ mod tests {
         );
         assert_eq!(task_body.chat_history.len(), 2);
     }
+
+    #[test]
+    fn test_task_body_seed() {
+        let task_body: TaskBody = serde_json::from_value(json!({
+            "model": "gemma3:4b",
+            "seed": 42,
+            "messages": [{"role": "user", "content": "Pick a random number."}]
+        }))
+        .unwrap();
+
+        assert_eq!(task_body.seed, Some(42));
+        assert_eq!(task_body.additional_params(), Some(json!({ "seed": 42 })));
+
+        // no params at all without a seed
+        let task_body = TaskBody::new_prompt("Pick a random number.", Model::Gemma3_4b);
+        assert_eq!(task_body.additional_params(), None);
+    }
+
+    #[test]
+    fn test_task_body_is_batchable() {
+        // tasks are routed to the batch worker as per their provider
+        assert!(!TaskBody::new_prompt("Hi.", Model::Gemma3_4b).is_batchable());
+        assert!(!TaskBody::new_prompt("Hi.", Model::External).is_batchable());
+        assert!(TaskBody::new_prompt("Hi.", Model::Wasm).is_batchable());
+    }
+
+    #[test]
+    fn test_task_body_generation_params() {
+        let task_body: TaskBody = serde_json::from_value(json!({
+            "model": "gemma3:4b",
+            "temperature": 0.2,
+            "top_p": 0.9,
+            "max_tokens": 128,
+            "stop": ["\n\n"],
+            "messages": [{"role": "user", "content": "Write a haiku."}]
+        }))
+        .unwrap();
+
+        assert_eq!(task_body.temperature, Some(0.2));
+        assert_eq!(
+            task_body.additional_params(),
+            Some(json!({ "top_p": 0.9, "num_predict": 128, "stop": ["\n\n"] }))
+        );
+
+        // out of range values are rejected
+        let bad_temperature = serde_json::from_value::<TaskBody>(json!({
+            "model": "gemma3:4b",
+            "temperature": 3.0,
+            "messages": [{"role": "user", "content": "Write a haiku."}]
+        }));
+        assert!(bad_temperature.is_err());
+    }
+
+    #[test]
+    fn test_task_body_wasm_module() {
+        let task_body: TaskBody = serde_json::from_value(json!({
+            "model": "wasm",
+            "module": BASE64_STANDARD.encode("(module)"),
+            "messages": [{"role": "user", "content": "input"}]
+        }))
+        .unwrap();
+        assert_eq!(task_body.model, Model::Wasm);
+        assert_eq!(task_body.module, Some(b"(module)".to_vec()));
+
+        // module is required for wasm, and not accepted otherwise
+        let without_module = serde_json::from_value::<TaskBody>(json!({
+            "model": "wasm",
+            "messages": [{"role": "user", "content": "input"}]
+        }));
+        assert!(without_module.is_err());
+        let with_module = serde_json::from_value::<TaskBody>(json!({
+            "model": "gemma3:4b",
+            "module": BASE64_STANDARD.encode("(module)"),
+            "messages": [{"role": "user", "content": "input"}]
+        }));
+        assert!(with_module.is_err());
+    }
 }
@@ -0,0 +1,45 @@
+use crate::TaskBody;
+
+/// Creates a task that asks the model of the given task to verify its own output against the
+/// instructions of the task, and repair it if needed.
+///
+/// The answer of this task is expected to be the final output as is, see [`TaskBody::verify`].
+pub fn verification_task(task: &TaskBody, output: &str) -> TaskBody {
+    let conversation = task.transcript();
+
+    let prompt = format!(
+        "Below is a conversation, followed by a draft answer to its last message.\n\n{conversation}\n### Draft answer\n{output}\n\n\
+        Check whether the draft answer follows all instructions of the conversation and is correct. \
+        If it does, reply with the draft answer exactly as is. Otherwise, reply with the repaired answer. \
+        Do not add any comments about the review."
+    );
+
+    let mut verification_task = TaskBody::new_prompt(prompt, task.model);
+    verification_task.preamble =
+        Some("You are a careful reviewer that verifies & repairs answers.".to_string());
+    verification_task.seed = task.seed;
+    verification_task
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::message_text;
+    use crate::Model;
+
+    #[test]
+    fn test_verification_task() {
+        let mut task =
+            TaskBody::new_prompt("Answer in one word: capital of France?", Model::Gemma3_4b);
+        task.seed = Some(7);
+
+        let verification = verification_task(&task, "Paris.");
+        assert_eq!(verification.model, task.model);
+        assert_eq!(verification.seed, task.seed);
+        assert!(!verification.verify);
+
+        let prompt = message_text(&verification.prompt);
+        assert!(prompt.contains("User: Answer in one word: capital of France?"));
+        assert!(prompt.contains("### Draft answer\nParis."));
+    }
+}
@@ -0,0 +1,126 @@
+//! Bandwidth accounting of the swarm, recorded by the transport through [`libp2p::metrics`].
+
+use libp2p::metrics::Registry;
+use std::collections::BTreeMap;
+
+use crate::metrics::encode_registry;
+
+/// Name of the bandwidth counter within the encoded registry, as created by `with_bandwidth_metrics`.
+const BANDWIDTH_METRIC_NAME: &str = "libp2p_bandwidth_bytes_total";
+
+/// Bytes received & sent over a transport protocol stack.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BandwidthUsage {
+    pub inbound: u64,
+    pub outbound: u64,
+}
+
+/// Bandwidth usage of the client since it has started.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DriaP2PStats {
+    /// Total bytes over all protocols.
+    pub total: BandwidthUsage,
+    /// Bytes per transport protocol stack, e.g. `/ip4/tcp`.
+    pub protocols: BTreeMap<String, BandwidthUsage>,
+}
+
+impl DriaP2PStats {
+    /// Reads the bandwidth counters from the given registry.
+    pub(crate) fn from_registry(registry: &Registry) -> Self {
+        Self::from_encoded(&encode_registry(registry))
+    }
+
+    /// Parses the bandwidth counters from a registry in text exposition format, where
+    /// each counter is a line like `libp2p_bandwidth_bytes_total{protocols="/ip4/tcp",direction="Inbound"} 42`.
+    fn from_encoded(encoded: &str) -> Self {
+        let mut stats = Self::default();
+        for line in encoded.lines() {
+            let Some(rest) = line.strip_prefix(BANDWIDTH_METRIC_NAME) else {
+                continue;
+            };
+            let Some((labels, value)) = rest
+                .strip_prefix('{')
+                .and_then(|rest| rest.split_once("} "))
+            else {
+                continue;
+            };
+            let Ok(value) = value.trim().parse::<u64>() else {
+                continue;
+            };
+
+            let mut protocols = None;
+            let mut direction = None;
+            for label in labels.split(',') {
+                match label.split_once('=') {
+                    Some(("protocols", v)) => protocols = Some(v.trim_matches('"')),
+                    Some(("direction", v)) => direction = Some(v.trim_matches('"')),
+                    _ => {}
+                }
+            }
+            let Some(protocols) = protocols else {
+                continue;
+            };
+
+            let usage = stats.protocols.entry(protocols.to_string()).or_default();
+            match direction {
+                Some(d) if d.eq_ignore_ascii_case("inbound") => {
+                    usage.inbound += value;
+                    stats.total.inbound += value;
+                }
+                Some(d) if d.eq_ignore_ascii_case("outbound") => {
+                    usage.outbound += value;
+                    stats.total.outbound += value;
+                }
+                _ => {}
+            }
+        }
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_from_registry() {
+        use prometheus_client::metrics::{counter::Counter, family::Family};
+        use prometheus_client::registry::Unit;
+
+        // registered the same way as the bandwidth transport does
+        let mut registry = Registry::default();
+        let family = Family::<Vec<(String, String)>, Counter>::default();
+        registry
+            .sub_registry_with_prefix("libp2p")
+            .register_with_unit(
+                "bandwidth",
+                "Bandwidth usage by direction and transport protocols",
+                Unit::Bytes,
+                family.clone(),
+            );
+        for (protocols, direction, bytes) in [
+            ("/ip4/tcp", "Inbound", 100),
+            ("/ip4/tcp", "Outbound", 40),
+            ("/ip6/tcp", "Inbound", 5),
+        ] {
+            family
+                .get_or_create(&vec![
+                    ("protocols".to_string(), protocols.to_string()),
+                    ("direction".to_string(), direction.to_string()),
+                ])
+                .inc_by(bytes);
+        }
+
+        let stats = DriaP2PStats::from_registry(&registry);
+        assert_eq!(
+            stats.total,
+            BandwidthUsage {
+                inbound: 105,
+                outbound: 40
+            }
+        );
+        assert_eq!(stats.protocols.len(), 2);
+        assert_eq!(stats.protocols["/ip6/tcp"].inbound, 5);
+    }
+}
@@ -1,45 +1,138 @@
 use eyre::Result;
+use libp2p::allow_block_list::{self, AllowedPeers, BlockedPeers};
 use libp2p::identity::{Keypair, PublicKey};
-use libp2p::{identify, request_response, StreamProtocol};
-use std::time::Duration;
+use libp2p::swarm::behaviour::toggle::Toggle;
+use libp2p::PeerId;
+use libp2p::{connection_limits, identify, request_response, StreamProtocol};
 
-use crate::DriaP2PProtocol;
+use crate::codec::{compressed_protocol, DriaCodec};
+use crate::gossipsub::GossipsubBehaviour;
+use crate::hole_punching::{DcutrBehaviour, RelayClientBehaviour};
+use crate::keep_alive;
+use crate::relay_server::{create_relay_server_behaviour, RelayServerBehaviour};
+use crate::stream;
+use crate::{DriaP2PConfig, DriaP2PProtocol};
 
 #[derive(libp2p::swarm::NetworkBehaviour)]
 pub struct DriaBehaviour {
+    pub connection_limits: connection_limits::Behaviour,
+    pub blocked_peers: allow_block_list::Behaviour<BlockedPeers>,
+    /// Only enabled if there are allowed peers in the config.
+    pub allowed_peers: Toggle<allow_block_list::Behaviour<AllowedPeers>>,
+    pub keep_alive: keep_alive::Behaviour,
     pub identify: identify::Behaviour,
-    pub request_response: request_response::cbor::Behaviour<Vec<u8>, Vec<u8>>,
+    pub request_response: request_response::Behaviour<DriaCodec>,
+    pub stream: stream::Behaviour,
+    pub relay_client: RelayClientBehaviour,
+    pub relay_server: RelayServerBehaviour,
+    pub dcutr: DcutrBehaviour,
+    pub gossipsub: GossipsubBehaviour,
+    pub ping: libp2p::ping::Behaviour,
 }
 
 impl DriaBehaviour {
-    pub fn new(key: &Keypair, protocol: &DriaP2PProtocol) -> Self {
+    pub fn new(
+        key: &Keypair,
+        relay_client: RelayClientBehaviour,
+        protocol: &DriaP2PProtocol,
+        config: &DriaP2PConfig,
+    ) -> Self {
         let public_key = key.public();
 
         Self {
+            connection_limits: connection_limits::Behaviour::new(config.connection_limits()),
+            blocked_peers: create_blocked_peers_behaviour(&config.blocked_peers),
+            allowed_peers: config
+                .allowed_peers
+                .as_ref()
+                .map(|peers| create_allowed_peers_behaviour(peers))
+                .into(),
+            keep_alive: keep_alive::Behaviour::new(config.keep_alive_peers.iter().copied()),
+            #[cfg(feature = "hole-punching")]
+            dcutr: libp2p::dcutr::Behaviour::new(public_key.to_peer_id()),
+            #[cfg(not(feature = "hole-punching"))]
+            dcutr: libp2p::swarm::dummy::Behaviour,
             identify: create_identify_behaviour(public_key, protocol.identity()),
-            request_response: create_request_response_behaviour(protocol.request_response()),
+            request_response: create_request_response_behaviour(
+                protocol
+                    .request_response_protocols()
+                    .into_iter()
+                    .flat_map(|(_, protocol)| {
+                        // the compressed variant is preferred, if enabled
+                        let compressed = config.compression.then(|| compressed_protocol(&protocol));
+                        compressed.into_iter().chain([protocol])
+                    }),
+                config,
+            ),
+            // frames of a stream are limited as responses are, as they carry the same outputs
+            stream: stream::Behaviour::new(
+                protocol.stream(),
+                config.max_response_size,
+                config.stream_peers.iter().copied(),
+                config.max_inbound_streams,
+            ),
+            relay_client,
+            relay_server: create_relay_server_behaviour(
+                key.public().to_peer_id(),
+                config.relay_server,
+            ),
+            #[cfg(feature = "gossipsub")]
+            gossipsub: crate::gossipsub::create_gossipsub_behaviour(
+                key,
+                config.gossip_peer_score.as_ref(),
+            ),
+            #[cfg(not(feature = "gossipsub"))]
+            gossipsub: libp2p::swarm::dummy::Behaviour,
+            ping: libp2p::ping::Behaviour::default(),
         }
     }
 }
 
+/// Configures the block-list behaviour, denying connections with the given peers.
+#[inline]
+fn create_blocked_peers_behaviour(peers: &[PeerId]) -> allow_block_list::Behaviour<BlockedPeers> {
+    let mut behaviour = allow_block_list::Behaviour::<BlockedPeers>::default();
+    for peer_id in peers {
+        behaviour.block_peer(*peer_id);
+    }
+    behaviour
+}
+
+/// Configures the allow-list behaviour, denying connections with all peers except the given ones.
+#[inline]
+fn create_allowed_peers_behaviour(peers: &[PeerId]) -> allow_block_list::Behaviour<AllowedPeers> {
+    let mut behaviour = allow_block_list::Behaviour::<AllowedPeers>::default();
+    for peer_id in peers {
+        behaviour.allow_peer(*peer_id);
+    }
+    behaviour
+}
+
 /// Configures the request-response behaviour for the node.
 ///
-/// The protocol supports bytes only.
+/// The protocols support bytes only, with the size limits & timeout of the given config,
+/// where the messages over the compressed protocols are compressed by [`DriaCodec`].
+/// They are given in the order of preference, i.e. the first one is negotiated if the peer supports it.
 #[inline]
 fn create_request_response_behaviour(
-    protocol_name: StreamProtocol,
-) -> request_response::cbor::Behaviour<Vec<u8>, Vec<u8>> {
+    protocols: impl IntoIterator<Item = StreamProtocol>,
+    config: &DriaP2PConfig,
+) -> request_response::Behaviour<DriaCodec> {
     use request_response::{Behaviour, Config, ProtocolSupport};
 
-    const REQUEST_RESPONSE_TIMEOUT: Duration = Duration::from_secs(512);
-
-    Behaviour::new(
-        [(protocol_name, ProtocolSupport::Full)],
-        Config::default().with_request_timeout(REQUEST_RESPONSE_TIMEOUT),
+    Behaviour::with_codec(
+        DriaCodec::new(config.max_request_size, config.max_response_size),
+        protocols
+            .into_iter()
+            .map(|protocol| (protocol, ProtocolSupport::Full)),
+        Config::default().with_request_timeout(config.request_timeout),
     )
 }
 
 /// Configures the Identify behavior to allow nodes to exchange information like supported protocols.
+///
+/// Listen addresses are hidden so that only the reachable ones are advertised,
+/// which are added as external addresses by the client.
 #[inline]
 fn create_identify_behaviour(
     local_public_key: PublicKey,
@@ -48,6 +141,8 @@ fn create_identify_behaviour(
     use identify::{Behaviour, Config};
 
     Behaviour::new(
-        Config::new(protocol_version, local_public_key).with_push_listen_addr_updates(true),
+        Config::new(protocol_version, local_public_key)
+            .with_push_listen_addr_updates(true)
+            .with_hide_listen_addrs(true),
     )
 }
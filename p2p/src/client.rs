@@ -1,17 +1,27 @@
 use eyre::Result;
 use libp2p::futures::StreamExt;
+use libp2p::metrics::Registry;
 use libp2p::swarm::{
     dial_opts::{DialOpts, PeerCondition},
     SwarmEvent,
 };
-use libp2p::{identify, noise, request_response, tcp, yamux};
+use libp2p::{identify, request_response};
+#[cfg(feature = "hole-punching")]
+use libp2p::{noise, yamux};
 use libp2p::{Multiaddr, PeerId, Swarm, SwarmBuilder};
 use libp2p_identity::Keypair;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc, oneshot};
 
 use crate::behaviour::{DriaBehaviour, DriaBehaviourEvent};
-use crate::DriaP2PProtocol;
+use crate::gossipsub::GossipSubscriptions;
+use crate::metrics::{encode_registry, DriaMetrics};
+use crate::ping::RttTracker;
+use crate::{
+    DriaGossipMessage, DriaGossipPeerScore, DriaP2PConfig, DriaP2PEvent, DriaP2PProtocol,
+    DriaP2PStats, HolePunchStats, RelayServerStats,
+};
 
 use super::commands::DriaP2PCommand;
 use super::DriaP2PCommander;
@@ -20,6 +30,10 @@ use super::DriaP2PCommander;
 const COMMAND_CHANNEL_BUFSIZE: usize = 1024;
 /// Buffer size for events channel.
 const MSG_CHANNEL_BUFSIZE: usize = 1024;
+/// Buffer size for the broadcasted client events, per subscriber.
+const EVENTS_CHANNEL_BUFSIZE: usize = 256;
+/// Maximum duration to wait for the queued responses to be sent during shutdown.
+const RESPONSE_FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Request-response message type for Dria protocol, accepts bytes as both request and response.
 ///
@@ -34,60 +48,141 @@ pub struct DriaP2PClient {
     /// Dria protocol, used for identifying the client.
     protocol: DriaP2PProtocol,
     /// Request-response protocol messages.
-    reqres_tx: mpsc::Sender<(PeerId, DriaReqResMessage)>,
+    reqres_tx: mpsc::Sender<(PeerId, String, DriaReqResMessage)>,
     /// Command receiver.
     cmd_rx: mpsc::Receiver<DriaP2PCommand>,
+    /// High-level events, broadcasted to the subscribers.
+    events_tx: broadcast::Sender<DriaP2PEvent>,
+    /// Outcomes of direct connection upgrades.
+    hole_punch_stats: HolePunchStats,
+    /// Reservations & circuits served by the relay server.
+    relay_server_stats: RelayServerStats,
+    /// Receivers of the subscribed GossipSub topics.
+    #[cfg_attr(not(feature = "gossipsub"), allow(unused))]
+    gossip_subscriptions: GossipSubscriptions,
+    /// Peer scoring of GossipSub, applied to each subscribed topic as well.
+    #[cfg_attr(not(feature = "gossipsub"), allow(unused))]
+    gossip_peer_score: Option<DriaGossipPeerScore>,
+    /// Identify info of the connected peers.
+    peer_infos: HashMap<PeerId, identify::Info>,
+    /// Rolling RTTs of the connected peers, measured by pings.
+    rtts: RttTracker,
+    /// Metrics registry, where the bandwidth counters of the transport are kept.
+    metrics: Registry,
+    /// Recorder of the swarm & behaviour metrics into the registry.
+    recorder: DriaMetrics,
+    /// Requests whose responses are queued but not yet sent (or failed).
+    pending_responses: HashSet<request_response::InboundRequestId>,
+    /// Whether the client is shutting down, where new requests are not forwarded anymore.
+    is_shutting_down: bool,
+    /// Notified once the client is shut down, after the queued responses are flushed.
+    shutdown_tx: Option<oneshot::Sender<()>>,
 }
 
 impl DriaP2PClient {
-    /// Creates a new P2P client with the given keypair and listen address.
+    /// Creates a new P2P client with the given keypair and listen addresses.
     ///
     /// The `version` is used to create the protocol strings for the client, and its very important that
     /// they match with the clients existing within the network.
     ///
-    /// If for any reason the given `listen_addr` is not available, it will try to listen on a random port on `localhost`.
+    /// It listens on all of the given `listen_addrs`, and if for any reason none of them are available,
+    /// it will try to listen on a random port on `localhost`.
+    ///
+    /// Connections are limited and pruned when idle as per the given `config`.
+    ///
+    /// Request-response messages are received along with the protocol version negotiated with the peer,
+    /// which is either the current or the previous `major.minor` version.
     #[allow(clippy::type_complexity)]
     pub fn new(
         keypair: Keypair,
-        listen_addr: Multiaddr,
+        listen_addrs: Vec<Multiaddr>,
         rpc_addr: &Multiaddr,
         protocol: DriaP2PProtocol,
+        config: DriaP2PConfig,
     ) -> Result<(
         DriaP2PClient,
         DriaP2PCommander,
-        mpsc::Receiver<(PeerId, DriaReqResMessage)>,
+        mpsc::Receiver<(PeerId, String, DriaReqResMessage)>,
     )> {
         let peer_id = keypair.public().to_peer_id();
 
-        let mut swarm = SwarmBuilder::with_existing_identity(keypair)
+        let builder = SwarmBuilder::with_existing_identity(keypair)
             .with_tokio()
-            .with_tcp(
-                tcp::Config::default(),
-                noise::Config::new,
-                yamux::Config::default,
-            )?
-            .with_behaviour(|key| DriaBehaviour::new(key, &protocol))?
-            // do not timeout at all, as we are only connected to an authority RPC at a given time and should stick to it
-            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(u64::MAX)))
+            .with_other_transport(|key| crate::transport::tcp_transport(key, &config))?;
+
+        // websocket is an additional transport, so that `/ws` and `/wss` addresses can be used as well
+        #[cfg(feature = "websocket")]
+        let builder = builder
+            .with_other_transport(|key| crate::websocket::websocket_transport(key, &config))?;
+        #[cfg(not(feature = "websocket"))]
+        for addr in listen_addrs.iter().chain([rpc_addr]) {
+            if addr.iter().any(|p| {
+                matches!(
+                    p,
+                    libp2p::multiaddr::Protocol::Ws(_) | libp2p::multiaddr::Protocol::Wss(_)
+                )
+            }) {
+                log::warn!("{addr} requires the `websocket` feature, which is not enabled.");
+            }
+        }
+
+        // bytes sent & received by the transport are counted within the registry
+        let mut metrics = Registry::default();
+
+        // relay client is added for hole punching, so that relayed connections can be upgraded to direct ones
+        #[cfg(feature = "hole-punching")]
+        let builder = builder
+            .with_relay_client(noise::Config::new, yamux::Config::default)?
+            .with_bandwidth_metrics(&mut metrics)
+            .with_behaviour(|key, relay_client| {
+                DriaBehaviour::new(key, relay_client, &protocol, &config)
+            })?;
+        #[cfg(not(feature = "hole-punching"))]
+        let builder = builder
+            .with_bandwidth_metrics(&mut metrics)
+            .with_behaviour(|key| {
+                DriaBehaviour::new(key, libp2p::swarm::dummy::Behaviour, &protocol, &config)
+            })?;
+
+        let mut swarm = builder
+            // idle connections are pruned, the RPC connection is kept active by the heartbeats
+            .with_swarm_config(|c| c.with_idle_connection_timeout(config.idle_connection_timeout))
             .build();
 
-        // listen on all interfaces for incoming connections
-        log::info!("Listening p2p network on: {listen_addr}");
-        if let Err(err) = swarm.listen_on(listen_addr) {
-            log::error!("Could not listen on address: {err:?}");
+        // listen on all given addresses for incoming connections, each bound address is reported
+        // with a `NewListenAddr` event, e.g. a single `0.0.0.0` address is bound on all interfaces
+        let mut num_listeners = 0;
+        for listen_addr in listen_addrs {
+            log::info!("Listening p2p network on: {listen_addr}");
+            match swarm.listen_on(listen_addr.clone()) {
+                Ok(_) => num_listeners += 1,
+                Err(err) => log::error!("Could not listen on address {listen_addr}: {err:?}"),
+            }
+        }
+        if num_listeners == 0 {
             log::warn!("Trying fallback address with localhost random port");
             swarm.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())?;
         }
 
+        // external addresses are advertised as is, in addition to the advertisable listen addresses
+        for external_addr in &config.external_addrs {
+            log::info!("Advertising external address: {external_addr}");
+            swarm.add_external_address(external_addr.clone());
+        }
+
         // dial rpc node, this will cause `identify` event to be called on their side
         log::info!("Dialing RPC node: {rpc_addr}");
         if let Err(err) = swarm.dial(rpc_addr.clone()) {
             log::error!("Could not dial RPC node: {err:?}");
         };
 
+        // swarm & behaviour events are recorded within the same registry
+        let recorder = DriaMetrics::new(&mut metrics);
+
         // create commander
         let (cmd_tx, cmd_rx) = mpsc::channel(COMMAND_CHANNEL_BUFSIZE);
-        let commander = DriaP2PCommander::new(cmd_tx, protocol.clone());
+        let (events_tx, _) = broadcast::channel(EVENTS_CHANNEL_BUFSIZE);
+        let commander = DriaP2PCommander::new(cmd_tx, protocol.clone(), events_tx.clone());
 
         // create p2p client itself
         let (reqres_tx, reqres_rx) = mpsc::channel(MSG_CHANNEL_BUFSIZE);
@@ -98,6 +193,18 @@ impl DriaP2PClient {
             protocol,
             reqres_tx,
             cmd_rx,
+            events_tx,
+            hole_punch_stats: HolePunchStats::default(),
+            relay_server_stats: RelayServerStats::default(),
+            peer_infos: HashMap::new(),
+            rtts: RttTracker::default(),
+            gossip_subscriptions: GossipSubscriptions::default(),
+            gossip_peer_score: config.gossip_peer_score,
+            metrics,
+            recorder,
+            pending_responses: HashSet::new(),
+            is_shutting_down: false,
+            shutdown_tx: None,
         };
 
         Ok((client, commander, reqres_rx))
@@ -105,7 +212,8 @@ impl DriaP2PClient {
 
     /// Waits for swarm events and Node commands at the same time.
     ///
-    /// To terminate, the command channel must be closed.
+    /// To terminate, the command channel must be closed. The responses that are queued by then
+    /// are flushed before returning, so that they are not lost.
     pub async fn run(mut self) {
         loop {
             tokio::select! {
@@ -114,6 +222,10 @@ impl DriaP2PClient {
                     // channel closed, thus shutting down the network event loop
                     None=>  {
                         log::info!("Closing peer-to-peer client.");
+                        self.flush_responses().await;
+                        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+                            let _ = shutdown_tx.send(());
+                        }
                         return
                     },
                 },
@@ -136,24 +248,114 @@ impl DriaP2PClient {
                     .build();
                 let _ = sender.send(self.swarm.dial(opts));
             }
+            DriaP2PCommand::BlockPeer { peer_id, sender } => {
+                log::info!("Blocking peer {peer_id}");
+                let _ = sender.send(self.swarm.behaviour_mut().blocked_peers.block_peer(peer_id));
+            }
+            DriaP2PCommand::UnblockPeer { peer_id, sender } => {
+                log::info!("Unblocking peer {peer_id}");
+                let _ = sender.send(
+                    self.swarm
+                        .behaviour_mut()
+                        .blocked_peers
+                        .unblock_peer(peer_id),
+                );
+            }
+            DriaP2PCommand::AllowPeer { peer_id, sender } => {
+                let allowed = self.swarm.behaviour_mut().allowed_peers.as_mut();
+                let _ = sender.send(allowed.is_some_and(|a| a.allow_peer(peer_id)));
+            }
+            DriaP2PCommand::DisallowPeer { peer_id, sender } => {
+                let allowed = self.swarm.behaviour_mut().allowed_peers.as_mut();
+                let _ = sender.send(allowed.is_some_and(|a| a.disallow_peer(peer_id)));
+            }
+            DriaP2PCommand::KeepAlive {
+                peer_id,
+                keep_alive,
+                sender,
+            } => {
+                let keep_alives = &mut self.swarm.behaviour_mut().keep_alive;
+                let _ = sender.send(keep_alives.set_keep_alive(peer_id, keep_alive));
+            }
+            DriaP2PCommand::OpenStream { peer_id, sender } => {
+                self.swarm
+                    .behaviour_mut()
+                    .stream
+                    .open_stream(peer_id, sender);
+            }
+            DriaP2PCommand::AuthorizeStreams {
+                peer_id,
+                authorized,
+                sender,
+            } => {
+                let stream = &mut self.swarm.behaviour_mut().stream;
+                let _ = sender.send(stream.set_authorized(peer_id, authorized));
+            }
+            DriaP2PCommand::AcceptStreams { sender } => {
+                let _ = sender.send(self.swarm.behaviour_mut().stream.accept_streams());
+            }
+            DriaP2PCommand::PeerInfo { peer_id, sender } => {
+                let _ = sender.send(self.peer_infos.get(&peer_id).cloned());
+            }
+            DriaP2PCommand::Rtt { peer_id, sender } => {
+                let _ = sender.send(self.rtts.average(&peer_id));
+            }
             DriaP2PCommand::IsConnected { peer_id, sender } => {
                 let _ = sender.send(self.swarm.is_connected(&peer_id));
             }
+            DriaP2PCommand::ConnectedPeers { sender } => {
+                let _ = sender.send(self.swarm.connected_peers().copied().collect());
+            }
             DriaP2PCommand::NetworkInfo { sender } => {
                 let _ = sender.send(self.swarm.network_info());
             }
+            DriaP2PCommand::Stats { sender } => {
+                let _ = sender.send(DriaP2PStats::from_registry(&self.metrics));
+            }
+            DriaP2PCommand::Metrics { sender } => {
+                let _ = sender.send(encode_registry(&self.metrics));
+            }
+            DriaP2PCommand::HolePunchStats { sender } => {
+                let _ = sender.send(self.hole_punch_stats);
+            }
+            DriaP2PCommand::RelayServerStats { sender } => {
+                let _ = sender.send(self.relay_server_stats.clone());
+            }
+            DriaP2PCommand::GossipPeerScore { peer_id, sender } => {
+                let _ = sender.send(self.gossip_peer_score(&peer_id));
+            }
+            DriaP2PCommand::GossipPeerScores { sender } => {
+                let _ = sender.send(self.gossip_peer_scores());
+            }
+            DriaP2PCommand::Subscribe { topic, sender } => {
+                let _ = sender.send(self.subscribe(&topic));
+            }
+            DriaP2PCommand::Unsubscribe { topic, sender } => {
+                let _ = sender.send(self.unsubscribe(&topic));
+            }
+            DriaP2PCommand::Publish {
+                topic,
+                data,
+                sender,
+            } => {
+                let _ = sender.send(self.publish(&topic, data));
+            }
             DriaP2PCommand::Respond {
+                request_id,
                 data,
                 channel,
                 sender,
             } => {
-                let _ = sender.send(
-                    self.swarm
-                        .behaviour_mut()
-                        .request_response
-                        .send_response(channel, data)
-                        .map_err(|_| eyre::eyre!("could not send response, channel is closed?")),
-                );
+                let result = self
+                    .swarm
+                    .behaviour_mut()
+                    .request_response
+                    .send_response(channel, data)
+                    .map_err(|_| eyre::eyre!("could not send response, channel is closed?"));
+                if result.is_ok() {
+                    self.pending_responses.insert(request_id);
+                }
+                let _ = sender.send(result);
             }
             DriaP2PCommand::Request {
                 data,
@@ -168,16 +370,161 @@ impl DriaP2PClient {
                 );
             }
             DriaP2PCommand::Shutdown { sender } => {
-                // close the command channel
+                // close the command channel, the remaining commands are still handled
+                // and the sender is notified once the queued responses are flushed
                 self.cmd_rx.close();
+                self.shutdown_tx = Some(sender);
+            }
+        }
+    }
 
-                let _ = sender.send(());
+    /// Drives the swarm until the queued responses are sent, or [`RESPONSE_FLUSH_TIMEOUT`] elapses.
+    ///
+    /// New requests are not forwarded during this time.
+    async fn flush_responses(&mut self) {
+        self.is_shutting_down = true;
+        if self.pending_responses.is_empty() {
+            return;
+        }
+
+        log::info!(
+            "Flushing {} pending responses.",
+            self.pending_responses.len()
+        );
+        let flush = async {
+            while !self.pending_responses.is_empty() {
+                let event = self.swarm.select_next_some().await;
+                self.handle_event(event).await;
             }
+        };
+        if tokio::time::timeout(RESPONSE_FLUSH_TIMEOUT, flush)
+            .await
+            .is_err()
+        {
+            log::warn!(
+                "Could not flush {} pending responses in time.",
+                self.pending_responses.len()
+            );
         }
     }
 
+    /// Subscribes to a GossipSub topic, and returns a new receiver for its messages.
+    fn subscribe(&mut self, topic: &str) -> Result<mpsc::Receiver<DriaGossipMessage>> {
+        #[cfg(feature = "gossipsub")]
+        {
+            let (receiver, is_first) = self.gossip_subscriptions.add(topic);
+            if is_first {
+                log::info!("Subscribing to topic {topic}");
+                let gossipsub = &mut self.swarm.behaviour_mut().gossipsub;
+                let ident_topic = libp2p::gossipsub::IdentTopic::new(topic);
+                if let Err(err) = gossipsub.subscribe(&ident_topic) {
+                    self.gossip_subscriptions.remove(topic);
+                    eyre::bail!("could not subscribe to {topic}: {err:?}");
+                }
+
+                // invalid messages are penalized per topic
+                if let Some(peer_score) = &self.gossip_peer_score {
+                    if let Err(err) =
+                        gossipsub.set_topic_params(ident_topic, peer_score.topic_params())
+                    {
+                        log::error!("Could not set the peer score of topic {topic}: {err}");
+                    }
+                }
+            }
+
+            Ok(receiver)
+        }
+
+        #[cfg(not(feature = "gossipsub"))]
+        eyre::bail!("can not subscribe to {topic}, the `gossipsub` feature is not enabled")
+    }
+
+    /// Unsubscribes from a GossipSub topic, closing its receivers.
+    fn unsubscribe(&mut self, topic: &str) -> Result<()> {
+        #[cfg(feature = "gossipsub")]
+        {
+            if self.gossip_subscriptions.remove(topic) {
+                log::info!("Unsubscribing from topic {topic}");
+                let _ = self
+                    .swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .unsubscribe(&libp2p::gossipsub::IdentTopic::new(topic));
+            }
+
+            Ok(())
+        }
+
+        #[cfg(not(feature = "gossipsub"))]
+        eyre::bail!("can not unsubscribe from {topic}, the `gossipsub` feature is not enabled")
+    }
+
+    /// Returns the GossipSub score of the peer, if the peers are scored.
+    fn gossip_peer_score(&self, peer_id: &PeerId) -> Option<f64> {
+        #[cfg(feature = "gossipsub")]
+        return self.swarm.behaviour().gossipsub.peer_score(peer_id);
+
+        #[cfg(not(feature = "gossipsub"))]
+        {
+            let _ = peer_id;
+            None
+        }
+    }
+
+    /// Returns the GossipSub scores of the known peers, if the peers are scored.
+    fn gossip_peer_scores(&self) -> Vec<(PeerId, f64)> {
+        #[cfg(feature = "gossipsub")]
+        return self
+            .swarm
+            .behaviour()
+            .gossipsub
+            .all_peers()
+            .filter_map(|(peer_id, _)| Some((*peer_id, self.gossip_peer_score(peer_id)?)))
+            .collect();
+
+        #[cfg(not(feature = "gossipsub"))]
+        Vec::new()
+    }
+
+    /// Publishes a message to a GossipSub topic.
+    fn publish(&mut self, topic: &str, data: Vec<u8>) -> Result<()> {
+        #[cfg(feature = "gossipsub")]
+        {
+            self.swarm
+                .behaviour_mut()
+                .gossipsub
+                .publish(libp2p::gossipsub::IdentTopic::new(topic), data)
+                .map(|_| ())
+                .map_err(|err| eyre::eyre!("could not publish to {topic}: {err:?}"))
+        }
+
+        #[cfg(not(feature = "gossipsub"))]
+        {
+            let _ = data;
+            eyre::bail!("can not publish to {topic}, the `gossipsub` feature is not enabled")
+        }
+    }
+
+    /// Returns the request-response protocol version negotiated with the peer, as per its Identify info.
+    ///
+    /// Defaults to the current version if the peer has not been identified yet.
+    fn negotiated_version(&self, peer_id: &PeerId) -> String {
+        self.peer_infos
+            .get(peer_id)
+            .and_then(|info| self.protocol.negotiated_version(&info.protocols))
+            .unwrap_or_else(|| self.protocol.version.clone())
+    }
+
+    /// Broadcasts an event to the subscribers, if there are any.
+    fn emit(&self, event: DriaP2PEvent) {
+        // an error only means that there are no subscribers
+        let _ = self.events_tx.send(event);
+    }
+
     /// Handles a single event from the `swarm` stream.
     pub async fn handle_event(&mut self, event: SwarmEvent<DriaBehaviourEvent>) {
+        self.recorder.record(&event);
+
         match event {
             /*****************************************
              * Request-response events               *
@@ -185,8 +532,19 @@ impl DriaP2PClient {
             SwarmEvent::Behaviour(DriaBehaviourEvent::RequestResponse(
                 request_response::Event::Message { message, peer, .. },
             )) => {
+                if matches!(message, request_response::Message::Request { .. }) {
+                    // the request is dropped along with its channel, so the peer is not left waiting
+                    if self.is_shutting_down {
+                        log::debug!("Ignoring request from {peer} during shutdown.");
+                        return;
+                    }
+
+                    self.emit(DriaP2PEvent::InboundRequest { peer_id: peer });
+                }
+
                 // whether its a request or response, we forward it to the main thread
-                if let Err(err) = self.reqres_tx.send((peer, message)).await {
+                let version = self.negotiated_version(&peer);
+                if let Err(err) = self.reqres_tx.send((peer, version, message)).await {
                     log::error!("Could not transfer request {err:?}");
                 }
             }
@@ -196,6 +554,7 @@ impl DriaP2PClient {
                     peer, request_id, ..
                 },
             )) => {
+                self.pending_responses.remove(&request_id);
                 log::debug!("Request-Response: response ({request_id}) sent to peer {peer} with",)
             }
             SwarmEvent::Behaviour(DriaBehaviourEvent::RequestResponse(
@@ -218,6 +577,8 @@ impl DriaP2PClient {
                     ..
                 },
             )) => {
+                // the request may have failed before its response was queued, e.g. timed out while being handled
+                self.pending_responses.remove(&request_id);
                 log::error!(
                     "Request-Response: Inbound failure to {peer} with request_id {request_id}: {error:?}"
                 );
@@ -231,7 +592,7 @@ impl DriaP2PClient {
                 info,
                 ..
             })) => {
-                if info.protocol_version != self.protocol.identity {
+                if !self.protocol.is_compatible_identity(&info.protocol_version) {
                     log::warn!(
                         "Identify: Peer {} has different Identify protocol: (them {}, you {})",
                         peer_id,
@@ -241,14 +602,61 @@ impl DriaP2PClient {
 
                     // disconnect them
                     let _ = self.swarm.disconnect_peer_id(peer_id);
+                } else {
+                    self.peer_infos.insert(peer_id, info);
                 }
             }
 
+            /*****************************************
+             * Ping events                           *
+             *****************************************/
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Ping(event)) => {
+                self.rtts.record_event(event);
+            }
+
+            /*****************************************
+             * GossipSub events                      *
+             *****************************************/
+            #[cfg(feature = "gossipsub")]
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Gossipsub(
+                libp2p::gossipsub::Event::Message {
+                    propagation_source,
+                    message,
+                    ..
+                },
+            )) => {
+                self.gossip_subscriptions.forward(DriaGossipMessage {
+                    topic: message.topic.into_string(),
+                    source: message.source,
+                    propagation_source,
+                    data: message.data,
+                });
+            }
+
+            /*****************************************
+             * Hole punching events                  *
+             *****************************************/
+            #[cfg(feature = "hole-punching")]
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Dcutr(event)) => {
+                self.hole_punch_stats.record(event);
+            }
+            #[cfg(feature = "hole-punching")]
+            SwarmEvent::Behaviour(DriaBehaviourEvent::RelayClient(event)) => {
+                log::debug!("Relay client: {event:?}");
+            }
+            #[cfg(feature = "relay-server")]
+            SwarmEvent::Behaviour(DriaBehaviourEvent::RelayServer(event)) => {
+                self.relay_server_stats.record(event);
+            }
+
             /*****************************************
              * Connection events and errors handling *
              *****************************************/
             SwarmEvent::NewListenAddr { address, .. } => {
                 log::warn!("Local node is listening on {address}");
+                if is_advertisable(&address) {
+                    self.swarm.add_external_address(address);
+                }
             }
             SwarmEvent::NewExternalAddrOfPeer { peer_id, address } => {
                 log::info!("External address of peer {peer_id} confirmed: {address}");
@@ -276,6 +684,11 @@ impl DriaP2PClient {
             }
 
             SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
+                self.emit(DriaP2PEvent::DialFailure {
+                    peer_id,
+                    error: error.to_string(),
+                });
+
                 if let Some(peer_id) = peer_id {
                     log::warn!("Could not connect to peer {peer_id}: {error:?}");
                 } else {
@@ -287,8 +700,16 @@ impl DriaP2PClient {
                 peer_id,
                 connection_id,
                 endpoint,
+                num_established,
                 ..
             } => {
+                if num_established.get() == 1 {
+                    self.emit(DriaP2PEvent::Connected {
+                        peer_id,
+                        address: endpoint.get_remote_address().clone(),
+                    });
+                }
+
                 if endpoint.is_dialer() {
                     // we only care about logs about the ones that we have dialed
                     log::info!(
@@ -308,8 +729,14 @@ impl DriaP2PClient {
                 connection_id,
                 endpoint,
                 cause,
-                ..
+                num_established,
             } => {
+                if num_established == 0 {
+                    self.peer_infos.remove(&peer_id);
+                    self.rtts.remove(&peer_id);
+                    self.emit(DriaP2PEvent::Disconnected { peer_id });
+                }
+
                 // we only care about the connections that we have dialed
                 if endpoint.is_dialer() {
                     // if we know the cause, it may be a good idea to re-dial
@@ -346,6 +773,7 @@ impl DriaP2PClient {
             } => {
                 // this may happen when your connection is lost, e.g. you turn off your machine / internet
                 log::warn!("Listener ({listener_id}) expired: {address}");
+                self.swarm.remove_external_address(&address);
             }
 
             SwarmEvent::ListenerError { listener_id, error } => {
@@ -356,3 +784,74 @@ impl DriaP2PClient {
         }
     }
 }
+
+/// Returns whether a listen address can be reached by other peers and thus should be advertised,
+/// i.e. its IP address is globally routable.
+///
+/// Private (RFC 1918), shared (CGNAT, `100.64.0.0/10`) and unique local (`fc00::/7`) addresses are
+/// only reachable within their own network, so a node behind a NAT should advertise its public address
+/// with `external_addrs` instead.
+fn is_advertisable(addr: &Multiaddr) -> bool {
+    use libp2p::multiaddr::Protocol;
+
+    addr.iter().all(|protocol| match protocol {
+        Protocol::Ip4(ip) => is_global_ipv4(ip),
+        Protocol::Ip6(ip) => is_global_ipv6(ip),
+        _ => true,
+    })
+}
+
+/// Returns whether an IPv6 address is globally routable, where an IPv4-mapped address is checked as IPv4.
+fn is_global_ipv6(ip: std::net::Ipv6Addr) -> bool {
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        return is_global_ipv4(ip);
+    }
+    let [first, second, ..] = ip.segments();
+    let is_documentation = first == 0x2001 && second == 0xdb8;
+
+    !ip.is_loopback()
+        && !ip.is_unspecified()
+        && !ip.is_unicast_link_local()
+        && !ip.is_unique_local()
+        && !is_documentation
+}
+
+/// Returns whether an IPv4 address is globally routable.
+fn is_global_ipv4(ip: std::net::Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    let is_shared = first == 100 && (second & 0b1100_0000) == 0b0100_0000;
+
+    !ip.is_loopback()
+        && !ip.is_unspecified()
+        && !ip.is_private()
+        && !ip.is_link_local()
+        && !ip.is_broadcast()
+        && !ip.is_documentation()
+        && !is_shared
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_advertisable() {
+        let advertisable = |addr: &str| is_advertisable(&addr.parse().unwrap());
+
+        assert!(advertisable("/ip4/1.1.1.1/tcp/4001"));
+        assert!(advertisable("/ip4/100.128.0.1/tcp/4001"));
+        assert!(advertisable("/ip6/2606:4700::1111/tcp/4001"));
+        assert!(!advertisable("/ip4/127.0.0.1/tcp/4001"));
+        assert!(!advertisable("/ip4/0.0.0.0/tcp/4001"));
+        assert!(!advertisable("/ip4/192.168.1.2/tcp/4001"));
+        assert!(!advertisable("/ip4/10.0.0.2/tcp/4001"));
+        assert!(!advertisable("/ip4/172.16.0.2/tcp/4001"));
+        assert!(!advertisable("/ip4/100.64.0.1/tcp/4001"));
+        assert!(!advertisable("/ip4/169.254.0.1/tcp/4001"));
+        assert!(!advertisable("/ip6/::1/tcp/4001"));
+        assert!(!advertisable("/ip6/fe80::1/tcp/4001"));
+        assert!(!advertisable("/ip6/fd00::1/tcp/4001"));
+        assert!(!advertisable("/ip6/2001:db8::1/tcp/4001"));
+        assert!(!advertisable("/ip6/::ffff:192.168.1.2/tcp/4001"));
+    }
+}
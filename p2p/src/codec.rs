@@ -0,0 +1,186 @@
+//! Request-response codec with transparent zstd compression.
+//!
+//! Compression is negotiated via the protocol suffix, e.g. `/dria/rr/0.6/zstd`, so that
+//! peers without compression support keep using the plain protocol with the very same encoding.
+
+use async_trait::async_trait;
+use futures::prelude::*;
+use libp2p::{request_response, StreamProtocol};
+use std::io;
+
+/// Suffix of the request-response protocols where messages are compressed.
+const ZSTD_SUFFIX: &str = "/zstd";
+
+/// Compression level of zstd, where `0` picks the library default.
+const ZSTD_LEVEL: i32 = 0;
+
+/// The CBOR codec of request-response, which is not exported by `libp2p` and is named through its behaviour.
+type CborCodec = <request_response::cbor::Behaviour<Vec<u8>, Vec<u8>> as BehaviourCodec>::Codec;
+
+trait BehaviourCodec {
+    type Codec;
+}
+
+impl<C: request_response::Codec + Clone + Send + 'static> BehaviourCodec
+    for request_response::Behaviour<C>
+{
+    type Codec = C;
+}
+
+/// Returns the compressed variant of the given request-response protocol.
+pub(crate) fn compressed_protocol(protocol: &StreamProtocol) -> StreamProtocol {
+    StreamProtocol::try_from_owned(format!("{protocol}{ZSTD_SUFFIX}"))
+        .expect("protocol with suffix should be valid")
+}
+
+/// Returns whether messages are compressed over the given request-response protocol.
+fn is_compressed(protocol: &StreamProtocol) -> bool {
+    protocol.as_ref().ends_with(ZSTD_SUFFIX)
+}
+
+/// A CBOR codec of bytes, where the bytes are compressed over the protocols with [`ZSTD_SUFFIX`].
+///
+/// The size limits of the inner codec apply to the compressed messages, and the same limits are
+/// applied to the decompressed ones as well so that a small message can not inflate indefinitely.
+#[derive(Clone)]
+pub struct DriaCodec {
+    inner: CborCodec,
+    max_request_size: u64,
+    max_response_size: u64,
+}
+
+impl DriaCodec {
+    pub(crate) fn new(max_request_size: u64, max_response_size: u64) -> Self {
+        Self {
+            inner: CborCodec::default()
+                .set_request_size_maximum(max_request_size)
+                .set_response_size_maximum(max_response_size),
+            max_request_size,
+            max_response_size,
+        }
+    }
+}
+
+/// Compresses the given bytes with zstd.
+fn compress(data: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::bulk::compress(data, ZSTD_LEVEL)
+}
+
+/// Decompresses the given bytes with zstd, failing if the result is larger than `max_size`.
+fn decompress(data: &[u8], max_size: u64) -> io::Result<Vec<u8>> {
+    zstd::bulk::decompress(data, usize::try_from(max_size).unwrap_or(usize::MAX))
+}
+
+#[async_trait]
+impl request_response::Codec for DriaCodec {
+    type Protocol = StreamProtocol;
+    type Request = Vec<u8>;
+    type Response = Vec<u8>;
+
+    async fn read_request<T>(
+        &mut self,
+        protocol: &StreamProtocol,
+        io: &mut T,
+    ) -> io::Result<Vec<u8>>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let request = self.inner.read_request(protocol, io).await?;
+        if is_compressed(protocol) {
+            decompress(&request, self.max_request_size)
+        } else {
+            Ok(request)
+        }
+    }
+
+    async fn read_response<T>(
+        &mut self,
+        protocol: &StreamProtocol,
+        io: &mut T,
+    ) -> io::Result<Vec<u8>>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let response = self.inner.read_response(protocol, io).await?;
+        if is_compressed(protocol) {
+            decompress(&response, self.max_response_size)
+        } else {
+            Ok(response)
+        }
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        protocol: &StreamProtocol,
+        io: &mut T,
+        request: Vec<u8>,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let request = if is_compressed(protocol) {
+            compress(&request)?
+        } else {
+            request
+        };
+
+        self.inner.write_request(protocol, io, request).await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        protocol: &StreamProtocol,
+        io: &mut T,
+        response: Vec<u8>,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let response = if is_compressed(protocol) {
+            compress(&response)?
+        } else {
+            response
+        };
+
+        self.inner.write_response(protocol, io, response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use request_response::Codec;
+
+    #[tokio::test]
+    async fn test_codec_compression() {
+        let plain = StreamProtocol::new("/test/rr/1.0");
+        let compressed = compressed_protocol(&plain);
+        assert_eq!(compressed.as_ref(), "/test/rr/1.0/zstd");
+
+        let mut codec = DriaCodec::new(1024, 1024);
+        let request = b"hello world ".repeat(32);
+        for protocol in [&plain, &compressed] {
+            let mut buf = Vec::new();
+            codec
+                .write_request(protocol, &mut buf, request.clone())
+                .await
+                .unwrap();
+            let read = codec
+                .read_request(protocol, &mut buf.as_slice())
+                .await
+                .unwrap();
+            assert_eq!(read, request);
+        }
+
+        // a compressed message that inflates beyond the limit is rejected
+        let mut buf = Vec::new();
+        codec
+            .write_response(&compressed, &mut buf, vec![0u8; 4096])
+            .await
+            .unwrap();
+        assert!(codec
+            .read_response(&compressed, &mut buf.as_slice())
+            .await
+            .is_err());
+    }
+}
@@ -1,8 +1,12 @@
 use eyre::{Context, Result};
-use libp2p::{request_response, swarm, Multiaddr, PeerId};
-use tokio::sync::{mpsc, oneshot};
+use libp2p::{identify, request_response, swarm, Multiaddr, PeerId};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot};
 
-use crate::DriaP2PProtocol;
+use crate::{
+    DriaGossipMessage, DriaP2PEvent, DriaP2PProtocol, DriaP2PStats, DriaStream, HolePunchStats,
+    RelayServerStats,
+};
 
 #[derive(Debug)]
 pub enum DriaP2PCommand {
@@ -10,19 +14,114 @@ pub enum DriaP2PCommand {
     NetworkInfo {
         sender: oneshot::Sender<swarm::NetworkInfo>,
     },
+    /// Returns all metrics of the client in the OpenMetrics text format.
+    Metrics { sender: oneshot::Sender<String> },
+    /// Returns the outcomes of direct connection upgrades, see [`HolePunchStats`].
+    HolePunchStats {
+        sender: oneshot::Sender<HolePunchStats>,
+    },
+    /// Returns the reservations & circuits served by the relay server, see [`RelayServerStats`].
+    RelayServerStats {
+        sender: oneshot::Sender<RelayServerStats>,
+    },
+    /// Returns the bandwidth usage of the client, see [`DriaP2PStats`].
+    Stats {
+        sender: oneshot::Sender<DriaP2PStats>,
+    },
+    /// Set whether the connections with a peer are kept alive regardless of the idle timeout.
+    KeepAlive {
+        peer_id: PeerId,
+        keep_alive: bool,
+        sender: oneshot::Sender<bool>,
+    },
+    /// Opens a new stream with a connected peer.
+    OpenStream {
+        peer_id: PeerId,
+        sender: oneshot::Sender<Result<DriaStream>>,
+    },
+    /// Set whether the incoming streams of a peer are accepted.
+    AuthorizeStreams {
+        peer_id: PeerId,
+        authorized: bool,
+        sender: oneshot::Sender<bool>,
+    },
+    /// Returns a receiver of the incoming streams, replacing the previous one.
+    AcceptStreams {
+        sender: oneshot::Sender<mpsc::Receiver<DriaStream>>,
+    },
+    /// Returns the Identify info of a connected peer, if it has been received.
+    PeerInfo {
+        peer_id: PeerId,
+        sender: oneshot::Sender<Option<identify::Info>>,
+    },
+    /// Returns the rolling average RTT to a connected peer, if it has been measured.
+    Rtt {
+        peer_id: PeerId,
+        sender: oneshot::Sender<Option<Duration>>,
+    },
     /// Check if there is an active connection to the given peer.
     IsConnected {
         peer_id: PeerId,
         sender: oneshot::Sender<bool>,
     },
+    /// Get the peers with an active connection.
+    ConnectedPeers {
+        sender: oneshot::Sender<Vec<PeerId>>,
+    },
+    /// Block a peer, closing its connections and denying new ones.
+    BlockPeer {
+        peer_id: PeerId,
+        sender: oneshot::Sender<bool>,
+    },
+    /// Unblock a previously blocked peer.
+    UnblockPeer {
+        peer_id: PeerId,
+        sender: oneshot::Sender<bool>,
+    },
+    /// Allow a peer to connect, only has effect if the allow-list is enabled.
+    AllowPeer {
+        peer_id: PeerId,
+        sender: oneshot::Sender<bool>,
+    },
+    /// Disallow a previously allowed peer, closing its connections.
+    DisallowPeer {
+        peer_id: PeerId,
+        sender: oneshot::Sender<bool>,
+    },
     /// Dial a known peer.
     Dial {
         peer_id: PeerId,
         address: Multiaddr,
         sender: oneshot::Sender<Result<(), swarm::DialError>>,
     },
+    /// Returns the GossipSub score of a peer, if the peers are scored.
+    GossipPeerScore {
+        peer_id: PeerId,
+        sender: oneshot::Sender<Option<f64>>,
+    },
+    /// Returns the GossipSub scores of the known peers, if the peers are scored.
+    GossipPeerScores {
+        sender: oneshot::Sender<Vec<(PeerId, f64)>>,
+    },
+    /// Subscribe to a GossipSub topic, returning a receiver for its messages.
+    Subscribe {
+        topic: String,
+        sender: oneshot::Sender<Result<mpsc::Receiver<DriaGossipMessage>>>,
+    },
+    /// Unsubscribe from a GossipSub topic, closing its receivers.
+    Unsubscribe {
+        topic: String,
+        sender: oneshot::Sender<Result<()>>,
+    },
+    /// Publish a message to a GossipSub topic.
+    Publish {
+        topic: String,
+        data: Vec<u8>,
+        sender: oneshot::Sender<Result<()>>,
+    },
     /// Respond to a request-response message.
     Respond {
+        request_id: request_response::InboundRequestId,
         data: Vec<u8>,
         channel: request_response::ResponseChannel<Vec<u8>>,
         sender: oneshot::Sender<Result<()>>,
@@ -36,17 +135,37 @@ pub enum DriaP2PCommand {
         sender: oneshot::Sender<request_response::OutboundRequestId>,
     },
     /// Shutsdown the client, closes the command channel.
+    ///
+    /// The sender is notified after the queued responses are flushed.
     Shutdown { sender: oneshot::Sender<()> },
 }
 
 pub struct DriaP2PCommander {
     sender: mpsc::Sender<DriaP2PCommand>,
     protocol: DriaP2PProtocol,
+    /// Sender of the client events, kept so that new subscribers can be created.
+    events_tx: broadcast::Sender<DriaP2PEvent>,
 }
 
 impl DriaP2PCommander {
-    pub fn new(sender: mpsc::Sender<DriaP2PCommand>, protocol: DriaP2PProtocol) -> Self {
-        Self { sender, protocol }
+    pub fn new(
+        sender: mpsc::Sender<DriaP2PCommand>,
+        protocol: DriaP2PProtocol,
+        events_tx: broadcast::Sender<DriaP2PEvent>,
+    ) -> Self {
+        Self {
+            sender,
+            protocol,
+            events_tx,
+        }
+    }
+
+    /// Subscribes to the events of the client, such as connections and inbound requests.
+    ///
+    /// Only the events after this call are received, and a receiver that lags behind misses
+    /// the oldest events, see [`broadcast::Receiver::recv`].
+    pub fn events(&self) -> broadcast::Receiver<DriaP2PEvent> {
+        self.events_tx.subscribe()
     }
 
     /// Returns a reference to the protocol.
@@ -67,8 +186,307 @@ impl DriaP2PCommander {
         receiver.await.wrap_err("could not receive")
     }
 
+    /// Returns all metrics of the client, i.e. the bandwidth, swarm, behaviour and request-response
+    /// counters, encoded in the OpenMetrics text format.
+    pub async fn metrics(&self) -> Result<String> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::Metrics { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the outcomes of direct connection upgrades, which are
+    /// always zero without the `hole-punching` feature.
+    pub async fn hole_punch_stats(&self) -> Result<HolePunchStats> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::HolePunchStats { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the reservations & circuits served by the relay server, which are
+    /// always zero without the `relay-server` feature & config.
+    pub async fn relay_server_stats(&self) -> Result<RelayServerStats> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::RelayServerStats { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the bytes received & sent since the client has started,
+    /// in total and per transport protocol stack.
+    pub async fn stats(&self) -> Result<DriaP2PStats> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::Stats { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Blocks the given peer, closing its existing connections and denying new ones.
+    ///
+    /// Returns `true` if the peer was not already blocked.
+    pub async fn block_peer(&self, peer_id: PeerId) -> Result<bool> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::BlockPeer { peer_id, sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Unblocks the given peer.
+    ///
+    /// Returns `true` if the peer was blocked.
+    pub async fn unblock_peer(&self, peer_id: PeerId) -> Result<bool> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::UnblockPeer { peer_id, sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Allows the given peer to connect, which only has effect if the allow-list is enabled.
+    ///
+    /// Returns `true` if the peer was not already allowed.
+    pub async fn allow_peer(&self, peer_id: PeerId) -> Result<bool> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::AllowPeer { peer_id, sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Removes the given peer from the allow-list, closing its existing connections.
+    ///
+    /// Returns `true` if the peer was allowed.
+    pub async fn disallow_peer(&self, peer_id: PeerId) -> Result<bool> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::DisallowPeer { peer_id, sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Sets whether the connections with the given peer are kept alive regardless of the
+    /// idle connection timeout, including the existing ones.
+    ///
+    /// Returns `true` if the peer was updated, `false` if it was already set as such.
+    pub async fn keep_alive(&self, peer_id: PeerId, keep_alive: bool) -> Result<bool> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::KeepAlive {
+                peer_id,
+                keep_alive,
+                sender,
+            })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Opens a new stream with a connected peer over the stream protocol, for outputs that are
+    /// too long for a single response, e.g. token streams.
+    ///
+    /// Data is written with [`DriaStream::write`] in frames, and the stream is finished with
+    /// [`DriaStream::close`].
+    pub async fn open_stream(&self, peer_id: PeerId) -> Result<DriaStream> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::OpenStream { peer_id, sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")?
+    }
+
+    /// Sets whether the incoming streams of the given peer are accepted, in addition to the
+    /// `stream_peers` of the config.
+    ///
+    /// Returns `true` if the peer was updated, `false` if it was already set as such.
+    pub async fn authorize_streams(&self, peer_id: PeerId, authorized: bool) -> Result<bool> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::AuthorizeStreams {
+                peer_id,
+                authorized,
+                sender,
+            })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns a receiver of the streams opened by the authorized peers, see [`Self::authorize_streams`].
+    ///
+    /// Only the latest receiver gets the streams, and incoming streams are dropped until this is called.
+    /// At most `max_inbound_streams` of the config are open at once, until the received ones are dropped.
+    pub async fn accept_streams(&self) -> Result<mpsc::Receiver<DriaStream>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::AcceptStreams { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the Identify info of a connected peer, such as its agent version, protocols
+    /// and the address that it observes for us.
+    ///
+    /// Returns `None` if the peer is not connected or it has not identified itself yet.
+    pub async fn peer_info(&self, peer_id: PeerId) -> Result<Option<identify::Info>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::PeerInfo { peer_id, sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the rolling average round-trip time (RTT) to a connected peer, as measured by pings.
+    ///
+    /// Returns `None` if the peer is not connected or it has not been pinged yet.
+    pub async fn rtt(&self, peer_id: PeerId) -> Result<Option<Duration>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::Rtt { peer_id, sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the GossipSub score of a peer, for diagnostics.
+    ///
+    /// Returns `None` if the peers are not scored, i.e. without a `gossip_peer_score` in the config
+    /// or without the `gossipsub` feature.
+    pub async fn gossip_peer_score(&self, peer_id: PeerId) -> Result<Option<f64>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::GossipPeerScore { peer_id, sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Returns the GossipSub scores of all known peers, for diagnostics.
+    ///
+    /// Returns an empty list if the peers are not scored, see [`Self::gossip_peer_score`].
+    pub async fn gossip_peer_scores(&self) -> Result<Vec<(PeerId, f64)>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::GossipPeerScores { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Subscribes to the given GossipSub topic, and returns a receiver for its messages.
+    ///
+    /// Each call returns a new receiver, and all of them receive the messages of the topic.
+    /// Requires the `gossipsub` feature.
+    pub async fn subscribe(
+        &mut self,
+        topic: impl Into<String>,
+    ) -> Result<mpsc::Receiver<DriaGossipMessage>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::Subscribe {
+                topic: topic.into(),
+                sender,
+            })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")?
+    }
+
+    /// Unsubscribes from the given GossipSub topic, which closes all of its receivers.
+    ///
+    /// Requires the `gossipsub` feature.
+    pub async fn unsubscribe(&mut self, topic: impl Into<String>) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::Unsubscribe {
+                topic: topic.into(),
+                sender,
+            })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")?
+    }
+
+    /// Publishes the given data to a GossipSub topic.
+    ///
+    /// Requires the `gossipsub` feature.
+    pub async fn publish(
+        &mut self,
+        topic: impl Into<String>,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::Publish {
+                topic: topic.into(),
+                data: data.into(),
+                sender,
+            })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")?
+    }
+
+    /// Responds to the request with the given id through its channel.
+    ///
+    /// The response is queued to be sent, and it is flushed during shutdown if it is still queued.
     pub async fn respond(
         &mut self,
+        request_id: request_response::InboundRequestId,
         data: Vec<u8>,
         channel: request_response::ResponseChannel<Vec<u8>>,
     ) -> Result<()> {
@@ -76,6 +494,7 @@ impl DriaP2PCommander {
 
         self.sender
             .send(DriaP2PCommand::Respond {
+                request_id,
                 data,
                 channel,
                 sender,
@@ -140,7 +559,20 @@ impl DriaP2PCommander {
         receiver.await.wrap_err("could not receive")
     }
 
-    /// Sends a shutdown signal to the client.
+    /// Returns the peers with an active connection.
+    pub async fn connected_peers(&self) -> Result<Vec<PeerId>> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DriaP2PCommand::ConnectedPeers { sender })
+            .await
+            .wrap_err("could not send")?;
+
+        receiver.await.wrap_err("could not receive")
+    }
+
+    /// Sends a shutdown signal to the client, and waits until the responses that were
+    /// queued before it are sent.
     pub async fn shutdown(&mut self) -> Result<()> {
         let (sender, receiver) = oneshot::channel();
 
@@ -0,0 +1,111 @@
+use libp2p::connection_limits::ConnectionLimits;
+use libp2p::{Multiaddr, PeerId};
+
+use crate::{DriaDnsResolver, DriaGossipPeerScore, DriaSocksProxy};
+use std::time::Duration;
+
+/// Default maximum number of established connections in total.
+pub const DEFAULT_MAX_ESTABLISHED: u32 = 64;
+/// Default maximum number of established connections per peer.
+///
+/// More than one is allowed so that a re-dial to the RPC can overlap with a closing connection.
+pub const DEFAULT_MAX_ESTABLISHED_PER_PEER: u32 = 2;
+/// Default maximum number of pending connections, for both incoming & outgoing.
+pub const DEFAULT_MAX_PENDING: u32 = 16;
+/// Default duration after which a connection without any active streams is closed.
+///
+/// The RPC connection is used by heartbeats way more often than this, so only the stale ones are pruned.
+pub const DEFAULT_IDLE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Default maximum size of a request-response request, in bytes.
+pub const DEFAULT_MAX_REQUEST_SIZE: u64 = 1024 * 1024;
+/// Default maximum size of a request-response response, in bytes.
+pub const DEFAULT_MAX_RESPONSE_SIZE: u64 = 10 * 1024 * 1024;
+/// Default timeout of a request-response request, until its response is received.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(512);
+/// Default maximum number of incoming streams that are open at once.
+pub const DEFAULT_MAX_INBOUND_STREAMS: usize = 16;
+
+/// Configuration of the peer-to-peer client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriaP2PConfig {
+    /// Maximum number of established connections in total, `None` for unlimited.
+    pub max_established: Option<u32>,
+    /// Maximum number of established connections per peer, `None` for unlimited.
+    pub max_established_per_peer: Option<u32>,
+    /// Maximum number of pending incoming connections, `None` for unlimited.
+    pub max_pending_incoming: Option<u32>,
+    /// Maximum number of pending outgoing connections, `None` for unlimited.
+    pub max_pending_outgoing: Option<u32>,
+    /// Duration after which a connection without any active streams is closed.
+    pub idle_connection_timeout: Duration,
+    /// Peers that are not allowed to connect, all of their connections are denied.
+    pub blocked_peers: Vec<PeerId>,
+    /// Peers that are allowed to connect, if given then connections with any other peer are denied.
+    ///
+    /// `None` allows all peers that are not blocked.
+    pub allowed_peers: Option<Vec<PeerId>>,
+    /// Peers whose connections are kept alive regardless of `idle_connection_timeout`, e.g. the RPCs.
+    pub keep_alive_peers: Vec<PeerId>,
+    /// Maximum size of a request-response request, in bytes; larger ones fail to be read.
+    pub max_request_size: u64,
+    /// Maximum size of a request-response response, in bytes; larger ones fail to be read.
+    pub max_response_size: u64,
+    /// Timeout of a request-response request, until its response is received.
+    pub request_timeout: Duration,
+    /// Peers whose incoming streams are accepted, e.g. the RPCs; the streams of other peers are dropped.
+    pub stream_peers: Vec<PeerId>,
+    /// Maximum number of incoming streams that are open at once, the new ones are dropped beyond this.
+    pub max_inbound_streams: usize,
+    /// Public addresses to advertise explicitly, e.g. for nodes behind a static NAT.
+    pub external_addrs: Vec<Multiaddr>,
+    /// Resolver of the `/dns`, `/dns4` and `/dns6` addresses.
+    pub dns_resolver: DriaDnsResolver,
+    /// SOCKS5 proxy to dial through, e.g. Tor, where host names are resolved by the proxy instead.
+    pub proxy: Option<DriaSocksProxy>,
+    /// Whether to prefer the zstd-compressed request-response protocols, with a fallback to
+    /// the plain ones for the peers that do not support compression.
+    pub compression: bool,
+    /// Whether to run a relay server for the NATed peers, which requires the `relay-server` feature
+    /// and is only useful for publicly reachable nodes.
+    pub relay_server: bool,
+    /// Peer scoring of GossipSub, which requires the `gossipsub` feature; `None` does not score the peers.
+    pub gossip_peer_score: Option<DriaGossipPeerScore>,
+}
+
+impl Default for DriaP2PConfig {
+    fn default() -> Self {
+        Self {
+            max_established: Some(DEFAULT_MAX_ESTABLISHED),
+            max_established_per_peer: Some(DEFAULT_MAX_ESTABLISHED_PER_PEER),
+            max_pending_incoming: Some(DEFAULT_MAX_PENDING),
+            max_pending_outgoing: Some(DEFAULT_MAX_PENDING),
+            idle_connection_timeout: DEFAULT_IDLE_CONNECTION_TIMEOUT,
+            blocked_peers: Vec::new(),
+            allowed_peers: None,
+            keep_alive_peers: Vec::new(),
+            max_request_size: DEFAULT_MAX_REQUEST_SIZE,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            stream_peers: Vec::new(),
+            max_inbound_streams: DEFAULT_MAX_INBOUND_STREAMS,
+            external_addrs: Vec::new(),
+            dns_resolver: DriaDnsResolver::default(),
+            proxy: None,
+            compression: true,
+            relay_server: false,
+            gossip_peer_score: None,
+        }
+    }
+}
+
+impl DriaP2PConfig {
+    /// Returns the connection limits for the `connection_limits` behaviour.
+    pub(crate) fn connection_limits(&self) -> ConnectionLimits {
+        ConnectionLimits::default()
+            .with_max_established(self.max_established)
+            .with_max_established_per_peer(self.max_established_per_peer)
+            .with_max_pending_incoming(self.max_pending_incoming)
+            .with_max_pending_outgoing(self.max_pending_outgoing)
+    }
+}
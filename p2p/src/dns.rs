@@ -0,0 +1,130 @@
+//! DNS resolution of `/dns`, `/dns4` and `/dns6` addresses.
+//!
+//! Some environments have broken system resolvers, so the resolver can be configured explicitly
+//! with custom name servers or well-known providers, including over DNS-over-HTTPS (DoH).
+
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+/// Default port of plain DNS name servers.
+const DNS_PORT: u16 = 53;
+
+/// DNS resolver of the transport.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DriaDnsResolver {
+    /// Name servers of the system, e.g. from `/etc/resolv.conf`.
+    ///
+    /// Falls back to Cloudflare if the system configuration can not be read.
+    #[default]
+    System,
+    /// The given name servers over plain DNS.
+    Servers(Vec<SocketAddr>),
+    /// Cloudflare over plain DNS.
+    Cloudflare,
+    /// Google over plain DNS.
+    Google,
+    /// Quad9 over plain DNS.
+    Quad9,
+    /// Cloudflare over DNS-over-HTTPS.
+    CloudflareHttps,
+    /// Google over DNS-over-HTTPS.
+    GoogleHttps,
+    /// Quad9 over DNS-over-HTTPS.
+    Quad9Https,
+}
+
+impl DriaDnsResolver {
+    /// Returns the resolver configuration & options.
+    pub(crate) fn resolver_config(&self) -> (ResolverConfig, ResolverOpts) {
+        let config = match self {
+            Self::System => match hickory_resolver::system_conf::read_system_conf() {
+                Ok(system) => return system,
+                Err(err) => {
+                    log::warn!("Could not read system DNS configuration, using Cloudflare: {err}");
+                    ResolverConfig::cloudflare()
+                }
+            },
+            Self::Servers(servers) => {
+                let mut name_servers = NameServerConfigGroup::new();
+                for server in servers {
+                    name_servers.merge(NameServerConfigGroup::from_ips_clear(
+                        &[server.ip()],
+                        server.port(),
+                        true,
+                    ));
+                }
+                ResolverConfig::from_parts(None, vec![], name_servers)
+            }
+            Self::Cloudflare => ResolverConfig::cloudflare(),
+            Self::Google => ResolverConfig::google(),
+            Self::Quad9 => ResolverConfig::quad9(),
+            Self::CloudflareHttps => ResolverConfig::cloudflare_https(),
+            Self::GoogleHttps => ResolverConfig::google_https(),
+            Self::Quad9Https => ResolverConfig::quad9_https(),
+        };
+
+        (config, ResolverOpts::default())
+    }
+}
+
+impl FromStr for DriaDnsResolver {
+    type Err = String;
+
+    /// Parses a resolver from either `system`, a provider such as `cloudflare` or `cloudflare-https`,
+    /// or comma-separated name servers such as `1.1.1.1,8.8.8.8:53`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "system" => Ok(Self::System),
+            "cloudflare" => Ok(Self::Cloudflare),
+            "google" => Ok(Self::Google),
+            "quad9" => Ok(Self::Quad9),
+            "cloudflare-https" => Ok(Self::CloudflareHttps),
+            "google-https" => Ok(Self::GoogleHttps),
+            "quad9-https" => Ok(Self::Quad9Https),
+            servers => servers
+                .split(',')
+                .map(str::trim)
+                .filter(|server| !server.is_empty())
+                .map(|server| {
+                    server
+                        .parse::<SocketAddr>()
+                        .or_else(|_| {
+                            server
+                                .parse::<IpAddr>()
+                                .map(|ip| SocketAddr::new(ip, DNS_PORT))
+                        })
+                        .map_err(|_| format!("invalid DNS name server: {server}"))
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Self::Servers),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dns_resolver() {
+        assert_eq!("".parse::<DriaDnsResolver>(), Ok(DriaDnsResolver::System));
+        assert_eq!(
+            "Cloudflare-HTTPS".parse::<DriaDnsResolver>(),
+            Ok(DriaDnsResolver::CloudflareHttps)
+        );
+        assert_eq!(
+            "1.1.1.1, 8.8.8.8:5353".parse::<DriaDnsResolver>(),
+            Ok(DriaDnsResolver::Servers(vec![
+                "1.1.1.1:53".parse().unwrap(),
+                "8.8.8.8:5353".parse().unwrap()
+            ]))
+        );
+        assert!("not-a-server".parse::<DriaDnsResolver>().is_err());
+
+        let (config, _) =
+            DriaDnsResolver::Servers(vec!["1.1.1.1:53".parse().unwrap()]).resolver_config();
+        // both UDP & TCP
+        assert_eq!(config.name_servers().len(), 2);
+    }
+}
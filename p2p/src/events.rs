@@ -0,0 +1,19 @@
+use libp2p::{Multiaddr, PeerId};
+
+/// High-level events of the peer-to-peer client, for applications to react to the network state.
+///
+/// These are broadcasted to all subscribers, see [`crate::DriaP2PCommander::events`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriaP2PEvent {
+    /// The first connection with a peer is established.
+    Connected { peer_id: PeerId, address: Multiaddr },
+    /// The last connection with a peer is closed.
+    Disconnected { peer_id: PeerId },
+    /// An outgoing connection could not be established.
+    DialFailure {
+        peer_id: Option<PeerId>,
+        error: String,
+    },
+    /// A request-response request is received from a peer.
+    InboundRequest { peer_id: PeerId },
+}
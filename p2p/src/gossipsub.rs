@@ -0,0 +1,236 @@
+//! GossipSub publish/subscribe over arbitrary topics.
+//!
+//! The behaviour is only enabled with the `gossipsub` feature, and is a no-op otherwise
+//! where the commands return an error.
+//!
+//! Peers can be scored as well with [`DriaGossipPeerScore`], so that the spammy ones are penalized.
+
+use libp2p::PeerId;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+/// GossipSub behaviour, which handles the topic subscriptions & message propagation.
+#[cfg(feature = "gossipsub")]
+pub(crate) type GossipsubBehaviour = libp2p::gossipsub::Behaviour;
+#[cfg(not(feature = "gossipsub"))]
+pub(crate) type GossipsubBehaviour = libp2p::swarm::dummy::Behaviour;
+
+/// Buffer size for the messages of a topic, per subscriber.
+#[cfg_attr(not(feature = "gossipsub"), allow(unused))]
+pub(crate) const GOSSIP_CHANNEL_BUFSIZE: usize = 1024;
+
+/// A message received on a subscribed topic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriaGossipMessage {
+    /// Topic of the message.
+    pub topic: String,
+    /// Original author of the message, if known.
+    pub source: Option<PeerId>,
+    /// Peer that has propagated the message to us.
+    pub propagation_source: PeerId,
+    pub data: Vec<u8>,
+}
+
+/// Peer scoring parameters of GossipSub, where the peers below the thresholds are excluded gradually.
+///
+/// Peers are penalized for the invalid messages on the subscribed topics, for misbehaving within the
+/// protocol (e.g. broken gossip promises) and for sharing an IP with too many other peers. The penalties
+/// are applied to the squared counts, which decay over time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriaGossipPeerScore {
+    /// Weight of the invalid messages on a subscribed topic, must be negative.
+    pub invalid_message_weight: f64,
+    /// Weight of the protocol misbehaviours, must be negative.
+    pub behaviour_penalty_weight: f64,
+    /// Weight of the peers that share an IP beyond the `ip_colocation_threshold`, must be negative.
+    pub ip_colocation_weight: f64,
+    /// Number of peers that can share an IP without being penalized.
+    pub ip_colocation_threshold: f64,
+    /// Score below which gossip is neither sent to nor accepted from the peer.
+    pub gossip_threshold: f64,
+    /// Score below which the published messages are not sent to the peer.
+    pub publish_threshold: f64,
+    /// Score below which all messages of the peer are ignored.
+    pub graylist_threshold: f64,
+}
+
+impl Default for DriaGossipPeerScore {
+    fn default() -> Self {
+        Self {
+            invalid_message_weight: -10.0,
+            behaviour_penalty_weight: -10.0,
+            ip_colocation_weight: -5.0,
+            ip_colocation_threshold: 10.0,
+            gossip_threshold: -10.0,
+            publish_threshold: -50.0,
+            graylist_threshold: -80.0,
+        }
+    }
+}
+
+#[cfg(feature = "gossipsub")]
+impl DriaGossipPeerScore {
+    /// Returns the topic-independent score parameters & the thresholds.
+    fn params(
+        &self,
+    ) -> (
+        libp2p::gossipsub::PeerScoreParams,
+        libp2p::gossipsub::PeerScoreThresholds,
+    ) {
+        use libp2p::gossipsub::{PeerScoreParams, PeerScoreThresholds};
+
+        let params = PeerScoreParams {
+            behaviour_penalty_weight: self.behaviour_penalty_weight,
+            ip_colocation_factor_weight: self.ip_colocation_weight,
+            ip_colocation_factor_threshold: self.ip_colocation_threshold,
+            ..Default::default()
+        };
+        let thresholds = PeerScoreThresholds {
+            gossip_threshold: self.gossip_threshold,
+            publish_threshold: self.publish_threshold,
+            graylist_threshold: self.graylist_threshold,
+            ..Default::default()
+        };
+
+        (params, thresholds)
+    }
+
+    /// Returns the score parameters of a subscribed topic, where only the invalid messages are penalized.
+    ///
+    /// The delivery-based parameters are disabled, as the topics are not expected to have a steady traffic.
+    pub(crate) fn topic_params(&self) -> libp2p::gossipsub::TopicScoreParams {
+        libp2p::gossipsub::TopicScoreParams {
+            topic_weight: 1.0,
+            time_in_mesh_weight: 0.0,
+            first_message_deliveries_weight: 0.0,
+            mesh_message_deliveries_weight: 0.0,
+            mesh_failure_penalty_weight: 0.0,
+            invalid_message_deliveries_weight: self.invalid_message_weight,
+            ..Default::default()
+        }
+    }
+}
+
+/// Creates the GossipSub behaviour, where messages are signed by the given key.
+///
+/// Peers are scored if `peer_score` is given, which panics if its parameters are invalid.
+#[cfg(feature = "gossipsub")]
+pub(crate) fn create_gossipsub_behaviour(
+    key: &libp2p::identity::Keypair,
+    peer_score: Option<&DriaGossipPeerScore>,
+) -> GossipsubBehaviour {
+    use libp2p::gossipsub::{Behaviour, ConfigBuilder, MessageAuthenticity, ValidationMode};
+
+    let config = ConfigBuilder::default()
+        .validation_mode(ValidationMode::Strict)
+        .build()
+        .expect("default gossipsub config should be valid");
+
+    let mut behaviour = Behaviour::new(MessageAuthenticity::Signed(key.clone()), config)
+        .expect("signed gossipsub behaviour should be valid");
+    if let Some(peer_score) = peer_score {
+        let (params, thresholds) = peer_score.params();
+        behaviour
+            .with_peer_score(params, thresholds)
+            .expect("gossipsub peer score should be valid");
+    }
+
+    behaviour
+}
+
+/// Receivers of the subscribed topics, where each subscription has its own channel.
+#[derive(Debug, Default)]
+pub(crate) struct GossipSubscriptions {
+    senders: HashMap<String, Vec<mpsc::Sender<DriaGossipMessage>>>,
+}
+
+impl GossipSubscriptions {
+    /// Adds a subscriber to the topic, and returns its receiver.
+    ///
+    /// Returns whether this is the first subscriber of the topic as well.
+    #[cfg_attr(not(feature = "gossipsub"), allow(unused))]
+    pub(crate) fn add(&mut self, topic: &str) -> (mpsc::Receiver<DriaGossipMessage>, bool) {
+        let (tx, rx) = mpsc::channel(GOSSIP_CHANNEL_BUFSIZE);
+        let senders = self.senders.entry(topic.to_string()).or_default();
+        senders.retain(|sender| !sender.is_closed());
+        senders.push(tx);
+
+        (rx, senders.len() == 1)
+    }
+
+    /// Removes all subscribers of the topic, closing their receivers.
+    ///
+    /// Returns whether the topic had any subscribers.
+    #[cfg_attr(not(feature = "gossipsub"), allow(unused))]
+    pub(crate) fn remove(&mut self, topic: &str) -> bool {
+        self.senders.remove(topic).is_some()
+    }
+
+    /// Forwards the message to the subscribers of its topic, dropping the ones that are closed.
+    ///
+    /// Messages are dropped for the subscribers that are lagging behind, so that the client is never blocked.
+    #[cfg_attr(not(feature = "gossipsub"), allow(unused))]
+    pub(crate) fn forward(&mut self, message: DriaGossipMessage) {
+        let Some(senders) = self.senders.get_mut(&message.topic) else {
+            log::debug!("Received a message on unknown topic {}", message.topic);
+            return;
+        };
+
+        senders.retain(|sender| match sender.try_send(message.clone()) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                log::warn!(
+                    "Dropping a message on topic {}, receiver is full",
+                    message.topic
+                );
+                true
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_gossip_subscriptions() {
+        let mut subscriptions = GossipSubscriptions::default();
+        let (mut rx_a, is_first) = subscriptions.add("topic");
+        assert!(is_first);
+        let (rx_b, is_first) = subscriptions.add("topic");
+        assert!(!is_first);
+        drop(rx_b);
+
+        let message = DriaGossipMessage {
+            topic: "topic".to_string(),
+            source: None,
+            propagation_source: PeerId::random(),
+            data: b"hello".to_vec(),
+        };
+        subscriptions.forward(message.clone());
+        assert_eq!(rx_a.recv().await, Some(message));
+        assert_eq!(subscriptions.senders["topic"].len(), 1);
+
+        assert!(subscriptions.remove("topic"));
+        assert_eq!(rx_a.recv().await, None);
+    }
+
+    #[cfg(feature = "gossipsub")]
+    #[test]
+    fn test_gossip_peer_score() {
+        let peer_score = DriaGossipPeerScore::default();
+        let (params, thresholds) = peer_score.params();
+        assert!(params.validate().is_ok());
+        assert!(thresholds.validate().is_ok());
+        assert!(peer_score.topic_params().validate().is_ok());
+
+        // the behaviour is scored, where unknown peers have no score
+        let key = libp2p::identity::Keypair::generate_ed25519();
+        let behaviour = create_gossipsub_behaviour(&key, Some(&peer_score));
+        assert_eq!(behaviour.peer_score(&PeerId::random()), Some(0.0));
+        let behaviour = create_gossipsub_behaviour(&key, None);
+        assert_eq!(behaviour.peer_score(&PeerId::random()), None);
+    }
+}
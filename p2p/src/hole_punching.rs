@@ -0,0 +1,48 @@
+//! Direct connection upgrade through relay (DCUtR), so that relayed connections of NATed nodes
+//! are upgraded to direct ones via hole punching.
+//!
+//! The behaviours are only enabled with the `hole-punching` feature, and are no-ops otherwise.
+
+/// Relay client behaviour, so that connections can be relayed before they are upgraded.
+#[cfg(feature = "hole-punching")]
+pub(crate) type RelayClientBehaviour = libp2p::relay::client::Behaviour;
+#[cfg(not(feature = "hole-punching"))]
+pub(crate) type RelayClientBehaviour = libp2p::swarm::dummy::Behaviour;
+
+/// DCUtR behaviour, which attempts hole punching over relayed connections.
+#[cfg(feature = "hole-punching")]
+pub(crate) type DcutrBehaviour = libp2p::dcutr::Behaviour;
+#[cfg(not(feature = "hole-punching"))]
+pub(crate) type DcutrBehaviour = libp2p::swarm::dummy::Behaviour;
+
+/// Outcomes of direct connection upgrades since the client has started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HolePunchStats {
+    /// Number of relayed connections upgraded to direct ones.
+    pub succeeded: usize,
+    /// Number of failed upgrades, where the connection stays relayed.
+    pub failed: usize,
+}
+
+#[cfg(feature = "hole-punching")]
+impl HolePunchStats {
+    /// Records the outcome of an upgrade.
+    pub(crate) fn record(&mut self, event: libp2p::dcutr::Event) {
+        match event.result {
+            Ok(connection_id) => {
+                log::info!(
+                    "Connection ({connection_id}) with {} upgraded to direct",
+                    event.remote_peer_id
+                );
+                self.succeeded += 1;
+            }
+            Err(err) => {
+                log::warn!(
+                    "Could not upgrade connection with {} to direct: {err}",
+                    event.remote_peer_id
+                );
+                self.failed += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Keeps the connections with certain peers alive, regardless of the idle connection timeout.
+//!
+//! Connections are otherwise closed once they have no active streams for the idle timeout,
+//! which drops the RPC connection during long idle periods & triggers needless rediscovery.
+
+use libp2p::core::{transport::PortUse, upgrade::DeniedUpgrade, Endpoint};
+use libp2p::swarm::{
+    handler::ConnectionEvent, ConnectionDenied, ConnectionHandlerEvent, ConnectionId, FromSwarm,
+    NetworkBehaviour, NotifyHandler, SubstreamProtocol, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use libp2p::{Multiaddr, PeerId};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::task::{Context, Poll};
+
+/// Behaviour that keeps the connections with the given peers alive.
+///
+/// It does not handle any protocols, it only marks the connections of the peers as alive.
+#[derive(Debug, Default)]
+pub struct Behaviour {
+    /// Peers whose connections are kept alive.
+    peers: HashSet<PeerId>,
+    /// Established connections, so that their handlers can be notified when a peer is updated.
+    connections: HashMap<PeerId, HashSet<ConnectionId>>,
+    /// Notifications to the handlers, to be returned by `poll`.
+    pending: VecDeque<ToSwarm<Infallible, bool>>,
+}
+
+impl Behaviour {
+    pub fn new(peers: impl IntoIterator<Item = PeerId>) -> Self {
+        Self {
+            peers: peers.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets whether the connections with the peer are kept alive, including the existing ones.
+    ///
+    /// Returns `true` if the peer was updated, `false` if it was already set as such.
+    pub fn set_keep_alive(&mut self, peer_id: PeerId, keep_alive: bool) -> bool {
+        let updated = if keep_alive {
+            self.peers.insert(peer_id)
+        } else {
+            self.peers.remove(&peer_id)
+        };
+
+        if updated {
+            for connection_id in self.connections.get(&peer_id).into_iter().flatten() {
+                self.pending.push_back(ToSwarm::NotifyHandler {
+                    peer_id,
+                    handler: NotifyHandler::One(*connection_id),
+                    event: keep_alive,
+                });
+            }
+        }
+
+        updated
+    }
+
+    /// Returns a new handler for a connection with the peer.
+    fn handler(&mut self, peer_id: PeerId, connection_id: ConnectionId) -> Handler {
+        self.connections
+            .entry(peer_id)
+            .or_default()
+            .insert(connection_id);
+
+        Handler {
+            keep_alive: self.peers.contains(&peer_id),
+        }
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = Handler;
+    type ToSwarm = Infallible;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer_id: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(self.handler(peer_id, connection_id))
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer_id: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+        _: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(self.handler(peer_id, connection_id))
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        if let FromSwarm::ConnectionClosed(closed) = event {
+            if let Some(connections) = self.connections.get_mut(&closed.peer_id) {
+                connections.remove(&closed.connection_id);
+                if connections.is_empty() {
+                    self.connections.remove(&closed.peer_id);
+                }
+            }
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        match event {}
+    }
+
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        match self.pending.pop_front() {
+            Some(event) => Poll::Ready(event),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// Connection handler that keeps its connection alive as told by the behaviour.
+#[derive(Debug, Clone)]
+pub struct Handler {
+    keep_alive: bool,
+}
+
+impl libp2p::swarm::ConnectionHandler for Handler {
+    type FromBehaviour = bool;
+    type ToBehaviour = Infallible;
+    type InboundProtocol = DeniedUpgrade;
+    type OutboundProtocol = DeniedUpgrade;
+    type InboundOpenInfo = ();
+    type OutboundOpenInfo = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol> {
+        SubstreamProtocol::new(DeniedUpgrade, ())
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        self.keep_alive
+    }
+
+    fn on_behaviour_event(&mut self, keep_alive: bool) {
+        self.keep_alive = keep_alive;
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<ConnectionHandlerEvent<Self::OutboundProtocol, (), Self::ToBehaviour>> {
+        Poll::Pending
+    }
+
+    fn on_connection_event(
+        &mut self,
+        _: ConnectionEvent<Self::InboundProtocol, Self::OutboundProtocol>,
+    ) {
+        // no protocols are supported, so there is nothing to handle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keep_alive() {
+        let peer_id = libp2p::identity::Keypair::generate_ed25519()
+            .public()
+            .to_peer_id();
+        let other_peer_id = libp2p::identity::Keypair::generate_ed25519()
+            .public()
+            .to_peer_id();
+
+        let mut behaviour = Behaviour::new([peer_id]);
+        let connection_id = ConnectionId::new_unchecked(0);
+        assert!(behaviour.handler(peer_id, connection_id).keep_alive);
+        assert!(
+            !behaviour
+                .handler(other_peer_id, ConnectionId::new_unchecked(1))
+                .keep_alive
+        );
+
+        // the existing connection is notified
+        assert!(behaviour.set_keep_alive(peer_id, false));
+        assert!(!behaviour.set_keep_alive(peer_id, false));
+        assert!(matches!(
+            behaviour.pending.pop_front(),
+            Some(ToSwarm::NotifyHandler {
+                handler: NotifyHandler::One(id),
+                event: false,
+                ..
+            }) if id == connection_id
+        ));
+        assert!(behaviour.pending.is_empty());
+    }
+}
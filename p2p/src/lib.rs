@@ -1,14 +1,53 @@
 mod behaviour;
+mod codec;
 
 mod client;
 pub use client::{DriaP2PClient, DriaReqResMessage};
 
+mod events;
+pub use events::DriaP2PEvent;
+
+mod dns;
+pub use dns::DriaDnsResolver;
+
+mod socks;
+pub use socks::DriaSocksProxy;
+
+mod transport;
+
+mod stream;
+pub use stream::DriaStream;
+
+mod config;
+pub use config::DriaP2PConfig;
+
 mod commands;
 pub use commands::{DriaP2PCommand, DriaP2PCommander};
 
 mod protocol;
 pub use protocol::DriaP2PProtocol;
 
+#[cfg(feature = "websocket")]
+mod websocket;
+
+mod metrics;
+
+mod bandwidth;
+pub use bandwidth::{BandwidthUsage, DriaP2PStats};
+
+mod gossipsub;
+pub use gossipsub::{DriaGossipMessage, DriaGossipPeerScore};
+
+mod keep_alive;
+
+mod ping;
+
+mod hole_punching;
+pub use hole_punching::HolePunchStats;
+
+mod relay_server;
+pub use relay_server::RelayServerStats;
+
 // re-exports
 pub use libp2p;
 pub use libp2p_identity;
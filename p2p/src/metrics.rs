@@ -0,0 +1,131 @@
+//! Metrics of the swarm & its behaviours, recorded through [`libp2p::metrics`] along with the
+//! request-response counters that it does not provide.
+
+use libp2p::metrics::{Metrics, Recorder, Registry};
+use libp2p::request_response;
+use libp2p::swarm::SwarmEvent;
+use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue};
+use prometheus_client::metrics::{counter::Counter, family::Family};
+
+use crate::behaviour::DriaBehaviourEvent;
+
+/// Kind of a request-response event.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum ReqResEventKind {
+    Request,
+    Response,
+    ResponseSent,
+    OutboundFailure,
+    InboundFailure,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ReqResLabels {
+    event: ReqResEventKind,
+}
+
+/// Recorder of the swarm, behaviour & request-response metrics into a registry.
+pub(crate) struct DriaMetrics {
+    libp2p: Metrics,
+    reqres_events: Family<ReqResLabels, Counter>,
+}
+
+impl DriaMetrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let libp2p = Metrics::new(registry);
+
+        let reqres_events = Family::default();
+        registry
+            .sub_registry_with_prefix("dria")
+            .sub_registry_with_prefix("request_response")
+            .register(
+                "events",
+                "Number of request-response events by kind",
+                reqres_events.clone(),
+            );
+
+        Self {
+            libp2p,
+            reqres_events,
+        }
+    }
+
+    /// Records the given swarm event, along with the behaviour event within.
+    pub(crate) fn record(&self, event: &SwarmEvent<DriaBehaviourEvent>) {
+        self.libp2p.record(event);
+
+        match event {
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Identify(event)) => self.libp2p.record(event),
+            SwarmEvent::Behaviour(DriaBehaviourEvent::RequestResponse(event)) => {
+                let kind = match event {
+                    request_response::Event::Message {
+                        message: request_response::Message::Request { .. },
+                        ..
+                    } => ReqResEventKind::Request,
+                    request_response::Event::Message {
+                        message: request_response::Message::Response { .. },
+                        ..
+                    } => ReqResEventKind::Response,
+                    request_response::Event::ResponseSent { .. } => ReqResEventKind::ResponseSent,
+                    request_response::Event::OutboundFailure { .. } => {
+                        ReqResEventKind::OutboundFailure
+                    }
+                    request_response::Event::InboundFailure { .. } => {
+                        ReqResEventKind::InboundFailure
+                    }
+                };
+                self.reqres_events
+                    .get_or_create(&ReqResLabels { event: kind })
+                    .inc();
+            }
+            #[cfg(feature = "gossipsub")]
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Gossipsub(event)) => {
+                self.libp2p.record(event)
+            }
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Ping(event)) => self.libp2p.record(event),
+            #[cfg(feature = "hole-punching")]
+            SwarmEvent::Behaviour(DriaBehaviourEvent::Dcutr(event)) => self.libp2p.record(event),
+            #[cfg(feature = "relay-server")]
+            SwarmEvent::Behaviour(DriaBehaviourEvent::RelayServer(event)) => {
+                self.libp2p.record(event)
+            }
+            #[cfg(feature = "hole-punching")]
+            SwarmEvent::Behaviour(DriaBehaviourEvent::RelayClient(_)) => {
+                // only the relay server events are recorded by libp2p
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Encodes the registry in the text exposition format of OpenMetrics.
+pub(crate) fn encode_registry(registry: &Registry) -> String {
+    let mut encoded = String::new();
+    if let Err(err) = prometheus_client::encoding::text::encode(&mut encoded, registry) {
+        log::error!("Could not encode metrics: {err:?}");
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reqres_metrics() {
+        let mut registry = Registry::default();
+        let metrics = DriaMetrics::new(&mut registry);
+        metrics
+            .reqres_events
+            .get_or_create(&ReqResLabels {
+                event: ReqResEventKind::OutboundFailure,
+            })
+            .inc();
+
+        let encoded = encode_registry(&registry);
+        assert!(encoded.contains("dria_request_response_events_total{event=\"OutboundFailure\"} 1"));
+        // swarm metrics of libp2p are registered as well
+        assert!(encoded.contains("libp2p_swarm_"));
+    }
+}
@@ -0,0 +1,73 @@
+//! Ping behaviour, which measures the round-trip time (RTT) to the connected peers.
+
+use libp2p::PeerId;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// Number of the most recent RTTs that the average is taken over, per peer.
+const RTT_WINDOW: usize = 10;
+
+/// Rolling RTTs of the connected peers.
+#[derive(Debug, Default)]
+pub(crate) struct RttTracker {
+    samples: HashMap<PeerId, VecDeque<Duration>>,
+}
+
+impl RttTracker {
+    /// Records an RTT to the peer, forgetting the oldest one if the window is full.
+    pub(crate) fn record(&mut self, peer_id: PeerId, rtt: Duration) {
+        let samples = self.samples.entry(peer_id).or_default();
+        if samples.len() == RTT_WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(rtt);
+    }
+
+    /// Forgets the RTTs to the peer, e.g. when it is disconnected.
+    pub(crate) fn remove(&mut self, peer_id: &PeerId) {
+        self.samples.remove(peer_id);
+    }
+
+    /// Returns the average of the recent RTTs to the peer, if any.
+    pub(crate) fn average(&self, peer_id: &PeerId) -> Option<Duration> {
+        let samples = self.samples.get(peer_id).filter(|s| !s.is_empty())?;
+        Some(samples.iter().sum::<Duration>() / samples.len() as u32)
+    }
+
+    /// Records the outcome of a ping.
+    pub(crate) fn record_event(&mut self, event: libp2p::ping::Event) {
+        match event.result {
+            Ok(rtt) => {
+                log::trace!("Ping to {} took {rtt:?}", event.peer);
+                self.record(event.peer, rtt);
+            }
+            Err(err) => log::debug!("Could not ping {}: {err}", event.peer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtt_tracker() {
+        let peer_id = libp2p::identity::Keypair::generate_ed25519()
+            .public()
+            .to_peer_id();
+        let mut tracker = RttTracker::default();
+        assert_eq!(tracker.average(&peer_id), None);
+
+        for ms in 1..=RTT_WINDOW as u64 + 10 {
+            tracker.record(peer_id, Duration::from_millis(ms));
+        }
+        // only the last 10 RTTs, i.e. 11ms to 20ms, are averaged
+        assert_eq!(
+            tracker.average(&peer_id),
+            Some(Duration::from_micros(15_500))
+        );
+
+        tracker.remove(&peer_id);
+        assert_eq!(tracker.average(&peer_id), None);
+    }
+}
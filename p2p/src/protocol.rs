@@ -18,6 +18,10 @@ pub struct DriaP2PProtocol {
     /// which is mandatory for a `StreamProtocol`.
     ///
     pub request_response: StreamProtocol,
+    /// Stream protocol for long outputs, must match with other peers in the network.
+    ///
+    /// This is usually `/{name}/stream/{version}`.
+    pub stream: StreamProtocol,
 }
 
 impl std::fmt::Display for DriaP2PProtocol {
@@ -33,6 +37,36 @@ impl Default for DriaP2PProtocol {
     }
 }
 
+impl std::str::FromStr for DriaP2PProtocol {
+    type Err = String;
+
+    /// Parses the protocol from `name/version`, e.g. `acme-dria/0.5`, or only its `name` with
+    /// the current `major.minor` version as in [`Self::new_major_minor`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, version) = match s.trim().split_once('/') {
+            Some((name, version)) => (name, Some(version)),
+            None => (s.trim(), None),
+        };
+
+        let is_valid = |part: &str| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        };
+        if !is_valid(name) {
+            return Err(format!("invalid protocol name: {name:?}"));
+        }
+        match version {
+            Some(version) if !is_valid(version) => {
+                Err(format!("invalid protocol version: {version:?}"))
+            }
+            Some(version) => Ok(Self::new(name, version)),
+            None => Ok(Self::new_major_minor(name)),
+        }
+    }
+}
+
 impl DriaP2PProtocol {
     /// Creates a new instance of the protocol with the given `name` and `version`.
     pub fn new(name: impl ToString, version: impl ToString) -> Self {
@@ -42,12 +76,14 @@ impl DriaP2PProtocol {
         let identity = format!("{name}/{version}");
         let request_response =
             StreamProtocol::try_from_owned(format!("/{name}/rr/{version}")).unwrap();
+        let stream = StreamProtocol::try_from_owned(format!("/{name}/stream/{version}")).unwrap();
 
         Self {
             name,
             version,
             identity,
             request_response,
+            stream,
         }
     }
 
@@ -72,6 +108,56 @@ impl DriaP2PProtocol {
     pub fn request_response(&self) -> StreamProtocol {
         self.request_response.clone()
     }
+
+    /// Returns the stream protocol, e.g. `/dria/stream/0.2`.
+    pub fn stream(&self) -> StreamProtocol {
+        self.stream.clone()
+    }
+
+    /// Returns the previous `major.minor` version, e.g. `0.1` for `0.2`.
+    ///
+    /// Returns `None` for the first minor version of a major, or if the version is not `major.minor`.
+    pub fn previous_version(&self) -> Option<String> {
+        let (major, minor) = self.version.split_once('.')?;
+        let major = major.parse::<u32>().ok()?;
+        let minor = minor.parse::<u32>().ok()?.checked_sub(1)?;
+
+        Some(format!("{major}.{minor}"))
+    }
+
+    /// Returns the supported request-response protocols along with their versions, the current one first.
+    ///
+    /// The previous version is supported as well, so that a protocol bump does not orphan the peers
+    /// that have not upgraded yet.
+    pub fn request_response_protocols(&self) -> Vec<(String, StreamProtocol)> {
+        let mut protocols = vec![(self.version.clone(), self.request_response())];
+        if let Some(version) = self.previous_version() {
+            if let Ok(protocol) =
+                StreamProtocol::try_from_owned(format!("/{}/rr/{version}", self.name))
+            {
+                protocols.push((version, protocol));
+            }
+        }
+
+        protocols
+    }
+
+    /// Returns whether the given Identify protocol is of a supported version, e.g. `dria/0.2` or `dria/0.1`.
+    pub fn is_compatible_identity(&self, identity: &str) -> bool {
+        identity == self.identity
+            || self
+                .previous_version()
+                .is_some_and(|version| identity == format!("{}/{version}", self.name))
+    }
+
+    /// Returns the version of the request-response protocol to be negotiated with a peer
+    /// that supports the given protocols, i.e. the latest version that both sides support.
+    pub fn negotiated_version(&self, peer_protocols: &[StreamProtocol]) -> Option<String> {
+        self.request_response_protocols()
+            .into_iter()
+            .find(|(_, protocol)| peer_protocols.contains(protocol))
+            .map(|(version, _)| version)
+    }
 }
 
 #[cfg(test)]
@@ -87,6 +173,52 @@ mod tests {
         assert_eq!(protocol.request_response.to_string(), "/test/rr/1.0");
     }
 
+    #[test]
+    fn test_previous_version() {
+        let protocol = DriaP2PProtocol::new("test", "0.6");
+        assert_eq!(protocol.previous_version(), Some("0.5".to_string()));
+        assert!(protocol.is_compatible_identity("test/0.6"));
+        assert!(protocol.is_compatible_identity("test/0.5"));
+        assert!(!protocol.is_compatible_identity("test/0.4"));
+
+        let protocols = protocol.request_response_protocols();
+        assert_eq!(protocols.len(), 2);
+        assert_eq!(protocols[1].1.to_string(), "/test/rr/0.5");
+
+        let previous = StreamProtocol::new("/test/rr/0.5");
+        let current = StreamProtocol::new("/test/rr/0.6");
+        assert_eq!(
+            protocol.negotiated_version(&[previous.clone(), current]),
+            Some("0.6".to_string())
+        );
+        assert_eq!(
+            protocol.negotiated_version(&[previous]),
+            Some("0.5".to_string())
+        );
+        assert_eq!(protocol.negotiated_version(&[]), None);
+
+        // no previous version for the first minor
+        let protocol = DriaP2PProtocol::new("test", "1.0");
+        assert_eq!(protocol.previous_version(), None);
+        assert_eq!(protocol.request_response_protocols().len(), 1);
+    }
+
+    #[test]
+    fn test_from_str() {
+        let protocol = "acme-dria/0.5".parse::<DriaP2PProtocol>().unwrap();
+        assert_eq!(protocol.identity, "acme-dria/0.5");
+        assert_eq!(protocol.request_response.to_string(), "/acme-dria/rr/0.5");
+
+        let protocol = "acme-dria".parse::<DriaP2PProtocol>().unwrap();
+        assert_eq!(protocol.name, "acme-dria");
+        assert_eq!(protocol.version, DriaP2PProtocol::default().version);
+
+        assert!("".parse::<DriaP2PProtocol>().is_err());
+        assert!("acme/".parse::<DriaP2PProtocol>().is_err());
+        assert!("acme/0.5/rr".parse::<DriaP2PProtocol>().is_err());
+        assert!("acme dria/0.5".parse::<DriaP2PProtocol>().is_err());
+    }
+
     #[test]
     fn test_new_major_minor() {
         let protocol = DriaP2PProtocol::new_major_minor("test");
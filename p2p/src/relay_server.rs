@@ -0,0 +1,114 @@
+//! Relay server, so that well-connected nodes can relay the connections of NATed peers,
+//! which can then be upgraded to direct ones by their hole punching.
+//!
+//! The behaviour is only enabled with the `relay-server` feature along with the `relay_server`
+//! config, and is a no-op otherwise.
+
+use libp2p::PeerId;
+use std::collections::HashSet;
+
+/// Relay server behaviour, which is toggled by the config.
+#[cfg(feature = "relay-server")]
+pub(crate) type RelayServerBehaviour =
+    libp2p::swarm::behaviour::toggle::Toggle<libp2p::relay::Behaviour>;
+#[cfg(not(feature = "relay-server"))]
+pub(crate) type RelayServerBehaviour = libp2p::swarm::dummy::Behaviour;
+
+/// Creates the relay server behaviour, if enabled.
+#[cfg(feature = "relay-server")]
+pub(crate) fn create_relay_server_behaviour(
+    peer_id: PeerId,
+    enabled: bool,
+) -> RelayServerBehaviour {
+    enabled
+        .then(|| libp2p::relay::Behaviour::new(peer_id, libp2p::relay::Config::default()))
+        .into()
+}
+#[cfg(not(feature = "relay-server"))]
+pub(crate) fn create_relay_server_behaviour(_: PeerId, enabled: bool) -> RelayServerBehaviour {
+    if enabled {
+        log::warn!("Relay server requires the `relay-server` feature, which is not enabled.");
+    }
+    libp2p::swarm::dummy::Behaviour
+}
+
+/// Reservations & circuits served by the relay server since the client has started.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelayServerStats {
+    /// Peers with an active reservation, i.e. that are reachable through this node.
+    pub reservations: HashSet<PeerId>,
+    /// Number of accepted reservations, excluding renewals.
+    pub reservations_served: usize,
+    /// Number of active circuits, i.e. relayed connections.
+    pub circuits: usize,
+    /// Number of accepted circuits.
+    pub circuits_served: usize,
+}
+
+impl RelayServerStats {
+    /// Records a reservation of the peer, renewals are not counted as new.
+    #[cfg_attr(not(feature = "relay-server"), allow(unused))]
+    fn reservation_accepted(&mut self, peer_id: PeerId) {
+        if self.reservations.insert(peer_id) {
+            self.reservations_served += 1;
+        }
+    }
+
+    /// Records the expiry of a reservation of the peer.
+    #[cfg_attr(not(feature = "relay-server"), allow(unused))]
+    fn reservation_expired(&mut self, peer_id: &PeerId) {
+        self.reservations.remove(peer_id);
+    }
+
+    /// Records the outcome of a relay server event.
+    #[cfg(feature = "relay-server")]
+    pub(crate) fn record(&mut self, event: libp2p::relay::Event) {
+        use libp2p::relay::Event;
+
+        match event {
+            Event::ReservationReqAccepted { src_peer_id, .. } => {
+                log::debug!("Relay server: accepted reservation of {src_peer_id}");
+                self.reservation_accepted(src_peer_id);
+            }
+            Event::ReservationTimedOut { src_peer_id } => {
+                log::debug!("Relay server: reservation of {src_peer_id} timed out");
+                self.reservation_expired(&src_peer_id);
+            }
+            Event::CircuitReqAccepted {
+                src_peer_id,
+                dst_peer_id,
+            } => {
+                log::debug!("Relay server: relaying {src_peer_id} to {dst_peer_id}");
+                self.circuits += 1;
+                self.circuits_served += 1;
+            }
+            Event::CircuitClosed { .. } => {
+                self.circuits = self.circuits.saturating_sub(1);
+            }
+            event => log::debug!("Relay server: {event:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_server_stats() {
+        let peer_id = libp2p::identity::Keypair::generate_ed25519()
+            .public()
+            .to_peer_id();
+        let mut stats = RelayServerStats::default();
+
+        // renewal is not counted again
+        stats.reservation_accepted(peer_id);
+        stats.reservation_accepted(peer_id);
+        assert_eq!(stats.reservations.len(), 1);
+        assert_eq!(stats.reservations_served, 1);
+
+        stats.reservation_expired(&peer_id);
+        assert!(stats.reservations.is_empty());
+        assert_eq!(stats.reservations_served, 1);
+    }
+}
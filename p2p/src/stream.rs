@@ -0,0 +1,385 @@
+//! Stream protocol for transferring long outputs, e.g. large results or token streams,
+//! over a dedicated substream instead of a single request-response message.
+//!
+//! Data is written in length-prefixed frames, so that each chunk is read as written.
+//!
+//! Incoming streams are only accepted from the authorized peers, e.g. the RPCs, and at most
+//! a limited number of them can be open at once; the others are dropped.
+
+use eyre::{eyre, Result};
+use futures::prelude::*;
+use libp2p::core::{transport::PortUse, upgrade::ReadyUpgrade, Endpoint};
+use libp2p::swarm::{
+    handler::ConnectionEvent, ConnectionDenied, ConnectionHandlerEvent, ConnectionId, FromSwarm,
+    NetworkBehaviour, NotifyHandler, SubstreamProtocol, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use libp2p::{Multiaddr, PeerId, Stream, StreamProtocol};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::io;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::{mpsc, oneshot, OwnedSemaphorePermit, Semaphore};
+
+/// Buffer size for the incoming streams that are not yet accepted.
+const INCOMING_STREAMS_BUFSIZE: usize = 64;
+
+/// An open stream with a peer, where data is written & read in frames.
+#[derive(Debug)]
+pub struct DriaStream {
+    peer_id: PeerId,
+    inner: Stream,
+    /// Maximum size of a frame to be read, larger ones fail to be read.
+    max_frame_size: u64,
+    /// Slot of an incoming stream among the open ones, released when the stream is dropped.
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl DriaStream {
+    /// Returns the peer at the other end of the stream.
+    pub fn peer_id(&self) -> PeerId {
+        self.peer_id
+    }
+
+    /// Writes the given data as a single frame.
+    pub async fn write(&mut self, data: &[u8]) -> Result<()> {
+        write_frame(&mut self.inner, data).await?;
+        Ok(())
+    }
+
+    /// Reads the next frame, returns `None` if the stream is closed by the peer.
+    pub async fn read(&mut self) -> Result<Option<Vec<u8>>> {
+        Ok(read_frame(&mut self.inner, self.max_frame_size).await?)
+    }
+
+    /// Closes the stream for writing, so that the peer reads `None` after the written frames.
+    pub async fn close(mut self) -> Result<()> {
+        self.inner.close().await?;
+        Ok(())
+    }
+}
+
+/// Writes the data prefixed by its length as a big-endian `u32`.
+async fn write_frame<W: AsyncWrite + Unpin>(io: &mut W, data: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(data.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    io.write_all(&len.to_be_bytes()).await?;
+    io.write_all(data).await?;
+    io.flush().await
+}
+
+/// Reads a length-prefixed frame, returns `None` if the stream ends before a new frame.
+async fn read_frame<R: AsyncRead + Unpin>(
+    io: &mut R,
+    max_size: u64,
+) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match io.read_exact(&mut len).await {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+
+    let len = u32::from_be_bytes(len);
+    if u64::from(len) > max_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {len} bytes exceeds the maximum of {max_size} bytes"),
+        ));
+    }
+
+    let mut data = vec![0u8; len as usize];
+    io.read_exact(&mut data).await?;
+    Ok(Some(data))
+}
+
+/// Behaviour that opens & accepts the streams of a single protocol.
+pub struct Behaviour {
+    protocol: StreamProtocol,
+    max_frame_size: u64,
+    /// Established connections, so that streams can be opened over them.
+    connections: HashMap<PeerId, HashSet<ConnectionId>>,
+    /// Receiver of the incoming streams, which are dropped if there is none.
+    incoming_tx: Option<mpsc::Sender<DriaStream>>,
+    /// Peers whose incoming streams are accepted.
+    authorized_peers: HashSet<PeerId>,
+    /// Slots of the incoming streams that can be open at once.
+    inbound_slots: Arc<Semaphore>,
+    /// Requests to the handlers to open streams, to be returned by `poll`.
+    pending: VecDeque<ToSwarm<Infallible, oneshot::Sender<Result<DriaStream>>>>,
+}
+
+impl Behaviour {
+    pub fn new(
+        protocol: StreamProtocol,
+        max_frame_size: u64,
+        authorized_peers: impl IntoIterator<Item = PeerId>,
+        max_inbound_streams: usize,
+    ) -> Self {
+        Self {
+            protocol,
+            max_frame_size,
+            connections: HashMap::new(),
+            incoming_tx: None,
+            authorized_peers: authorized_peers.into_iter().collect(),
+            inbound_slots: Arc::new(Semaphore::new(max_inbound_streams)),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Sets whether the incoming streams of the peer are accepted, the ones that are already
+    /// accepted are kept open.
+    ///
+    /// Returns `true` if the peer was updated, `false` if it was already set as such.
+    pub fn set_authorized(&mut self, peer_id: PeerId, authorized: bool) -> bool {
+        if authorized {
+            self.authorized_peers.insert(peer_id)
+        } else {
+            self.authorized_peers.remove(&peer_id)
+        }
+    }
+
+    /// Opens a new stream with the peer over one of its connections, which is sent to the `sender`.
+    pub fn open_stream(&mut self, peer_id: PeerId, sender: oneshot::Sender<Result<DriaStream>>) {
+        let Some(connection_id) = self
+            .connections
+            .get(&peer_id)
+            .and_then(|connections| connections.iter().next())
+        else {
+            let _ = sender.send(Err(eyre!("not connected to {peer_id}")));
+            return;
+        };
+
+        self.pending.push_back(ToSwarm::NotifyHandler {
+            peer_id,
+            handler: NotifyHandler::One(*connection_id),
+            event: sender,
+        });
+    }
+
+    /// Returns a receiver of the incoming streams, replacing the previous one.
+    pub fn accept_streams(&mut self) -> mpsc::Receiver<DriaStream> {
+        let (incoming_tx, incoming_rx) = mpsc::channel(INCOMING_STREAMS_BUFSIZE);
+        self.incoming_tx = Some(incoming_tx);
+        incoming_rx
+    }
+
+    /// Returns a new handler for a connection with the peer.
+    fn handler(&mut self, peer_id: PeerId, connection_id: ConnectionId) -> Handler {
+        self.connections
+            .entry(peer_id)
+            .or_default()
+            .insert(connection_id);
+
+        Handler {
+            peer_id,
+            protocol: self.protocol.clone(),
+            max_frame_size: self.max_frame_size,
+            pending_opens: VecDeque::new(),
+            requested_opens: VecDeque::new(),
+            incoming: VecDeque::new(),
+        }
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = Handler;
+    type ToSwarm = Infallible;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer_id: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(self.handler(peer_id, connection_id))
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer_id: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+        _: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(self.handler(peer_id, connection_id))
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        if let FromSwarm::ConnectionClosed(closed) = event {
+            if let Some(connections) = self.connections.get_mut(&closed.peer_id) {
+                connections.remove(&closed.connection_id);
+                if connections.is_empty() {
+                    self.connections.remove(&closed.peer_id);
+                }
+            }
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        _: ConnectionId,
+        mut stream: THandlerOutEvent<Self>,
+    ) {
+        let Some(incoming_tx) = &self.incoming_tx else {
+            log::debug!("Dropping stream from {peer_id}, streams are not accepted.");
+            return;
+        };
+        if !self.authorized_peers.contains(&peer_id) {
+            log::warn!("Dropping stream from unauthorized peer {peer_id}");
+            return;
+        }
+        let Ok(permit) = self.inbound_slots.clone().try_acquire_owned() else {
+            log::warn!("Dropping stream from {peer_id}, too many open incoming streams.");
+            return;
+        };
+
+        stream._permit = Some(permit);
+        if let Err(err) = incoming_tx.try_send(stream) {
+            log::warn!("Dropping stream from {peer_id}: {err}");
+        }
+    }
+
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        match self.pending.pop_front() {
+            Some(event) => Poll::Ready(event),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// Connection handler that opens the requested streams, and hands over the incoming ones.
+pub struct Handler {
+    peer_id: PeerId,
+    protocol: StreamProtocol,
+    max_frame_size: u64,
+    /// Streams to be opened, not yet requested from the connection.
+    pending_opens: VecDeque<oneshot::Sender<Result<DriaStream>>>,
+    /// Streams that are requested from the connection, awaiting negotiation.
+    requested_opens: VecDeque<oneshot::Sender<Result<DriaStream>>>,
+    /// Incoming streams, to be handed to the behaviour.
+    incoming: VecDeque<DriaStream>,
+}
+
+impl Handler {
+    fn stream(&self, inner: Stream) -> DriaStream {
+        DriaStream {
+            peer_id: self.peer_id,
+            inner,
+            max_frame_size: self.max_frame_size,
+            _permit: None,
+        }
+    }
+}
+
+impl libp2p::swarm::ConnectionHandler for Handler {
+    type FromBehaviour = oneshot::Sender<Result<DriaStream>>;
+    type ToBehaviour = DriaStream;
+    type InboundProtocol = ReadyUpgrade<StreamProtocol>;
+    type OutboundProtocol = ReadyUpgrade<StreamProtocol>;
+    type InboundOpenInfo = ();
+    type OutboundOpenInfo = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol> {
+        SubstreamProtocol::new(ReadyUpgrade::new(self.protocol.clone()), ())
+    }
+
+    fn on_behaviour_event(&mut self, sender: Self::FromBehaviour) {
+        self.pending_opens.push_back(sender);
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<ConnectionHandlerEvent<Self::OutboundProtocol, (), Self::ToBehaviour>> {
+        if let Some(stream) = self.incoming.pop_front() {
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(stream));
+        }
+
+        match self.pending_opens.pop_front() {
+            Some(sender) => {
+                self.requested_opens.push_back(sender);
+                Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
+                    protocol: SubstreamProtocol::new(ReadyUpgrade::new(self.protocol.clone()), ()),
+                })
+            }
+            None => Poll::Pending,
+        }
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<Self::InboundProtocol, Self::OutboundProtocol>,
+    ) {
+        match event {
+            ConnectionEvent::FullyNegotiatedInbound(inbound) => {
+                let stream = self.stream(inbound.protocol);
+                self.incoming.push_back(stream);
+            }
+            // the requested streams are interchangeable, so they are handed out in order
+            ConnectionEvent::FullyNegotiatedOutbound(outbound) => {
+                let stream = self.stream(outbound.protocol);
+                if let Some(sender) = self.requested_opens.pop_front() {
+                    let _ = sender.send(Ok(stream));
+                }
+            }
+            ConnectionEvent::DialUpgradeError(failure) => {
+                if let Some(sender) = self.requested_opens.pop_front() {
+                    let _ = sender.send(Err(eyre!(
+                        "could not open stream with {}: {}",
+                        self.peer_id,
+                        failure.error
+                    )));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_frames() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"hello").await.unwrap();
+        write_frame(&mut buf, b"").await.unwrap();
+        write_frame(&mut buf, b"world").await.unwrap();
+
+        let mut io = buf.as_slice();
+        assert_eq!(
+            read_frame(&mut io, 5).await.unwrap(),
+            Some(b"hello".to_vec())
+        );
+        assert_eq!(read_frame(&mut io, 5).await.unwrap(), Some(Vec::new()));
+        assert_eq!(
+            read_frame(&mut io, 5).await.unwrap(),
+            Some(b"world".to_vec())
+        );
+        assert_eq!(read_frame(&mut io, 5).await.unwrap(), None);
+
+        // frames larger than the maximum are rejected
+        let mut io = buf.as_slice();
+        assert!(read_frame(&mut io, 4).await.is_err());
+    }
+
+    #[test]
+    fn test_authorized_peers() {
+        let rpc = PeerId::random();
+        let mut behaviour = Behaviour::new(StreamProtocol::new("/dria/stream/0.1"), 1024, [rpc], 2);
+        assert!(behaviour.authorized_peers.contains(&rpc));
+        assert_eq!(behaviour.inbound_slots.available_permits(), 2);
+
+        let peer = PeerId::random();
+        assert!(behaviour.set_authorized(peer, true));
+        assert!(!behaviour.set_authorized(peer, true));
+        assert!(behaviour.set_authorized(rpc, false));
+        assert!(!behaviour.authorized_peers.contains(&rpc));
+    }
+}
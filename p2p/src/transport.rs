@@ -0,0 +1,45 @@
+use libp2p::core::{muxing::StreamMuxerBox, transport::Boxed, upgrade, Transport};
+use libp2p::{dns, noise, tcp, yamux, PeerId};
+use libp2p_identity::Keypair;
+
+use crate::socks::SocksTransport;
+use crate::DriaP2PConfig;
+
+/// Boxed error type, as expected by the swarm builder for additional transports.
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Creates the raw TCP transport, which the other transports are built upon.
+///
+/// Without a proxy, `/dns` addresses are resolved with the configured resolver and dialed directly.
+/// With a proxy, every TCP address is dialed through it & resolved by it instead, so that nothing
+/// is dialed or resolved directly; listening is still done over plain TCP.
+pub(crate) fn base_transport(config: &DriaP2PConfig) -> Boxed<tcp::tokio::TcpStream> {
+    let tcp = tcp::tokio::Transport::new(tcp::Config::default());
+
+    match &config.proxy {
+        Some(proxy) => {
+            log::info!("Dialing through SOCKS5 proxy at {}", proxy.addr);
+            SocksTransport::new(proxy.clone())
+                .or_transport(tcp)
+                .map(|output, _| output.into_inner())
+                .boxed()
+        }
+        None => {
+            let (dns_config, dns_opts) = config.dns_resolver.resolver_config();
+            dns::tokio::Transport::custom(tcp, dns_config, dns_opts).boxed()
+        }
+    }
+}
+
+/// Creates the TCP transport, authenticated with Noise and multiplexed with Yamux.
+pub(crate) fn tcp_transport(
+    keypair: &Keypair,
+    config: &DriaP2PConfig,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>, BoxError> {
+    Ok(base_transport(config)
+        .upgrade(upgrade::Version::V1)
+        .authenticate(noise::Config::new(keypair)?)
+        .multiplex(yamux::Config::default())
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        .boxed())
+}
@@ -0,0 +1,77 @@
+use libp2p::core::{muxing::StreamMuxerBox, transport::Boxed, upgrade, Transport};
+use libp2p::{noise, websocket, yamux, PeerId};
+use libp2p_identity::Keypair;
+
+use crate::transport::{base_transport, BoxError};
+use crate::DriaP2PConfig;
+
+/// Path to a CA certificate (PEM or DER) to trust when dialing `/wss` addresses,
+/// in addition to the Mozilla root certificates, e.g. for a corporate proxy that re-signs TLS.
+const WSS_CA_CERT_ENV: &str = "DKN_P2P_WSS_CA_CERT";
+
+/// Creates a WebSocket transport over the base TCP transport, authenticated with Noise and multiplexed with Yamux,
+/// same as the TCP transport.
+///
+/// It supports both `/ws` and `/wss` (or `/tls/ws`) addresses, so that nodes behind proxies that only
+/// allow port 443 can still reach the RPCs.
+///
+/// Host names are resolved & the proxy is used as per the `config`, same as the TCP transport.
+pub(crate) fn websocket_transport(
+    keypair: &Keypair,
+    config: &DriaP2PConfig,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>, BoxError> {
+    let mut ws = websocket::WsConfig::new(base_transport(config));
+    ws.set_tls_config(wss_tls_config()?);
+
+    Ok(ws
+        .upgrade(upgrade::Version::V1)
+        .authenticate(noise::Config::new(keypair)?)
+        .multiplex(yamux::Config::default())
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        .boxed())
+}
+
+/// Returns the TLS config for dialing `/wss` addresses, trusting the CA certificate
+/// given by `DKN_P2P_WSS_CA_CERT` if any.
+fn wss_tls_config() -> Result<websocket::tls::Config, BoxError> {
+    let Ok(path) = std::env::var(WSS_CA_CERT_ENV) else {
+        return Ok(websocket::tls::Config::client());
+    };
+
+    log::info!("Trusting CA certificate at {path} for WSS");
+    let cert = std::fs::read(&path)?;
+    let cert = pem_to_der(&cert).unwrap_or(cert);
+
+    let mut builder = websocket::tls::Config::builder();
+    builder.add_trust(&websocket::tls::Certificate::new(cert))?;
+    Ok(builder.finish())
+}
+
+/// Decodes the first certificate in a PEM file to DER, returns `None` if it is not PEM.
+fn pem_to_der(pem: &[u8]) -> Option<Vec<u8>> {
+    use base64::Engine;
+
+    let pem = std::str::from_utf8(pem).ok()?;
+    let body = pem
+        .split("-----BEGIN CERTIFICATE-----")
+        .nth(1)?
+        .split("-----END CERTIFICATE-----")
+        .next()?;
+    let body = body.split_whitespace().collect::<String>();
+
+    base64::engine::general_purpose::STANDARD.decode(body).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pem_to_der() {
+        let pem = b"-----BEGIN CERTIFICATE-----\naGVs\nbG8=\n-----END CERTIFICATE-----\n";
+        assert_eq!(pem_to_der(pem), Some(b"hello".to_vec()));
+
+        // DER is given as is
+        assert_eq!(pem_to_der(&[0x30, 0x82, 0x01]), None);
+    }
+}
@@ -2,7 +2,7 @@ use std::str::FromStr;
 use std::thread::sleep;
 use std::time::Duration;
 
-use dkn_p2p::{DriaP2PClient, DriaP2PProtocol};
+use dkn_p2p::{DriaP2PClient, DriaP2PConfig, DriaP2PProtocol};
 use eyre::Result;
 use libp2p::PeerId;
 use libp2p_identity::Keypair;
@@ -30,9 +30,10 @@ async fn test_request_message() -> Result<()> {
     // spawn P2P client in another task
     let (client, mut commander, mut req_rx) = DriaP2PClient::new(
         Keypair::generate_secp256k1(),
-        "/ip4/127.0.0.1/tcp/0".parse().unwrap(),
+        vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()],
         &rpc_addr,
         DriaP2PProtocol::default(),
+        DriaP2PConfig::default(),
     )
     .expect("could not create p2p client");
 
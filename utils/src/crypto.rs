@@ -1,13 +1,65 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{prelude::BASE64_STANDARD, Engine};
 use libp2p_identity;
 use sha2::{Digest, Sha256};
 use sha3::Keccak256;
 
+use crate::payloads::{EncryptedResult, KeyRotation};
+
+/// BLS12-381 signatures of the task receipts.
+#[cfg(feature = "bls")]
+pub mod bls;
+
+/// End-to-end encrypted sessions between the node & the RPC.
+pub mod session;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EncryptionError {
+    #[error("At least one recipient is required")]
+    NoRecipients,
+    #[error("Could not encrypt: {0}")]
+    Encrypt(String),
+    #[error("Could not decrypt, the key is not a recipient or the data is corrupted")]
+    Decrypt,
+}
+
 /// Generic SHA256 function.
 #[inline(always)]
 pub fn sha256hash(data: impl AsRef<[u8]>) -> [u8; 32] {
     Sha256::digest(data).into()
 }
 
+/// SHA256 of each of the given data, reusing a single hasher.
+pub fn sha256hash_many<T: AsRef<[u8]>>(data: impl IntoIterator<Item = T>) -> Vec<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    data.into_iter()
+        .map(|data| {
+            hasher.update(data);
+            hasher.finalize_reset().into()
+        })
+        .collect()
+}
+
+/// Signs each of the given digests, reusing the signing context for all of them.
+pub fn sign_digests(
+    secret_key: &libsecp256k1::SecretKey,
+    digests: &[[u8; 32]],
+) -> Vec<(libsecp256k1::Signature, libsecp256k1::RecoveryId)> {
+    digests
+        .iter()
+        .map(|digest| {
+            libsecp256k1::sign_with_context(
+                &libsecp256k1::Message::parse(digest),
+                secret_key,
+                &libsecp256k1::ECMULT_GEN_CONTEXT,
+            )
+        })
+        .collect()
+}
+
 /// Generic KECCAK256 function.
 #[inline(always)]
 pub fn keccak256hash(data: impl AsRef<[u8]>) -> [u8; 32] {
@@ -25,6 +77,199 @@ pub fn secret_to_keypair(secret_key: &libsecp256k1::SecretKey) -> libp2p_identit
     libp2p_identity::secp256k1::Keypair::from(secret_key).into()
 }
 
+/// Derives an Ed25519 `libp2p_identity::Keypair` from a `libsecp256k1::SecretKey`,
+/// for deployments that use Ed25519 for the P2P identity.
+///
+/// The seed is the SHA256 of a domain tag & the secret key, so that the same secret key
+/// always results in the same keypair without exposing the secret key itself.
+#[inline]
+pub fn secret_to_ed25519_keypair(secret_key: &libsecp256k1::SecretKey) -> libp2p_identity::Keypair {
+    let mut seed = sha256hash([b"dria-p2p-ed25519".as_slice(), &secret_key.serialize()].concat());
+    libp2p_identity::Keypair::ed25519_from_bytes(&mut seed).expect("seed is 32 bytes")
+}
+
+/// Recovers the secp256k1 public key that signed the given digest, from the 64-byte signature & its recovery id.
+///
+/// Signatures with a high `s` are rejected, as the twin `(r, n - s)` of each signature is valid for the same key
+/// otherwise, so that a signed message can not be resent with another signature.
+#[inline]
+pub fn recover_public_key(
+    digest: &[u8; 32],
+    signature: &[u8],
+    recovery_id: u8,
+) -> Result<libsecp256k1::PublicKey, libsecp256k1::Error> {
+    let message = libsecp256k1::Message::parse(digest);
+    let signature = libsecp256k1::Signature::parse_standard_slice(signature)?;
+    if signature.s.is_high() {
+        return Err(libsecp256k1::Error::InvalidSignature);
+    }
+    let recovery_id = libsecp256k1::RecoveryId::parse(recovery_id)?;
+
+    libsecp256k1::recover(&message, &signature, &recovery_id)
+}
+
+/// Returns `true` if the signature over the given digest is made by the secp256k1 key of the given peer id,
+/// e.g. for a message that is claimed to be from a known RPC.
+///
+/// Peer ids of other key types can not be verified this way, and always return `false`.
+#[inline]
+pub fn verify_peer_id_signature(
+    digest: &[u8; 32],
+    signature: &[u8],
+    recovery_id: u8,
+    peer_id: &libp2p_identity::PeerId,
+) -> bool {
+    recover_public_key(digest, signature, recovery_id)
+        .is_ok_and(|public_key| public_key_to_peer_id(&public_key) == *peer_id)
+}
+
+/// Returns `true` if the signature over the given digest is made by the key of the given address.
+#[inline]
+pub fn verify_address_signature(
+    digest: &[u8; 32],
+    signature: &[u8],
+    recovery_id: u8,
+    address: &[u8; 20],
+) -> bool {
+    recover_public_key(digest, signature, recovery_id)
+        .is_ok_and(|public_key| public_key_to_address(&public_key) == *address)
+}
+
+/// Recovers the address that signed the given message from a hex encoded 65-byte `r || s || v` signature,
+/// where `v` is the recovery id as `0`/`1` or `27`/`28`.
+///
+/// The message is hashed with SHA256 before, as the payloads of the [`DriaMessage`](crate::DriaMessage)s are,
+/// so that the messages of the nodes can be verified without their crypto code, e.g. by the RPCs & indexers.
+/// The caller is to check that the returned address is the expected one.
+pub fn verify_recoverable_signature(
+    message: impl AsRef<[u8]>,
+    signature_hex: &str,
+) -> Result<[u8; 20], libsecp256k1::Error> {
+    let signature = hex::decode(signature_hex.trim().trim_start_matches("0x"))
+        .map_err(|_| libsecp256k1::Error::InvalidSignature)?;
+    let [signature @ .., v] = signature.as_slice() else {
+        return Err(libsecp256k1::Error::InvalidSignature);
+    };
+    let recovery_id = match v {
+        27 | 28 => v - 27,
+        v => *v,
+    };
+
+    recover_public_key(&sha256hash(message), signature, recovery_id)
+        .map(|public_key| public_key_to_address(&public_key))
+}
+
+/// Encrypts the data for all of the given recipients, any of which can decrypt it with [`decrypt_bytes`].
+///
+/// The data is encrypted once with a random content key, which is then wrapped for each recipient with ECIES,
+/// so that the size of the result does not grow with the number of recipients.
+pub fn encrypt_bytes(
+    data: impl AsRef<[u8]>,
+    recipients: &[libsecp256k1::PublicKey],
+) -> Result<EncryptedResult, EncryptionError> {
+    if recipients.is_empty() {
+        return Err(EncryptionError::NoRecipients);
+    }
+
+    let content_key = Aes256Gcm::generate_key(OsRng);
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    let ciphertext = Aes256Gcm::new(&content_key)
+        .encrypt(&nonce, data.as_ref())
+        .map_err(|err| EncryptionError::Encrypt(err.to_string()))?;
+
+    let wrapped_keys = recipients
+        .iter()
+        .map(|recipient| {
+            ecies::encrypt(&recipient.serialize(), content_key.as_slice())
+                .map(hex::encode)
+                .map_err(|err| EncryptionError::Encrypt(err.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(EncryptedResult {
+        nonce: hex::encode(nonce),
+        ciphertext: BASE64_STANDARD.encode(ciphertext),
+        wrapped_keys,
+    })
+}
+
+/// Decrypts the data encrypted by [`encrypt_bytes`] with the secret key of one of its recipients.
+pub fn decrypt_bytes(
+    encrypted: &EncryptedResult,
+    secret_key: &libsecp256k1::SecretKey,
+) -> Result<Vec<u8>, EncryptionError> {
+    let nonce = hex::decode(&encrypted.nonce).map_err(|_| EncryptionError::Decrypt)?;
+    if nonce.len() != 12 {
+        return Err(EncryptionError::Decrypt);
+    }
+    let ciphertext = BASE64_STANDARD
+        .decode(&encrypted.ciphertext)
+        .map_err(|_| EncryptionError::Decrypt)?;
+
+    // the recipients are not disclosed, so each wrapped key is tried in turn
+    let secret_key = secret_key.serialize();
+    encrypted
+        .wrapped_keys
+        .iter()
+        .filter_map(|wrapped_key| hex::decode(wrapped_key).ok())
+        .filter_map(|wrapped_key| ecies::decrypt(&secret_key, &wrapped_key).ok())
+        .filter(|content_key| content_key.len() == 32)
+        .find_map(|content_key| {
+            Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&content_key))
+                .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+                .ok()
+        })
+        .ok_or(EncryptionError::Decrypt)
+}
+
+/// Signs the rotation of the wallet from the given previous secret key to the new address (hex), at the given time.
+///
+/// The time is truncated to seconds, as it is within the signed statement.
+pub fn sign_key_rotation(
+    previous_secret_key: &libsecp256k1::SecretKey,
+    new_address: &str,
+    rotated_at: chrono::DateTime<chrono::Utc>,
+) -> KeyRotation {
+    let previous_public_key = libsecp256k1::PublicKey::from_secret_key(previous_secret_key);
+    let mut rotation = KeyRotation {
+        previous_address: hex::encode(public_key_to_address(&previous_public_key)),
+        new_address: new_address.trim_start_matches("0x").to_lowercase(),
+        rotated_at: chrono::DateTime::from_timestamp(rotated_at.timestamp(), 0)
+            .unwrap_or(rotated_at),
+        signature: String::new(),
+        recovery_id: 0,
+    };
+
+    let (signature, recovery_id) = libsecp256k1::sign(
+        &libsecp256k1::Message::parse(&sha256hash(rotation.statement())),
+        previous_secret_key,
+    );
+    rotation.signature = hex::encode(signature.serialize());
+    rotation.recovery_id = recovery_id.serialize();
+
+    rotation
+}
+
+/// Returns `true` if the key rotation is signed by the wallet of its previous address.
+pub fn verify_key_rotation(rotation: &KeyRotation) -> bool {
+    let Ok(signature) = hex::decode(&rotation.signature) else {
+        return false;
+    };
+
+    recover_public_key(
+        &sha256hash(rotation.statement()),
+        &signature,
+        rotation.recovery_id,
+    )
+    .is_ok_and(|public_key| {
+        hex::encode(public_key_to_address(&public_key))
+            == rotation
+                .previous_address
+                .trim_start_matches("0x")
+                .to_lowercase()
+    })
+}
+
 /// Given a secp256k1 public key, finds the corresponding Ethereum address.
 ///
 /// Internally, the public key is serialized in uncompressed format at 65 bytes (0x04 || x || y),
@@ -67,6 +312,54 @@ mod tests {
         assert_eq!(sha256hash(MESSAGE), expected.as_slice());
     }
 
+    #[test]
+    fn test_verify_recoverable_signature() {
+        let sk = SecretKey::parse_slice(DUMMY_SECRET_KEY).expect("Should parse key.");
+        let address = public_key_to_address(&PublicKey::from_secret_key(&sk));
+        let digest = sha256hash(MESSAGE);
+        let (signature, recovery_id) = sign(&Message::parse(&digest), &sk);
+        assert!(verify_address_signature(
+            &digest,
+            &signature.serialize(),
+            recovery_id.serialize(),
+            &address
+        ));
+
+        // both forms of `v` are accepted
+        for v in [recovery_id.serialize(), recovery_id.serialize() + 27] {
+            let signature_hex = format!("0x{}{:02x}", hex::encode(signature.serialize()), v);
+            assert_eq!(
+                verify_recoverable_signature(MESSAGE, &signature_hex).unwrap(),
+                address
+            );
+        }
+
+        // another message recovers another address, and malformed signatures are errors
+        let signature_hex = format!("{}00", hex::encode(signature.serialize()));
+        assert_ne!(
+            verify_recoverable_signature(b"hello dria", &signature_hex).ok(),
+            Some(address)
+        );
+        assert!(verify_recoverable_signature(MESSAGE, "not-hex").is_err());
+        assert!(verify_recoverable_signature(MESSAGE, "").is_err());
+        assert!(verify_recoverable_signature(MESSAGE, &signature_hex[2..]).is_err());
+    }
+
+    #[test]
+    fn test_sign_digests() {
+        let sk = SecretKey::parse_slice(DUMMY_SECRET_KEY).expect("Should parse key.");
+        let data = [MESSAGE, b"", b"hello dria"];
+
+        // batch results are the same as the individual ones
+        let digests = sha256hash_many(data);
+        assert_eq!(digests, data.map(sha256hash));
+        let signatures = sign_digests(&sk, &digests);
+        for (digest, signature) in digests.iter().zip(signatures) {
+            assert_eq!(signature, sign(&Message::parse(digest), &sk));
+        }
+        assert!(sign_digests(&sk, &[]).is_empty());
+    }
+
     #[test]
     fn test_address() {
         let sk = SecretKey::parse_slice(DUMMY_SECRET_KEY).expect("Should parse key.");
@@ -89,6 +382,88 @@ mod tests {
         assert_eq!(MESSAGE, plaintext.as_slice());
     }
 
+    #[test]
+    fn test_verify_peer_id_signature() {
+        let secret_key = SecretKey::parse_slice(DUMMY_SECRET_KEY).expect("to parse secret key");
+        let peer_id = public_key_to_peer_id(&PublicKey::from_secret_key(&secret_key));
+        let other_peer_id = secret_to_keypair(&SecretKey::parse(&[1u8; 32]).unwrap())
+            .public()
+            .to_peer_id();
+
+        let digest = sha256hash(MESSAGE);
+        let (signature, recid) = sign(&Message::parse(&digest), &secret_key);
+        let signature = signature.serialize();
+        assert!(verify_peer_id_signature(
+            &digest,
+            &signature,
+            recid.serialize(),
+            &peer_id
+        ));
+
+        // another signer, another digest or a malformed signature are rejected
+        assert!(!verify_peer_id_signature(
+            &digest,
+            &signature,
+            recid.serialize(),
+            &other_peer_id
+        ));
+        assert!(!verify_peer_id_signature(
+            &sha256hash(b"other"),
+            &signature,
+            recid.serialize(),
+            &peer_id
+        ));
+        assert!(!verify_peer_id_signature(&digest, &[], 0, &peer_id));
+    }
+
+    #[test]
+    fn test_encrypt_bytes_for_recipients() {
+        let owner = SecretKey::parse_slice(DUMMY_SECRET_KEY).expect("Should parse key.");
+        let auditor = SecretKey::parse(&[1u8; 32]).expect("Should parse key.");
+        let other = SecretKey::parse(&[2u8; 32]).expect("Should parse key.");
+
+        let encrypted = encrypt_bytes(
+            MESSAGE,
+            &[
+                PublicKey::from_secret_key(&owner),
+                PublicKey::from_secret_key(&auditor),
+            ],
+        )
+        .expect("Should encrypt.");
+        assert_eq!(encrypted.wrapped_keys.len(), 2);
+
+        // each recipient can decrypt, but no one else
+        for secret_key in [owner, auditor] {
+            let plaintext = decrypt_bytes(&encrypted, &secret_key).expect("Should decrypt.");
+            assert_eq!(MESSAGE, plaintext.as_slice());
+        }
+        assert!(decrypt_bytes(&encrypted, &other).is_err());
+        assert!(encrypt_bytes(MESSAGE, &[]).is_err());
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let previous = SecretKey::parse_slice(DUMMY_SECRET_KEY).expect("Should parse key.");
+        let new_address = "0xABCDEFabcdef0123456789012345678901234567";
+        let rotation = sign_key_rotation(&previous, new_address, chrono::Utc::now());
+        assert_eq!(
+            rotation.previous_address,
+            "d79fdf178547614cfdd0df6397c53569716bd596"
+        );
+        assert_eq!(
+            rotation.new_address,
+            "abcdefabcdef0123456789012345678901234567"
+        );
+        assert!(verify_key_rotation(&rotation));
+
+        // survives a round-trip, but not a change of the new address
+        let json = serde_json::to_string(&rotation).unwrap();
+        let mut rotation: KeyRotation = serde_json::from_str(&json).unwrap();
+        assert!(verify_key_rotation(&rotation));
+        rotation.new_address = "1111111111111111111111111111111111111111".to_string();
+        assert!(!verify_key_rotation(&rotation));
+    }
+
     #[test]
     fn test_sign_verify() {
         let secret_key =
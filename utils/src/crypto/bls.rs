@@ -0,0 +1,144 @@
+//! BLS12-381 signatures of the task receipts, so that the RPC can aggregate the receipts of many nodes
+//! into a single signature.
+//!
+//! The keys are in G1 & the signatures are in G2 (`min_pk`), with the proof-of-possession scheme.
+
+use blst::min_pk::{AggregateSignature, PublicKey, Signature};
+use blst::BLST_ERROR;
+
+pub use blst::min_pk::SecretKey;
+
+use super::sha256hash;
+use crate::payloads::TaskResponsePayload;
+
+/// Domain separation tag of the receipt signatures.
+pub const RECEIPT_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlsError {
+    #[error("At least one signature is required")]
+    NoSignatures,
+    #[error("Invalid BLS key or signature: {0:?}")]
+    Blst(BLST_ERROR),
+}
+
+/// Derives the BLS secret key of the node from its secp256k1 secret key, so that no other key is to be kept.
+pub fn bls_secret_key(secret_key: &libsecp256k1::SecretKey) -> SecretKey {
+    let ikm = sha256hash([b"dria-bls-receipts".as_slice(), &secret_key.serialize()].concat());
+    SecretKey::key_gen(&ikm, &[]).expect("key material is 32 bytes")
+}
+
+/// Parses a hex-encoded BLS secret key, e.g. one given explicitly when the secp256k1 secret key is kept elsewhere.
+pub fn parse_bls_secret_key(secret_key: &str) -> Result<SecretKey, BlsError> {
+    let bytes = hex::decode(secret_key.trim().trim_start_matches("0x"))
+        .map_err(|_| BlsError::Blst(BLST_ERROR::BLST_BAD_ENCODING))?;
+    SecretKey::from_bytes(&bytes).map_err(BlsError::Blst)
+}
+
+/// Returns the digest of the receipt of a task response, which is what is signed.
+///
+/// The receipt covers the task along with its outcome, i.e. its result (plaintext or encrypted) or its error.
+pub fn task_receipt_digest(payload: &TaskResponsePayload) -> [u8; 32] {
+    let receipt = (
+        &payload.file_id,
+        &payload.row_id,
+        &payload.task_id,
+        &payload.model,
+        &payload.result,
+        &payload.encrypted_result,
+        &payload.error,
+    );
+    sha256hash(serde_json::to_vec(&receipt).expect("should be serializable"))
+}
+
+/// Signs the receipt of the task response, see [`task_receipt_digest`].
+pub fn sign_task_receipt(secret_key: &SecretKey, payload: &TaskResponsePayload) -> Signature {
+    secret_key.sign(&task_receipt_digest(payload), RECEIPT_DST, &[])
+}
+
+/// Aggregates the signatures into one, which can be verified with [`verify_aggregate_receipts`].
+pub fn aggregate_signatures(signatures: &[Signature]) -> Result<Signature, BlsError> {
+    if signatures.is_empty() {
+        return Err(BlsError::NoSignatures);
+    }
+
+    let signatures = signatures.iter().collect::<Vec<_>>();
+    AggregateSignature::aggregate(&signatures, true)
+        .map(|aggregate| aggregate.to_signature())
+        .map_err(BlsError::Blst)
+}
+
+/// Verifies an aggregated signature of the receipt digests, each of which is signed by the public key at the same index.
+pub fn verify_aggregate_receipts(
+    signature: &Signature,
+    digests: &[[u8; 32]],
+    public_keys: &[PublicKey],
+) -> Result<(), BlsError> {
+    if digests.is_empty() || digests.len() != public_keys.len() {
+        return Err(BlsError::Blst(BLST_ERROR::BLST_VERIFY_FAIL));
+    }
+
+    let digests = digests
+        .iter()
+        .map(|digest| digest.as_slice())
+        .collect::<Vec<_>>();
+    let public_keys = public_keys.iter().collect::<Vec<_>>();
+    match signature.aggregate_verify(true, &digests, RECEIPT_DST, &public_keys, true) {
+        BLST_ERROR::BLST_SUCCESS => Ok(()),
+        err => Err(BlsError::Blst(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payloads::{SchemaVersion, TaskStats};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_aggregate_receipts() {
+        let payload = |result: &str| TaskResponsePayload {
+            schema_version: SchemaVersion::CURRENT,
+            file_id: Uuid::now_v7(),
+            row_id: Uuid::now_v7(),
+            task_id: "task".to_string(),
+            model: "gemma3:4b".to_string(),
+            stats: TaskStats::default(),
+            result: Some(result.to_string()),
+            error: None,
+            encrypted_result: None,
+            receipt_signature: None,
+        };
+
+        let nodes = [[1u8; 32], [2u8; 32], [3u8; 32]]
+            .map(|bytes| bls_secret_key(&libsecp256k1::SecretKey::parse(&bytes).unwrap()));
+        let payloads = ["a", "b", "c"].map(payload);
+        let signatures = nodes
+            .iter()
+            .zip(&payloads)
+            .map(|(secret_key, payload)| sign_task_receipt(secret_key, payload))
+            .collect::<Vec<_>>();
+
+        let aggregate = aggregate_signatures(&signatures).unwrap();
+        let digests = payloads.each_ref().map(task_receipt_digest);
+        let public_keys = nodes.each_ref().map(SecretKey::sk_to_pk);
+        assert!(verify_aggregate_receipts(&aggregate, &digests, &public_keys).is_ok());
+
+        // a receipt of another outcome does not verify
+        let mut tampered = digests;
+        tampered[1] = task_receipt_digest(&payload("d"));
+        assert!(verify_aggregate_receipts(&aggregate, &tampered, &public_keys).is_err());
+        assert!(aggregate_signatures(&[]).is_err());
+    }
+
+    #[test]
+    fn test_parse_bls_secret_key() {
+        let secret_key = bls_secret_key(&libsecp256k1::SecretKey::parse(&[1u8; 32]).unwrap());
+        let parsed =
+            parse_bls_secret_key(&format!("0x{}", hex::encode(secret_key.to_bytes()))).unwrap();
+        assert_eq!(parsed.to_bytes(), secret_key.to_bytes());
+
+        assert!(parse_bls_secret_key("not hex").is_err());
+        assert!(parse_bls_secret_key(&"ff".repeat(32)).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! Sessions between the node & the RPC that encrypt the message payloads end-to-end, above the transport.
+//!
+//! The node starts each session with an ephemeral key, and offers its public key to the RPC. The key of the session
+//! is derived from the ECDH of the ephemeral key & the static key of the RPC (the one of its peer id), which the RPC
+//! derives as well with its secret key & the offered public key. A new session is started for each RPC connection,
+//! so that the keys are rotated.
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use uuid::Uuid;
+
+use super::{sha256hash, EncryptionError};
+
+/// Length of the nonce that is prepended to each ciphertext.
+const NONCE_LEN: usize = 12;
+
+/// Key of an encrypted session, see the [module docs](self).
+pub struct SessionKey {
+    id: Uuid,
+    cipher: Aes256Gcm,
+}
+
+impl std::fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionKey").field("id", &self.id).finish()
+    }
+}
+
+impl SessionKey {
+    /// Derives the key of the session from the ECDH of the secret key of one side & the public key of the other.
+    pub fn derive(
+        id: Uuid,
+        secret_key: &libsecp256k1::SecretKey,
+        public_key: &libsecp256k1::PublicKey,
+    ) -> Result<Self, EncryptionError> {
+        let mut shared_point = *public_key;
+        shared_point
+            .tweak_mul_assign(secret_key)
+            .map_err(|err| EncryptionError::Encrypt(err.to_string()))?;
+
+        let key = sha256hash(
+            [
+                b"dria-session".as_slice(),
+                &shared_point.serialize_compressed(),
+                id.as_bytes(),
+            ]
+            .concat(),
+        );
+
+        Ok(Self {
+            id,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        })
+    }
+
+    /// Identifier of the session, which the encrypted messages refer to.
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Encrypts the data with a random nonce, which is prepended to the ciphertext.
+    pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: data,
+                    aad: self.id.as_bytes(),
+                },
+            )
+            .map_err(|err| EncryptionError::Encrypt(err.to_string()))?;
+
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    /// Decrypts the data encrypted by [`SessionKey::encrypt`] within the same session.
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        if data.len() < NONCE_LEN {
+            return Err(EncryptionError::Decrypt);
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: self.id.as_bytes(),
+                },
+            )
+            .map_err(|_| EncryptionError::Decrypt)
+    }
+}
+
+/// Returns the secp256k1 public key within the peer id, `None` if the peer id is not of an inlined secp256k1 key.
+pub fn peer_id_to_public_key(peer_id: &libp2p_identity::PeerId) -> Option<libsecp256k1::PublicKey> {
+    // public keys of up to 42 bytes are inlined with the identity multihash
+    const IDENTITY_MULTIHASH: u64 = 0x00;
+
+    let multihash = peer_id.as_ref();
+    if multihash.code() != IDENTITY_MULTIHASH {
+        return None;
+    }
+
+    let public_key = libp2p_identity::PublicKey::try_decode_protobuf(multihash.digest())
+        .ok()?
+        .try_into_secp256k1()
+        .ok()?;
+    libsecp256k1::PublicKey::parse_compressed(&public_key.to_bytes()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::public_key_to_peer_id;
+    use libsecp256k1::{PublicKey, SecretKey};
+
+    #[test]
+    fn test_session_key() {
+        let rpc = SecretKey::parse(&[1u8; 32]).unwrap();
+        let ephemeral = SecretKey::parse(&[2u8; 32]).unwrap();
+        let rpc_peer_id = public_key_to_peer_id(&PublicKey::from_secret_key(&rpc));
+        let rpc_public_key = peer_id_to_public_key(&rpc_peer_id).unwrap();
+        assert_eq!(rpc_public_key, PublicKey::from_secret_key(&rpc));
+
+        // both sides derive the same key
+        let id = Uuid::now_v7();
+        let node_session = SessionKey::derive(id, &ephemeral, &rpc_public_key).unwrap();
+        let rpc_session =
+            SessionKey::derive(id, &rpc, &PublicKey::from_secret_key(&ephemeral)).unwrap();
+        let ciphertext = node_session.encrypt(b"a sensitive prompt").unwrap();
+        assert_eq!(
+            rpc_session.decrypt(&ciphertext).unwrap(),
+            b"a sensitive prompt"
+        );
+
+        // another session can not decrypt it
+        let other_session = SessionKey::derive(Uuid::now_v7(), &rpc, &rpc_public_key).unwrap();
+        assert!(other_session.decrypt(&ciphertext).is_err());
+        assert!(rpc_session.decrypt(&ciphertext[..8]).is_err());
+    }
+}
@@ -18,7 +18,7 @@ pub use version::SemanticVersion;
 #[cfg(feature = "crypto")]
 mod message;
 #[cfg(feature = "crypto")]
-pub use message::DriaMessage;
+pub use message::{DriaMessage, DriaMessageEncoding};
 
 // re-exports
 pub use chrono;
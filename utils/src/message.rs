@@ -1,9 +1,13 @@
-use crate::crypto::sha256hash;
+use crate::crypto::{
+    public_key_to_address, public_key_to_peer_id, recover_public_key, session::SessionKey,
+    sha256hash, EncryptionError,
+};
 
 use super::SemanticVersion;
 use base64::{prelude::BASE64_STANDARD, Engine};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use thiserror::Error;
+use uuid::Uuid;
 
 /// Message format for Dria network communication.
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -21,10 +25,83 @@ pub struct DriaMessage {
     pub protocol: String,
     // Message timestamp in nanoseconds
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Seconds after the `timestamp` that the message expires at, see [`Self::with_ttl`].
+    ///
+    /// If given, the timestamp & the TTL are signed along with the payload.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
     // 64-byte hex-encoded signature
     pub signature: String,
     // Signature recovery ID
     pub recovery_id: u8,
+    /// Session that the payload is encrypted within, see [`Self::encrypt_payload`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<Uuid>,
+}
+
+/// Encoding of a [`DriaMessage`] on the wire.
+///
+/// With CBOR, the payload & signature are carried as raw bytes instead of base64 & hex strings,
+/// which makes the messages smaller and cheaper to parse. The encoding of a received message is
+/// detected, so that it can be responded to with the same encoding.
+///
+/// The encodings are negotiated by name, see [`Self::name`]: the node advertises the ones it supports,
+/// and sends its own requests to a peer in CBOR only once that peer has sent a request in CBOR.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DriaMessageEncoding {
+    #[default]
+    Json,
+    Cbor,
+}
+
+impl DriaMessageEncoding {
+    /// All of the supported encodings, in the order of preference.
+    pub const ALL: [Self; 2] = [Self::Cbor, Self::Json];
+
+    /// Name of the encoding within the negotiation, e.g. `cbor`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Cbor => "cbor",
+        }
+    }
+
+    /// Parses the given bytes of a plain serialized value (not a [`DriaMessage`]), in the detected encoding,
+    /// e.g. for the responses of the peers.
+    pub fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, DriaMessageError> {
+        match Self::detect(data) {
+            Self::Json => serde_json::from_slice(data).map_err(DriaMessageError::ParseError),
+            Self::Cbor => cbor4ii::serde::from_slice(data)
+                .map_err(|err| DriaMessageError::CborError(err.to_string())),
+        }
+    }
+
+    /// Detects the encoding of the given message bytes, where a JSON message is an object
+    /// and a CBOR message is a map that can not start with `{`.
+    pub fn detect(data: &[u8]) -> Self {
+        match data.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{') => Self::Json,
+            _ => Self::Cbor,
+        }
+    }
+}
+
+/// The CBOR representation of a [`DriaMessage`], see [`DriaMessageEncoding::Cbor`].
+#[derive(Serialize, Deserialize)]
+struct DriaMessageCbor {
+    #[serde(with = "serde_bytes")]
+    payload: Vec<u8>,
+    topic: String,
+    version: SemanticVersion,
+    protocol: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ttl_secs: Option<u64>,
+    #[serde(with = "serde_bytes")]
+    signature: Vec<u8>,
+    recovery_id: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    session_id: Option<Uuid>,
 }
 
 #[derive(Error, Debug)]
@@ -33,6 +110,8 @@ pub enum DriaMessageError {
     DecodeError(base64::DecodeError),
     #[error("Could not parse message: {0}")]
     ParseError(serde_json::Error),
+    #[error("Could not encode or decode CBOR message: {0}")]
+    CborError(String),
     #[error("Protocol mismatch (expected {expected:?}, got {found:?})")]
     ProtocolMismatch { expected: String, found: String },
     #[error("Version mismatch (expected {expected:?}, got {found:?})")]
@@ -42,6 +121,10 @@ pub enum DriaMessageError {
     },
     #[error("Invalid signature ({0})")]
     InvalidSignature(libsecp256k1::Error),
+    #[error("Could not encrypt or decrypt within session: {0}")]
+    SessionError(EncryptionError),
+    #[error("Session mismatch (expected {expected}, got {found:?})")]
+    SessionMismatch { expected: Uuid, found: Option<Uuid> },
 }
 
 impl DriaMessage {
@@ -58,34 +141,106 @@ impl DriaMessage {
         signing_key: &libsecp256k1::SecretKey,
         version: SemanticVersion,
     ) -> Self {
-        // base64 encode the data to obtain payload
-        let payload = BASE64_STANDARD.encode(data);
+        let mut message = Self::new_unsigned(data, topic, protocol, version);
 
         // sign the SHA256 hash of the payload
         let (signature, recovery_id) = libsecp256k1::sign(
-            &libsecp256k1::Message::parse(&sha256hash(&payload)),
+            &libsecp256k1::Message::parse(&message.signing_digest()),
             signing_key,
         );
+        message.set_signature(&signature, &recovery_id);
 
+        message
+    }
+
+    /// Creates a new Dria message without a signature, to be signed over [`Self::signing_digest`]
+    /// elsewhere, e.g. by a remote signer, and then given with [`Self::set_signature`].
+    pub fn new_unsigned(
+        data: impl AsRef<[u8]>,
+        topic: impl ToString,
+        protocol: String,
+        version: SemanticVersion,
+    ) -> Self {
         Self {
-            payload,
+            // base64 encode the data to obtain payload
+            payload: BASE64_STANDARD.encode(data),
             topic: topic.to_string(),
             protocol,
             timestamp: chrono::Utc::now(),
+            ttl_secs: None,
             version,
-            signature: hex::encode(signature.serialize()),
-            recovery_id: recovery_id.serialize(),
+            signature: String::new(),
+            recovery_id: 0,
+            session_id: None,
+        }
+    }
+
+    /// Sets the time-to-live of the message, after which it is to be rejected as stale.
+    ///
+    /// This must be done before the message is signed, as the timestamp & the TTL are signed as well.
+    pub fn with_ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.ttl_secs = Some(ttl.as_secs());
+        self
+    }
+
+    /// Returns the time that the message expires at, if it has a TTL.
+    ///
+    /// Returns `None` as well if the TTL is out of the range of representable times.
+    pub fn expires_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let ttl = chrono::TimeDelta::try_seconds(i64::try_from(self.ttl_secs?).ok()?)?;
+        self.timestamp.checked_add_signed(ttl)
+    }
+
+    /// Returns `true` if the message has a TTL & it has expired at the given time.
+    ///
+    /// A message with an out-of-range TTL is treated as expired, so that it is rejected.
+    pub fn is_expired(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        match (self.ttl_secs, self.expires_at()) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(_), Some(expires_at)) => now > expires_at,
+        }
+    }
+
+    /// Returns the data that is signed, i.e. the payload, followed by the timestamp in nanoseconds & the TTL
+    /// in seconds (both as 8-byte big-endian) if the message has a TTL.
+    ///
+    /// Messages without a TTL are signed over the payload alone, as they were before the TTL.
+    pub fn signed_data(&self) -> Vec<u8> {
+        let mut data = self.payload.as_bytes().to_vec();
+        if let Some(ttl) = self.ttl_secs {
+            let timestamp = self.timestamp.timestamp_nanos_opt().unwrap_or_default();
+            data.extend_from_slice(&timestamp.to_be_bytes());
+            data.extend_from_slice(&ttl.to_be_bytes());
         }
+        data
+    }
+
+    /// Returns the digest that is signed, i.e. the SHA256 hash of [`Self::signed_data`].
+    #[inline(always)]
+    pub fn signing_digest(&self) -> [u8; 32] {
+        sha256hash(self.signed_data())
+    }
+
+    /// Sets the signature over [`Self::signing_digest`].
+    pub fn set_signature(
+        &mut self,
+        signature: &libsecp256k1::Signature,
+        recovery_id: &libsecp256k1::RecoveryId,
+    ) {
+        self.signature = hex::encode(signature.serialize());
+        self.recovery_id = recovery_id.serialize();
     }
 
     /// Parses a slice of bytes into a `DriaMessage`, and checks for protocol & network matches.
+    ///
+    /// The encoding of the message is detected, see [`DriaMessageEncoding::detect`].
     pub fn from_slice_checked(
         data: &[u8],
         protocol: String,
         version: SemanticVersion,
     ) -> Result<DriaMessage, DriaMessageError> {
-        let message: DriaMessage =
-            serde_json::from_slice(data).map_err(DriaMessageError::ParseError)?;
+        let message = Self::from_slice(data)?;
 
         // ensure that protocol names match
         if protocol != message.protocol {
@@ -105,6 +260,56 @@ impl DriaMessage {
         }
     }
 
+    /// Parses a slice of bytes into a `DriaMessage`, in the detected encoding.
+    pub fn from_slice(data: &[u8]) -> Result<DriaMessage, DriaMessageError> {
+        match DriaMessageEncoding::detect(data) {
+            DriaMessageEncoding::Json => {
+                serde_json::from_slice(data).map_err(DriaMessageError::ParseError)
+            }
+            DriaMessageEncoding::Cbor => {
+                let message: DriaMessageCbor = cbor4ii::serde::from_slice(data)
+                    .map_err(|err| DriaMessageError::CborError(err.to_string()))?;
+
+                // the signature is over the base64 payload, so it is re-encoded as is
+                Ok(Self {
+                    payload: BASE64_STANDARD.encode(message.payload),
+                    topic: message.topic,
+                    version: message.version,
+                    protocol: message.protocol,
+                    timestamp: message.timestamp,
+                    ttl_secs: message.ttl_secs,
+                    signature: hex::encode(message.signature),
+                    recovery_id: message.recovery_id,
+                    session_id: message.session_id,
+                })
+            }
+        }
+    }
+
+    /// Encodes the message into bytes with the given encoding.
+    pub fn to_bytes(&self, encoding: DriaMessageEncoding) -> Result<Vec<u8>, DriaMessageError> {
+        match encoding {
+            DriaMessageEncoding::Json => Ok(self.into()),
+            DriaMessageEncoding::Cbor => {
+                let message = DriaMessageCbor {
+                    payload: self.decode_payload()?,
+                    topic: self.topic.clone(),
+                    version: self.version,
+                    protocol: self.protocol.clone(),
+                    timestamp: self.timestamp,
+                    ttl_secs: self.ttl_secs,
+                    signature: hex::decode(&self.signature)
+                        .map_err(|err| DriaMessageError::CborError(err.to_string()))?,
+                    recovery_id: self.recovery_id,
+                    session_id: self.session_id,
+                };
+
+                cbor4ii::serde::to_vec(Vec::new(), &message)
+                    .map_err(|err| DriaMessageError::CborError(err.to_string()))
+            }
+        }
+    }
+
     /// Decodes the base64 payload into bytes.
     #[inline(always)]
     pub fn decode_payload(&self) -> Result<Vec<u8>, DriaMessageError> {
@@ -120,24 +325,76 @@ impl DriaMessage {
         serde_json::from_slice::<T>(&decoded).map_err(DriaMessageError::ParseError)
     }
 
+    /// Encrypts the payload within the given session, which must be done before the message is signed,
+    /// so that the signature is over the encrypted payload.
+    pub fn encrypt_payload(&mut self, session: &SessionKey) -> Result<(), DriaMessageError> {
+        let encrypted = session
+            .encrypt(&self.decode_payload()?)
+            .map_err(DriaMessageError::SessionError)?;
+        self.payload = BASE64_STANDARD.encode(encrypted);
+        self.session_id = Some(session.id());
+
+        Ok(())
+    }
+
+    /// Decrypts the payload that is encrypted within the given session, see [`Self::encrypt_payload`].
+    ///
+    /// The signature is no longer valid afterwards, so it should be verified before.
+    pub fn decrypt_payload(&mut self, session: &SessionKey) -> Result<(), DriaMessageError> {
+        if self.session_id != Some(session.id()) {
+            return Err(DriaMessageError::SessionMismatch {
+                expected: session.id(),
+                found: self.session_id,
+            });
+        }
+
+        let decrypted = session
+            .decrypt(&self.decode_payload()?)
+            .map_err(DriaMessageError::SessionError)?;
+        self.payload = BASE64_STANDARD.encode(decrypted);
+        self.session_id = None;
+
+        Ok(())
+    }
+
     /// Recovers the signature from the message payload.
     ///
     /// This may be costly to do in a hot loop.
     #[inline(always)]
     pub fn recover_public_key(&self) -> Result<libsecp256k1::PublicKey, DriaMessageError> {
-        let message = libsecp256k1::Message::parse(&sha256hash(&self.payload));
+        // a signature that is not in lowercase hex is as invalid as a malformed one, so that it has a single form
+        let signature = hex::decode(&self.signature)
+            .ok()
+            .filter(|signature| hex::encode(signature) == self.signature)
+            .ok_or(DriaMessageError::InvalidSignature(
+                libsecp256k1::Error::InvalidSignature,
+            ))?;
 
-        // parse the signature and recovery ID
-        let signature =
-            libsecp256k1::Signature::parse_standard_slice(&hex::decode(&self.signature).unwrap())
-                .map_err(DriaMessageError::InvalidSignature)?;
-        let recovery_id = libsecp256k1::RecoveryId::parse(self.recovery_id)
-            .map_err(DriaMessageError::InvalidSignature)?;
-
-        // recover the public key from the signature
-        libsecp256k1::recover(&message, &signature, &recovery_id)
+        recover_public_key(&self.signing_digest(), &signature, self.recovery_id)
             .map_err(DriaMessageError::InvalidSignature)
     }
+
+    /// Recovers the address of the signer, see [`Self::recover_public_key`].
+    #[inline(always)]
+    pub fn recover_address(&self) -> Result<[u8; 20], DriaMessageError> {
+        self.recover_public_key()
+            .map(|public_key| public_key_to_address(&public_key))
+    }
+
+    /// Returns the hex encoded 65-byte `r || s || v` signature, which can be verified along with
+    /// [`Self::signed_data`] by [`verify_recoverable_signature`](crate::crypto::verify_recoverable_signature).
+    pub fn recoverable_signature(&self) -> String {
+        format!("{}{:02x}", self.signature, self.recovery_id)
+    }
+
+    /// Recovers the peer id of the signer, see [`Self::recover_public_key`].
+    ///
+    /// Unlike the peer id of the connection, this tells which key has actually signed the message.
+    #[inline(always)]
+    pub fn recover_peer_id(&self) -> Result<libp2p_identity::PeerId, DriaMessageError> {
+        self.recover_public_key()
+            .map(|public_key| public_key_to_peer_id(&public_key))
+    }
 }
 
 impl From<&DriaMessage> for Vec<u8> {
@@ -212,4 +469,159 @@ mod tests {
         let parsed_body = message.parse_payload().expect("Should decode");
         assert_eq!(body, parsed_body);
     }
+
+    #[test]
+    fn test_message_encodings() {
+        let sk = SecretKey::parse(b"driadriadriadriadriadriadriadria").unwrap();
+        let message = DriaMessage::new_signed(
+            "{\"hello\":\"world\"}",
+            TOPIC,
+            "test".into(),
+            &sk,
+            SemanticVersion::default(),
+        );
+
+        let json = message.to_bytes(DriaMessageEncoding::Json).unwrap();
+        let cbor = message.to_bytes(DriaMessageEncoding::Cbor).unwrap();
+        assert_eq!(
+            DriaMessageEncoding::detect(&json),
+            DriaMessageEncoding::Json
+        );
+        assert_eq!(
+            DriaMessageEncoding::detect(&cbor),
+            DriaMessageEncoding::Cbor
+        );
+        assert!(cbor.len() < json.len());
+
+        for data in [json, cbor] {
+            let decoded =
+                DriaMessage::from_slice_checked(&data, "test".into(), SemanticVersion::default())
+                    .unwrap();
+            assert_eq!(decoded.payload, message.payload);
+            assert_eq!(decoded.signature, message.signature);
+            assert_eq!(decoded.timestamp, message.timestamp);
+            assert!(decoded.recover_public_key().is_ok());
+        }
+
+        // plain values such as the responses are decoded in either encoding
+        let body = TestStruct {
+            hello: "world".to_string(),
+        };
+        for data in [
+            serde_json::to_vec(&body).unwrap(),
+            cbor4ii::serde::to_vec(Vec::new(), &body).unwrap(),
+        ] {
+            assert_eq!(
+                DriaMessageEncoding::decode::<TestStruct>(&data).unwrap(),
+                body
+            );
+        }
+    }
+
+    #[test]
+    fn test_message_ttl() {
+        let sk = SecretKey::parse(b"driadriadriadriadriadriadriadria").unwrap();
+        let peer_id = public_key_to_peer_id(&libsecp256k1::PublicKey::from_secret_key(&sk));
+        let mut message = DriaMessage::new_unsigned("{}", TOPIC, "test".into(), Default::default())
+            .with_ttl(std::time::Duration::from_secs(30));
+        let (signature, recovery_id) = libsecp256k1::sign(
+            &libsecp256k1::Message::parse(&message.signing_digest()),
+            &sk,
+        );
+        message.set_signature(&signature, &recovery_id);
+
+        // the TTL is carried in both encodings
+        for encoding in [DriaMessageEncoding::Json, DriaMessageEncoding::Cbor] {
+            let decoded = DriaMessage::from_slice(&message.to_bytes(encoding).unwrap()).unwrap();
+            assert_eq!(decoded.ttl_secs, Some(30));
+            assert_eq!(decoded.recover_peer_id().unwrap(), peer_id);
+        }
+
+        assert!(!message.is_expired(message.timestamp));
+        assert!(message.is_expired(message.timestamp + chrono::Duration::seconds(31)));
+
+        // the timestamp & the TTL are signed, so they can not be extended
+        let mut extended = message.clone();
+        extended.ttl_secs = Some(3600);
+        assert_ne!(extended.recover_peer_id().unwrap(), peer_id);
+        let mut delayed = message.clone();
+        delayed.timestamp += chrono::Duration::seconds(60);
+        assert_ne!(delayed.recover_peer_id().unwrap(), peer_id);
+
+        // messages without a TTL are signed over the payload alone
+        let legacy = DriaMessage::new_unsigned("{}", TOPIC, "test".into(), Default::default());
+        assert_eq!(legacy.signing_digest(), sha256hash(&legacy.payload));
+        assert!(!legacy.is_expired(legacy.timestamp + chrono::Duration::days(1)));
+
+        // out-of-range TTLs are rejected instead of overflowing
+        let mut unbounded = message.clone();
+        unbounded.ttl_secs = Some(u64::MAX);
+        assert_eq!(unbounded.expires_at(), None);
+        assert!(unbounded.is_expired(unbounded.timestamp));
+        unbounded.ttl_secs = Some(i64::MAX as u64);
+        assert!(unbounded.is_expired(unbounded.timestamp));
+    }
+
+    #[test]
+    fn test_recover_peer_id() {
+        let sk = SecretKey::parse(b"driadriadriadriadriadriadriadria").unwrap();
+        let mut message = DriaMessage::new_signed(
+            "{\"hello\":\"world\"}",
+            TOPIC,
+            "test".into(),
+            &sk,
+            SemanticVersion::default(),
+        );
+        assert_eq!(
+            message.recover_peer_id().unwrap(),
+            public_key_to_peer_id(&libsecp256k1::PublicKey::from_secret_key(&sk))
+        );
+        assert_eq!(
+            crate::crypto::verify_recoverable_signature(
+                message.signed_data(),
+                &message.recoverable_signature()
+            )
+            .unwrap(),
+            message.recover_address().unwrap()
+        );
+
+        // unsigned or tampered messages do not panic
+        message.signature = "not-hex".to_string();
+        assert!(message.recover_peer_id().is_err());
+        let unsigned = DriaMessage::new_unsigned("{}", TOPIC, "test".into(), Default::default());
+        assert!(unsigned.recover_peer_id().is_err());
+    }
+
+    #[test]
+    fn test_session_payload() {
+        let sk = SecretKey::parse(b"driadriadriadriadriadriadriadria").unwrap();
+        let session = SessionKey::derive(
+            Uuid::now_v7(),
+            &sk,
+            &libsecp256k1::PublicKey::from_secret_key(&sk),
+        )
+        .unwrap();
+
+        let mut message = DriaMessage::new_unsigned(
+            "{\"hello\":\"world\"}",
+            TOPIC,
+            "test".into(),
+            Default::default(),
+        );
+        message.encrypt_payload(&session).unwrap();
+        assert!(message.parse_payload::<TestStruct>().is_err());
+
+        // the session id is carried in both encodings
+        let cbor = message.to_bytes(DriaMessageEncoding::Cbor).unwrap();
+        let mut decoded = DriaMessage::from_slice(&cbor).unwrap();
+        assert_eq!(decoded.session_id, Some(session.id()));
+        decoded.decrypt_payload(&session).unwrap();
+        assert_eq!(
+            decoded.parse_payload::<TestStruct>().unwrap().hello,
+            "world"
+        );
+
+        // a plaintext message is not of the session
+        assert!(decoded.decrypt_payload(&session).is_err());
+    }
 }
@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::KeyRotation;
+use super::SchemaVersion;
+
 /// Topic used within [`crate::DriaMessage`] for heartbeat messages.
 pub const HEARTBEAT_TOPIC: &str = "heartbeat";
 
@@ -19,6 +22,43 @@ pub struct HeartbeatRequest {
     /// If `pending_batch` is greater than this value, the node will not be able to process them
     /// and will stall until the channel is free to do more.
     pub batch_size: usize,
+    /// Rolling average round-trip time to the RPC in milliseconds, if it has been measured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rtt_ms: Option<u64>,
+    /// Tasks that were accepted but not responded to before the node crashed, so that the RPC can reissue them.
+    ///
+    /// These are reported until a heartbeat is acknowledged.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lost_tasks: Vec<LostTask>,
+    /// Rotation of the wallet from a previous one, announced for a while after the rotation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_rotation: Option<KeyRotation>,
+    /// Session that the RPC can encrypt the payloads of its requests within, if enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionOffer>,
+    /// Names of the message encodings that the node accepts, e.g. `cbor` & `json`; JSON is assumed if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub encodings: Vec<String>,
+    /// Version of the schema of this payload, see [`SchemaVersion`].
+    #[serde(default = "SchemaVersion::legacy")]
+    pub schema_version: SchemaVersion,
+}
+
+/// A session offered by the node, whose key is derived by the RPC from the ECDH of its own secret key
+/// & the ephemeral public key of the node.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SessionOffer {
+    pub session_id: Uuid,
+    /// Hex encoded compressed ephemeral public key of the node.
+    pub public_key: String,
+}
+
+/// A task that was lost by a crash of the node.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LostTask {
+    pub row_id: Uuid,
+    pub task_id: String,
+    pub file_id: Uuid,
 }
 
 /// The response is an object with UUID along with an ACK (acknowledgement).
@@ -33,4 +73,7 @@ pub struct HeartbeatResponse {
     /// - `None` means that the heartbeat was acknowledged.
     /// - `Some` means that the heartbeat was not acknowledged for the given reason.
     pub error: Option<String>,
+    /// Version of the schema of this payload, see [`SchemaVersion`].
+    #[serde(default = "SchemaVersion::legacy")]
+    pub schema_version: SchemaVersion,
 }
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::SchemaVersion;
+use super::TaskResponsePayload;
+
+/// Topic used within [`crate::DriaMessage`] for late task result messages.
+pub const LATE_RESULT_TOPIC: &str = "late_result";
+
+/// A task result that could not be responded to, because the connection to the RPC was lost while
+/// the task was being executed; it is delivered as a request once the node is reconnected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LateResultRequest {
+    /// UUID of the late result request, a result is sent again with the same id until it is acknowledged.
+    pub late_id: Uuid,
+    /// The time that the task was completed at, as the result could not be responded to.
+    pub completed_at: chrono::DateTime<chrono::Utc>,
+    /// The result, as it would be responded to the task request.
+    pub result: TaskResponsePayload,
+    /// Version of the schema of this payload, see [`SchemaVersion`].
+    #[serde(default = "SchemaVersion::legacy")]
+    pub schema_version: SchemaVersion,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LateResultResponse {
+    /// UUID as given in the request.
+    pub late_id: Uuid,
+    /// An associated error with the response, if the result was not accepted, e.g. the task is reissued already.
+    pub error: Option<String>,
+    /// Version of the schema of this payload, see [`SchemaVersion`].
+    #[serde(default = "SchemaVersion::legacy")]
+    pub schema_version: SchemaVersion,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payloads::TaskStats;
+
+    #[test]
+    fn test_late_result_serialization() {
+        let request = LateResultRequest {
+            schema_version: SchemaVersion::CURRENT,
+            late_id: Uuid::now_v7(),
+            completed_at: chrono::Utc::now(),
+            result: TaskResponsePayload {
+                schema_version: SchemaVersion::CURRENT,
+                file_id: Uuid::now_v7(),
+                row_id: Uuid::now_v7(),
+                task_id: "task-1".to_string(),
+                model: "gpt-4o".to_string(),
+                stats: TaskStats::default(),
+                result: Some("hello".to_string()),
+                error: None,
+                encrypted_result: None,
+                receipt_signature: None,
+            },
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json["lateId"].is_string());
+        assert_eq!(json["result"]["taskId"], "task-1");
+        assert_eq!(json["result"]["result"], "hello");
+
+        // a response can not be mistaken for the other responses
+        let response = serde_json::from_str::<LateResultResponse>(&format!(
+            r#"{{"lateId":"{}","error":null}}"#,
+            request.late_id
+        ))
+        .unwrap();
+        assert_eq!(response.late_id, request.late_id);
+        assert!(serde_json::from_str::<LateResultResponse>(r#"{"progressId":"x"}"#).is_err());
+    }
+}
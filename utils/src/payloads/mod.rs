@@ -1,11 +1,27 @@
+mod schema;
+pub use schema::SchemaVersion;
+
 mod tasks;
-pub use tasks::{TaskError, TaskRequestPayload, TaskResponsePayload, TaskStats};
+pub use tasks::{EncryptedResult, TaskError, TaskRequestPayload, TaskResponsePayload, TaskStats};
 pub use tasks::{TASK_REQUEST_TOPIC, TASK_RESULT_TOPIC};
 
+mod progress;
+pub use progress::TASK_PROGRESS_TOPIC;
+pub use progress::{TaskProgress, TaskProgressRequest, TaskProgressResponse};
+
+mod late;
+pub use late::LATE_RESULT_TOPIC;
+pub use late::{LateResultRequest, LateResultResponse};
+
 mod heartbeat;
 pub use heartbeat::HEARTBEAT_TOPIC;
-pub use heartbeat::{HeartbeatRequest, HeartbeatResponse};
+pub use heartbeat::{HeartbeatRequest, HeartbeatResponse, LostTask, SessionOffer};
+
+mod rotation;
+pub use rotation::KeyRotation;
 
 mod specs;
 pub use specs::SPECS_TOPIC;
-pub use specs::{SpecModelPerformance, Specs, SpecsRequest, SpecsResponse};
+pub use specs::{
+    LifetimeStats, SpecModelPerformance, SpecModelState, Specs, SpecsRequest, SpecsResponse,
+};
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::SchemaVersion;
+
+/// Topic used within [`crate::DriaMessage`] for task progress messages.
+pub const TASK_PROGRESS_TOPIC: &str = "progress";
+
+/// A lightweight progress update about a task that is being handled by the node,
+/// so that the liveness of long-running tasks can be seen before they are completed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskProgressRequest {
+    /// UUID of the progress request.
+    pub progress_id: Uuid,
+    /// The file that this task is associated with.
+    pub file_id: Uuid,
+    /// The unique identifier of the task.
+    pub row_id: Uuid,
+    /// The custom identifier of the task, not necessarily unique.
+    pub task_id: String,
+    /// The progress of the task.
+    pub progress: TaskProgress,
+    /// Version of the schema of this payload, see [`SchemaVersion`].
+    #[serde(default = "SchemaVersion::legacy")]
+    pub schema_version: SchemaVersion,
+}
+
+/// The progress of a task, in order of occurrence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum TaskProgress {
+    /// Task is accepted & is waiting for a worker.
+    Queued,
+    /// Task is being executed by a worker.
+    Executing,
+    /// Step `current` out of `total` is completed, for tasks that take several executions
+    /// such as quorums & verifications.
+    Step { current: usize, total: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskProgressResponse {
+    /// UUID as given in the request.
+    pub progress_id: Uuid,
+    /// An associated error with the response, if the progress was not acknowledged.
+    pub error: Option<String>,
+    /// Version of the schema of this payload, see [`SchemaVersion`].
+    #[serde(default = "SchemaVersion::legacy")]
+    pub schema_version: SchemaVersion,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_progress_serialization() {
+        let progress = TaskProgress::Step {
+            current: 2,
+            total: 3,
+        };
+        assert_eq!(
+            serde_json::to_string(&progress).unwrap(),
+            r#"{"status":"step","current":2,"total":3}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&TaskProgress::Queued).unwrap(),
+            r#"{"status":"queued"}"#
+        );
+    }
+}
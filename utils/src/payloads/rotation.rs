@@ -0,0 +1,35 @@
+use chrono::SecondsFormat;
+use serde::{Deserialize, Serialize};
+
+/// A statement that the wallet of a node is rotated to a new one, signed by the previous wallet.
+///
+/// The node announces it within its heartbeats & specs for a while after the rotation, so that the
+/// identity of the previous address (e.g. its points) can be carried over to the new one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyRotation {
+    /// Address of the previous wallet, hex without `0x` prefix.
+    pub previous_address: String,
+    /// Address of the new wallet, hex without `0x` prefix.
+    pub new_address: String,
+    /// The time of the rotation.
+    pub rotated_at: chrono::DateTime<chrono::Utc>,
+    /// 64-byte hex-encoded signature of the previous wallet over the SHA256 of [`KeyRotation::statement`].
+    pub signature: String,
+    /// Signature recovery ID.
+    pub recovery_id: u8,
+}
+
+impl KeyRotation {
+    /// Returns the statement that is signed by the previous wallet.
+    pub fn statement(&self) -> String {
+        format!(
+            "Rotating Dria wallet 0x{} to 0x{} at {}",
+            self.previous_address
+                .trim_start_matches("0x")
+                .to_lowercase(),
+            self.new_address.trim_start_matches("0x").to_lowercase(),
+            self.rotated_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+/// Version of the schema of a payload, so that the RPC & the nodes on adjacent versions can interoperate
+/// during the rolling upgrades.
+///
+/// It is incremented on each change to the payloads that an older reader would misread, while the fields that
+/// are added with a default are read by the older versions anyways. Payloads without it are of
+/// [`SchemaVersion::LEGACY`], i.e. from before the payloads were versioned.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct SchemaVersion(pub u32);
+
+impl SchemaVersion {
+    /// Version of the payloads that are not versioned, given when the field is missing.
+    pub const LEGACY: Self = Self(0);
+    /// Version of the payloads that are written by this crate.
+    pub const CURRENT: Self = Self(1);
+
+    /// Returns the version for the missing fields, to be used with `#[serde(default = "...")]`.
+    pub fn legacy() -> Self {
+        Self::LEGACY
+    }
+
+    /// Returns `true` if a payload of this version can be read, i.e. it is at most one version away from
+    /// [`SchemaVersion::CURRENT`] in either direction.
+    pub fn is_compatible(&self) -> bool {
+        self.0.abs_diff(Self::CURRENT.0) <= 1
+    }
+}
+
+impl Default for SchemaVersion {
+    fn default() -> Self {
+        Self::CURRENT
+    }
+}
+
+impl std::fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payloads::HeartbeatResponse;
+
+    #[test]
+    fn test_schema_version() {
+        // unversioned payloads are of the legacy version
+        let legacy: HeartbeatResponse = serde_json::from_str(
+            r#"{"heartbeat_id":"01970000-0000-7000-8000-000000000000","error":null}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.schema_version, SchemaVersion::LEGACY);
+        assert!(legacy.schema_version.is_compatible());
+
+        // versioned payloads are written with the current version
+        let mut response = legacy.clone();
+        response.schema_version = SchemaVersion::default();
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["schema_version"], SchemaVersion::CURRENT.0);
+
+        // adjacent versions are compatible, others are not
+        assert!(SchemaVersion(SchemaVersion::CURRENT.0 + 1).is_compatible());
+        assert!(!SchemaVersion(SchemaVersion::CURRENT.0 + 2).is_compatible());
+    }
+}
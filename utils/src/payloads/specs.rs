@@ -2,6 +2,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+use super::KeyRotation;
+use super::SchemaVersion;
+
 /// Topic used within [`crate::DriaMessage`] for specs messages.
 pub const SPECS_TOPIC: &str = "specs";
 
@@ -13,12 +16,18 @@ pub struct SpecsRequest {
     pub specs: Specs,
     /// Address of the node, used by frontend etc. instead of peer id.
     pub address: String,
+    /// Version of the schema of this payload, see [`SchemaVersion`].
+    #[serde(default = "SchemaVersion::legacy")]
+    pub schema_version: SchemaVersion,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct SpecsResponse {
     /// UUID of the specs request, prevents replays.
     pub specs_id: Uuid,
+    /// Version of the schema of this payload, see [`SchemaVersion`].
+    #[serde(default = "SchemaVersion::legacy")]
+    pub schema_version: SchemaVersion,
 }
 
 /// The specs of a node, containing information about the hardware and software it runs on.
@@ -52,10 +61,37 @@ pub struct Specs {
     /// Peer id of the node.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub peer_id: Option<String>,
+    /// Whether each model is loaded & ready to serve, keyed by model name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_state: Option<HashMap<String, SpecModelState>>,
+    /// Statistics of the node across its restarts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lifetime: Option<LifetimeStats>,
+    /// Rotation of the wallet from a previous one, announced for a while after the rotation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_rotation: Option<KeyRotation>,
+    /// Hex encoded BLS12-381 public key that the task receipts are signed with, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bls_public_key: Option<String>,
     // GPU adapter infos, showing information about the available GPUs.
     // gpus: Vec<wgpu::AdapterInfo>,
 }
 
+/// Cumulative statistics of a node across its restarts, for operators to verify their long-term contribution.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifetimeStats {
+    /// The time that the node was first started at.
+    pub since: chrono::DateTime<chrono::Utc>,
+    /// Total uptime in seconds.
+    pub uptime_secs: u64,
+    /// Number of times the node was started.
+    pub sessions: u64,
+    /// Total number of completed tasks.
+    pub tasks: u64,
+    /// Total number of tokens generated by the tasks.
+    pub tokens: u64,
+}
+
 /// Performance metrics for a model, used in the specs.
 ///
 /// These are measured at the start of the compute node, and those that are not succesfull.
@@ -82,6 +118,19 @@ pub enum SpecModelPerformance {
     Passed,
 }
 
+/// Load state of a model, used in the specs.
+///
+/// API-based models are always warm, whereas local models (e.g. Ollama) go cold
+/// when they are unloaded from memory after being idle for a while.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecModelState {
+    /// Model is loaded in memory, tasks can start right away.
+    Warm,
+    /// Model is not loaded, the first task will have to wait for the model to be loaded.
+    Cold,
+}
+
 impl std::fmt::Display for SpecModelPerformance {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
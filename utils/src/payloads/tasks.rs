@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::SchemaVersion;
+
 /// Topic used within [`crate::DriaMessage`] for task request messages.
 pub const TASK_REQUEST_TOPIC: &str = "task";
 
@@ -38,6 +40,33 @@ pub struct TaskResponsePayload {
     /// If this is `Some`, you can ignore the `result` field.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<TaskError>,
+    /// Result encrypted for the `result_public_keys` of the request, given instead of `result`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_result: Option<EncryptedResult>,
+    /// Hex encoded BLS12-381 signature of the receipt of this task, which can be aggregated with the others.
+    ///
+    /// Only given by the nodes with `DKN_BLS_RECEIPTS`, see `crypto::bls` for the receipt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receipt_signature: Option<String>,
+    /// Version of the schema of this payload, see [`SchemaVersion`].
+    #[serde(default = "SchemaVersion::legacy")]
+    pub schema_version: SchemaVersion,
+}
+
+/// A result that is encrypted for one or more recipients, e.g. the owner of the task along with an auditor.
+///
+/// The result is encrypted once with a random content key (AES-256-GCM), and the content key is wrapped
+/// for each recipient with ECIES, which uses an ephemeral key of its own. See `crypto::decrypt_bytes`
+/// to decrypt it with the secret key of any of the recipients.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedResult {
+    /// Hex encoded 12-byte nonce of the content encryption.
+    pub nonce: String,
+    /// `base64` encoded ciphertext of the result, along with its tag.
+    pub ciphertext: String,
+    /// Hex encoded content key wrapped for each recipient, in the order of the recipients.
+    pub wrapped_keys: Vec<String>,
 }
 
 /// A generic task request, given by Dria.
@@ -55,6 +84,14 @@ pub struct TaskRequestPayload<T> {
     pub task_id: String,
     /// The input to the compute function.
     pub input: T,
+    /// Hex encoded secp256k1 public keys to encrypt the result for, e.g. the owner of the task & an auditor.
+    ///
+    /// The result is returned as is if this is empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub result_public_keys: Vec<String>,
+    /// Version of the schema of this payload, see [`SchemaVersion`].
+    #[serde(default = "SchemaVersion::legacy")]
+    pub schema_version: SchemaVersion,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, thiserror::Error)]
@@ -91,6 +128,25 @@ pub enum TaskError {
         /// The error message returned by the network.
         message: String,
     },
+    /// The node has too many pending tasks & did not accept this one, it can be reassigned.
+    #[error("Overloaded: {pending} pending tasks, high-water mark is {high_water_mark}")]
+    Overloaded {
+        /// Number of pending tasks at the node.
+        pending: usize,
+        /// Number of pending tasks at which the node stops accepting new tasks.
+        high_water_mark: usize,
+    },
+    /// The node has as many pending tasks as it can hold & did not accept this one, it must be reassigned.
+    #[error("Capacity exceeded: {pending} pending tasks, maximum is {max_pending}")]
+    CapacityExceeded {
+        /// Number of pending tasks at the node.
+        pending: usize,
+        /// Maximum number of pending tasks that the node holds.
+        max_pending: usize,
+    },
+    /// The node is not accepting tasks at the moment, e.g. during its quiet hours.
+    #[error("Unavailable: {0}")]
+    Unavailable(String),
     /// Any other error
     #[error("Other error: {0}")]
     Other(String),
@@ -112,6 +168,9 @@ pub struct TaskStats {
     pub execution_ended_at: chrono::DateTime<chrono::Utc>,
     /// Number of tokens of the result.
     pub token_count: usize,
+    /// Number of tokens spent on the verification pass, if the task had asked for one.
+    #[serde(default)]
+    pub verification_token_count: usize,
 }
 
 impl TaskStats {
@@ -148,4 +207,10 @@ impl TaskStats {
         self.token_count = token_count;
         self
     }
+
+    /// Records the token count of the verification pass within `verification_token_count`.
+    pub fn record_verification_token_count(mut self, token_count: usize) -> Self {
+        self.verification_token_count = token_count;
+        self
+    }
 }